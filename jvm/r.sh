@@ -24,3 +24,6 @@ JDK=$(join_by $SEP ${jars[@]})
 
 export JAVA_HOME
 cargo run -- --cp $JDK:$MY_SAMPLE HelloWorld
+
+#the ArrayList loop of note.txt, build with release mode
+#cargo run --release -- --cp $JDK:$MY_SAMPLE ArrayListLoop
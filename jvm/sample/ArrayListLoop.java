@@ -0,0 +1,22 @@
+import java.util.ArrayList;
+
+//the ArrayList loop of note.txt, the time of each round in ms
+class ArrayListLoop
+{
+    public static void main(String[] args)
+    {
+        int rounds = args.length > 0 ? Integer.parseInt(args[0]) : 5;
+        for (int i = 0; i < rounds; i++) {
+            long begin = System.nanoTime();
+
+            ArrayList<Integer> list = new ArrayList<Integer>();
+            int start = 0;
+            while (start < 50000) {
+                list.add(start++);
+            }
+
+            long end = System.nanoTime();
+            System.out.println("list_add = " + (end - begin) / 1000000 + " ms, size = " + list.size());
+        }
+    }
+}
@@ -9,10 +9,20 @@ use vm::util;
 
 fn main() {
     env_logger::init();
-    vm::init_vm();
-
     let opt = options::parse();
 
+    vm::gc::set_heap_size(opt.heap_size);
+    if let Some(size) = opt.stack_size {
+        runtime::vm::set_thread_stack_size(size);
+    }
+    for (k, v) in opt.properties.iter() {
+        runtime::vm::set_property(k, v);
+    }
+    if let Some(mode) = opt.verify {
+        runtime::verifier::set_mode(mode);
+    }
+    vm::init_vm();
+
     if let Some(cp) = &opt.cp {
         runtime::add_class_paths(cp);
     }
@@ -24,6 +34,21 @@ fn main() {
     let class = opt.class;
     let args = opt.args;
     // println!("main class: {}, args: {:?}", class, args);
-    let mut thread = MainThread::new(class.replace(".", util::FILE_SEP), args);
-    thread.run();
+
+    //the main thread has the stack size of -Xss, like the other java threads
+    let mut builder = std::thread::Builder::new().name("main".to_string());
+    if let Some(size) = opt.stack_size {
+        builder = builder.stack_size(size);
+    }
+    let main = builder
+        .spawn(move || {
+            let mut thread = MainThread::new(class.replace(".", util::FILE_SEP), args);
+            thread.run()
+        })
+        .expect("spawn main thread failed");
+    //an uncaught exception, or a panic of the vm
+    match main.join() {
+        Ok(Ok(())) => (),
+        Ok(Err(_)) | Err(_) => std::process::exit(1),
+    }
 }
@@ -1,4 +1,7 @@
 use clap::Clap;
+use vm::gc::HeapSize;
+use vm::runtime::verifier;
+use vm::util;
 
 #[derive(Clap, Debug)]
 #[clap(version)]
@@ -15,8 +18,116 @@ pub struct Opt {
     pub class: String,
 
     pub args: Vec<String>,
+
+    /// -Xms<size> -Xmx<size> -Xmn<size>
+    #[clap(skip)]
+    pub heap_size: HeapSize,
+
+    /// -Xss<size>
+    #[clap(skip)]
+    pub stack_size: Option<usize>,
+
+    /// -D<name>=<value>
+    #[clap(skip)]
+    pub properties: Vec<(String, String)>,
+
+    /// -Xverify:none|remote|all
+    #[clap(skip)]
+    pub verify: Option<verifier::Mode>,
 }
 
 pub fn parse() -> Opt {
-    Opt::parse()
+    //'-X' and '-D' options can't be described by clap, pick them out first
+    let (xopts, args) = split_x_options(std::env::args());
+    let mut opt = Opt::parse_from(args);
+
+    for it in xopts.iter() {
+        if let Some(kv) = it.strip_prefix("-D") {
+            let (k, v) = match kv.find('=') {
+                Some(idx) => (&kv[..idx], &kv[idx + 1..]),
+                None => (kv, ""),
+            };
+            opt.properties.push((k.to_string(), v.to_string()));
+            continue;
+        }
+
+        if let Some(v) = it.strip_prefix("-Xverify:") {
+            match verifier::parse_mode(v) {
+                Some(mode) => opt.verify = Some(mode),
+                None => fail(&format!("Unrecognized option: {}", it)),
+            }
+            continue;
+        }
+
+        let name = it.get(..4).unwrap_or(it);
+        if name == "-Xss" {
+            match util::parse_size(&it[name.len()..]) {
+                Some(v) => opt.stack_size = Some(v),
+                None => fail(&format!("Invalid thread stack size: {}", it)),
+            }
+            continue;
+        }
+
+        let size = match name {
+            "-Xms" => &mut opt.heap_size.initial,
+            "-Xmx" => &mut opt.heap_size.max,
+            "-Xmn" => &mut opt.heap_size.young,
+            _ => fail(&format!("Unrecognized option: {}", it)),
+        };
+
+        match util::parse_size(&it[name.len()..]) {
+            Some(v) => *size = v,
+            None => fail(&format!("Invalid heap size: {}", it)),
+        }
+    }
+
+    let has = |k: &str| xopts.iter().any(|it| it.starts_with(k));
+    let size = &mut opt.heap_size;
+    if size.initial > size.max {
+        match (has("-Xms"), has("-Xmx")) {
+            (true, true) => {
+                fail("Initial heap size set to a larger value than the maximum heap size")
+            }
+            (true, false) => size.max = size.initial,
+            _ => size.initial = size.max,
+        }
+    }
+    if size.young >= size.max {
+        fail("Young generation size is not less than the maximum heap size");
+    }
+
+    opt
+}
+
+//options before the main class only, the rest are args of the program
+fn split_x_options<I: Iterator<Item = String>>(args: I) -> (Vec<String>, Vec<String>) {
+    let mut xopts = Vec::new();
+    let mut rest = Vec::new();
+    let mut args = args.enumerate();
+
+    while let Some((i, it)) = args.next() {
+        if i == 0 {
+            rest.push(it);
+        } else if it == "--cp" || it == "--classpath" {
+            rest.push(it);
+            rest.extend(args.next().map(|(_, v)| v));
+        } else if it.starts_with("-X") || it.starts_with("-D") {
+            xopts.push(it);
+        } else if it.starts_with('-') {
+            rest.push(it);
+        } else {
+            rest.push(it);
+            rest.extend(args.map(|(_, v)| v));
+            break;
+        }
+    }
+
+    (xopts, rest)
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    eprintln!("Error: Could not create the Java Virtual Machine.");
+    eprintln!("Error: A fatal exception has occurred. Program will exit.");
+    std::process::exit(1);
 }
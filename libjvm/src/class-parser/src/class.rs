@@ -1,6 +1,7 @@
 use classfile::{
     attributes::{self, Tag as AttrTag, TargetInfo, TypeAnnotation},
-    constant_pool, AttributeType, ClassFile, ConstantPool, FieldInfo, MethodInfo, Version,
+    constant_pool, AttributeType, BytesRef, ClassFile, ConstantPool, FieldInfo, MethodInfo,
+    Version,
 };
 use std::sync::Arc;
 
@@ -27,8 +28,9 @@ macro_rules! gen_take_exact {
     ($count: expr, $name: ident) => {
         fn $name(input: &[u8]) -> nom::IResult<&[u8], [u8; $count]> {
             let mut output = [0; $count];
-            // TODO: Nom error
-            assert!(input.len() >= $count);
+            if input.len() < $count {
+                return Err(nom::Err::Incomplete(nom::Needed::Size($count)));
+            }
             for i in 0..$count {
                 output[i] = input[i];
             }
@@ -151,8 +153,8 @@ named!(
                     0 => value!(VerificationTypeInfo::Top) |
                     1 => value!(VerificationTypeInfo::Integer) |
                     2 => value!(VerificationTypeInfo::Float) |
-                    3 => value!(VerificationTypeInfo::Long) |
-                    4 => value!(VerificationTypeInfo::Double) |
+                    3 => value!(VerificationTypeInfo::Double) |
+                    4 => value!(VerificationTypeInfo::Long) |
                     5 => value!(VerificationTypeInfo::Null) |
                     6 => value!(VerificationTypeInfo::UninitializedThis) |
                     7 => do_parse!(
@@ -373,7 +375,7 @@ named_args!(annotation_entry(cp: ConstantPool)<attributes::AnnotationEntry>, do_
     type_index: be_u16 >>
     pair_count: be_u16 >>
     pairs: count!(call!(element_value_pair, cp.clone()), pair_count as usize) >>
-    type_name: value!(constant_pool::get_utf8(&cp, type_index as usize).clone()) >>
+    type_name: call!(cp_utf8, cp.clone(), type_index) >>
     (attributes::AnnotationEntry {type_name, pairs})
 ));
 
@@ -627,15 +629,32 @@ named_args!(attr_sized(tag: AttrTag, self_len: usize, cp: ConstantPool)<Attribut
         parameters: count!(method_parameter, parameter_count as usize) >>
         (AttributeType::MethodParameters {parameters})
     ) |
+    AttrTag::NestHost => do_parse!(
+        host_class_index: be_u16 >>
+        (AttributeType::NestHost {host_class_index})
+    ) |
+    AttrTag::NestMembers => do_parse!(
+        class_count: be_u16 >>
+        classes: count!(be_u16, class_count as usize) >>
+        (AttributeType::NestMembers {classes})
+    ) |
     AttrTag::Unknown => do_parse!(
         _data: take!(self_len) >>
         (AttributeType::Unknown)
     )
 ));
 
+//the Utf8 entry at 'idx', the class file is malformed if it's not
+fn cp_utf8(input: &[u8], cp: ConstantPool, idx: u16) -> nom::IResult<&[u8], BytesRef> {
+    match cp.get(idx as usize) {
+        Some(constant_pool::Type::Utf8 { bytes }) => Ok((input, bytes.clone())),
+        _ => Err(nom::Err::Error((input, nom::error::ErrorKind::Verify))),
+    }
+}
+
 named_args!(attr_tag(cp: ConstantPool)<AttrTag>, do_parse!(
     name_index: be_u16 >>
-    name: value!(constant_pool::get_utf8(&cp, name_index as usize)) >>
+    name: call!(cp_utf8, cp.clone(), name_index) >>
     inner: value!(AttrTag::from(name.as_slice())) >>
     (inner)
 ));
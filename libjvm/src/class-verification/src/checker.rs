@@ -1,7 +1,25 @@
-use crate::types::ConstantPool;
+use crate::types::{self, VType};
+use classfile::attributes::{Code, Type as AttrType};
+use classfile::constant_pool::Type as CpType;
+use classfile::flags::*;
+use classfile::{BytesRef, ClassFile, ConstantPool, FieldInfo, MethodInfo, U2};
+use std::collections::HashSet;
+use std::fmt;
+
+/*
+Format checking, JVMS 4.8
+
+The class file is checked before the vm uses it, the constant pool
+entries are the types expected where they are referenced, the names and
+the descriptors are legal, the access flags are legal combinations, and
+the members are not duplicated.
+*/
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Err {
+    UnsupportedVersion,
+    InvalidCpTag,
+    InvalidCpUtf8,
     InvalidCpClassNameIdx,
     InvalidCpFieldRefClsIdx,
     InvalidCpFieldRefNameAndTypeIdx,
@@ -17,12 +35,76 @@ pub enum Err {
     InvalidCpMethodTypeDescIdx,
     InvalidCpInvokeDynBootstrapMethodAttrIdx,
     InvalidCpInvokeDynNameAndTypeIdx,
+    InvalidClassAccFlags,
+    InvalidThisClass,
+    InvalidSuperClass,
+    InvalidInterfaceIdx,
+    DuplicateInterface,
     InvalidFieldAccFlags,
     InvalidFieldNameIdx,
     InvalidFieldDescIdx,
+    InvalidConstantValue,
+    DuplicateField,
     InvalidMethodAccFlags,
     InvalidMethodNameIdx,
     InvalidMethodDescIdx,
+    DuplicateMethod,
+    MissingCode,
+    UnexpectedCode,
+    DuplicateCode,
+    InvalidCodeLength,
+    InvalidMaxLocals,
+    InvalidExceptionTable,
+}
+
+impl fmt::Display for Err {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            Err::UnsupportedVersion => "Unsupported class file version",
+            Err::InvalidCpTag => "Illegal constant pool type",
+            Err::InvalidCpUtf8 => "Illegal UTF8 string in constant pool",
+            Err::InvalidCpClassNameIdx => "Invalid class name in constant pool",
+            Err::InvalidCpFieldRefClsIdx => "Invalid class index in Fieldref",
+            Err::InvalidCpFieldRefNameAndTypeIdx => "Invalid name and type in Fieldref",
+            Err::InvalidCpMethodRefClsIdx => "Invalid class index in Methodref",
+            Err::InvalidCpMethodRefNameAndTypeIdx => "Invalid name and type in Methodref",
+            Err::InvalidCpInterfaceMethodRefClsIdx => "Invalid class index in InterfaceMethodref",
+            Err::InvalidCpInterfaceMethodRefNameAndTypeIdx => {
+                "Invalid name and type in InterfaceMethodref"
+            }
+            Err::InvalidCpStrStrIdx => "Invalid string index in String",
+            Err::InvalidCpNameAndTypeNameIdx => "Invalid name index in NameAndType",
+            Err::InvalidCpNameAndTypeDescIdx => "Invalid descriptor index in NameAndType",
+            Err::InvalidCpMethodHandleRefKind => "Bad method handle kind",
+            Err::InvalidCpMethodHandleRefIdx => "Invalid reference index in MethodHandle",
+            Err::InvalidCpMethodTypeDescIdx => "Invalid descriptor in MethodType",
+            Err::InvalidCpInvokeDynBootstrapMethodAttrIdx => {
+                "Invalid bootstrap method index in InvokeDynamic"
+            }
+            Err::InvalidCpInvokeDynNameAndTypeIdx => "Invalid name and type in InvokeDynamic",
+            Err::InvalidClassAccFlags => "Illegal class modifiers",
+            Err::InvalidThisClass => "Invalid this class index",
+            Err::InvalidSuperClass => "Invalid superclass index",
+            Err::InvalidInterfaceIdx => "Interface name has bad constant pool index",
+            Err::DuplicateInterface => "Duplicate interface name",
+            Err::InvalidFieldAccFlags => "Illegal field modifiers",
+            Err::InvalidFieldNameIdx => "Illegal field name",
+            Err::InvalidFieldDescIdx => "Field has illegal signature",
+            Err::InvalidConstantValue => "Inconsistent constant value type",
+            Err::DuplicateField => "Duplicate field name and signature",
+            Err::InvalidMethodAccFlags => "Method has illegal modifiers",
+            Err::InvalidMethodNameIdx => "Illegal method name",
+            Err::InvalidMethodDescIdx => "Method has illegal signature",
+            Err::DuplicateMethod => "Duplicate method name and signature",
+            Err::MissingCode => "Absent Code attribute in method that is not native or abstract",
+            Err::UnexpectedCode => "Code attribute in native or abstract method",
+            Err::DuplicateCode => "Multiple Code attributes in method",
+            Err::InvalidCodeLength => "Invalid method Code length",
+            Err::InvalidMaxLocals => "Arguments can't fit into locals",
+            Err::InvalidExceptionTable => "Illegal exception table",
+        };
+        write!(f, "{}", msg)
+    }
 }
 
 pub type CheckResult = Result<(), Err>;
@@ -30,3 +112,661 @@ pub type CheckResult = Result<(), Err>;
 pub trait Checker {
     fn check(&self, cp: &ConstantPool) -> CheckResult;
 }
+
+//the versions of class file supported, 45.0 ~ 52.0
+pub const MIN_MAJOR_VERSION: U2 = 45;
+pub const MAX_MAJOR_VERSION: U2 = 52;
+
+const J_OBJECT: &[u8] = b"java/lang/Object";
+
+//the class file is checked with its own constant pool
+impl Checker for ClassFile {
+    fn check(&self, cp: &ConstantPool) -> CheckResult {
+        let major = self.version.major;
+        if !(MIN_MAJOR_VERSION..=MAX_MAJOR_VERSION).contains(&major)
+            || (major == MAX_MAJOR_VERSION && self.version.minor > 0)
+        {
+            return Err(Err::UnsupportedVersion);
+        }
+
+        let ctx = Context {
+            cp,
+            major,
+            is_interface: self.acc_flags & ACC_INTERFACE != 0,
+            n_bootstrap_methods: self.bootstrap_methods().map(|v| v.len()),
+        };
+        for (i, it) in cp.iter().enumerate() {
+            ctx.check_cp_entry(i, it)?;
+        }
+        if let Some(methods) = self.bootstrap_methods() {
+            for it in methods {
+                match cp.get(it.method_ref as usize) {
+                    Some(CpType::MethodHandle { .. }) => (),
+                    _ => return Err(Err::InvalidCpInvokeDynBootstrapMethodAttrIdx),
+                }
+            }
+        }
+
+        ctx.check_class(self)?;
+
+        let mut fields = HashSet::new();
+        for it in self.fields.iter() {
+            ctx.check_field(it)?;
+            if !fields.insert(ctx.member_key(it.name_index, it.desc_index)) {
+                return Err(Err::DuplicateField);
+            }
+        }
+
+        let mut methods = HashSet::new();
+        for it in self.methods.iter() {
+            ctx.check_method(it)?;
+            if !methods.insert(ctx.member_key(it.name_index, it.desc_index)) {
+                return Err(Err::DuplicateMethod);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct Context<'a> {
+    cp: &'a ConstantPool,
+    major: U2,
+    is_interface: bool,
+    n_bootstrap_methods: Option<usize>,
+}
+
+impl<'a> Context<'a> {
+    fn utf8(&self, idx: U2) -> Option<&BytesRef> {
+        match self.cp.get(idx as usize)? {
+            CpType::Utf8 { bytes } => Some(bytes),
+            _ => None,
+        }
+    }
+
+    fn class_name(&self, idx: U2) -> Option<&BytesRef> {
+        match self.cp.get(idx as usize)? {
+            CpType::Class { name_index } => self.utf8(*name_index),
+            _ => None,
+        }
+    }
+
+    fn name_and_type(&self, idx: U2) -> Option<(&BytesRef, &BytesRef)> {
+        match self.cp.get(idx as usize)? {
+            CpType::NameAndType {
+                name_index,
+                desc_index,
+            } => Some((self.utf8(*name_index)?, self.utf8(*desc_index)?)),
+            _ => None,
+        }
+    }
+
+    //the name and descriptor of a member already checked
+    fn member_key(&self, name: U2, desc: U2) -> (Option<&BytesRef>, Option<&BytesRef>) {
+        (self.utf8(name), self.utf8(desc))
+    }
+
+    fn check_cp_entry(&self, idx: usize, entry: &CpType) -> CheckResult {
+        let cp = self.cp;
+        match entry {
+            //index 0, the slot after long and double
+            CpType::Nop => {
+                let after_wide = idx > 0
+                    && matches!(
+                        cp.get(idx - 1),
+                        Some(CpType::Long { .. }) | Some(CpType::Double { .. })
+                    );
+                if idx != 0 && !after_wide {
+                    return Err(Err::InvalidCpTag);
+                }
+            }
+            CpType::Unknown => return Err(Err::InvalidCpTag),
+            CpType::Utf8 { bytes } => {
+                if !is_valid_utf8(bytes) {
+                    return Err(Err::InvalidCpUtf8);
+                }
+            }
+            CpType::Integer { .. } | CpType::Float { .. } => (),
+            CpType::Long { .. } | CpType::Double { .. } => {
+                if !matches!(cp.get(idx + 1), Some(CpType::Nop)) {
+                    return Err(Err::InvalidCpTag);
+                }
+            }
+            CpType::Class { name_index } => match self.utf8(*name_index) {
+                Some(name) if is_class_name(name) => (),
+                _ => return Err(Err::InvalidCpClassNameIdx),
+            },
+            CpType::String { string_index } => {
+                if self.utf8(*string_index).is_none() {
+                    return Err(Err::InvalidCpStrStrIdx);
+                }
+            }
+            CpType::NameAndType {
+                name_index,
+                desc_index,
+            } => {
+                if self.utf8(*name_index).is_none() {
+                    return Err(Err::InvalidCpNameAndTypeNameIdx);
+                }
+                if self.utf8(*desc_index).is_none() {
+                    return Err(Err::InvalidCpNameAndTypeDescIdx);
+                }
+            }
+            CpType::FieldRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                if !self.is_ref_class(*class_index) {
+                    return Err(Err::InvalidCpFieldRefClsIdx);
+                }
+                match self.name_and_type(*name_and_type_index) {
+                    Some((name, desc)) if is_unqualified_name(name) && is_field_desc(desc) => (),
+                    _ => return Err(Err::InvalidCpFieldRefNameAndTypeIdx),
+                }
+            }
+            CpType::MethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                if !self.is_ref_class(*class_index) {
+                    return Err(Err::InvalidCpMethodRefClsIdx);
+                }
+                if !self.is_method_ref_nat(*name_and_type_index) {
+                    return Err(Err::InvalidCpMethodRefNameAndTypeIdx);
+                }
+            }
+            CpType::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                if !self.is_ref_class(*class_index) {
+                    return Err(Err::InvalidCpInterfaceMethodRefClsIdx);
+                }
+                let is_init = match self.name_and_type(*name_and_type_index) {
+                    Some((name, _)) => name.first() == Some(&b'<'),
+                    None => false,
+                };
+                if is_init || !self.is_method_ref_nat(*name_and_type_index) {
+                    return Err(Err::InvalidCpInterfaceMethodRefNameAndTypeIdx);
+                }
+            }
+            CpType::MethodHandle {
+                ref_kind,
+                ref_index,
+            } => {
+                if self.major < 51 {
+                    return Err(Err::InvalidCpTag);
+                }
+                self.check_method_handle(*ref_kind, *ref_index)?;
+            }
+            CpType::MethodType { desc_index } => {
+                if self.major < 51 {
+                    return Err(Err::InvalidCpTag);
+                }
+                match self.utf8(*desc_index) {
+                    Some(desc) if method_desc(desc).is_some() => (),
+                    _ => return Err(Err::InvalidCpMethodTypeDescIdx),
+                }
+            }
+            CpType::InvokeDynamic {
+                bootstrap_method_attr_index,
+                name_and_type_index,
+            } => {
+                if self.major < 51 {
+                    return Err(Err::InvalidCpTag);
+                }
+                match self.n_bootstrap_methods {
+                    Some(n) if (*bootstrap_method_attr_index as usize) < n => (),
+                    _ => return Err(Err::InvalidCpInvokeDynBootstrapMethodAttrIdx),
+                }
+                match self.name_and_type(*name_and_type_index) {
+                    Some((name, desc))
+                        if is_method_name(name)
+                            && name.first() != Some(&b'<')
+                            && method_desc(desc).is_some() => {}
+                    _ => return Err(Err::InvalidCpInvokeDynNameAndTypeIdx),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    //the class of a member ref, an array for the methods like clone
+    fn is_ref_class(&self, idx: U2) -> bool {
+        self.class_name(idx).is_some()
+    }
+
+    fn is_method_ref_nat(&self, idx: U2) -> bool {
+        match self.name_and_type(idx) {
+            Some((name, desc)) => match method_desc(desc) {
+                //<init> returns void, <clinit> is never referenced
+                Some((_, ret)) => match name.as_slice() {
+                    b"<init>" => ret.is_none(),
+                    _ => name.first() != Some(&b'<') && is_method_name(name),
+                },
+                None => false,
+            },
+            None => false,
+        }
+    }
+
+    //JVMS 4.4.8
+    fn check_method_handle(&self, kind: u8, idx: U2) -> CheckResult {
+        let entry = self.cp.get(idx as usize);
+        let (nat, ok) = match (kind, entry) {
+            //getField, getStatic, putField, putStatic
+            (
+                1..=4,
+                Some(CpType::FieldRef {
+                    name_and_type_index,
+                    ..
+                }),
+            ) => (*name_and_type_index, true),
+            //invokeVirtual, newInvokeSpecial
+            (
+                5,
+                Some(CpType::MethodRef {
+                    name_and_type_index,
+                    ..
+                }),
+            )
+            | (
+                8,
+                Some(CpType::MethodRef {
+                    name_and_type_index,
+                    ..
+                }),
+            ) => (*name_and_type_index, true),
+            //invokeStatic, invokeSpecial
+            (
+                6,
+                Some(CpType::MethodRef {
+                    name_and_type_index,
+                    ..
+                }),
+            )
+            | (
+                7,
+                Some(CpType::MethodRef {
+                    name_and_type_index,
+                    ..
+                }),
+            ) => (*name_and_type_index, true),
+            (
+                6,
+                Some(CpType::InterfaceMethodRef {
+                    name_and_type_index,
+                    ..
+                }),
+            )
+            | (
+                7,
+                Some(CpType::InterfaceMethodRef {
+                    name_and_type_index,
+                    ..
+                }),
+            ) => (*name_and_type_index, self.major >= 52),
+            //invokeInterface
+            (
+                9,
+                Some(CpType::InterfaceMethodRef {
+                    name_and_type_index,
+                    ..
+                }),
+            ) => (*name_and_type_index, true),
+            (1..=9, _) => return Err(Err::InvalidCpMethodHandleRefIdx),
+            _ => return Err(Err::InvalidCpMethodHandleRefKind),
+        };
+        if !ok {
+            return Err(Err::InvalidCpMethodHandleRefIdx);
+        }
+
+        //only newInvokeSpecial refers to <init>
+        let is_init = match self.name_and_type(nat) {
+            Some((name, _)) => name.as_slice() == b"<init>",
+            None => false,
+        };
+        match kind {
+            8 if !is_init => Err(Err::InvalidCpMethodHandleRefIdx),
+            5..=7 | 9 if is_init => Err(Err::InvalidCpMethodHandleRefIdx),
+            _ => Ok(()),
+        }
+    }
+
+    //JVMS 4.1
+    fn check_class(&self, cf: &ClassFile) -> CheckResult {
+        let flags = cf.acc_flags;
+        let has = |f: U2| flags & f != 0;
+        let illegal = if self.is_interface {
+            !has(ACC_ABSTRACT) || has(ACC_FINAL) || (self.major >= 49 && has(ACC_ENUM))
+        } else {
+            has(ACC_ANNOTATION) || (has(ACC_FINAL) && has(ACC_ABSTRACT))
+        };
+        if illegal {
+            return Err(Err::InvalidClassAccFlags);
+        }
+
+        let this_name = match self.class_name(cf.this_class) {
+            Some(name) if name.first() != Some(&b'[') => name,
+            _ => return Err(Err::InvalidThisClass),
+        };
+        if cf.super_class == 0 {
+            if this_name.as_slice() != J_OBJECT {
+                return Err(Err::InvalidSuperClass);
+            }
+        } else {
+            match self.class_name(cf.super_class) {
+                Some(name) if name.first() == Some(&b'[') => return Err(Err::InvalidSuperClass),
+                Some(name) if self.is_interface && name.as_slice() != J_OBJECT => {
+                    return Err(Err::InvalidSuperClass)
+                }
+                Some(_) if this_name.as_slice() == J_OBJECT => return Err(Err::InvalidSuperClass),
+                Some(_) => (),
+                None => return Err(Err::InvalidSuperClass),
+            }
+        }
+
+        let mut interfaces = HashSet::new();
+        for &it in cf.interfaces.iter() {
+            match self.class_name(it) {
+                Some(name) if name.first() != Some(&b'[') => {
+                    if !interfaces.insert(name) {
+                        return Err(Err::DuplicateInterface);
+                    }
+                }
+                _ => return Err(Err::InvalidInterfaceIdx),
+            }
+        }
+
+        Ok(())
+    }
+
+    //JVMS 4.5
+    fn check_field(&self, field: &FieldInfo) -> CheckResult {
+        match self.utf8(field.name_index) {
+            Some(name) if is_unqualified_name(name) => (),
+            _ => return Err(Err::InvalidFieldNameIdx),
+        }
+        let desc = match self.utf8(field.desc_index) {
+            Some(desc) if is_field_desc(desc) => desc,
+            _ => return Err(Err::InvalidFieldDescIdx),
+        };
+
+        let flags = field.acc_flags;
+        let has = |f: U2| flags & f != 0;
+        let illegal = if self.is_interface {
+            let allowed = ACC_PUBLIC | ACC_STATIC | ACC_FINAL | ACC_SYNTHETIC;
+            !has(ACC_PUBLIC) || !has(ACC_STATIC) || !has(ACC_FINAL) || flags & !allowed != 0
+        } else {
+            !is_one_access(flags) || (has(ACC_FINAL) && has(ACC_VOLATILE))
+        };
+        if illegal {
+            return Err(Err::InvalidFieldAccFlags);
+        }
+
+        //ignored by the instance fields
+        let mut values = field.attrs.iter().filter_map(|it| match it {
+            AttrType::ConstantValue {
+                constant_value_index,
+            } => Some(*constant_value_index),
+            _ => None,
+        });
+        if let Some(idx) = values.next() {
+            if values.next().is_some() {
+                return Err(Err::InvalidConstantValue);
+            }
+            if has(ACC_STATIC) {
+                let ok = matches!(
+                    (desc.as_slice(), self.cp.get(idx as usize)),
+                    (b"J", Some(CpType::Long { .. }))
+                        | (b"F", Some(CpType::Float { .. }))
+                        | (b"D", Some(CpType::Double { .. }))
+                        | (b"I", Some(CpType::Integer { .. }))
+                        | (b"S", Some(CpType::Integer { .. }))
+                        | (b"C", Some(CpType::Integer { .. }))
+                        | (b"B", Some(CpType::Integer { .. }))
+                        | (b"Z", Some(CpType::Integer { .. }))
+                        | (b"Ljava/lang/String;", Some(CpType::String { .. }))
+                );
+                if !ok {
+                    return Err(Err::InvalidConstantValue);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    //JVMS 4.6
+    fn check_method(&self, method: &MethodInfo) -> CheckResult {
+        let name = match self.utf8(method.name_index) {
+            Some(name) if is_method_name(name) => name.as_slice(),
+            _ => return Err(Err::InvalidMethodNameIdx),
+        };
+        let (args, ret) = match self.utf8(method.desc_index).and_then(|d| method_desc(d)) {
+            Some(sig) => sig,
+            None => return Err(Err::InvalidMethodDescIdx),
+        };
+
+        let flags = method.acc_flags;
+        let has = |f: U2| flags & f != 0;
+        let is_clinit = name == b"<clinit>";
+        let mut slots = args.iter().map(|t| t.size()).sum::<usize>();
+        if !has(ACC_STATIC) {
+            slots += 1;
+        }
+        if slots > 255 {
+            return Err(Err::InvalidMethodDescIdx);
+        }
+
+        if is_clinit {
+            //the other flags are ignored
+            if !args.is_empty() || ret.is_some() {
+                return Err(Err::InvalidMethodDescIdx);
+            }
+            if self.major >= 51 && !has(ACC_STATIC) {
+                return Err(Err::InvalidMethodAccFlags);
+            }
+        } else if name == b"<init>" {
+            if ret.is_some() {
+                return Err(Err::InvalidMethodDescIdx);
+            }
+            if self.is_interface {
+                return Err(Err::InvalidMethodNameIdx);
+            }
+            let allowed =
+                ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED | ACC_VARARGS | ACC_STRICT | ACC_SYNTHETIC;
+            if !is_one_access(flags) || flags & !allowed != 0 {
+                return Err(Err::InvalidMethodAccFlags);
+            }
+        } else {
+            self.check_method_flags(flags)?;
+        }
+
+        let mut codes = method.attrs.iter().filter_map(|it| match it {
+            AttrType::Code(code) => Some(code),
+            _ => None,
+        });
+        let code = codes.next();
+        if codes.next().is_some() {
+            return Err(Err::DuplicateCode);
+        }
+        let no_code = has(ACC_NATIVE) || has(ACC_ABSTRACT);
+        match code {
+            Some(_) if no_code && !is_clinit => Err(Err::UnexpectedCode),
+            Some(code) => self.check_code(code, slots),
+            None if no_code => Ok(()),
+            None => Err(Err::MissingCode),
+        }
+    }
+
+    fn check_method_flags(&self, flags: U2) -> CheckResult {
+        let has = |f: U2| flags & f != 0;
+        if !is_one_access(flags) {
+            return Err(Err::InvalidMethodAccFlags);
+        }
+
+        let illegal = if self.is_interface {
+            if self.major < 52 {
+                let allowed = ACC_PUBLIC | ACC_ABSTRACT | ACC_VARARGS | ACC_BRIDGE | ACC_SYNTHETIC;
+                !has(ACC_PUBLIC) || !has(ACC_ABSTRACT) || flags & !allowed != 0
+            } else {
+                let forbidden = ACC_PROTECTED | ACC_FINAL | ACC_SYNCHRONIZED | ACC_NATIVE;
+                has(ACC_PUBLIC) == has(ACC_PRIVATE) || flags & forbidden != 0
+            }
+        } else {
+            false
+        };
+        let abstract_illegal = has(ACC_ABSTRACT)
+            && flags
+                & (ACC_FINAL
+                    | ACC_NATIVE
+                    | ACC_PRIVATE
+                    | ACC_STATIC
+                    | ACC_STRICT
+                    | ACC_SYNCHRONIZED)
+                != 0;
+
+        if illegal || abstract_illegal {
+            Err(Err::InvalidMethodAccFlags)
+        } else {
+            Ok(())
+        }
+    }
+
+    //JVMS 4.7.3, the instructions are checked by the verifier
+    fn check_code(&self, code: &Code, arg_slots: usize) -> CheckResult {
+        let len = code.code.len();
+        if len == 0 || len > 65535 {
+            return Err(Err::InvalidCodeLength);
+        }
+        if (code.max_locals as usize) < arg_slots {
+            return Err(Err::InvalidMaxLocals);
+        }
+
+        for it in code.exceptions.iter() {
+            let (start, end, handler) = (
+                it.start_pc as usize,
+                it.end_pc as usize,
+                it.handler_pc as usize,
+            );
+            let catch_ok = it.catch_type == 0 || self.class_name(it.catch_type).is_some();
+            if start >= end || end > len || handler >= len || !catch_ok {
+                return Err(Err::InvalidExceptionTable);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+//at most one of public, private and protected
+fn is_one_access(flags: U2) -> bool {
+    (flags & (ACC_PUBLIC | ACC_PRIVATE | ACC_PROTECTED)).count_ones() <= 1
+}
+
+fn is_field_desc(desc: &[u8]) -> bool {
+    let dims = desc.iter().take_while(|&&c| c == b'[').count();
+    let elem = &desc[dims..];
+    match elem {
+        _ if dims > 255 => false,
+        [b'L', name @ .., b';'] => is_binary_name(name),
+        [c] => b"BCDFIJSZ".contains(c),
+        _ => false,
+    }
+}
+
+//the method descriptor with the legal class names
+fn method_desc(desc: &[u8]) -> Option<(Vec<VType>, Option<VType>)> {
+    let sig = types::parse_method(desc)?;
+    let mut pos = 1;
+    while desc[pos] != b')' {
+        let (_, n) = types::parse_field(&desc[pos..])?;
+        if !is_field_desc(&desc[pos..pos + n]) {
+            return None;
+        }
+        pos += n;
+    }
+    match &desc[pos + 1..] {
+        b"V" => Some(sig),
+        ret if is_field_desc(ret) => Some(sig),
+        _ => None,
+    }
+}
+
+//JVMS 4.2.2
+fn is_unqualified_name(name: &[u8]) -> bool {
+    !name.is_empty() && !name.iter().any(|c| b".;[/".contains(c))
+}
+
+fn is_method_name(name: &[u8]) -> bool {
+    match name {
+        b"<init>" | b"<clinit>" => true,
+        _ => is_unqualified_name(name) && !name.iter().any(|c| b"<>".contains(c)),
+    }
+}
+
+//java/lang/Object, the internal form of JVMS 4.2.1
+fn is_binary_name(name: &[u8]) -> bool {
+    !name.is_empty() && name.split(|&c| c == b'/').all(is_unqualified_name)
+}
+
+//a binary name, or the descriptor of an array
+fn is_class_name(name: &[u8]) -> bool {
+    if name.first() == Some(&b'[') {
+        is_field_desc(name)
+    } else {
+        is_binary_name(name)
+    }
+}
+
+//modified UTF-8, JVMS 4.4.7
+fn is_valid_utf8(bytes: &[u8]) -> bool {
+    let mut i = 0;
+    while i < bytes.len() {
+        let n = match bytes[i] {
+            0x01..=0x7f => 0,
+            0xc0..=0xdf => 1,
+            0xe0..=0xef => 2,
+            _ => return false,
+        };
+        for k in 1..=n {
+            match bytes.get(i + k) {
+                Some(c) if c & 0xc0 == 0x80 => (),
+                _ => return false,
+            }
+        }
+        i += n + 1;
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_names() {
+        assert!(is_class_name(b"java/lang/Object"));
+        assert!(is_class_name(b"[[Ljava/lang/String;"));
+        assert!(is_class_name(b"[I"));
+        assert!(!is_class_name(b"java.lang.Object"));
+        assert!(!is_class_name(b"java//Object"));
+        assert!(!is_class_name(b"[Ljava.lang.String;"));
+        assert!(is_method_name(b"<init>"));
+        assert!(is_method_name(b"lambda$main$0"));
+        assert!(!is_method_name(b"<foo>"));
+        assert!(!is_unqualified_name(b"a/b"));
+    }
+
+    #[test]
+    fn t_utf8() {
+        assert!(is_valid_utf8("caf\u{e9}".as_bytes()));
+        assert!(is_valid_utf8(&[0xc0, 0x80]));
+        assert!(!is_valid_utf8(&[0]));
+        assert!(!is_valid_utf8(&[0xf0, 0x9f, 0x98, 0x80]));
+        assert!(!is_valid_utf8(&[0xe0, 0x80]));
+    }
+}
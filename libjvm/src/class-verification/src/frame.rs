@@ -0,0 +1,41 @@
+use crate::types::VType;
+
+//the types of the locals and the operand stack before an instruction
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    //max_locals entries, long and double followed by Top
+    pub locals: Vec<VType>,
+    pub stack: Vec<VType>,
+    //flagThisUninit, <init> has not called super() or this() yet
+    pub this_uninit: bool,
+}
+
+impl Frame {
+    //the size of the operand stack in slots
+    pub fn stack_size(&self) -> usize {
+        self.stack.iter().map(|t| t.size()).sum()
+    }
+
+    //the object created as 'from' is initialized, it becomes 'to'
+    pub fn init_object(&mut self, from: &VType, to: &VType) {
+        for t in self.locals.iter_mut().chain(self.stack.iter_mut()) {
+            if t == from {
+                *t = to.clone();
+            }
+        }
+    }
+
+    /*
+    Set the local 'idx', a long or double also takes 'idx + 1'.
+    The long or double overwritten partly becomes Top.
+    */
+    pub fn set_local(&mut self, idx: usize, t: VType) {
+        if idx > 0 && self.locals[idx - 1].size() == 2 {
+            self.locals[idx - 1] = VType::Top;
+        }
+        if t.size() == 2 {
+            self.locals[idx + 1] = VType::Top;
+        }
+        self.locals[idx] = t;
+    }
+}
@@ -0,0 +1,7 @@
+//! Verification of class files
+pub mod checker;
+mod frame;
+mod types;
+mod verifier;
+
+pub use verifier::{verify_class, ClassHierarchy, VerifyError};
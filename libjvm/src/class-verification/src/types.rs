@@ -0,0 +1,190 @@
+use classfile::BytesRef;
+use std::fmt;
+use std::sync::Arc;
+
+/*
+The verification types, JVMS 4.10.1.2
+
+long and double take two local variables, the second one is Top,
+on the operand stack they are one entry of size 2.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VType {
+    Top,
+    Int,
+    Float,
+    Long,
+    Double,
+    Null,
+    UninitThis,
+    //created by the 'new' at the offset
+    Uninit(u16),
+    //class name, or the descriptor of an array
+    Ref(BytesRef),
+    //pushed by the jsr to the subroutine at the offset, only in the inference
+    RetAddr(u16),
+}
+
+impl VType {
+    pub fn obj(name: &[u8]) -> VType {
+        VType::Ref(Arc::new(Vec::from(name)))
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            VType::Long | VType::Double => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn is_reference(&self) -> bool {
+        matches!(
+            self,
+            VType::Null | VType::UninitThis | VType::Uninit(_) | VType::Ref(_)
+        )
+    }
+
+    //the component type of an array
+    pub fn component(&self) -> Option<VType> {
+        match self {
+            VType::Ref(name) if name.first() == Some(&b'[') => {
+                let (t, n) = parse_field(&name[1..])?;
+                if n == name.len() - 1 {
+                    Some(t)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
+    //the array of elements of this type
+    pub fn array_of(&self) -> Option<VType> {
+        let mut desc = vec![b'['];
+        match self {
+            VType::Int => desc.push(b'I'),
+            VType::Float => desc.push(b'F'),
+            VType::Long => desc.push(b'J'),
+            VType::Double => desc.push(b'D'),
+            VType::Ref(name) if name.first() == Some(&b'[') => desc.extend_from_slice(name),
+            VType::Ref(name) => {
+                desc.push(b'L');
+                desc.extend_from_slice(name);
+                desc.push(b';');
+            }
+            _ => return None,
+        }
+        Some(VType::Ref(Arc::new(desc)))
+    }
+}
+
+impl fmt::Display for VType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VType::Top => write!(f, "top"),
+            VType::Int => write!(f, "integer"),
+            VType::Float => write!(f, "float"),
+            VType::Long => write!(f, "long"),
+            VType::Double => write!(f, "double"),
+            VType::Null => write!(f, "null"),
+            VType::UninitThis => write!(f, "uninitializedThis"),
+            VType::Uninit(pc) => write!(f, "uninitialized({})", pc),
+            VType::Ref(name) => write!(f, "'{}'", String::from_utf8_lossy(name)),
+            VType::RetAddr(_) => write!(f, "returnAddress"),
+        }
+    }
+}
+
+/*
+Parse a field descriptor at the start of 'desc', returns the type and
+the length consumed. boolean, byte, char and short are int.
+*/
+pub fn parse_field(desc: &[u8]) -> Option<(VType, usize)> {
+    let t = match desc.first()? {
+        b'B' | b'C' | b'I' | b'S' | b'Z' => VType::Int,
+        b'F' => VType::Float,
+        b'J' => VType::Long,
+        b'D' => VType::Double,
+        b'L' => {
+            let end = desc.iter().position(|&c| c == b';')?;
+            if end == 1 {
+                return None;
+            }
+            return Some((VType::obj(&desc[1..end]), end + 1));
+        }
+        b'[' => {
+            let dims = desc.iter().take_while(|&&c| c == b'[').count();
+            if dims > 255 {
+                return None;
+            }
+            let (_, n) = parse_field(&desc[dims..])?;
+            return Some((VType::obj(&desc[..dims + n]), dims + n));
+        }
+        _ => return None,
+    };
+
+    Some((t, 1))
+}
+
+//the argument types and the return type (None for void) of a method descriptor
+pub fn parse_method(desc: &[u8]) -> Option<(Vec<VType>, Option<VType>)> {
+    if desc.first() != Some(&b'(') {
+        return None;
+    }
+
+    let mut args = Vec::new();
+    let mut pos = 1;
+    while *desc.get(pos)? != b')' {
+        let (t, n) = parse_field(&desc[pos..])?;
+        args.push(t);
+        pos += n;
+    }
+    pos += 1;
+
+    let ret = &desc[pos..];
+    if ret == b"V" {
+        return Some((args, None));
+    }
+    match parse_field(ret)? {
+        (t, n) if n == ret.len() => Some((args, Some(t))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_parse_method() {
+        let (args, ret) = parse_method(b"(IJ[[Ljava/lang/String;Z)D").unwrap();
+        assert_eq!(
+            args,
+            vec![
+                VType::Int,
+                VType::Long,
+                VType::obj(b"[[Ljava/lang/String;"),
+                VType::Int
+            ]
+        );
+        assert_eq!(ret, Some(VType::Double));
+        assert_eq!(parse_method(b"()V").unwrap(), (vec![], None));
+        assert!(parse_method(b"(L;)V").is_none());
+        assert!(parse_method(b"(I)VV").is_none());
+    }
+
+    #[test]
+    fn t_component() {
+        assert_eq!(VType::obj(b"[I").component(), Some(VType::Int));
+        assert_eq!(VType::obj(b"[[J").component(), Some(VType::obj(b"[J")));
+        assert_eq!(
+            VType::obj(b"[Ljava/lang/Object;").component(),
+            Some(VType::obj(b"java/lang/Object"))
+        );
+        assert_eq!(
+            VType::obj(b"java/lang/Object").array_of(),
+            Some(VType::obj(b"[Ljava/lang/Object;"))
+        );
+    }
+}
@@ -0,0 +1,2080 @@
+use crate::frame::Frame;
+use crate::types::{self, VType};
+use classfile::attributes::{
+    Code, CodeException, StackMapFrame, Type as AttrType, VerificationTypeInfo,
+};
+use classfile::constant_pool::Type as CpType;
+use classfile::flags::{ACC_INTERFACE, ACC_STATIC};
+use classfile::{BytesRef, ClassFile, MethodInfo, OpCode};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+/*
+Verification by type checking, JVMS 4.10.1
+
+Every method is checked with the frames of its StackMapTable, one pass
+over the instructions in order. The frame before an instruction is the
+frame of the stack map at its offset if there is one, the frame computed
+by the previous instruction must be assignable to it. Branch targets and
+exception handlers must have a stack map frame the current frame is
+assignable to.
+
+Verification by type inference, JVMS 4.10.2
+
+The class files older than 50 have no StackMapTable. The frame before
+every instruction is inferred, the frames reaching an instruction by
+different paths are merged, until none of them changes. A class file of
+version 50 failing the type checking is verified again by inference,
+like the failover of hotspot.
+
+jsr and ret are only accepted by the inference. The frame at a ret goes
+to the instructions after all the jsr calling the subroutine, merged,
+some valid subroutines may be rejected, never an invalid one accepted.
+*/
+
+//the questions about the other classes, answered by the vm
+pub trait ClassHierarchy {
+    fn is_interface(&self, name: &[u8]) -> bool;
+    //'sub' is 'sup' or a subclass of it
+    fn is_subclass(&self, sub: &[u8], sup: &[u8]) -> bool;
+    fn super_class(&self, name: &[u8]) -> Option<BytesRef>;
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifyError {
+    pub class: String,
+    //name and descriptor
+    pub method: String,
+    pub pc: usize,
+    pub op: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}\nException Details:\n  Location:\n    {}.{} @{}: {}",
+            self.reason, self.class, self.method, self.pc, self.op
+        )
+    }
+}
+
+const J_OBJECT: &[u8] = b"java/lang/Object";
+const J_THROWABLE: &[u8] = b"java/lang/Throwable";
+const J_STRING: &[u8] = b"java/lang/String";
+const J_CLASS: &[u8] = b"java/lang/Class";
+const J_METHOD_TYPE: &[u8] = b"java/lang/invoke/MethodType";
+const J_METHOD_HANDLE: &[u8] = b"java/lang/invoke/MethodHandle";
+const J_CLONEABLE: &[u8] = b"java/lang/Cloneable";
+const J_SERIALIZABLE: &[u8] = b"java/io/Serializable";
+
+//verify the methods of 'cf', by type checking since 50, by type inference before
+pub fn verify_class(cf: &ClassFile, hierarchy: &dyn ClassHierarchy) -> Result<(), VerifyError> {
+    let mut ctx = Context {
+        cf,
+        hierarchy,
+        this_name: BytesRef::default(),
+        super_name: None,
+    };
+    let err = |reason: &str| VerifyError {
+        class: String::new(),
+        method: String::new(),
+        pc: 0,
+        op: "",
+        reason: reason.to_string(),
+    };
+    ctx.this_name = ctx
+        .class_name(cf.this_class)
+        .cloned()
+        .ok_or_else(|| err("Bad this_class"))?;
+    if cf.super_class != 0 {
+        ctx.super_name = Some(
+            ctx.class_name(cf.super_class)
+                .cloned()
+                .ok_or_else(|| err("Bad super_class"))?,
+        );
+    }
+
+    let infer = cf.version.major < 50;
+    match verify_methods(&ctx, infer) {
+        Err(_) if cf.version.major == 50 => verify_methods(&ctx, true),
+        r => r,
+    }
+}
+
+fn verify_methods(ctx: &Context, infer: bool) -> Result<(), VerifyError> {
+    for method in ctx.cf.methods.iter() {
+        let code = method.attrs.iter().find_map(|it| match it {
+            AttrType::Code(code) => Some(code),
+            _ => None,
+        });
+        if let Some(code) = code {
+            MethodVerifier::new(ctx, method, code, infer)?.verify()?;
+        }
+    }
+
+    Ok(())
+}
+
+struct Context<'a> {
+    cf: &'a ClassFile,
+    hierarchy: &'a dyn ClassHierarchy,
+    this_name: BytesRef,
+    super_name: Option<BytesRef>,
+}
+
+impl<'a> Context<'a> {
+    fn utf8(&self, idx: u16) -> Option<&BytesRef> {
+        match self.cf.cp.get(idx as usize)? {
+            CpType::Utf8 { bytes } => Some(bytes),
+            _ => None,
+        }
+    }
+
+    fn class_name(&self, idx: u16) -> Option<&BytesRef> {
+        match self.cf.cp.get(idx as usize)? {
+            CpType::Class { name_index } => self.utf8(*name_index),
+            _ => None,
+        }
+    }
+
+    fn name_and_type(&self, idx: u16) -> Option<(&BytesRef, &BytesRef)> {
+        match self.cf.cp.get(idx as usize)? {
+            CpType::NameAndType {
+                name_index,
+                desc_index,
+            } => Some((self.utf8(*name_index)?, self.utf8(*desc_index)?)),
+            _ => None,
+        }
+    }
+
+    //(class name, name, descriptor) of a field or method ref
+    fn member(&self, idx: u16) -> Option<(&BytesRef, &BytesRef, &BytesRef)> {
+        match self.cf.cp.get(idx as usize)? {
+            CpType::FieldRef {
+                class_index,
+                name_and_type_index,
+            }
+            | CpType::MethodRef {
+                class_index,
+                name_and_type_index,
+            }
+            | CpType::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            } => {
+                let cls = self.class_name(*class_index)?;
+                let (name, desc) = self.name_and_type(*name_and_type_index)?;
+                Some((cls, name, desc))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_interface(&self, name: &[u8]) -> bool {
+        if name == self.this_name.as_slice() {
+            self.cf.acc_flags & ACC_INTERFACE != 0
+        } else {
+            self.hierarchy.is_interface(name)
+        }
+    }
+
+    fn super_class(&self, name: &[u8]) -> Option<BytesRef> {
+        if name == self.this_name.as_slice() {
+            self.super_name.clone()
+        } else {
+            self.hierarchy.super_class(name)
+        }
+    }
+
+    fn is_subclass(&self, sub: &[u8], sup: &[u8]) -> bool {
+        if sub == sup {
+            return true;
+        }
+
+        //the class being verified may not be known by the vm yet
+        if sub == self.this_name.as_slice() {
+            match &self.super_name {
+                Some(name) => self.is_subclass(name, sup),
+                None => false,
+            }
+        } else {
+            self.hierarchy.is_subclass(sub, sup)
+        }
+    }
+
+    //isAssignable, JVMS 4.10.1.2
+    fn is_assignable(&self, from: &VType, to: &VType) -> bool {
+        if from == to {
+            return true;
+        }
+
+        match (from, to) {
+            (_, VType::Top) => true,
+            (VType::Null, VType::Ref(_)) => true,
+            (VType::Ref(from), VType::Ref(to)) => self.is_ref_assignable(from, to),
+            _ => false,
+        }
+    }
+
+    fn is_ref_assignable(&self, from: &[u8], to: &[u8]) -> bool {
+        if from == to || to == J_OBJECT {
+            return true;
+        }
+
+        let from_array = from.first() == Some(&b'[');
+        if to.first() == Some(&b'[') {
+            if !from_array {
+                return false;
+            }
+            //the components of different primitive arrays are not assignable
+            return match (VType::obj(from).component(), VType::obj(to).component()) {
+                (Some(VType::Ref(a)), Some(VType::Ref(b))) => self.is_ref_assignable(&a, &b),
+                _ => false,
+            };
+        }
+
+        if from_array {
+            return to == J_CLONEABLE || to == J_SERIALIZABLE;
+        }
+
+        //an interface is treated like Object, checked at runtime
+        self.is_interface(to) || self.is_subclass(from, to)
+    }
+
+    //the type of a value reaching a join by two paths, None if they don't merge
+    fn merge_type(&self, a: &VType, b: &VType) -> Option<VType> {
+        match (a, b) {
+            _ if a == b => Some(a.clone()),
+            (VType::Null, VType::Ref(_)) => Some(b.clone()),
+            (VType::Ref(_), VType::Null) => Some(a.clone()),
+            (VType::Ref(a), VType::Ref(b)) => Some(VType::Ref(self.common_super(a, b))),
+            _ => None,
+        }
+    }
+
+    //the first common super class, Object if any is an interface
+    fn common_super(&self, a: &BytesRef, b: &BytesRef) -> BytesRef {
+        let object = || Arc::new(J_OBJECT.to_vec());
+        match (
+            VType::Ref(a.clone()).component(),
+            VType::Ref(b.clone()).component(),
+        ) {
+            (Some(VType::Ref(ca)), Some(VType::Ref(cb))) => {
+                return match VType::Ref(self.common_super(&ca, &cb)).array_of() {
+                    Some(VType::Ref(desc)) => desc,
+                    _ => object(),
+                };
+            }
+            (None, None) => (),
+            _ => return object(),
+        }
+
+        if self.is_interface(a) || self.is_interface(b) {
+            return object();
+        }
+
+        let mut cur = Some(a.clone());
+        while let Some(it) = cur {
+            if self.is_subclass(b, &it) {
+                return it;
+            }
+            cur = self.super_class(&it);
+        }
+
+        object()
+    }
+}
+
+struct Handler {
+    start: usize,
+    end: usize,
+    pc: usize,
+    catch: VType,
+}
+
+struct MethodVerifier<'a> {
+    ctx: &'a Context<'a>,
+    method: &'a MethodInfo,
+    code: &'a [u8],
+    exceptions: &'a [CodeException],
+    max_stack: usize,
+    max_locals: usize,
+    is_init: bool,
+    ret: Option<VType>,
+    args: Vec<VType>,
+
+    //the start of every instruction
+    starts: Vec<bool>,
+    pcs: Vec<usize>,
+    maps: Vec<Option<Frame>>,
+    handlers: Vec<Handler>,
+
+    //the instruction being verified
+    pc: usize,
+
+    //by inference, the frames going to the branch targets and handlers
+    infer: bool,
+    targets: RefCell<Vec<(usize, Frame)>>,
+    //(the start of the subroutine, the instruction after the jsr)
+    subroutines: Vec<(usize, usize)>,
+}
+
+impl<'a> MethodVerifier<'a> {
+    fn new(
+        ctx: &'a Context<'a>,
+        method: &'a MethodInfo,
+        code: &'a Code,
+        infer: bool,
+    ) -> Result<Self, VerifyError> {
+        let mut v = MethodVerifier {
+            ctx,
+            method,
+            code: code.code.as_slice(),
+            exceptions: code.exceptions.as_slice(),
+            max_stack: code.max_stack as usize,
+            max_locals: code.max_locals as usize,
+            is_init: false,
+            ret: None,
+            args: Vec::new(),
+            starts: vec![false; code.code.len()],
+            pcs: Vec::new(),
+            maps: vec![None; code.code.len()],
+            handlers: Vec::new(),
+            pc: 0,
+            infer,
+            targets: RefCell::new(Vec::new()),
+            subroutines: Vec::new(),
+        };
+
+        let name = ctx.utf8(method.name_index);
+        let desc = ctx.utf8(method.desc_index);
+        match (name, desc.and_then(|desc| types::parse_method(desc))) {
+            (Some(name), Some((args, ret))) => {
+                v.is_init = name.as_slice() == b"<init>";
+                v.args = args;
+                v.ret = ret;
+            }
+            _ => return Err(v.err("Illegal method signature".to_string())),
+        }
+
+        if v.code.is_empty() || v.code.len() > 65535 {
+            return Err(v.err("Invalid method Code length".to_string()));
+        }
+
+        Ok(v)
+    }
+
+    fn verify(&mut self) -> Result<(), VerifyError> {
+        self.decode()?;
+        self.load_handlers()?;
+
+        let (locals, this_uninit) = self.initial_locals();
+        let frame = Frame {
+            locals: self.expand(&locals, "Arguments can't fit into locals")?,
+            stack: Vec::new(),
+            this_uninit,
+        };
+        if self.infer {
+            return self.infer_frames(frame);
+        }
+        self.load_stack_map(locals)?;
+
+        let mut cur = Some(frame);
+        for i in 0..self.pcs.len() {
+            let pc = self.pcs[i];
+            self.pc = pc;
+
+            let frame = match (self.maps[pc].clone(), cur.take()) {
+                (Some(map), Some(frame)) => {
+                    self.check_frame(&frame, &map, "Instruction type does not match stack map")?;
+                    map
+                }
+                (Some(map), None) => map,
+                (None, Some(frame)) => frame,
+                (None, None) => return Err(self.err("Expecting a stack map frame".to_string())),
+            };
+
+            self.check_handlers(&frame)?;
+            cur = self.execute(frame)?;
+        }
+
+        if cur.is_some() {
+            self.pc = *self.pcs.last().unwrap();
+            return Err(self.err("Falling off the end of the code".to_string()));
+        }
+
+        Ok(())
+    }
+
+    fn infer_frames(&mut self, frame: Frame) -> Result<(), VerifyError> {
+        for &pc in self.pcs.iter() {
+            let offset = match opcode(self.code[pc]) {
+                OpCode::jsr => self.i2(pc + 1),
+                OpCode::jsr_w => self.i4(pc + 1),
+                _ => continue,
+            };
+            let start = pc as i64 + offset;
+            if start >= 0 {
+                let next = pc + insn_len(self.code, pc).unwrap();
+                self.subroutines.push((start as usize, next));
+            }
+        }
+
+        let mut frames: Vec<Option<Frame>> = vec![None; self.code.len()];
+        let mut changed = BTreeSet::new();
+        frames[0] = Some(frame);
+        changed.insert(0);
+
+        while let Some(pc) = changed.pop_first() {
+            self.pc = pc;
+            let frame = frames[pc].clone().unwrap();
+            self.check_handlers(&frame)?;
+
+            let next = self.execute(frame)?;
+            let mut targets = self.targets.take();
+            if let Some(next) = next {
+                targets.push((pc + insn_len(self.code, pc).unwrap(), next));
+            }
+
+            for (target, frame) in targets {
+                if target >= self.code.len() {
+                    return Err(self.err("Falling off the end of the code".to_string()));
+                }
+                let frame = match &frames[target] {
+                    Some(old) => {
+                        let merged = self.merge(old, &frame, target)?;
+                        if &merged == old {
+                            continue;
+                        }
+                        merged
+                    }
+                    None => frame,
+                };
+                frames[target] = Some(frame);
+                changed.insert(target);
+            }
+        }
+
+        Ok(())
+    }
+
+    //the frame at a join of the control flow, the locals not merged become Top
+    fn merge(&self, a: &Frame, b: &Frame, target: usize) -> Result<Frame, VerifyError> {
+        if a.stack.len() != b.stack.len() {
+            let msg = format!("Inconsistent stack height at branch target {}", target);
+            return Err(self.err(msg));
+        }
+
+        let mut stack = Vec::with_capacity(a.stack.len());
+        for (x, y) in a.stack.iter().zip(b.stack.iter()) {
+            match self.ctx.merge_type(x, y) {
+                Some(t) => stack.push(t),
+                None => {
+                    let msg = format!(
+                        "Mismatched stack types at branch target {}: Type {} and {}",
+                        target, x, y
+                    );
+                    return Err(self.err(msg));
+                }
+            }
+        }
+        let locals = a
+            .locals
+            .iter()
+            .zip(b.locals.iter())
+            .map(|(x, y)| self.ctx.merge_type(x, y).unwrap_or(VType::Top))
+            .collect();
+
+        Ok(Frame {
+            locals,
+            stack,
+            this_uninit: a.this_uninit || b.this_uninit,
+        })
+    }
+
+    fn err(&self, reason: String) -> VerifyError {
+        let name = self
+            .ctx
+            .utf8(self.method.name_index)
+            .map(|v| String::from_utf8_lossy(v).to_string())
+            .unwrap_or_default();
+        let desc = self
+            .ctx
+            .utf8(self.method.desc_index)
+            .map(|v| String::from_utf8_lossy(v).to_string())
+            .unwrap_or_default();
+        let op = match self.code.get(self.pc) {
+            Some(&b) if b <= 201 => opcode(b).into(),
+            _ => "unknown",
+        };
+
+        VerifyError {
+            class: String::from_utf8_lossy(&self.ctx.this_name).replace('/', "."),
+            method: format!("{}{}", name, desc),
+            pc: self.pc,
+            op,
+            reason,
+        }
+    }
+
+    //find the instructions, check the opcodes and operands length
+    fn decode(&mut self) -> Result<(), VerifyError> {
+        let mut pc = 0;
+        while pc < self.code.len() {
+            self.pc = pc;
+            let n = insn_len(self.code, pc).map_err(|e| self.err(e.to_string()))?;
+            if pc + n > self.code.len() {
+                return Err(self.err("Code truncated".to_string()));
+            }
+            self.starts[pc] = true;
+            self.pcs.push(pc);
+            pc += n;
+        }
+
+        Ok(())
+    }
+
+    fn load_handlers(&mut self) -> Result<(), VerifyError> {
+        let code = self.code;
+        for it in self.exceptions.iter() {
+            let (start, end, pc) = (
+                it.start_pc as usize,
+                it.end_pc as usize,
+                it.handler_pc as usize,
+            );
+            let is_start = |pc: usize| pc < code.len() && self.starts[pc];
+            if start >= end || !is_start(start) || !(end == code.len() || is_start(end)) {
+                return Err(self.err("Illegal exception table range".to_string()));
+            }
+            if !is_start(pc) {
+                return Err(self.err("Illegal exception table handler".to_string()));
+            }
+
+            let catch = if it.catch_type == 0 {
+                VType::obj(J_THROWABLE)
+            } else {
+                match self.ctx.class_name(it.catch_type) {
+                    Some(name) => VType::Ref(name.clone()),
+                    None => return Err(self.err("Bad catch type index".to_string())),
+                }
+            };
+            if !self.ctx.is_assignable(&catch, &VType::obj(J_THROWABLE)) {
+                let msg = format!(
+                    "Catch type is not a subclass of Throwable in exception handler {}",
+                    pc
+                );
+                return Err(self.err(msg));
+            }
+
+            self.handlers.push(Handler {
+                start,
+                end,
+                pc,
+                catch,
+            });
+        }
+
+        Ok(())
+    }
+
+    //the locals of the method entry, not expanded
+    fn initial_locals(&self) -> (Vec<VType>, bool) {
+        let mut locals = Vec::with_capacity(self.args.len() + 1);
+        let mut this_uninit = false;
+        if self.method.acc_flags & ACC_STATIC == 0 {
+            if self.is_init && self.ctx.this_name.as_slice() != J_OBJECT {
+                locals.push(VType::UninitThis);
+                this_uninit = true;
+            } else {
+                locals.push(VType::Ref(self.ctx.this_name.clone()));
+            }
+        }
+        locals.extend(self.args.iter().cloned());
+
+        (locals, this_uninit)
+    }
+
+    //long and double followed by Top, the rest filled with Top
+    fn expand(&self, compact: &[VType], msg: &str) -> Result<Vec<VType>, VerifyError> {
+        let mut locals = Vec::with_capacity(self.max_locals);
+        for t in compact {
+            locals.push(t.clone());
+            if t.size() == 2 {
+                locals.push(VType::Top);
+            }
+        }
+
+        if locals.len() > self.max_locals {
+            return Err(self.err(msg.to_string()));
+        }
+        locals.resize(self.max_locals, VType::Top);
+
+        Ok(locals)
+    }
+
+    fn load_stack_map(&mut self, mut locals: Vec<VType>) -> Result<(), VerifyError> {
+        let entries = match self.method.get_stack_map_table() {
+            Some(entries) => entries,
+            None => return Ok(()),
+        };
+
+        let mut offset = 0;
+        for (i, entry) in entries.iter().enumerate() {
+            let (delta, stack) = match entry {
+                StackMapFrame::Same { offset_delta, .. }
+                | StackMapFrame::SameExtended { offset_delta, .. } => (*offset_delta, vec![]),
+                StackMapFrame::SameLocals1StackItem {
+                    offset_delta,
+                    stack,
+                    ..
+                }
+                | StackMapFrame::SameLocals1StackItemExtended {
+                    offset_delta,
+                    stack,
+                    ..
+                } => (*offset_delta, vec![self.vtype(&stack[0])?]),
+                StackMapFrame::Chop { tag, offset_delta } => {
+                    let k = 251 - *tag as usize;
+                    if k > locals.len() {
+                        return Err(self.err("StackMapTable error: bad chop frame".to_string()));
+                    }
+                    locals.truncate(locals.len() - k);
+                    (*offset_delta, vec![])
+                }
+                StackMapFrame::Append {
+                    offset_delta,
+                    locals: appended,
+                    ..
+                } => {
+                    for it in appended {
+                        locals.push(self.vtype(it)?);
+                    }
+                    (*offset_delta, vec![])
+                }
+                StackMapFrame::Full {
+                    offset_delta,
+                    locals: full,
+                    stack,
+                    ..
+                } => {
+                    locals.clear();
+                    for it in full {
+                        locals.push(self.vtype(it)?);
+                    }
+                    let mut v = Vec::with_capacity(stack.len());
+                    for it in stack {
+                        v.push(self.vtype(it)?);
+                    }
+                    (*offset_delta, v)
+                }
+                StackMapFrame::Reserved(_) => {
+                    return Err(self.err("StackMapTable error: reserved frame type".to_string()))
+                }
+            };
+
+            offset = if i == 0 {
+                delta as usize
+            } else {
+                offset + delta as usize + 1
+            };
+            if offset >= self.code.len() || !self.starts[offset] {
+                let msg = format!("StackMapTable error: bad offset {}", offset);
+                return Err(self.err(msg));
+            }
+
+            let frame = Frame {
+                locals: self.expand(&locals, "StackMapTable error: local size exceeded")?,
+                this_uninit: locals.contains(&VType::UninitThis),
+                stack,
+            };
+            if frame.stack_size() > self.max_stack {
+                return Err(self.err("StackMapTable error: stack size exceeded".to_string()));
+            }
+            self.maps[offset] = Some(frame);
+        }
+
+        Ok(())
+    }
+
+    fn vtype(&self, info: &VerificationTypeInfo) -> Result<VType, VerifyError> {
+        let t = match info {
+            VerificationTypeInfo::Top => VType::Top,
+            VerificationTypeInfo::Integer => VType::Int,
+            VerificationTypeInfo::Float => VType::Float,
+            VerificationTypeInfo::Long => VType::Long,
+            VerificationTypeInfo::Double => VType::Double,
+            VerificationTypeInfo::Null => VType::Null,
+            VerificationTypeInfo::UninitializedThis => VType::UninitThis,
+            VerificationTypeInfo::Object { cpool_index } => match self.ctx.class_name(*cpool_index)
+            {
+                Some(name) => VType::Ref(name.clone()),
+                None => {
+                    let msg = format!("StackMapTable error: bad class index {}", cpool_index);
+                    return Err(self.err(msg));
+                }
+            },
+            VerificationTypeInfo::Uninitialized { offset } => {
+                let pc = *offset as usize;
+                if pc >= self.code.len() || !self.starts[pc] || self.code[pc] != OpCode::new as u8 {
+                    let msg = format!("StackMapTable error: bad uninitialized offset {}", pc);
+                    return Err(self.err(msg));
+                }
+                VType::Uninit(*offset)
+            }
+        };
+
+        Ok(t)
+    }
+
+    //frameIsAssignable, JVMS 4.10.1.4
+    fn check_frame(&self, from: &Frame, to: &Frame, msg: &str) -> Result<(), VerifyError> {
+        if from.stack.len() != to.stack.len() {
+            let msg = format!("{}: current frame's stack size doesn't match stackmap", msg);
+            return Err(self.err(msg));
+        }
+
+        for (i, (a, b)) in from.locals.iter().zip(to.locals.iter()).enumerate() {
+            if !self.ctx.is_assignable(a, b) {
+                let msg = format!(
+                    "{}: Type {} (current frame, locals[{}]) is not assignable to {} (stack map, locals[{}])",
+                    msg, a, i, b, i
+                );
+                return Err(self.err(msg));
+            }
+        }
+
+        for (i, (a, b)) in from.stack.iter().zip(to.stack.iter()).enumerate() {
+            if !self.ctx.is_assignable(a, b) {
+                let msg = format!(
+                    "{}: Type {} (current frame, stack[{}]) is not assignable to {} (stack map, stack[{}])",
+                    msg, a, i, b, i
+                );
+                return Err(self.err(msg));
+            }
+        }
+
+        if from.this_uninit && !to.this_uninit {
+            let msg = format!("{}: flags of current frame are not assignable", msg);
+            return Err(self.err(msg));
+        }
+
+        Ok(())
+    }
+
+    //the handlers covering the instruction get the locals before it
+    fn check_handlers(&self, frame: &Frame) -> Result<(), VerifyError> {
+        for h in self.handlers.iter() {
+            if self.pc < h.start || self.pc >= h.end {
+                continue;
+            }
+
+            let ex_frame = Frame {
+                locals: frame.locals.clone(),
+                stack: vec![h.catch.clone()],
+                this_uninit: frame.this_uninit,
+            };
+            if self.infer {
+                self.targets.borrow_mut().push((h.pc, ex_frame));
+                continue;
+            }
+
+            let target = match &self.maps[h.pc] {
+                Some(target) => target,
+                None => {
+                    let msg = format!("Expecting a stackmap frame at branch target {}", h.pc);
+                    return Err(self.err(msg));
+                }
+            };
+            let msg = format!("Bad exception handler {}", h.pc);
+            self.check_frame(&ex_frame, target, &msg)?;
+        }
+
+        Ok(())
+    }
+
+    fn branch(&self, frame: &Frame, offset: i64) -> Result<(), VerifyError> {
+        let target = self.pc as i64 + offset;
+        if target < 0 || target >= self.code.len() as i64 || !self.starts[target as usize] {
+            return Err(self.err("Illegal target of jump or branch".to_string()));
+        }
+
+        if self.infer {
+            self.targets
+                .borrow_mut()
+                .push((target as usize, frame.clone()));
+            return Ok(());
+        }
+
+        match &self.maps[target as usize] {
+            Some(map) => {
+                let msg = format!("Inconsistent stackmap frames at branch target {}", target);
+                self.check_frame(frame, map, &msg)
+            }
+            None => {
+                let msg = format!("Expecting a stackmap frame at branch target {}", target);
+                Err(self.err(msg))
+            }
+        }
+    }
+
+    fn push(&self, frame: &mut Frame, t: VType) -> Result<(), VerifyError> {
+        if frame.stack_size() + t.size() > self.max_stack {
+            return Err(self.err("Operand stack overflow".to_string()));
+        }
+        frame.stack.push(t);
+        Ok(())
+    }
+
+    fn pop_any(&self, frame: &mut Frame) -> Result<VType, VerifyError> {
+        frame
+            .stack
+            .pop()
+            .ok_or_else(|| self.err("Operand stack underflow".to_string()))
+    }
+
+    fn pop(&self, frame: &mut Frame, expected: &VType) -> Result<VType, VerifyError> {
+        let t = self.pop_any(frame)?;
+        if !self.ctx.is_assignable(&t, expected) {
+            let msg = format!(
+                "Bad type on operand stack: Type {} is not assignable to {}",
+                t, expected
+            );
+            return Err(self.err(msg));
+        }
+        Ok(t)
+    }
+
+    fn pop_cat1(&self, frame: &mut Frame) -> Result<VType, VerifyError> {
+        let t = self.pop_any(frame)?;
+        if t.size() != 1 {
+            let msg = format!("Bad type on operand stack: Type {} is category 2", t);
+            return Err(self.err(msg));
+        }
+        Ok(t)
+    }
+
+    //a reference, initialized or not
+    fn pop_ref(&self, frame: &mut Frame) -> Result<VType, VerifyError> {
+        let t = self.pop_any(frame)?;
+        if !t.is_reference() {
+            let msg = format!("Bad type on operand stack: Type {} is not a reference", t);
+            return Err(self.err(msg));
+        }
+        Ok(t)
+    }
+
+    //an initialized object, or null
+    fn pop_obj(&self, frame: &mut Frame) -> Result<VType, VerifyError> {
+        self.pop(frame, &VType::obj(J_OBJECT))
+    }
+
+    //an array with one of the descriptors 'accepted', or null
+    fn pop_array(&self, frame: &mut Frame, accepted: &[&[u8]]) -> Result<VType, VerifyError> {
+        let t = self.pop_any(frame)?;
+        let ok = match &t {
+            VType::Null => true,
+            VType::Ref(name) if accepted.is_empty() => name.first() == Some(&b'['),
+            VType::Ref(name) => accepted.contains(&name.as_slice()),
+            _ => false,
+        };
+        if !ok {
+            let msg = format!("Bad type on operand stack: Type {} is not a valid array", t);
+            return Err(self.err(msg));
+        }
+        Ok(t)
+    }
+
+    fn load(&self, frame: &mut Frame, idx: usize, expected: &VType) -> Result<(), VerifyError> {
+        if idx + expected.size() > self.max_locals {
+            return Err(self.err("Illegal local variable number".to_string()));
+        }
+
+        let t = frame.locals[idx].clone();
+        let ok = match expected {
+            VType::Ref(_) => t.is_reference(),
+            _ => &t == expected,
+        };
+        if !ok {
+            let msg = format!(
+                "Bad local variable type: Type {} (current frame, locals[{}]) is not assignable to {}",
+                t, idx, expected
+            );
+            return Err(self.err(msg));
+        }
+
+        self.push(frame, t)
+    }
+
+    fn store(&self, frame: &mut Frame, idx: usize, expected: &VType) -> Result<(), VerifyError> {
+        let t = match expected {
+            //astore takes the returnAddress of jsr too
+            VType::Ref(_) => match self.pop_any(frame)? {
+                t @ VType::RetAddr(_) => t,
+                t if t.is_reference() => t,
+                t => {
+                    let msg = format!("Bad type on operand stack: Type {} is not a reference", t);
+                    return Err(self.err(msg));
+                }
+            },
+            _ => self.pop(frame, expected)?,
+        };
+        if idx + t.size() > self.max_locals {
+            return Err(self.err("Illegal local variable number".to_string()));
+        }
+
+        frame.set_local(idx, t);
+        Ok(())
+    }
+
+    fn u1(&self, pc: usize) -> u8 {
+        self.code[pc]
+    }
+
+    fn u2(&self, pc: usize) -> u16 {
+        u16::from_be_bytes([self.code[pc], self.code[pc + 1]])
+    }
+
+    fn i2(&self, pc: usize) -> i64 {
+        self.u2(pc) as i16 as i64
+    }
+
+    fn i4(&self, pc: usize) -> i64 {
+        read_i4(self.code, pc).unwrap() as i64
+    }
+
+    //the frame after the instruction at self.pc, None if it can't fall through
+    fn execute(&self, mut frame: Frame) -> Result<Option<Frame>, VerifyError> {
+        let pc = self.pc;
+        let op = opcode(self.code[pc]);
+        let f = &mut frame;
+
+        let int = VType::Int;
+        let float = VType::Float;
+        let long = VType::Long;
+        let double = VType::Double;
+        let obj = VType::obj(J_OBJECT);
+
+        match op {
+            OpCode::nop => (),
+            OpCode::aconst_null => self.push(f, VType::Null)?,
+            OpCode::iconst_m1
+            | OpCode::iconst_0
+            | OpCode::iconst_1
+            | OpCode::iconst_2
+            | OpCode::iconst_3
+            | OpCode::iconst_4
+            | OpCode::iconst_5
+            | OpCode::bipush
+            | OpCode::sipush => self.push(f, int)?,
+            OpCode::lconst_0 | OpCode::lconst_1 => self.push(f, long)?,
+            OpCode::fconst_0 | OpCode::fconst_1 | OpCode::fconst_2 => self.push(f, float)?,
+            OpCode::dconst_0 | OpCode::dconst_1 => self.push(f, double)?,
+            OpCode::ldc | OpCode::ldc_w | OpCode::ldc2_w => {
+                let idx = if op == OpCode::ldc {
+                    self.u1(pc + 1) as u16
+                } else {
+                    self.u2(pc + 1)
+                };
+                let t = match (op, self.ctx.cf.cp.get(idx as usize)) {
+                    (OpCode::ldc2_w, Some(CpType::Long { .. })) => long,
+                    (OpCode::ldc2_w, Some(CpType::Double { .. })) => double,
+                    (OpCode::ldc2_w, _) => {
+                        return Err(self.err("Invalid index in ldc2_w".to_string()))
+                    }
+                    (_, Some(CpType::Integer { .. })) => int,
+                    (_, Some(CpType::Float { .. })) => float,
+                    (_, Some(CpType::String { .. })) => VType::obj(J_STRING),
+                    (_, Some(CpType::Class { .. })) => VType::obj(J_CLASS),
+                    (_, Some(CpType::MethodType { .. })) => VType::obj(J_METHOD_TYPE),
+                    (_, Some(CpType::MethodHandle { .. })) => VType::obj(J_METHOD_HANDLE),
+                    _ => return Err(self.err("Invalid index in ldc".to_string())),
+                };
+                self.push(f, t)?;
+            }
+
+            OpCode::iload => self.load(f, self.u1(pc + 1) as usize, &int)?,
+            OpCode::lload => self.load(f, self.u1(pc + 1) as usize, &long)?,
+            OpCode::fload => self.load(f, self.u1(pc + 1) as usize, &float)?,
+            OpCode::dload => self.load(f, self.u1(pc + 1) as usize, &double)?,
+            OpCode::aload => self.load(f, self.u1(pc + 1) as usize, &obj)?,
+            OpCode::iload_0 | OpCode::iload_1 | OpCode::iload_2 | OpCode::iload_3 => {
+                self.load(f, (op as u8 - OpCode::iload_0 as u8) as usize, &int)?
+            }
+            OpCode::lload_0 | OpCode::lload_1 | OpCode::lload_2 | OpCode::lload_3 => {
+                self.load(f, (op as u8 - OpCode::lload_0 as u8) as usize, &long)?
+            }
+            OpCode::fload_0 | OpCode::fload_1 | OpCode::fload_2 | OpCode::fload_3 => {
+                self.load(f, (op as u8 - OpCode::fload_0 as u8) as usize, &float)?
+            }
+            OpCode::dload_0 | OpCode::dload_1 | OpCode::dload_2 | OpCode::dload_3 => {
+                self.load(f, (op as u8 - OpCode::dload_0 as u8) as usize, &double)?
+            }
+            OpCode::aload_0 | OpCode::aload_1 | OpCode::aload_2 | OpCode::aload_3 => {
+                self.load(f, (op as u8 - OpCode::aload_0 as u8) as usize, &obj)?
+            }
+            OpCode::iaload | OpCode::baload | OpCode::caload | OpCode::saload => {
+                let accepted: &[&[u8]] = match op {
+                    OpCode::iaload => &[b"[I"],
+                    OpCode::baload => &[b"[B", b"[Z"],
+                    OpCode::caload => &[b"[C"],
+                    _ => &[b"[S"],
+                };
+                self.pop(f, &int)?;
+                self.pop_array(f, accepted)?;
+                self.push(f, int)?;
+            }
+            OpCode::laload => {
+                self.pop(f, &int)?;
+                self.pop_array(f, &[b"[J"])?;
+                self.push(f, long)?;
+            }
+            OpCode::faload => {
+                self.pop(f, &int)?;
+                self.pop_array(f, &[b"[F"])?;
+                self.push(f, float)?;
+            }
+            OpCode::daload => {
+                self.pop(f, &int)?;
+                self.pop_array(f, &[b"[D"])?;
+                self.push(f, double)?;
+            }
+            OpCode::aaload => {
+                self.pop(f, &int)?;
+                let t = match self.pop_array(f, &[])? {
+                    VType::Null => VType::Null,
+                    t => match t.component() {
+                        Some(c) if c.is_reference() => c,
+                        _ => {
+                            return Err(self.err("Bad type on operand stack in aaload".to_string()))
+                        }
+                    },
+                };
+                self.push(f, t)?;
+            }
+
+            OpCode::istore => self.store(f, self.u1(pc + 1) as usize, &int)?,
+            OpCode::lstore => self.store(f, self.u1(pc + 1) as usize, &long)?,
+            OpCode::fstore => self.store(f, self.u1(pc + 1) as usize, &float)?,
+            OpCode::dstore => self.store(f, self.u1(pc + 1) as usize, &double)?,
+            OpCode::astore => self.store(f, self.u1(pc + 1) as usize, &obj)?,
+            OpCode::istore_0 | OpCode::istore_1 | OpCode::istore_2 | OpCode::istore_3 => {
+                self.store(f, (op as u8 - OpCode::istore_0 as u8) as usize, &int)?
+            }
+            OpCode::lstore_0 | OpCode::lstore_1 | OpCode::lstore_2 | OpCode::lstore_3 => {
+                self.store(f, (op as u8 - OpCode::lstore_0 as u8) as usize, &long)?
+            }
+            OpCode::fstore_0 | OpCode::fstore_1 | OpCode::fstore_2 | OpCode::fstore_3 => {
+                self.store(f, (op as u8 - OpCode::fstore_0 as u8) as usize, &float)?
+            }
+            OpCode::dstore_0 | OpCode::dstore_1 | OpCode::dstore_2 | OpCode::dstore_3 => {
+                self.store(f, (op as u8 - OpCode::dstore_0 as u8) as usize, &double)?
+            }
+            OpCode::astore_0 | OpCode::astore_1 | OpCode::astore_2 | OpCode::astore_3 => {
+                self.store(f, (op as u8 - OpCode::astore_0 as u8) as usize, &obj)?
+            }
+            OpCode::iastore | OpCode::bastore | OpCode::castore | OpCode::sastore => {
+                let accepted: &[&[u8]] = match op {
+                    OpCode::iastore => &[b"[I"],
+                    OpCode::bastore => &[b"[B", b"[Z"],
+                    OpCode::castore => &[b"[C"],
+                    _ => &[b"[S"],
+                };
+                self.pop(f, &int)?;
+                self.pop(f, &int)?;
+                self.pop_array(f, accepted)?;
+            }
+            OpCode::lastore => {
+                self.pop(f, &long)?;
+                self.pop(f, &int)?;
+                self.pop_array(f, &[b"[J"])?;
+            }
+            OpCode::fastore => {
+                self.pop(f, &float)?;
+                self.pop(f, &int)?;
+                self.pop_array(f, &[b"[F"])?;
+            }
+            OpCode::dastore => {
+                self.pop(f, &double)?;
+                self.pop(f, &int)?;
+                self.pop_array(f, &[b"[D"])?;
+            }
+            //the type of the value is checked at runtime
+            OpCode::aastore => {
+                self.pop_obj(f)?;
+                self.pop(f, &int)?;
+                match self.pop_array(f, &[])? {
+                    VType::Null => (),
+                    t => {
+                        if !t.component().map(|c| c.is_reference()).unwrap_or(false) {
+                            let msg = "Bad type on operand stack in aastore".to_string();
+                            return Err(self.err(msg));
+                        }
+                    }
+                }
+            }
+
+            OpCode::pop => {
+                self.pop_cat1(f)?;
+            }
+            OpCode::pop2 => {
+                if self.pop_any(f)?.size() == 1 {
+                    self.pop_cat1(f)?;
+                }
+            }
+            OpCode::dup => {
+                let v1 = self.pop_cat1(f)?;
+                self.push(f, v1.clone())?;
+                self.push(f, v1)?;
+            }
+            OpCode::dup_x1 => {
+                let v1 = self.pop_cat1(f)?;
+                let v2 = self.pop_cat1(f)?;
+                self.push_all(f, &[&v1, &v2, &v1])?;
+            }
+            OpCode::dup_x2 => {
+                let v1 = self.pop_cat1(f)?;
+                let v2 = self.pop_any(f)?;
+                if v2.size() == 1 {
+                    let v3 = self.pop_cat1(f)?;
+                    self.push_all(f, &[&v1, &v3, &v2, &v1])?;
+                } else {
+                    self.push_all(f, &[&v1, &v2, &v1])?;
+                }
+            }
+            OpCode::dup2 => {
+                let v1 = self.pop_any(f)?;
+                if v1.size() == 1 {
+                    let v2 = self.pop_cat1(f)?;
+                    self.push_all(f, &[&v2, &v1, &v2, &v1])?;
+                } else {
+                    self.push_all(f, &[&v1, &v1])?;
+                }
+            }
+            OpCode::dup2_x1 => {
+                let v1 = self.pop_any(f)?;
+                if v1.size() == 1 {
+                    let v2 = self.pop_cat1(f)?;
+                    let v3 = self.pop_cat1(f)?;
+                    self.push_all(f, &[&v2, &v1, &v3, &v2, &v1])?;
+                } else {
+                    let v2 = self.pop_cat1(f)?;
+                    self.push_all(f, &[&v1, &v2, &v1])?;
+                }
+            }
+            OpCode::dup2_x2 => {
+                let v1 = self.pop_any(f)?;
+                if v1.size() == 1 {
+                    let v2 = self.pop_cat1(f)?;
+                    let v3 = self.pop_any(f)?;
+                    if v3.size() == 1 {
+                        let v4 = self.pop_cat1(f)?;
+                        self.push_all(f, &[&v2, &v1, &v4, &v3, &v2, &v1])?;
+                    } else {
+                        self.push_all(f, &[&v2, &v1, &v3, &v2, &v1])?;
+                    }
+                } else {
+                    let v2 = self.pop_any(f)?;
+                    if v2.size() == 1 {
+                        let v3 = self.pop_cat1(f)?;
+                        self.push_all(f, &[&v1, &v3, &v2, &v1])?;
+                    } else {
+                        self.push_all(f, &[&v1, &v2, &v1])?;
+                    }
+                }
+            }
+            OpCode::swap => {
+                let v1 = self.pop_cat1(f)?;
+                let v2 = self.pop_cat1(f)?;
+                self.push_all(f, &[&v1, &v2])?;
+            }
+
+            OpCode::iadd
+            | OpCode::isub
+            | OpCode::imul
+            | OpCode::idiv
+            | OpCode::irem
+            | OpCode::ishl
+            | OpCode::ishr
+            | OpCode::iushr
+            | OpCode::iand
+            | OpCode::ior
+            | OpCode::ixor => self.binary(f, &int, &int, &int)?,
+            OpCode::ladd
+            | OpCode::lsub
+            | OpCode::lmul
+            | OpCode::ldiv
+            | OpCode::lrem
+            | OpCode::land
+            | OpCode::lor
+            | OpCode::lxor => self.binary(f, &long, &long, &long)?,
+            OpCode::lshl | OpCode::lshr | OpCode::lushr => self.binary(f, &long, &int, &long)?,
+            OpCode::fadd | OpCode::fsub | OpCode::fmul | OpCode::fdiv | OpCode::frem => {
+                self.binary(f, &float, &float, &float)?
+            }
+            OpCode::dadd | OpCode::dsub | OpCode::dmul | OpCode::ddiv | OpCode::drem => {
+                self.binary(f, &double, &double, &double)?
+            }
+            OpCode::ineg => self.unary(f, &int, &int)?,
+            OpCode::lneg => self.unary(f, &long, &long)?,
+            OpCode::fneg => self.unary(f, &float, &float)?,
+            OpCode::dneg => self.unary(f, &double, &double)?,
+            OpCode::iinc => {
+                let idx = self.u1(pc + 1) as usize;
+                self.check_local(f, idx, &int)?;
+            }
+            OpCode::i2l => self.unary(f, &int, &long)?,
+            OpCode::i2f => self.unary(f, &int, &float)?,
+            OpCode::i2d => self.unary(f, &int, &double)?,
+            OpCode::l2i => self.unary(f, &long, &int)?,
+            OpCode::l2f => self.unary(f, &long, &float)?,
+            OpCode::l2d => self.unary(f, &long, &double)?,
+            OpCode::f2i => self.unary(f, &float, &int)?,
+            OpCode::f2l => self.unary(f, &float, &long)?,
+            OpCode::f2d => self.unary(f, &float, &double)?,
+            OpCode::d2i => self.unary(f, &double, &int)?,
+            OpCode::d2l => self.unary(f, &double, &long)?,
+            OpCode::d2f => self.unary(f, &double, &float)?,
+            OpCode::i2b | OpCode::i2c | OpCode::i2s => self.unary(f, &int, &int)?,
+            OpCode::lcmp => self.binary(f, &long, &long, &int)?,
+            OpCode::fcmpl | OpCode::fcmpg => self.binary(f, &float, &float, &int)?,
+            OpCode::dcmpl | OpCode::dcmpg => self.binary(f, &double, &double, &int)?,
+
+            OpCode::ifeq
+            | OpCode::ifne
+            | OpCode::iflt
+            | OpCode::ifge
+            | OpCode::ifgt
+            | OpCode::ifle => {
+                self.pop(f, &int)?;
+                self.branch(f, self.i2(pc + 1))?;
+            }
+            OpCode::if_icmpeq
+            | OpCode::if_icmpne
+            | OpCode::if_icmplt
+            | OpCode::if_icmpge
+            | OpCode::if_icmpgt
+            | OpCode::if_icmple => {
+                self.pop(f, &int)?;
+                self.pop(f, &int)?;
+                self.branch(f, self.i2(pc + 1))?;
+            }
+            OpCode::if_acmpeq | OpCode::if_acmpne => {
+                self.pop_ref(f)?;
+                self.pop_ref(f)?;
+                self.branch(f, self.i2(pc + 1))?;
+            }
+            OpCode::ifnull | OpCode::ifnonnull => {
+                self.pop_ref(f)?;
+                self.branch(f, self.i2(pc + 1))?;
+            }
+            OpCode::goto => {
+                self.branch(f, self.i2(pc + 1))?;
+                return Ok(None);
+            }
+            OpCode::goto_w => {
+                self.branch(f, self.i4(pc + 1))?;
+                return Ok(None);
+            }
+            OpCode::jsr | OpCode::jsr_w if self.infer => {
+                let offset = if op == OpCode::jsr {
+                    self.i2(pc + 1)
+                } else {
+                    self.i4(pc + 1)
+                };
+                let start = (pc as i64 + offset) as u16;
+                self.push(f, VType::RetAddr(start))?;
+                self.branch(f, offset)?;
+                return Ok(None);
+            }
+            OpCode::ret if self.infer => {
+                self.ret(f, self.u1(pc + 1) as usize)?;
+                return Ok(None);
+            }
+            OpCode::jsr | OpCode::jsr_w | OpCode::ret => {
+                let msg = "jsr and ret can't be used with StackMapTable".to_string();
+                return Err(self.err(msg));
+            }
+            OpCode::tableswitch => {
+                self.pop(f, &int)?;
+                let base = (pc + 4) & !3;
+                let (low, high) = (self.i4(base + 4), self.i4(base + 8));
+                self.branch(f, self.i4(base))?;
+                for i in 0..(high - low + 1) as usize {
+                    self.branch(f, self.i4(base + 12 + i * 4))?;
+                }
+                return Ok(None);
+            }
+            OpCode::lookupswitch => {
+                self.pop(f, &int)?;
+                let base = (pc + 4) & !3;
+                let npairs = self.i4(base + 4) as usize;
+                self.branch(f, self.i4(base))?;
+                for i in 0..npairs {
+                    let pair = base + 8 + i * 8;
+                    if i > 0 && self.i4(pair) <= self.i4(pair - 8) {
+                        return Err(self.err("Bad lookupswitch instruction".to_string()));
+                    }
+                    self.branch(f, self.i4(pair + 4))?;
+                }
+                return Ok(None);
+            }
+            OpCode::ireturn | OpCode::lreturn | OpCode::freturn | OpCode::dreturn => {
+                let t = match op {
+                    OpCode::ireturn => int,
+                    OpCode::lreturn => long,
+                    OpCode::freturn => float,
+                    _ => double,
+                };
+                if self.ret.as_ref() != Some(&t) {
+                    return Err(self.err("Method expects a different return type".to_string()));
+                }
+                self.pop(f, &t)?;
+                return Ok(None);
+            }
+            OpCode::areturn => {
+                let t = match &self.ret {
+                    Some(t) if t.is_reference() => t.clone(),
+                    _ => return Err(self.err("Method expects a different return type".to_string())),
+                };
+                self.pop(f, &t)?;
+                return Ok(None);
+            }
+            OpCode::return_void => {
+                if self.ret.is_some() {
+                    return Err(self.err("Method expects a return value".to_string()));
+                }
+                if self.is_init && f.this_uninit {
+                    let msg = "Constructor must call super() or this() before return".to_string();
+                    return Err(self.err(msg));
+                }
+                return Ok(None);
+            }
+
+            OpCode::getstatic | OpCode::putstatic | OpCode::getfield | OpCode::putfield => {
+                self.field(f, op)?
+            }
+            OpCode::invokevirtual
+            | OpCode::invokespecial
+            | OpCode::invokestatic
+            | OpCode::invokeinterface
+            | OpCode::invokedynamic => self.invoke(f, op)?,
+
+            OpCode::new => {
+                let name = self.class_operand(pc + 1)?;
+                if name.first() == Some(&b'[') {
+                    return Err(self.err("Illegal new instruction".to_string()));
+                }
+                let t = VType::Uninit(pc as u16);
+                if f.stack.contains(&t) {
+                    return Err(
+                        self.err("Uninitialized object exists on backward branch".to_string())
+                    );
+                }
+                for it in f.locals.iter_mut() {
+                    if *it == t {
+                        *it = VType::Top;
+                    }
+                }
+                self.push(f, t)?;
+            }
+            OpCode::newarray => {
+                let desc: &[u8] = match self.u1(pc + 1) {
+                    4 => b"[Z",
+                    5 => b"[C",
+                    6 => b"[F",
+                    7 => b"[D",
+                    8 => b"[B",
+                    9 => b"[S",
+                    10 => b"[I",
+                    11 => b"[J",
+                    _ => return Err(self.err("Illegal newarray instruction".to_string())),
+                };
+                self.pop(f, &int)?;
+                self.push(f, VType::obj(desc))?;
+            }
+            OpCode::anewarray => {
+                let name = self.class_operand(pc + 1)?;
+                let t = match VType::Ref(name).array_of() {
+                    Some(VType::Ref(desc)) if array_dims(&desc) <= 255 => VType::Ref(desc),
+                    _ => return Err(self.err("Illegal anewarray instruction".to_string())),
+                };
+                self.pop(f, &int)?;
+                self.push(f, t)?;
+            }
+            OpCode::arraylength => {
+                self.pop_array(f, &[])?;
+                self.push(f, int)?;
+            }
+            OpCode::athrow => {
+                self.pop(f, &VType::obj(J_THROWABLE))?;
+                return Ok(None);
+            }
+            OpCode::checkcast => {
+                let name = self.class_operand(pc + 1)?;
+                self.pop_obj(f)?;
+                self.push(f, VType::Ref(name))?;
+            }
+            OpCode::instanceof => {
+                self.class_operand(pc + 1)?;
+                self.pop_obj(f)?;
+                self.push(f, int)?;
+            }
+            OpCode::monitorenter | OpCode::monitorexit => {
+                self.pop_obj(f)?;
+            }
+            OpCode::wide => {
+                let idx = self.u2(pc + 2) as usize;
+                match opcode(self.u1(pc + 1)) {
+                    OpCode::iload => self.load(f, idx, &int)?,
+                    OpCode::lload => self.load(f, idx, &long)?,
+                    OpCode::fload => self.load(f, idx, &float)?,
+                    OpCode::dload => self.load(f, idx, &double)?,
+                    OpCode::aload => self.load(f, idx, &obj)?,
+                    OpCode::istore => self.store(f, idx, &int)?,
+                    OpCode::lstore => self.store(f, idx, &long)?,
+                    OpCode::fstore => self.store(f, idx, &float)?,
+                    OpCode::dstore => self.store(f, idx, &double)?,
+                    OpCode::astore => self.store(f, idx, &obj)?,
+                    OpCode::iinc => self.check_local(f, idx, &int)?,
+                    OpCode::ret if self.infer => {
+                        self.ret(f, idx)?;
+                        return Ok(None);
+                    }
+                    _ => {
+                        let msg = "jsr and ret can't be used with StackMapTable".to_string();
+                        return Err(self.err(msg));
+                    }
+                }
+            }
+            OpCode::multianewarray => {
+                let name = self.class_operand(pc + 1)?;
+                let dims = self.u1(pc + 3) as usize;
+                if dims == 0 || array_dims(&name) < dims {
+                    return Err(self.err("Illegal multianewarray instruction".to_string()));
+                }
+                for _ in 0..dims {
+                    self.pop(f, &int)?;
+                }
+                self.push(f, VType::Ref(name))?;
+            }
+            _ => return Err(self.err("Bad instruction".to_string())),
+        }
+
+        Ok(Some(frame))
+    }
+
+    fn push_all(&self, frame: &mut Frame, types: &[&VType]) -> Result<(), VerifyError> {
+        for &t in types {
+            self.push(frame, t.clone())?;
+        }
+        Ok(())
+    }
+
+    fn unary(&self, frame: &mut Frame, arg: &VType, ret: &VType) -> Result<(), VerifyError> {
+        self.pop(frame, arg)?;
+        self.push(frame, ret.clone())
+    }
+
+    //'arg2' is on the top of the stack
+    fn binary(
+        &self,
+        frame: &mut Frame,
+        arg1: &VType,
+        arg2: &VType,
+        ret: &VType,
+    ) -> Result<(), VerifyError> {
+        self.pop(frame, arg2)?;
+        self.pop(frame, arg1)?;
+        self.push(frame, ret.clone())
+    }
+
+    //the frame goes to the instructions after the jsr calling the subroutine
+    fn ret(&self, frame: &Frame, idx: usize) -> Result<(), VerifyError> {
+        let start = match frame.locals.get(idx) {
+            Some(VType::RetAddr(start)) => *start as usize,
+            Some(t) => {
+                let msg = format!(
+                    "Bad local variable type: Type {} (current frame, locals[{}]) is not a returnAddress",
+                    t, idx
+                );
+                return Err(self.err(msg));
+            }
+            None => return Err(self.err("Illegal local variable number".to_string())),
+        };
+
+        let mut targets = self.targets.borrow_mut();
+        for (_, next) in self.subroutines.iter().filter(|(it, _)| *it == start) {
+            targets.push((*next, frame.clone()));
+        }
+        Ok(())
+    }
+
+    fn check_local(&self, frame: &Frame, idx: usize, expected: &VType) -> Result<(), VerifyError> {
+        match frame.locals.get(idx) {
+            Some(t) if t == expected => Ok(()),
+            Some(t) => {
+                let msg = format!(
+                    "Bad local variable type: Type {} (current frame, locals[{}]) is not assignable to {}",
+                    t, idx, expected
+                );
+                Err(self.err(msg))
+            }
+            None => Err(self.err("Illegal local variable number".to_string())),
+        }
+    }
+
+    fn class_operand(&self, pc: usize) -> Result<BytesRef, VerifyError> {
+        let idx = self.u2(pc);
+        self.ctx
+            .class_name(idx)
+            .cloned()
+            .ok_or_else(|| self.err(format!("Illegal constant pool index {} for class", idx)))
+    }
+
+    fn field(&self, f: &mut Frame, op: OpCode) -> Result<(), VerifyError> {
+        let idx = self.u2(self.pc + 1);
+        let (cls, _, desc) = match (self.ctx.cf.cp.get(idx as usize), self.ctx.member(idx)) {
+            (Some(CpType::FieldRef { .. }), Some(member)) => member,
+            _ => {
+                let msg = format!("Illegal constant pool index {} for field", idx);
+                return Err(self.err(msg));
+            }
+        };
+        let t = match types::parse_field(desc) {
+            Some((t, n)) if n == desc.len() => t,
+            _ => {
+                let msg = format!("Illegal field signature {}", String::from_utf8_lossy(desc));
+                return Err(self.err(msg));
+            }
+        };
+
+        match op {
+            OpCode::getstatic => self.push(f, t)?,
+            OpCode::putstatic => {
+                self.pop(f, &t)?;
+            }
+            OpCode::getfield => {
+                self.pop(f, &VType::Ref(cls.clone()))?;
+                self.push(f, t)?;
+            }
+            _ => {
+                self.pop(f, &t)?;
+                //the fields of this class can be set before super() is called
+                let objref = self.pop_ref(f)?;
+                let is_this = objref == VType::UninitThis && cls == &self.ctx.this_name;
+                if !is_this && !self.ctx.is_assignable(&objref, &VType::Ref(cls.clone())) {
+                    let msg = format!(
+                        "Bad type on operand stack: Type {} is not assignable to '{}'",
+                        objref,
+                        String::from_utf8_lossy(cls)
+                    );
+                    return Err(self.err(msg));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn invoke(&self, f: &mut Frame, op: OpCode) -> Result<(), VerifyError> {
+        let pc = self.pc;
+        let idx = self.u2(pc + 1);
+        let cp = &self.ctx.cf.cp;
+
+        let (cls, name, desc) = if op == OpCode::invokedynamic {
+            if self.u1(pc + 3) != 0 || self.u1(pc + 4) != 0 {
+                let msg = "Third and fourth operand bytes of invokedynamic must be zero";
+                return Err(self.err(msg.to_string()));
+            }
+            let nat = match cp.get(idx as usize) {
+                Some(CpType::InvokeDynamic {
+                    name_and_type_index,
+                    ..
+                }) => self.ctx.name_and_type(*name_and_type_index),
+                _ => None,
+            };
+            match nat {
+                Some((name, desc)) => (None, name, desc),
+                None => {
+                    let msg = format!("Illegal constant pool index {} for invokedynamic", idx);
+                    return Err(self.err(msg));
+                }
+            }
+        } else {
+            let interface_ok = op == OpCode::invokeinterface
+                || (op != OpCode::invokevirtual && self.ctx.cf.version.major >= 52);
+            let ok = match cp.get(idx as usize) {
+                Some(CpType::MethodRef { .. }) => op != OpCode::invokeinterface,
+                Some(CpType::InterfaceMethodRef { .. }) => interface_ok,
+                _ => false,
+            };
+            match self.ctx.member(idx) {
+                Some((cls, name, desc)) if ok => (Some(cls), name, desc),
+                _ => {
+                    let msg = format!("Illegal constant pool index {} for method", idx);
+                    return Err(self.err(msg));
+                }
+            }
+        };
+
+        let is_ctor = name.as_slice() == b"<init>";
+        if name.first() == Some(&b'<') && !(is_ctor && op == OpCode::invokespecial) {
+            return Err(self.err("Illegal call to internal method".to_string()));
+        }
+
+        let (args, ret) = match types::parse_method(desc) {
+            Some(sig) => sig,
+            None => {
+                let msg = format!("Illegal method signature {}", String::from_utf8_lossy(desc));
+                return Err(self.err(msg));
+            }
+        };
+
+        if op == OpCode::invokeinterface {
+            let count = args.iter().map(|t| t.size()).sum::<usize>() + 1;
+            if self.u1(pc + 3) as usize != count {
+                let msg = "Inconsistent args count operand in invokeinterface";
+                return Err(self.err(msg.to_string()));
+            }
+            if self.u1(pc + 4) != 0 {
+                let msg = "Fourth operand byte of invokeinterface must be zero";
+                return Err(self.err(msg.to_string()));
+            }
+        }
+
+        for t in args.iter().rev() {
+            self.pop(f, t)?;
+        }
+
+        let cls = match cls {
+            Some(cls) => cls,
+            None => {
+                if let Some(t) = ret {
+                    self.push(f, t)?;
+                }
+                return Ok(());
+            }
+        };
+
+        match op {
+            OpCode::invokestatic => (),
+            OpCode::invokespecial if is_ctor => {
+                if ret.is_some() {
+                    return Err(self.err("Illegal method signature".to_string()));
+                }
+
+                let this = &self.ctx.this_name;
+                match self.pop_ref(f)? {
+                    VType::UninitThis => {
+                        let is_super = self.ctx.super_name.as_ref() == Some(cls);
+                        if cls != this && !is_super {
+                            return Err(self.err("Bad <init> method call".to_string()));
+                        }
+                        f.init_object(&VType::UninitThis, &VType::Ref(this.clone()));
+                        f.this_uninit = false;
+                    }
+                    VType::Uninit(offset) => {
+                        let created = self.u2(offset as usize + 1);
+                        if self.ctx.class_name(created) != Some(cls) {
+                            return Err(self.err("Call to wrong <init> method".to_string()));
+                        }
+                        f.init_object(&VType::Uninit(offset), &VType::Ref(cls.clone()));
+                    }
+                    t => {
+                        let msg = format!(
+                            "Bad operand type when invoking <init>: Type {} is not uninitialized",
+                            t
+                        );
+                        return Err(self.err(msg));
+                    }
+                }
+            }
+            OpCode::invokespecial => {
+                let this = &self.ctx.this_name;
+                let ok =
+                    cls == this || self.ctx.is_subclass(this, cls) || self.ctx.is_interface(cls);
+                if !ok {
+                    let msg =
+                        "Bad invokespecial instruction: current class isn't assignable to reference class.";
+                    return Err(self.err(msg.to_string()));
+                }
+                self.pop(f, &VType::Ref(this.clone()))?;
+            }
+            _ => {
+                self.pop(f, &VType::Ref(cls.clone()))?;
+            }
+        }
+
+        if let Some(t) = ret {
+            self.push(f, t)?;
+        }
+
+        Ok(())
+    }
+}
+
+//94 is dup2_x2, OpCode::from maps it to dup_x2
+fn opcode(b: u8) -> OpCode {
+    if b == OpCode::dup2_x2 as u8 {
+        OpCode::dup2_x2
+    } else {
+        OpCode::from(b)
+    }
+}
+
+fn array_dims(desc: &[u8]) -> usize {
+    desc.iter().take_while(|&&c| c == b'[').count()
+}
+
+fn read_i4(code: &[u8], pc: usize) -> Option<i32> {
+    let v = code.get(pc..pc + 4)?;
+    Some(i32::from_be_bytes([v[0], v[1], v[2], v[3]]))
+}
+
+//the length of the instruction at 'pc'
+fn insn_len(code: &[u8], pc: usize) -> Result<usize, &'static str> {
+    let n = match code[pc] {
+        16 | 18 | 21..=25 | 54..=58 | 169 | 188 => 2,
+        17 | 19 | 20 | 132 | 153..=168 | 178..=184 | 187 | 189 | 192 | 193 | 198 | 199 => 3,
+        197 => 4,
+        185 | 186 | 200 | 201 => 5,
+        196 => match code.get(pc + 1) {
+            Some(132) => 6,
+            Some(21..=25) | Some(54..=58) | Some(169) => 4,
+            _ => return Err("Bad wide instruction"),
+        },
+        //tableswitch, the operands are aligned to 4 bytes
+        170 => {
+            let base = (pc + 4) & !3;
+            let low = read_i4(code, base + 4).ok_or("Code truncated")? as i64;
+            let high = read_i4(code, base + 8).ok_or("Code truncated")? as i64;
+            if low > high {
+                return Err("low must be less than or equal to high in tableswitch");
+            }
+            let n = (base - pc) as i64 + 12 + (high - low + 1) * 4;
+            if n > code.len() as i64 {
+                return Err("Code truncated");
+            }
+            n as usize
+        }
+        //lookupswitch
+        171 => {
+            let base = (pc + 4) & !3;
+            let npairs = read_i4(code, base + 4).ok_or("Code truncated")? as i64;
+            if npairs < 0 {
+                return Err("npairs must be non-negative in lookupswitch");
+            }
+            let n = (base - pc) as i64 + 8 + npairs * 8;
+            if n > code.len() as i64 {
+                return Err("Code truncated");
+            }
+            n as usize
+        }
+        0..=201 => 1,
+        _ => return Err("Bad instruction"),
+    };
+
+    Ok(n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use classfile::attributes::Type as AttrType;
+    use classfile::flags::ACC_PUBLIC;
+    use classfile::Version;
+
+    //Test and Other extend Object, nothing else is known
+    struct Hierarchy;
+
+    impl ClassHierarchy for Hierarchy {
+        fn is_interface(&self, _name: &[u8]) -> bool {
+            false
+        }
+
+        fn is_subclass(&self, sub: &[u8], sup: &[u8]) -> bool {
+            sub == sup || sup == J_OBJECT
+        }
+
+        fn super_class(&self, name: &[u8]) -> Option<BytesRef> {
+            match name {
+                b"Other" => Some(Arc::new(J_OBJECT.to_vec())),
+                _ => None,
+            }
+        }
+    }
+
+    const OBJECT_INIT: u8 = 8;
+    const OTHER: u8 = 12;
+    const OTHER_FOO: u8 = 15;
+    const OTHER_INIT: u8 = 16;
+
+    fn utf8(s: &str) -> CpType {
+        CpType::Utf8 {
+            bytes: Arc::new(s.as_bytes().to_vec()),
+        }
+    }
+
+    //the class Test with the method 'm' of 'desc'
+    fn class(
+        major: u16,
+        flags: u16,
+        name: &str,
+        desc: &str,
+        max: (u16, u16),
+        code: &[u8],
+    ) -> ClassFile {
+        let cp = vec![
+            CpType::Nop,
+            utf8("Test"),
+            CpType::Class { name_index: 1 },
+            utf8("java/lang/Object"),
+            CpType::Class { name_index: 3 },
+            utf8("<init>"),
+            utf8("()V"),
+            CpType::NameAndType {
+                name_index: 5,
+                desc_index: 6,
+            },
+            CpType::MethodRef {
+                class_index: 4,
+                name_and_type_index: 7,
+            },
+            utf8(name),
+            utf8(desc),
+            utf8("Other"),
+            CpType::Class { name_index: 11 },
+            utf8("foo"),
+            CpType::NameAndType {
+                name_index: 13,
+                desc_index: 6,
+            },
+            CpType::MethodRef {
+                class_index: 12,
+                name_and_type_index: 14,
+            },
+            CpType::MethodRef {
+                class_index: 12,
+                name_and_type_index: 7,
+            },
+        ];
+        let code = Code {
+            max_stack: max.0,
+            max_locals: max.1,
+            code: Arc::new(code.to_vec()),
+            exceptions: vec![],
+            attrs: vec![],
+        };
+        let method = MethodInfo {
+            acc_flags: flags,
+            name_index: 9,
+            desc_index: 10,
+            attrs: vec![AttrType::Code(code)],
+        };
+
+        ClassFile {
+            version: Version { minor: 0, major },
+            cp: Arc::new(cp),
+            acc_flags: ACC_PUBLIC,
+            this_class: 2,
+            super_class: 4,
+            interfaces: vec![],
+            fields: vec![],
+            methods: vec![method],
+            attrs: vec![],
+        }
+    }
+
+    fn verify_static(
+        major: u16,
+        desc: &str,
+        max: (u16, u16),
+        code: &[u8],
+    ) -> Result<(), VerifyError> {
+        let cf = class(major, ACC_STATIC, "m", desc, max, code);
+        verify_class(&cf, &Hierarchy)
+    }
+
+    fn expect_err(r: Result<(), VerifyError>, method: &str, pc: usize, reason: &str) {
+        let e = r.unwrap_err();
+        assert_eq!(e.class, "Test");
+        assert_eq!(e.method, method);
+        assert_eq!(e.pc, pc);
+        assert!(e.reason.starts_with(reason), "{}", e.reason);
+    }
+
+    #[test]
+    fn t_stack_type() {
+        //iconst_0, fconst_0, iadd, pop, return
+        let code = [0x03, 0x0b, 0x60, 0x57, 0xb1];
+        for &major in &[52, 50, 49] {
+            let r = verify_static(major, "()V", (2, 0), &code);
+            expect_err(r, "m()V", 2, "Bad type on operand stack");
+        }
+
+        //iconst_0, pop2, return
+        let r = verify_static(52, "()V", (2, 0), &[0x03, 0x58, 0xb1]);
+        expect_err(r, "m()V", 1, "Operand stack underflow");
+        //iconst_0, iconst_0, return, with max_stack 1
+        let r = verify_static(52, "()V", (1, 0), &[0x03, 0x03, 0xb1]);
+        expect_err(r, "m()V", 1, "Operand stack overflow");
+    }
+
+    #[test]
+    fn t_locals_type() {
+        //iconst_0, istore_0, aload_0, pop, return
+        let code = [0x03, 0x3b, 0x2a, 0x57, 0xb1];
+        for &major in &[52, 49] {
+            let r = verify_static(major, "()V", (1, 1), &code);
+            expect_err(r, "m()V", 2, "Bad local variable type");
+        }
+
+        //lload_0 of the int argument
+        let r = verify_static(52, "(I)V", (2, 2), &[0x1e, 0x58, 0xb1]);
+        expect_err(r, "m(I)V", 0, "Bad local variable type");
+        //iload_1 out of max_locals
+        let r = verify_static(52, "()V", (1, 1), &[0x1b, 0x57, 0xb1]);
+        expect_err(r, "m()V", 0, "Illegal local variable number");
+    }
+
+    #[test]
+    fn t_branch_target() {
+        //sipush 1, pop, goto 1 (into sipush)
+        let code = [0x11, 0x00, 0x01, 0x57, 0xa7, 0xff, 0xfd];
+        for &major in &[52, 49] {
+            let r = verify_static(major, "()V", (1, 0), &code);
+            expect_err(r, "m()V", 4, "Illegal target of jump or branch");
+        }
+
+        //goto 100, past the end
+        let r = verify_static(49, "()V", (0, 0), &[0xa7, 0x00, 0x64]);
+        expect_err(r, "m()V", 0, "Illegal target of jump or branch");
+
+        //iconst_0, ifeq 5, nop, return, without a stack map frame at 5
+        let code = [0x03, 0x99, 0x00, 0x04, 0x00, 0xb1];
+        let r = verify_static(51, "()V", (1, 0), &code);
+        expect_err(
+            r,
+            "m()V",
+            1,
+            "Expecting a stackmap frame at branch target 5",
+        );
+
+        //falls off the end
+        let r = verify_static(52, "()V", (1, 0), &[0x00]);
+        expect_err(r, "m()V", 0, "Falling off the end of the code");
+        let r = verify_static(49, "()V", (1, 0), &[0x00]);
+        expect_err(r, "m()V", 0, "Falling off the end of the code");
+    }
+
+    #[test]
+    fn t_invokespecial() {
+        //aload_0, invokespecial Other.foo()V, return
+        let code = [0x2a, 0xb7, 0x00, OTHER_FOO, 0xb1];
+        for &major in &[52, 49] {
+            let cf = class(major, 0, "m", "()V", (1, 1), &code);
+            let r = verify_class(&cf, &Hierarchy);
+            expect_err(r, "m()V", 1, "Bad invokespecial instruction");
+        }
+
+        //new Other, dup, invokespecial Object.<init>, pop, return
+        let code = [0xbb, 0x00, OTHER, 0x59, 0xb7, 0x00, OBJECT_INIT, 0x57, 0xb1];
+        let r = verify_static(52, "()V", (2, 0), &code);
+        expect_err(r, "m()V", 4, "Call to wrong <init> method");
+
+        //<init> returning before calling super()
+        let cf = class(52, 0, "<init>", "()V", (1, 1), &[0xb1]);
+        let r = verify_class(&cf, &Hierarchy);
+        expect_err(r, "<init>()V", 0, "Constructor must call super()");
+
+        //aload_0, invokespecial Object.<init>, return
+        let cf = class(
+            52,
+            0,
+            "<init>",
+            "()V",
+            (1, 1),
+            &[0x2a, 0xb7, 0x00, OBJECT_INIT, 0xb1],
+        );
+        assert!(verify_class(&cf, &Hierarchy).is_ok());
+    }
+
+    #[test]
+    fn t_inference() {
+        //iconst_0, istore_0, iinc 0 1, iload_0, bipush 10, if_icmplt 2, return
+        let code = [
+            0x03, 0x3b, 0x84, 0x00, 0x01, 0x1a, 0x10, 0x0a, 0xa1, 0xff, 0xfa, 0xb1,
+        ];
+        assert!(verify_static(49, "()V", (2, 1), &code).is_ok());
+        //no stack map for the loop, type checking fails over to the inference at 50
+        assert!(verify_static(50, "()V", (2, 1), &code).is_ok());
+        let r = verify_static(51, "()V", (2, 1), &code);
+        expect_err(
+            r,
+            "m()V",
+            8,
+            "Expecting a stackmap frame at branch target 2",
+        );
+
+        //iload_0, ifeq 8, fconst_0, fstore_0, iload_0, pop, return, the int and float merge to top
+        let code = [0x1a, 0x99, 0x00, 0x05, 0x0b, 0x43, 0x1a, 0x57, 0xb1];
+        let r = verify_static(49, "(I)V", (1, 1), &code);
+        expect_err(r, "m(I)V", 6, "Bad local variable type");
+
+        //iload_0, ifeq 6, iconst_1, nop, return, different stack heights at 6
+        let code = [0x1a, 0x99, 0x00, 0x05, 0x04, 0x00, 0xb1];
+        let r = verify_static(49, "(I)V", (1, 1), &code);
+        expect_err(
+            r,
+            "m(I)V",
+            5,
+            "Inconsistent stack height at branch target 6",
+        );
+
+        //an uninitialized Other and null don't merge
+        //iload_0, ifeq 10, new Other, goto 11, aconst_null, areturn
+        let code = [
+            0x1a, 0x99, 0x00, 0x09, 0xbb, 0x00, OTHER, 0xa7, 0x00, 0x04, 0x01, 0xb0,
+        ];
+        let r = verify_static(49, "(I)LOther;", (1, 1), &code);
+        expect_err(
+            r,
+            "m(I)LOther;",
+            10,
+            "Mismatched stack types at branch target 11",
+        );
+
+        //null and Other merge to Other
+        //iload_0, ifeq 14, new Other, dup, invokespecial Other.<init>, goto 15,
+        //aconst_null, areturn
+        let code = [
+            0x1a, 0x99, 0x00, 0x0d, 0xbb, 0x00, OTHER, 0x59, 0xb7, 0x00, OTHER_INIT, 0xa7, 0x00,
+            0x04, 0x01, 0xb0,
+        ];
+        assert!(verify_static(49, "(I)LOther;", (2, 1), &code).is_ok());
+    }
+
+    #[test]
+    fn t_jsr() {
+        //jsr 4, return, astore_0, ret 0
+        let code = [0xa8, 0x00, 0x04, 0xb1, 0x4b, 0xa9, 0x00];
+        assert!(verify_static(49, "()V", (1, 1), &code).is_ok());
+        let r = verify_static(52, "()V", (1, 1), &code);
+        expect_err(r, "m()V", 0, "jsr and ret can't be used with StackMapTable");
+
+        //the returnAddress can't be loaded
+        //jsr 4, return, astore_0, aload_0, pop, ret 0
+        let code = [0xa8, 0x00, 0x04, 0xb1, 0x4b, 0x2a, 0x57, 0xa9, 0x00];
+        let r = verify_static(49, "()V", (1, 1), &code);
+        expect_err(r, "m()V", 5, "Bad local variable type");
+
+        //ret of an int
+        let code = [0x03, 0x3b, 0xa9, 0x00];
+        let r = verify_static(49, "()V", (1, 1), &code);
+        expect_err(r, "m()V", 2, "Bad local variable type");
+    }
+}
@@ -74,6 +74,12 @@ pub enum Type {
     MethodParameters {
         parameters: Vec<MethodParameter>,
     },
+    NestHost {
+        host_class_index: U2,
+    },
+    NestMembers {
+        classes: Vec<U2>,
+    },
     Unknown,
 }
 
@@ -102,6 +108,8 @@ pub enum Tag {
     AnnotationDefault,
     BootstrapMethods,
     MethodParameters,
+    NestHost,
+    NestMembers,
     Unknown,
 }
 
@@ -131,6 +139,8 @@ impl From<&[u8]> for Tag {
             b"AnnotationDefault" => Tag::AnnotationDefault,
             b"BootstrapMethods" => Tag::BootstrapMethods,
             b"MethodParameters" => Tag::MethodParameters,
+            b"NestHost" => Tag::NestHost,
+            b"NestMembers" => Tag::NestMembers,
             _ => {
                 info!("Unknown attr {}", unsafe {
                     std::str::from_utf8_unchecked(raw)
@@ -1,4 +1,4 @@
-use crate::attributes::InnerClass;
+use crate::attributes::{BootstrapMethod, InnerClass};
 use crate::{attributes::Type, field_info::FieldInfo, method_info::MethodInfo, version::Version};
 use crate::{ConstantPool, U2};
 
@@ -35,4 +35,34 @@ impl ClassFile {
 
         None
     }
+
+    pub fn nest_host(&self) -> Option<usize> {
+        for it in self.attrs.iter() {
+            if let Type::NestHost { host_class_index } = it {
+                return Some(*host_class_index as usize);
+            }
+        }
+
+        None
+    }
+
+    pub fn nest_members(&self) -> Option<&Vec<U2>> {
+        for it in self.attrs.iter() {
+            if let Type::NestMembers { classes } = it {
+                return Some(classes);
+            }
+        }
+
+        None
+    }
+
+    pub fn bootstrap_methods(&self) -> Option<&Vec<BootstrapMethod>> {
+        for it in self.attrs.iter() {
+            if let Type::BootstrapMethods { methods, .. } = it {
+                return Some(methods);
+            }
+        }
+
+        None
+    }
 }
@@ -192,6 +192,7 @@ pub enum Tag {
     MethodHandle,
     MethodType,
     InvokeDynamic,
+    Unknown,
 }
 
 impl From<u8> for Tag {
@@ -211,7 +212,7 @@ impl From<u8> for Tag {
             15 => Tag::MethodHandle,
             16 => Tag::MethodType,
             18 => Tag::InvokeDynamic,
-            _ => unreachable!(),
+            _ => Tag::Unknown,
         }
     }
 }
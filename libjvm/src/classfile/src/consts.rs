@@ -22,6 +22,8 @@ pub const J_CLASS: &[u8] = b"java/lang/Class";
 pub const J_STRING: &[u8] = b"java/lang/String";
 pub const J_THREAD: &[u8] = b"java/lang/Thread";
 pub const J_THREAD_GROUP: &[u8] = b"java/lang/ThreadGroup";
+pub const J_REFERENCE: &[u8] = b"java/lang/ref/Reference";
+pub const J_FINALIZER: &[u8] = b"java/lang/ref/Finalizer";
 pub const J_SYSTEM: &[u8] = b"java/lang/System";
 
 pub const J_INPUT_STREAM: &[u8] = b"java/io/InputStream";
@@ -34,17 +36,56 @@ pub const J_METHOD_CTOR: &[u8] = b"java/lang/reflect/Constructor";
 pub const J_ACCESSIBLE_OBJECT: &[u8] = b"java/lang/reflect/AccessibleObject";
 pub const J_METHODHANDLE: &[u8] = b"java/lang/invoke/MethodHandle";
 pub const J_METHODTYPE: &[u8] = b"java/lang/invoke/MethodType";
+pub const J_LAMBDA_METAFACTORY: &[u8] = b"java/lang/invoke/LambdaMetafactory";
+pub const J_STRING_CONCAT_FACTORY: &[u8] = b"java/lang/invoke/StringConcatFactory";
+pub const J_METHOD_HANDLE_NATIVES: &[u8] = b"java/lang/invoke/MethodHandleNatives";
+pub const J_MEMBER_NAME: &[u8] = b"java/lang/invoke/MemberName";
+pub const J_LAMBDA_FORM: &[u8] = b"java/lang/invoke/LambdaForm";
+pub const J_CALL_SITE: &[u8] = b"java/lang/invoke/CallSite";
 
 pub const J_INTERNAL_ERROR: &[u8] = b"java/lang/InternalError";
 pub const J_NPE: &[u8] = b"java/lang/NullPointerException";
 pub const J_IOEXCEPTION: &[u8] = b"java/io/IOException";
 pub const J_ARRAY_INDEX_OUT_OF_BOUNDS: &[u8] = b"java/lang/ArrayIndexOutOfBoundsException";
+pub const J_STRING_INDEX_OUT_OF_BOUNDS: &[u8] = b"java/lang/StringIndexOutOfBoundsException";
+pub const J_ARRAY_STORE_EX: &[u8] = b"java/lang/ArrayStoreException";
 pub const J_CLASS_NOT_FOUND: &[u8] = b"java/lang/ClassNotFoundException";
 pub const J_ARITHMETIC_EX: &[u8] = b"java/lang/ArithmeticException";
 pub const J_SOE: &[u8] = b"java/lang/StackOverflowError";
+pub const J_OOM: &[u8] = b"java/lang/OutOfMemoryError";
+pub const J_INTERRUPTED_EXCEPTION: &[u8] = b"java/lang/InterruptedException";
+pub const J_ILLEGAL_ARGUMENT: &[u8] = b"java/lang/IllegalArgumentException";
+pub const J_ILLEGAL_ACCESS: &[u8] = b"java/lang/IllegalAccessException";
+pub const J_ILLEGAL_MONITOR_STATE: &[u8] = b"java/lang/IllegalMonitorStateException";
 pub const J_NASE: &[u8] = b"java/lang/NegativeArraySizeException";
 pub const J_CCE: &[u8] = b"java/lang/ClassCastException";
+pub const J_INSTANTIATION_EXCEPTION: &[u8] = b"java/lang/InstantiationException";
 pub const J_THROWABLE: &[u8] = b"java/lang/Throwable";
+pub const J_ERROR: &[u8] = b"java/lang/Error";
+pub const J_BOOTSTRAP_METHOD_ERROR: &[u8] = b"java/lang/BootstrapMethodError";
+pub const J_NO_SUCH_METHOD_ERROR: &[u8] = b"java/lang/NoSuchMethodError";
+pub const J_NO_SUCH_FIELD_ERROR: &[u8] = b"java/lang/NoSuchFieldError";
+pub const J_CLASS_FORMAT_ERROR: &[u8] = b"java/lang/ClassFormatError";
+pub const J_LINKAGE_ERROR: &[u8] = b"java/lang/LinkageError";
+pub const J_NO_CLASS_DEF_FOUND_ERROR: &[u8] = b"java/lang/NoClassDefFoundError";
+pub const J_VERIFY_ERROR: &[u8] = b"java/lang/VerifyError";
+pub const J_UNSUPPORTED_CLASS_VERSION_ERROR: &[u8] = b"java/lang/UnsupportedClassVersionError";
+pub const J_UNSATISFIED_LINK_ERROR: &[u8] = b"java/lang/UnsatisfiedLinkError";
+pub const J_INCOMPATIBLE_CLASS_CHANGE_ERROR: &[u8] = b"java/lang/IncompatibleClassChangeError";
+pub const J_ABSTRACT_METHOD_ERROR: &[u8] = b"java/lang/AbstractMethodError";
+pub const J_EXCEPTION_IN_INITIALIZER_ERROR: &[u8] = b"java/lang/ExceptionInInitializerError";
+pub const J_ILLEGAL_ACCESS_ERROR: &[u8] = b"java/lang/IllegalAccessError";
 
 pub const CONSTANT_METHOD_REF_TAG: u8 = 10;
 pub const CONSTANT_INTERFACE_METHOD_REF_TAG: u8 = 11;
+
+//JVM spec, 5.4.3.5, reference_kind of CONSTANT_MethodHandle_info
+pub const REF_GET_FIELD: u8 = 1;
+pub const REF_GET_STATIC: u8 = 2;
+pub const REF_PUT_FIELD: u8 = 3;
+pub const REF_PUT_STATIC: u8 = 4;
+pub const REF_INVOKE_VIRTUAL: u8 = 5;
+pub const REF_INVOKE_STATIC: u8 = 6;
+pub const REF_INVOKE_SPECIAL: u8 = 7;
+pub const REF_NEW_INVOKE_SPECIAL: u8 = 8;
+pub const REF_INVOKE_INTERFACE: u8 = 9;
@@ -1,6 +1,15 @@
-// #![feature(c_variadic)]
+#![cfg_attr(feature = "c_variadic", feature(c_variadic))]
+#![allow(clippy::missing_safety_doc)]
 
 pub mod invocation;
 pub mod native;
 pub mod private;
 pub mod util;
+
+//hand the jni function table to the vm, for calling the native methods of the libraries
+pub fn init() {
+	vm::native::jni::install(vm::native::jni::Hooks {
+		env: || native::jni_env() as *mut std::ffi::c_void,
+		java_vm: || invocation::java_vm() as *mut std::ffi::c_void,
+	});
+}
@@ -1,15 +1,40 @@
 #![allow(non_snake_case)]
 #![allow(unused_variables)]
-#![allow(unused_imports)]
 
+use crate::util::{
+	self, class_ref_to_jclass, jclass_to_class_ref, jfield_to_field_id, jmethod_to_method_id,
+	jobject_to_oop, oop_to_jobject,
+};
+use classfile::flags::{ACC_PRIVATE, ACC_STATIC};
+use classfile::{consts as cls_consts, BytesRef, SignatureType};
 use jni_sys::*;
+use lazy_static::lazy_static;
+#[cfg(feature = "c_variadic")]
+use std::ffi::VaList;
+use std::ffi::{c_char, c_void, CStr};
+use std::ptr;
+use std::sync::Arc;
+use vm::gc;
+use vm::native::common::reflect;
+use vm::native::jni;
+use vm::new_br;
+use vm::oop::{self, Class, ClassKind, Oop, OopPtr, RefKind, TypeArrayDesc};
+use vm::runtime::{cmp, exception, invoke, method_handles, require_class3, thread, ClassLoader};
+use vm::types::{ClassRef, MethodIdRef};
 
-// use libc::{c_char, c_void};
-use sys::ffi::{c_void, c_char};
-pub type va_list = *mut c_void;
+/*
+The JNI functions
+
+Objects are passed to native code as references, see vm::native::jni,
+jmethodID and jfieldID are the raw pointers of MethodIdRef and FieldIdRef.
+An exception thrown here is left pending in the current thread.
+Native methods run detached from the collector, each function attaches
+the calling thread again for the time of the call.
+*/
 
 pub unsafe extern "system" fn GetVersion(env: *mut JNIEnv) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	JNI_VERSION_1_8
 }
 pub unsafe extern "system" fn DefineClass(
 	env: *mut JNIEnv,
@@ -18,16 +43,33 @@ pub unsafe extern "system" fn DefineClass(
 	buf: *const jbyte,
 	len: jsize,
 ) -> jclass {
-	todo!();
+	let _vm = gc::in_vm();
+	let name = if name.is_null() {
+		Oop::Null
+	} else {
+		vm::util::oop::new_java_lang_string2(&cstr(name))
+	};
+	let bytes = std::slice::from_raw_parts(buf as *const u8, len as usize);
+	match vm::native::java_lang_ClassLoader::define_class(&jobject_to_oop(loader), &name, bytes) {
+		Ok(Some(mirror)) => oop_to_jobject(mirror),
+		Ok(None) => ptr::null_mut(),
+		Err(ex) => {
+			set_ex(ex);
+			ptr::null_mut()
+		}
+	}
 }
 pub unsafe extern "system" fn FindClass(env: *mut JNIEnv, name: *const c_char) -> jclass {
-	todo!();
+	let _vm = gc::in_vm();
+	find_class(&cstr(name))
 }
 pub unsafe extern "system" fn FromReflectedMethod(env: *mut JNIEnv, method: jobject) -> jmethodID {
-	todo!();
+	let _vm = gc::in_vm();
+	from_reflected_method(jobject_to_oop(method))
 }
 pub unsafe extern "system" fn FromReflectedField(env: *mut JNIEnv, field: jobject) -> jfieldID {
-	todo!();
+	let _vm = gc::in_vm();
+	from_reflected_field(jobject_to_oop(field))
 }
 pub unsafe extern "system" fn ToReflectedMethod(
 	env: *mut JNIEnv,
@@ -35,17 +77,31 @@ pub unsafe extern "system" fn ToReflectedMethod(
 	methodID: jmethodID,
 	isStatic: jboolean,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	let mir = jmethod_to_method_id(methodID);
+	let v = if mir.method.name.as_slice() == b"<init>" {
+		reflect::new_method_ctor(mir)
+	} else {
+		reflect::new_method_normal(mir)
+	};
+	oop_to_jobject(v)
 }
 pub unsafe extern "system" fn GetSuperclass(env: *mut JNIEnv, sub: jclass) -> jclass {
-	todo!();
+	let _vm = gc::in_vm();
+	match jclass_to_class_ref(sub) {
+		Some(cls) if !cls.get_class().is_interface() => {
+			class_ref_to_jclass(cls.get_class().get_super_class())
+		}
+		_ => ptr::null_mut(),
+	}
 }
 pub unsafe extern "system" fn IsAssignableFrom(
 	env: *mut JNIEnv,
 	sub: jclass,
 	sup: jclass,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	is_assignable(&jobject_to_oop(sub), &jobject_to_oop(sup)) as jboolean
 }
 pub unsafe extern "system" fn ToReflectedField(
 	env: *mut JNIEnv,
@@ -53,68 +109,105 @@ pub unsafe extern "system" fn ToReflectedField(
 	fieldID: jfieldID,
 	isStatic: jboolean,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	oop_to_jobject(reflect::new_field(jfield_to_field_id(fieldID)))
 }
 pub unsafe extern "system" fn Throw(env: *mut JNIEnv, obj: jthrowable) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	set_ex(jobject_to_oop(obj));
+	JNI_OK
 }
 pub unsafe extern "system" fn ThrowNew(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	msg: *const c_char,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	let cls = jclass_to_class_ref(clazz).unwrap();
+	let msg = if msg.is_null() {
+		Oop::Null
+	} else {
+		vm::util::oop::new_java_lang_string2(&cstr(msg))
+	};
+	if oop::class::init_class(&cls).is_err() {
+		return JNI_ERR;
+	}
+	let ex = Oop::new_inst(cls.clone());
+	invoke::invoke_ctor(cls, new_br("(Ljava/lang/String;)V"), vec![ex.clone(), msg]);
+	if !thread::is_meet_ex() {
+		set_ex(ex);
+	}
+	JNI_OK
 }
 pub unsafe extern "system" fn ExceptionOccurred(env: *mut JNIEnv) -> jthrowable {
-	todo!();
+	let _vm = gc::in_vm();
+	let jt = thread::current_java_thread();
+	let ex = jt.read().unwrap().ex.clone();
+	oop_to_jobject(ex.unwrap_or(Oop::Null))
 }
 pub unsafe extern "system" fn ExceptionDescribe(env: *mut JNIEnv) {
-	todo!();
+	let _vm = gc::in_vm();
+	exception_describe()
 }
 pub unsafe extern "system" fn ExceptionClear(env: *mut JNIEnv) {
-	todo!();
+	let _vm = gc::in_vm();
+	thread::current_java_thread().write().unwrap().take_ex();
 }
 pub unsafe extern "system" fn FatalError(env: *mut JNIEnv, msg: *const c_char) -> ! {
-	todo!();
+	let _vm = gc::in_vm();
+	eprintln!("FATAL ERROR in native method: {}", cstr(msg));
+	std::process::abort()
 }
 pub unsafe extern "system" fn PushLocalFrame(env: *mut JNIEnv, capacity: jint) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	jni::push_local_frame();
+	JNI_OK
 }
 pub unsafe extern "system" fn PopLocalFrame(env: *mut JNIEnv, result: jobject) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	jni::pop_local_frame(jobject_to_oop(result)) as jobject
 }
 pub unsafe extern "system" fn NewGlobalRef(env: *mut JNIEnv, lobj: jobject) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	jni::new_global_ref(jobject_to_oop(lobj), false) as jobject
 }
 pub unsafe extern "system" fn DeleteGlobalRef(env: *mut JNIEnv, gref: jobject) {
-	todo!();
+	let _vm = gc::in_vm();
+	jni::delete_global_ref(gref as jni::Handle)
 }
 pub unsafe extern "system" fn DeleteLocalRef(env: *mut JNIEnv, obj: jobject) {
-	todo!();
+	let _vm = gc::in_vm();
+	jni::delete_local_ref(obj as jni::Handle)
 }
 pub unsafe extern "system" fn IsSameObject(
 	env: *mut JNIEnv,
 	obj1: jobject,
 	obj2: jobject,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	OopPtr::is_eq(&jobject_to_oop(obj1), &jobject_to_oop(obj2)) as jboolean
 }
 pub unsafe extern "system" fn NewLocalRef(env: *mut JNIEnv, ref_: jobject) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	oop_to_jobject(jobject_to_oop(ref_))
 }
 pub unsafe extern "system" fn EnsureLocalCapacity(env: *mut JNIEnv, capacity: jint) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	JNI_OK
 }
 pub unsafe extern "system" fn AllocObject(env: *mut JNIEnv, clazz: jclass) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	alloc_object(clazz).map_or(ptr::null_mut(), oop_to_jobject)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn NewObject(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	NewObjectV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn NewObjectV(
 	env: *mut JNIEnv,
@@ -122,7 +215,8 @@ pub unsafe extern "system" fn NewObjectV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	new_object(clazz, methodID, Args::V(args)).map_or(ptr::null_mut(), oop_to_jobject)
 }
 pub unsafe extern "system" fn NewObjectA(
 	env: *mut JNIEnv,
@@ -130,17 +224,26 @@ pub unsafe extern "system" fn NewObjectA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	new_object(clazz, methodID, Args::A(args)).map_or(ptr::null_mut(), oop_to_jobject)
 }
 pub unsafe extern "system" fn GetObjectClass(env: *mut JNIEnv, obj: jobject) -> jclass {
-	todo!();
+	let _vm = gc::in_vm();
+	class_ref_to_jclass(Some(object_class(&jobject_to_oop(obj))))
 }
 pub unsafe extern "system" fn IsInstanceOf(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	match jobject_to_oop(obj) {
+		Oop::Null => JNI_TRUE,
+		obj => {
+			let cls = jclass_to_class_ref(clazz).unwrap();
+			cmp::instance_of(object_class(&obj), cls) as jboolean
+		}
+	}
 }
 pub unsafe extern "system" fn GetMethodID(
 	env: *mut JNIEnv,
@@ -148,15 +251,18 @@ pub unsafe extern "system" fn GetMethodID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jmethodID {
-	todo!();
+	let _vm = gc::in_vm();
+	get_method_id(clazz, name, sig, false)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallObjectMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	CallObjectMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallObjectMethodV(
 	env: *mut JNIEnv,
@@ -164,7 +270,9 @@ pub unsafe extern "system" fn CallObjectMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual)
+		.map_or(ptr::null_mut(), oop_to_jobject)
 }
 pub unsafe extern "system" fn CallObjectMethodA(
 	env: *mut JNIEnv,
@@ -172,15 +280,19 @@ pub unsafe extern "system" fn CallObjectMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual)
+		.map_or(ptr::null_mut(), oop_to_jobject)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallBooleanMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	CallBooleanMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallBooleanMethodV(
 	env: *mut JNIEnv,
@@ -188,7 +300,9 @@ pub unsafe extern "system" fn CallBooleanMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jboolean)
 }
 pub unsafe extern "system" fn CallBooleanMethodA(
 	env: *mut JNIEnv,
@@ -196,15 +310,19 @@ pub unsafe extern "system" fn CallBooleanMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jboolean)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallByteMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	CallByteMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallByteMethodV(
 	env: *mut JNIEnv,
@@ -212,7 +330,9 @@ pub unsafe extern "system" fn CallByteMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jbyte)
 }
 pub unsafe extern "system" fn CallByteMethodA(
 	env: *mut JNIEnv,
@@ -220,15 +340,19 @@ pub unsafe extern "system" fn CallByteMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jbyte)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallCharMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	CallCharMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallCharMethodV(
 	env: *mut JNIEnv,
@@ -236,7 +360,9 @@ pub unsafe extern "system" fn CallCharMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jchar)
 }
 pub unsafe extern "system" fn CallCharMethodA(
 	env: *mut JNIEnv,
@@ -244,15 +370,19 @@ pub unsafe extern "system" fn CallCharMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jchar)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallShortMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	CallShortMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallShortMethodV(
 	env: *mut JNIEnv,
@@ -260,7 +390,9 @@ pub unsafe extern "system" fn CallShortMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jshort)
 }
 pub unsafe extern "system" fn CallShortMethodA(
 	env: *mut JNIEnv,
@@ -268,15 +400,19 @@ pub unsafe extern "system" fn CallShortMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual)
+		.map_or(0, |v| v.extract_int() as jshort)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallIntMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	CallIntMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallIntMethodV(
 	env: *mut JNIEnv,
@@ -284,7 +420,8 @@ pub unsafe extern "system" fn CallIntMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual).map_or(0, |v| v.extract_int())
 }
 pub unsafe extern "system" fn CallIntMethodA(
 	env: *mut JNIEnv,
@@ -292,15 +429,18 @@ pub unsafe extern "system" fn CallIntMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual).map_or(0, |v| v.extract_int())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallLongMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	CallLongMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallLongMethodV(
 	env: *mut JNIEnv,
@@ -308,7 +448,8 @@ pub unsafe extern "system" fn CallLongMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual).map_or(0, |v| v.extract_long())
 }
 pub unsafe extern "system" fn CallLongMethodA(
 	env: *mut JNIEnv,
@@ -316,15 +457,18 @@ pub unsafe extern "system" fn CallLongMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual).map_or(0, |v| v.extract_long())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallFloatMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	CallFloatMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallFloatMethodV(
 	env: *mut JNIEnv,
@@ -332,7 +476,8 @@ pub unsafe extern "system" fn CallFloatMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual).map_or(0.0, |v| v.extract_float())
 }
 pub unsafe extern "system" fn CallFloatMethodA(
 	env: *mut JNIEnv,
@@ -340,15 +485,18 @@ pub unsafe extern "system" fn CallFloatMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual).map_or(0.0, |v| v.extract_float())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallDoubleMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	CallDoubleMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallDoubleMethodV(
 	env: *mut JNIEnv,
@@ -356,7 +504,8 @@ pub unsafe extern "system" fn CallDoubleMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual).map_or(0.0, |v| v.extract_double())
 }
 pub unsafe extern "system" fn CallDoubleMethodA(
 	env: *mut JNIEnv,
@@ -364,10 +513,18 @@ pub unsafe extern "system" fn CallDoubleMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual).map_or(0.0, |v| v.extract_double())
 }
-pub unsafe extern "C" fn CallVoidMethod(env: *mut JNIEnv, obj: jobject, methodID: jmethodID, ...) {
-	todo!();
+#[cfg(feature = "c_variadic")]
+pub unsafe extern "C" fn CallVoidMethod(
+	env: *mut JNIEnv,
+	obj: jobject,
+	methodID: jmethodID,
+	mut args: ...
+) {
+	let _vm = gc::in_vm();
+	CallVoidMethodV(env, obj, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallVoidMethodV(
 	env: *mut JNIEnv,
@@ -375,7 +532,8 @@ pub unsafe extern "system" fn CallVoidMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Virtual);
 }
 pub unsafe extern "system" fn CallVoidMethodA(
 	env: *mut JNIEnv,
@@ -383,16 +541,25 @@ pub unsafe extern "system" fn CallVoidMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Virtual);
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualObjectMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualObjectMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualObjectMethodV(
 	env: *mut JNIEnv,
@@ -401,7 +568,9 @@ pub unsafe extern "system" fn CallNonvirtualObjectMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual)
+		.map_or(ptr::null_mut(), oop_to_jobject)
 }
 pub unsafe extern "system" fn CallNonvirtualObjectMethodA(
 	env: *mut JNIEnv,
@@ -410,16 +579,26 @@ pub unsafe extern "system" fn CallNonvirtualObjectMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual)
+		.map_or(ptr::null_mut(), oop_to_jobject)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualBooleanMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualBooleanMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualBooleanMethodV(
 	env: *mut JNIEnv,
@@ -428,7 +607,9 @@ pub unsafe extern "system" fn CallNonvirtualBooleanMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jboolean)
 }
 pub unsafe extern "system" fn CallNonvirtualBooleanMethodA(
 	env: *mut JNIEnv,
@@ -437,16 +618,26 @@ pub unsafe extern "system" fn CallNonvirtualBooleanMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jboolean)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualByteMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualByteMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualByteMethodV(
 	env: *mut JNIEnv,
@@ -455,7 +646,9 @@ pub unsafe extern "system" fn CallNonvirtualByteMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jbyte)
 }
 pub unsafe extern "system" fn CallNonvirtualByteMethodA(
 	env: *mut JNIEnv,
@@ -464,16 +657,26 @@ pub unsafe extern "system" fn CallNonvirtualByteMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jbyte)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualCharMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualCharMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualCharMethodV(
 	env: *mut JNIEnv,
@@ -482,7 +685,9 @@ pub unsafe extern "system" fn CallNonvirtualCharMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jchar)
 }
 pub unsafe extern "system" fn CallNonvirtualCharMethodA(
 	env: *mut JNIEnv,
@@ -491,16 +696,26 @@ pub unsafe extern "system" fn CallNonvirtualCharMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jchar)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualShortMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualShortMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualShortMethodV(
 	env: *mut JNIEnv,
@@ -509,7 +724,9 @@ pub unsafe extern "system" fn CallNonvirtualShortMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jshort)
 }
 pub unsafe extern "system" fn CallNonvirtualShortMethodA(
 	env: *mut JNIEnv,
@@ -518,16 +735,26 @@ pub unsafe extern "system" fn CallNonvirtualShortMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual)
+		.map_or(0, |v| v.extract_int() as jshort)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualIntMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualIntMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualIntMethodV(
 	env: *mut JNIEnv,
@@ -536,7 +763,8 @@ pub unsafe extern "system" fn CallNonvirtualIntMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual).map_or(0, |v| v.extract_int())
 }
 pub unsafe extern "system" fn CallNonvirtualIntMethodA(
 	env: *mut JNIEnv,
@@ -545,16 +773,25 @@ pub unsafe extern "system" fn CallNonvirtualIntMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual).map_or(0, |v| v.extract_int())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualLongMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualLongMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualLongMethodV(
 	env: *mut JNIEnv,
@@ -563,7 +800,8 @@ pub unsafe extern "system" fn CallNonvirtualLongMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual).map_or(0, |v| v.extract_long())
 }
 pub unsafe extern "system" fn CallNonvirtualLongMethodA(
 	env: *mut JNIEnv,
@@ -572,16 +810,25 @@ pub unsafe extern "system" fn CallNonvirtualLongMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual).map_or(0, |v| v.extract_long())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualFloatMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualFloatMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualFloatMethodV(
 	env: *mut JNIEnv,
@@ -590,7 +837,9 @@ pub unsafe extern "system" fn CallNonvirtualFloatMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual)
+		.map_or(0.0, |v| v.extract_float())
 }
 pub unsafe extern "system" fn CallNonvirtualFloatMethodA(
 	env: *mut JNIEnv,
@@ -599,16 +848,26 @@ pub unsafe extern "system" fn CallNonvirtualFloatMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual)
+		.map_or(0.0, |v| v.extract_float())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualDoubleMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualDoubleMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualDoubleMethodV(
 	env: *mut JNIEnv,
@@ -617,7 +876,9 @@ pub unsafe extern "system" fn CallNonvirtualDoubleMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual)
+		.map_or(0.0, |v| v.extract_double())
 }
 pub unsafe extern "system" fn CallNonvirtualDoubleMethodA(
 	env: *mut JNIEnv,
@@ -626,16 +887,26 @@ pub unsafe extern "system" fn CallNonvirtualDoubleMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual)
+		.map_or(0.0, |v| v.extract_double())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallNonvirtualVoidMethod(
 	env: *mut JNIEnv,
 	obj: jobject,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	CallNonvirtualVoidMethodV(
+		env,
+		obj,
+		clazz,
+		methodID,
+		&mut args as *mut VaList as va_list,
+	)
 }
 pub unsafe extern "system" fn CallNonvirtualVoidMethodV(
 	env: *mut JNIEnv,
@@ -644,7 +915,8 @@ pub unsafe extern "system" fn CallNonvirtualVoidMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::V(args), Dispatch::Nonvirtual);
 }
 pub unsafe extern "system" fn CallNonvirtualVoidMethodA(
 	env: *mut JNIEnv,
@@ -653,7 +925,8 @@ pub unsafe extern "system" fn CallNonvirtualVoidMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(obj, methodID, Args::A(args), Dispatch::Nonvirtual);
 }
 pub unsafe extern "system" fn GetFieldID(
 	env: *mut JNIEnv,
@@ -661,70 +934,80 @@ pub unsafe extern "system" fn GetFieldID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jfieldID {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field_id(clazz, name, sig, false)
 }
 pub unsafe extern "system" fn GetObjectField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	oop_to_jobject(get_field(obj, fieldID))
 }
 pub unsafe extern "system" fn GetBooleanField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_int() as jboolean
 }
 pub unsafe extern "system" fn GetByteField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_int() as jbyte
 }
 pub unsafe extern "system" fn GetCharField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_int() as jchar
 }
 pub unsafe extern "system" fn GetShortField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_int() as jshort
 }
 pub unsafe extern "system" fn GetIntField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_int()
 }
 pub unsafe extern "system" fn GetLongField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_long()
 }
 pub unsafe extern "system" fn GetFloatField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_float()
 }
 pub unsafe extern "system" fn GetDoubleField(
 	env: *mut JNIEnv,
 	obj: jobject,
 	fieldID: jfieldID,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field(obj, fieldID).extract_double()
 }
 pub unsafe extern "system" fn SetObjectField(
 	env: *mut JNIEnv,
@@ -732,7 +1015,8 @@ pub unsafe extern "system" fn SetObjectField(
 	fieldID: jfieldID,
 	val: jobject,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, jobject_to_oop(val))
 }
 pub unsafe extern "system" fn SetBooleanField(
 	env: *mut JNIEnv,
@@ -740,7 +1024,8 @@ pub unsafe extern "system" fn SetBooleanField(
 	fieldID: jfieldID,
 	val: jboolean,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_int(val as i32))
 }
 pub unsafe extern "system" fn SetByteField(
 	env: *mut JNIEnv,
@@ -748,7 +1033,8 @@ pub unsafe extern "system" fn SetByteField(
 	fieldID: jfieldID,
 	val: jbyte,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_int(val as i32))
 }
 pub unsafe extern "system" fn SetCharField(
 	env: *mut JNIEnv,
@@ -756,7 +1042,8 @@ pub unsafe extern "system" fn SetCharField(
 	fieldID: jfieldID,
 	val: jchar,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_int(val as i32))
 }
 pub unsafe extern "system" fn SetShortField(
 	env: *mut JNIEnv,
@@ -764,7 +1051,8 @@ pub unsafe extern "system" fn SetShortField(
 	fieldID: jfieldID,
 	val: jshort,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_int(val as i32))
 }
 pub unsafe extern "system" fn SetIntField(
 	env: *mut JNIEnv,
@@ -772,7 +1060,8 @@ pub unsafe extern "system" fn SetIntField(
 	fieldID: jfieldID,
 	val: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_int(val))
 }
 pub unsafe extern "system" fn SetLongField(
 	env: *mut JNIEnv,
@@ -780,7 +1069,8 @@ pub unsafe extern "system" fn SetLongField(
 	fieldID: jfieldID,
 	val: jlong,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_long(val))
 }
 pub unsafe extern "system" fn SetFloatField(
 	env: *mut JNIEnv,
@@ -788,7 +1078,8 @@ pub unsafe extern "system" fn SetFloatField(
 	fieldID: jfieldID,
 	val: jfloat,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_float(val))
 }
 pub unsafe extern "system" fn SetDoubleField(
 	env: *mut JNIEnv,
@@ -796,7 +1087,8 @@ pub unsafe extern "system" fn SetDoubleField(
 	fieldID: jfieldID,
 	val: jdouble,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_field(obj, fieldID, Oop::new_double(val))
 }
 pub unsafe extern "system" fn GetStaticMethodID(
 	env: *mut JNIEnv,
@@ -804,15 +1096,18 @@ pub unsafe extern "system" fn GetStaticMethodID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jmethodID {
-	todo!();
+	let _vm = gc::in_vm();
+	get_method_id(clazz, name, sig, true)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticObjectMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticObjectMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticObjectMethodV(
 	env: *mut JNIEnv,
@@ -820,7 +1115,9 @@ pub unsafe extern "system" fn CallStaticObjectMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(ptr::null_mut(), oop_to_jobject)
 }
 pub unsafe extern "system" fn CallStaticObjectMethodA(
 	env: *mut JNIEnv,
@@ -828,15 +1125,19 @@ pub unsafe extern "system" fn CallStaticObjectMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(ptr::null_mut(), oop_to_jobject)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticBooleanMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticBooleanMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticBooleanMethodV(
 	env: *mut JNIEnv,
@@ -844,7 +1145,9 @@ pub unsafe extern "system" fn CallStaticBooleanMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jboolean)
 }
 pub unsafe extern "system" fn CallStaticBooleanMethodA(
 	env: *mut JNIEnv,
@@ -852,15 +1155,19 @@ pub unsafe extern "system" fn CallStaticBooleanMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jboolean)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticByteMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticByteMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticByteMethodV(
 	env: *mut JNIEnv,
@@ -868,7 +1175,9 @@ pub unsafe extern "system" fn CallStaticByteMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jbyte)
 }
 pub unsafe extern "system" fn CallStaticByteMethodA(
 	env: *mut JNIEnv,
@@ -876,15 +1185,19 @@ pub unsafe extern "system" fn CallStaticByteMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jbyte)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticCharMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticCharMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticCharMethodV(
 	env: *mut JNIEnv,
@@ -892,7 +1205,9 @@ pub unsafe extern "system" fn CallStaticCharMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jchar)
 }
 pub unsafe extern "system" fn CallStaticCharMethodA(
 	env: *mut JNIEnv,
@@ -900,15 +1215,19 @@ pub unsafe extern "system" fn CallStaticCharMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jchar)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticShortMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticShortMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticShortMethodV(
 	env: *mut JNIEnv,
@@ -916,7 +1235,9 @@ pub unsafe extern "system" fn CallStaticShortMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jshort)
 }
 pub unsafe extern "system" fn CallStaticShortMethodA(
 	env: *mut JNIEnv,
@@ -924,15 +1245,19 @@ pub unsafe extern "system" fn CallStaticShortMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int() as jshort)
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticIntMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticIntMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticIntMethodV(
 	env: *mut JNIEnv,
@@ -940,7 +1265,9 @@ pub unsafe extern "system" fn CallStaticIntMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int())
 }
 pub unsafe extern "system" fn CallStaticIntMethodA(
 	env: *mut JNIEnv,
@@ -948,15 +1275,19 @@ pub unsafe extern "system" fn CallStaticIntMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_int())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticLongMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticLongMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticLongMethodV(
 	env: *mut JNIEnv,
@@ -964,7 +1295,9 @@ pub unsafe extern "system" fn CallStaticLongMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_long())
 }
 pub unsafe extern "system" fn CallStaticLongMethodA(
 	env: *mut JNIEnv,
@@ -972,15 +1305,19 @@ pub unsafe extern "system" fn CallStaticLongMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0, |v| v.extract_long())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticFloatMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticFloatMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticFloatMethodV(
 	env: *mut JNIEnv,
@@ -988,7 +1325,9 @@ pub unsafe extern "system" fn CallStaticFloatMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0.0, |v| v.extract_float())
 }
 pub unsafe extern "system" fn CallStaticFloatMethodA(
 	env: *mut JNIEnv,
@@ -996,15 +1335,19 @@ pub unsafe extern "system" fn CallStaticFloatMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0.0, |v| v.extract_float())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticDoubleMethod(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticDoubleMethodV(env, clazz, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticDoubleMethodV(
 	env: *mut JNIEnv,
@@ -1012,7 +1355,9 @@ pub unsafe extern "system" fn CallStaticDoubleMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static)
+		.map_or(0.0, |v| v.extract_double())
 }
 pub unsafe extern "system" fn CallStaticDoubleMethodA(
 	env: *mut JNIEnv,
@@ -1020,15 +1365,19 @@ pub unsafe extern "system" fn CallStaticDoubleMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static)
+		.map_or(0.0, |v| v.extract_double())
 }
+#[cfg(feature = "c_variadic")]
 pub unsafe extern "C" fn CallStaticVoidMethod(
 	env: *mut JNIEnv,
 	cls: jclass,
 	methodID: jmethodID,
-	...
+	mut args: ...
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	CallStaticVoidMethodV(env, cls, methodID, &mut args as *mut VaList as va_list)
 }
 pub unsafe extern "system" fn CallStaticVoidMethodV(
 	env: *mut JNIEnv,
@@ -1036,7 +1385,8 @@ pub unsafe extern "system" fn CallStaticVoidMethodV(
 	methodID: jmethodID,
 	args: va_list,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::V(args), Dispatch::Static);
 }
 pub unsafe extern "system" fn CallStaticVoidMethodA(
 	env: *mut JNIEnv,
@@ -1044,7 +1394,8 @@ pub unsafe extern "system" fn CallStaticVoidMethodA(
 	methodID: jmethodID,
 	args: *const jvalue,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	call_method(ptr::null_mut(), methodID, Args::A(args), Dispatch::Static);
 }
 pub unsafe extern "system" fn GetStaticFieldID(
 	env: *mut JNIEnv,
@@ -1052,70 +1403,80 @@ pub unsafe extern "system" fn GetStaticFieldID(
 	name: *const c_char,
 	sig: *const c_char,
 ) -> jfieldID {
-	todo!();
+	let _vm = gc::in_vm();
+	get_field_id(clazz, name, sig, true)
 }
 pub unsafe extern "system" fn GetStaticObjectField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	oop_to_jobject(get_static_field(fieldID))
 }
 pub unsafe extern "system" fn GetStaticBooleanField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_int() as jboolean
 }
 pub unsafe extern "system" fn GetStaticByteField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_int() as jbyte
 }
 pub unsafe extern "system" fn GetStaticCharField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_int() as jchar
 }
 pub unsafe extern "system" fn GetStaticShortField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_int() as jshort
 }
 pub unsafe extern "system" fn GetStaticIntField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_int()
 }
 pub unsafe extern "system" fn GetStaticLongField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_long()
 }
 pub unsafe extern "system" fn GetStaticFloatField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_float()
 }
 pub unsafe extern "system" fn GetStaticDoubleField(
 	env: *mut JNIEnv,
 	clazz: jclass,
 	fieldID: jfieldID,
 ) -> jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	get_static_field(fieldID).extract_double()
 }
 pub unsafe extern "system" fn SetStaticObjectField(
 	env: *mut JNIEnv,
@@ -1123,7 +1484,8 @@ pub unsafe extern "system" fn SetStaticObjectField(
 	fieldID: jfieldID,
 	value: jobject,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, jobject_to_oop(value))
 }
 pub unsafe extern "system" fn SetStaticBooleanField(
 	env: *mut JNIEnv,
@@ -1131,7 +1493,8 @@ pub unsafe extern "system" fn SetStaticBooleanField(
 	fieldID: jfieldID,
 	value: jboolean,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_int(value as i32))
 }
 pub unsafe extern "system" fn SetStaticByteField(
 	env: *mut JNIEnv,
@@ -1139,7 +1502,8 @@ pub unsafe extern "system" fn SetStaticByteField(
 	fieldID: jfieldID,
 	value: jbyte,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_int(value as i32))
 }
 pub unsafe extern "system" fn SetStaticCharField(
 	env: *mut JNIEnv,
@@ -1147,7 +1511,8 @@ pub unsafe extern "system" fn SetStaticCharField(
 	fieldID: jfieldID,
 	value: jchar,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_int(value as i32))
 }
 pub unsafe extern "system" fn SetStaticShortField(
 	env: *mut JNIEnv,
@@ -1155,7 +1520,8 @@ pub unsafe extern "system" fn SetStaticShortField(
 	fieldID: jfieldID,
 	value: jshort,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_int(value as i32))
 }
 pub unsafe extern "system" fn SetStaticIntField(
 	env: *mut JNIEnv,
@@ -1163,7 +1529,8 @@ pub unsafe extern "system" fn SetStaticIntField(
 	fieldID: jfieldID,
 	value: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_int(value))
 }
 pub unsafe extern "system" fn SetStaticLongField(
 	env: *mut JNIEnv,
@@ -1171,7 +1538,8 @@ pub unsafe extern "system" fn SetStaticLongField(
 	fieldID: jfieldID,
 	value: jlong,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_long(value))
 }
 pub unsafe extern "system" fn SetStaticFloatField(
 	env: *mut JNIEnv,
@@ -1179,7 +1547,8 @@ pub unsafe extern "system" fn SetStaticFloatField(
 	fieldID: jfieldID,
 	value: jfloat,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_float(value))
 }
 pub unsafe extern "system" fn SetStaticDoubleField(
 	env: *mut JNIEnv,
@@ -1187,54 +1556,78 @@ pub unsafe extern "system" fn SetStaticDoubleField(
 	fieldID: jfieldID,
 	value: jdouble,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	set_static_field(fieldID, Oop::new_double(value))
 }
 pub unsafe extern "system" fn NewString(
 	env: *mut JNIEnv,
 	unicode: *const jchar,
 	len: jsize,
 ) -> jstring {
-	todo!();
+	let _vm = gc::in_vm();
+	let chars = std::slice::from_raw_parts(unicode, len as usize);
+	oop_to_jobject(vm::util::oop::new_java_lang_string4(chars))
 }
 pub unsafe extern "system" fn GetStringLength(env: *mut JNIEnv, str: jstring) -> jsize {
-	todo!();
+	let _vm = gc::in_vm();
+	string_value(str).len() as jsize
 }
 pub unsafe extern "system" fn GetStringChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	isCopy: *mut jboolean,
 ) -> *const jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_TRUE);
+	copy_to_c(&string_value(str)) as *const jchar
 }
 pub unsafe extern "system" fn ReleaseStringChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	chars: *const jchar,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	libc::free(chars as *mut c_void)
 }
 pub unsafe extern "system" fn NewStringUTF(env: *mut JNIEnv, utf: *const c_char) -> jstring {
-	todo!();
+	let _vm = gc::in_vm();
+	let bytes = CStr::from_ptr(utf).to_bytes();
+	let chars = util::from_modified_utf8(bytes);
+	oop_to_jobject(vm::util::oop::new_java_lang_string4(&chars))
 }
 pub unsafe extern "system" fn GetStringUTFLength(env: *mut JNIEnv, str: jstring) -> jsize {
-	todo!();
+	let _vm = gc::in_vm();
+	util::to_modified_utf8(&string_value(str)).len() as jsize
 }
 pub unsafe extern "system" fn GetStringUTFChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	isCopy: *mut jboolean,
 ) -> *const c_char {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_TRUE);
+	let mut v = util::to_modified_utf8(&string_value(str));
+	v.push(0);
+	copy_to_c(&v) as *const c_char
 }
 pub unsafe extern "system" fn ReleaseStringUTFChars(
 	env: *mut JNIEnv,
 	str: jstring,
 	chars: *const c_char,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	libc::free(chars as *mut c_void)
 }
 pub unsafe extern "system" fn GetArrayLength(env: *mut JNIEnv, array: jarray) -> jsize {
-	todo!();
+	let _vm = gc::in_vm();
+	let array = jobject_to_oop(array);
+	let rf = array.extract_ref();
+	let ptr = rf.get_raw_ptr();
+	match &(*ptr).v {
+		RefKind::Array(ary) => ary.elements.len() as jsize,
+		RefKind::TypeArray(ary) => ary.len() as jsize,
+		_ => unreachable!(),
+	}
 }
 pub unsafe extern "system" fn NewObjectArray(
 	env: *mut JNIEnv,
@@ -1242,14 +1635,23 @@ pub unsafe extern "system" fn NewObjectArray(
 	clazz: jclass,
 	init: jobject,
 ) -> jobjectArray {
-	todo!();
+	let _vm = gc::in_vm();
+	new_object_array(len, clazz, jobject_to_oop(init)).map_or(ptr::null_mut(), oop_to_jobject)
 }
 pub unsafe extern "system" fn GetObjectArrayElement(
 	env: *mut JNIEnv,
 	array: jobjectArray,
 	index: jsize,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	let array = jobject_to_oop(array);
+	match check_bounds(&array, index, 1) {
+		Some(_) => {
+			let rf = array.extract_ref();
+			oop_to_jobject(rf.extract_array().elements[index as usize].clone())
+		}
+		None => ptr::null_mut(),
+	}
 }
 pub unsafe extern "system" fn SetObjectArrayElement(
 	env: *mut JNIEnv,
@@ -1257,87 +1659,155 @@ pub unsafe extern "system" fn SetObjectArrayElement(
 	index: jsize,
 	val: jobject,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	let array = jobject_to_oop(array);
+	if check_bounds(&array, index, 1).is_some() {
+		let v = jobject_to_oop(val);
+		if !v.is_null() {
+			let elm = array_component(&array).unwrap();
+			if !cmp::instance_of(object_class(&v), elm) {
+				throw(cls_consts::J_ARRAY_STORE_EX, None);
+				return;
+			}
+		}
+		Class::put_field_value2(array.extract_ref(), index as usize, v);
+	}
 }
 pub unsafe extern "system" fn NewBooleanArray(env: *mut JNIEnv, len: jsize) -> jbooleanArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_bool_ary2(vec![0u8; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn NewByteArray(env: *mut JNIEnv, len: jsize) -> jbyteArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_byte_ary2(vec![0u8; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn NewCharArray(env: *mut JNIEnv, len: jsize) -> jcharArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_char_ary2(vec![0u16; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn NewShortArray(env: *mut JNIEnv, len: jsize) -> jshortArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_short_ary2(vec![0i16; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn NewIntArray(env: *mut JNIEnv, len: jsize) -> jintArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_int_ary2(vec![0i32; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn NewLongArray(env: *mut JNIEnv, len: jsize) -> jlongArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_long_ary2(vec![0i64; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn NewFloatArray(env: *mut JNIEnv, len: jsize) -> jfloatArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_float_ary2(vec![0f32; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn NewDoubleArray(env: *mut JNIEnv, len: jsize) -> jdoubleArray {
-	todo!();
+	let _vm = gc::in_vm();
+	if check_len(len) {
+		oop_to_jobject(Oop::new_double_ary2(vec![0f64; len as usize]))
+	} else {
+		ptr::null_mut()
+	}
 }
 pub unsafe extern "system" fn GetBooleanArrayElements(
 	env: *mut JNIEnv,
 	array: jbooleanArray,
 	isCopy: *mut jboolean,
 ) -> *mut jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jboolean
 }
 pub unsafe extern "system" fn GetByteArrayElements(
 	env: *mut JNIEnv,
 	array: jbyteArray,
 	isCopy: *mut jboolean,
 ) -> *mut jbyte {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jbyte
 }
 pub unsafe extern "system" fn GetCharArrayElements(
 	env: *mut JNIEnv,
 	array: jcharArray,
 	isCopy: *mut jboolean,
 ) -> *mut jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jchar
 }
 pub unsafe extern "system" fn GetShortArrayElements(
 	env: *mut JNIEnv,
 	array: jshortArray,
 	isCopy: *mut jboolean,
 ) -> *mut jshort {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jshort
 }
 pub unsafe extern "system" fn GetIntArrayElements(
 	env: *mut JNIEnv,
 	array: jintArray,
 	isCopy: *mut jboolean,
 ) -> *mut jint {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jint
 }
 pub unsafe extern "system" fn GetLongArrayElements(
 	env: *mut JNIEnv,
 	array: jlongArray,
 	isCopy: *mut jboolean,
 ) -> *mut jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jlong
 }
 pub unsafe extern "system" fn GetFloatArrayElements(
 	env: *mut JNIEnv,
 	array: jfloatArray,
 	isCopy: *mut jboolean,
 ) -> *mut jfloat {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jfloat
 }
 pub unsafe extern "system" fn GetDoubleArrayElements(
 	env: *mut JNIEnv,
 	array: jdoubleArray,
 	isCopy: *mut jboolean,
 ) -> *mut jdouble {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array)) as *mut jdouble
 }
 pub unsafe extern "system" fn ReleaseBooleanArrayElements(
 	env: *mut JNIEnv,
@@ -1345,7 +1815,8 @@ pub unsafe extern "system" fn ReleaseBooleanArrayElements(
 	elems: *mut jboolean,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn ReleaseByteArrayElements(
 	env: *mut JNIEnv,
@@ -1353,7 +1824,8 @@ pub unsafe extern "system" fn ReleaseByteArrayElements(
 	elems: *mut jbyte,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn ReleaseCharArrayElements(
 	env: *mut JNIEnv,
@@ -1361,7 +1833,8 @@ pub unsafe extern "system" fn ReleaseCharArrayElements(
 	elems: *mut jchar,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn ReleaseShortArrayElements(
 	env: *mut JNIEnv,
@@ -1369,7 +1842,8 @@ pub unsafe extern "system" fn ReleaseShortArrayElements(
 	elems: *mut jshort,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn ReleaseIntArrayElements(
 	env: *mut JNIEnv,
@@ -1377,7 +1851,8 @@ pub unsafe extern "system" fn ReleaseIntArrayElements(
 	elems: *mut jint,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn ReleaseLongArrayElements(
 	env: *mut JNIEnv,
@@ -1385,7 +1860,8 @@ pub unsafe extern "system" fn ReleaseLongArrayElements(
 	elems: *mut jlong,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn ReleaseFloatArrayElements(
 	env: *mut JNIEnv,
@@ -1393,7 +1869,8 @@ pub unsafe extern "system" fn ReleaseFloatArrayElements(
 	elems: *mut jfloat,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn ReleaseDoubleArrayElements(
 	env: *mut JNIEnv,
@@ -1401,7 +1878,8 @@ pub unsafe extern "system" fn ReleaseDoubleArrayElements(
 	elems: *mut jdouble,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn GetBooleanArrayRegion(
 	env: *mut JNIEnv,
@@ -1410,7 +1888,10 @@ pub unsafe extern "system" fn GetBooleanArrayRegion(
 	l: jsize,
 	buf: *mut jboolean,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, l) {
+		ptr::copy_nonoverlapping(ptr as *const jboolean, buf, l as usize);
+	}
 }
 pub unsafe extern "system" fn GetByteArrayRegion(
 	env: *mut JNIEnv,
@@ -1419,7 +1900,10 @@ pub unsafe extern "system" fn GetByteArrayRegion(
 	len: jsize,
 	buf: *mut jbyte,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(ptr as *const jbyte, buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn GetCharArrayRegion(
 	env: *mut JNIEnv,
@@ -1428,7 +1912,10 @@ pub unsafe extern "system" fn GetCharArrayRegion(
 	len: jsize,
 	buf: *mut jchar,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(ptr as *const jchar, buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn GetShortArrayRegion(
 	env: *mut JNIEnv,
@@ -1437,7 +1924,10 @@ pub unsafe extern "system" fn GetShortArrayRegion(
 	len: jsize,
 	buf: *mut jshort,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(ptr as *const jshort, buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn GetIntArrayRegion(
 	env: *mut JNIEnv,
@@ -1446,7 +1936,10 @@ pub unsafe extern "system" fn GetIntArrayRegion(
 	len: jsize,
 	buf: *mut jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(ptr as *const jint, buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn GetLongArrayRegion(
 	env: *mut JNIEnv,
@@ -1455,7 +1948,10 @@ pub unsafe extern "system" fn GetLongArrayRegion(
 	len: jsize,
 	buf: *mut jlong,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(ptr as *const jlong, buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn GetFloatArrayRegion(
 	env: *mut JNIEnv,
@@ -1464,7 +1960,10 @@ pub unsafe extern "system" fn GetFloatArrayRegion(
 	len: jsize,
 	buf: *mut jfloat,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(ptr as *const jfloat, buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn GetDoubleArrayRegion(
 	env: *mut JNIEnv,
@@ -1473,7 +1972,10 @@ pub unsafe extern "system" fn GetDoubleArrayRegion(
 	len: jsize,
 	buf: *mut jdouble,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(ptr as *const jdouble, buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn SetBooleanArrayRegion(
 	env: *mut JNIEnv,
@@ -1482,7 +1984,10 @@ pub unsafe extern "system" fn SetBooleanArrayRegion(
 	l: jsize,
 	buf: *const jboolean,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, l) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jboolean, l as usize);
+	}
 }
 pub unsafe extern "system" fn SetByteArrayRegion(
 	env: *mut JNIEnv,
@@ -1491,7 +1996,10 @@ pub unsafe extern "system" fn SetByteArrayRegion(
 	len: jsize,
 	buf: *const jbyte,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jbyte, len as usize);
+	}
 }
 pub unsafe extern "system" fn SetCharArrayRegion(
 	env: *mut JNIEnv,
@@ -1500,7 +2008,10 @@ pub unsafe extern "system" fn SetCharArrayRegion(
 	len: jsize,
 	buf: *const jchar,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jchar, len as usize);
+	}
 }
 pub unsafe extern "system" fn SetShortArrayRegion(
 	env: *mut JNIEnv,
@@ -1509,7 +2020,10 @@ pub unsafe extern "system" fn SetShortArrayRegion(
 	len: jsize,
 	buf: *const jshort,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jshort, len as usize);
+	}
 }
 pub unsafe extern "system" fn SetIntArrayRegion(
 	env: *mut JNIEnv,
@@ -1518,7 +2032,10 @@ pub unsafe extern "system" fn SetIntArrayRegion(
 	len: jsize,
 	buf: *const jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jint, len as usize);
+	}
 }
 pub unsafe extern "system" fn SetLongArrayRegion(
 	env: *mut JNIEnv,
@@ -1527,7 +2044,10 @@ pub unsafe extern "system" fn SetLongArrayRegion(
 	len: jsize,
 	buf: *const jlong,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jlong, len as usize);
+	}
 }
 pub unsafe extern "system" fn SetFloatArrayRegion(
 	env: *mut JNIEnv,
@@ -1536,7 +2056,10 @@ pub unsafe extern "system" fn SetFloatArrayRegion(
 	len: jsize,
 	buf: *const jfloat,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jfloat, len as usize);
+	}
 }
 pub unsafe extern "system" fn SetDoubleArrayRegion(
 	env: *mut JNIEnv,
@@ -1545,7 +2068,10 @@ pub unsafe extern "system" fn SetDoubleArrayRegion(
 	len: jsize,
 	buf: *const jdouble,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	if let Some(ptr) = check_bounds(&jobject_to_oop(array), start, len) {
+		ptr::copy_nonoverlapping(buf, ptr as *mut jdouble, len as usize);
+	}
 }
 pub unsafe extern "system" fn RegisterNatives(
 	env: *mut JNIEnv,
@@ -1553,19 +2079,68 @@ pub unsafe extern "system" fn RegisterNatives(
 	methods: *const JNINativeMethod,
 	nMethods: jint,
 ) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	let cls = jclass_to_class_ref(clazz).unwrap();
+	let cls = cls.get_class();
+	let methods = std::slice::from_raw_parts(methods, nMethods as usize);
+	for it in methods {
+		let name = CStr::from_ptr(it.name).to_bytes();
+		let signature = CStr::from_ptr(it.signature).to_bytes();
+		if cls
+			.get_this_class_method(&br(name), &br(signature))
+			.is_err()
+		{
+			let msg = format!(
+				"{}.{}{}",
+				String::from_utf8_lossy(cls.name.as_slice()).replace('/', "."),
+				String::from_utf8_lossy(name),
+				String::from_utf8_lossy(signature)
+			);
+			throw(cls_consts::J_NO_SUCH_METHOD_ERROR, Some(msg));
+			return JNI_ERR;
+		}
+		jni::register_native(cls.name.as_slice(), name, signature, it.fnPtr as usize);
+	}
+	JNI_OK
 }
 pub unsafe extern "system" fn UnregisterNatives(env: *mut JNIEnv, clazz: jclass) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	let cls = jclass_to_class_ref(clazz).unwrap();
+	jni::unregister_natives(cls.get_class().name.as_slice());
+	JNI_OK
 }
 pub unsafe extern "system" fn MonitorEnter(env: *mut JNIEnv, obj: jobject) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	match jobject_to_oop(obj) {
+		Oop::Ref(rf) => {
+			rf.monitor_enter();
+			JNI_OK
+		}
+		_ => JNI_ERR,
+	}
 }
 pub unsafe extern "system" fn MonitorExit(env: *mut JNIEnv, obj: jobject) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	match jobject_to_oop(obj) {
+		Oop::Ref(rf) => match rf.monitor_exit() {
+			Ok(_) => JNI_OK,
+			Err(_) => {
+				throw(cls_consts::J_ILLEGAL_MONITOR_STATE, None);
+				JNI_ERR
+			}
+		},
+		_ => JNI_ERR,
+	}
 }
 pub unsafe extern "system" fn GetJavaVM(env: *mut JNIEnv, vm: *mut *mut JavaVM) -> jint {
-	todo!();
+	let _vm = gc::in_vm();
+	match crate::invocation::get_java_vm() {
+		Some(it) => {
+			*vm = it;
+			JNI_OK
+		}
+		None => JNI_ERR,
+	}
 }
 pub unsafe extern "system" fn GetStringRegion(
 	env: *mut JNIEnv,
@@ -1574,7 +2149,11 @@ pub unsafe extern "system" fn GetStringRegion(
 	len: jsize,
 	buf: *mut jchar,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	let chars = string_value(str);
+	if check_string_bounds(chars.len(), start, len) {
+		ptr::copy_nonoverlapping(chars.as_ptr().add(start as usize), buf, len as usize);
+	}
 }
 pub unsafe extern "system" fn GetStringUTFRegion(
 	env: *mut JNIEnv,
@@ -1583,14 +2162,23 @@ pub unsafe extern "system" fn GetStringUTFRegion(
 	len: jsize,
 	buf: *mut c_char,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	let chars = string_value(str);
+	if check_string_bounds(chars.len(), start, len) {
+		let (start, len) = (start as usize, len as usize);
+		let mut v = util::to_modified_utf8(&chars[start..start + len]);
+		v.push(0);
+		ptr::copy_nonoverlapping(v.as_ptr() as *const c_char, buf, v.len());
+	}
 }
 pub unsafe extern "system" fn GetPrimitiveArrayCritical(
 	env: *mut JNIEnv,
 	array: jarray,
 	isCopy: *mut jboolean,
 ) -> *mut c_void {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	pinned_array_ptr(&jobject_to_oop(array))
 }
 pub unsafe extern "system" fn ReleasePrimitiveArrayCritical(
 	env: *mut JNIEnv,
@@ -1598,47 +2186,886 @@ pub unsafe extern "system" fn ReleasePrimitiveArrayCritical(
 	carray: *mut c_void,
 	mode: jint,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the elements are not copied
 }
 pub unsafe extern "system" fn GetStringCritical(
 	env: *mut JNIEnv,
 	string: jstring,
 	isCopy: *mut jboolean,
 ) -> *const jchar {
-	todo!();
+	let _vm = gc::in_vm();
+	set_is_copy(isCopy, JNI_FALSE);
+	let v = vm::runtime::method_handles::get_field(
+		&jobject_to_oop(string),
+		b"java/lang/String",
+		"value",
+		"[C",
+	);
+	pinned_array_ptr(&v) as *const jchar
 }
 pub unsafe extern "system" fn ReleaseStringCritical(
 	env: *mut JNIEnv,
 	string: jstring,
 	cstring: *const jchar,
 ) {
-	todo!();
+	let _vm = gc::in_vm();
+	//the chars are not copied
 }
 pub unsafe extern "system" fn NewWeakGlobalRef(env: *mut JNIEnv, obj: jobject) -> jweak {
-	todo!();
+	let _vm = gc::in_vm();
+	jni::new_global_ref(jobject_to_oop(obj), true) as jweak
 }
 pub unsafe extern "system" fn DeleteWeakGlobalRef(env: *mut JNIEnv, ref_: jweak) {
-	todo!();
+	let _vm = gc::in_vm();
+	jni::delete_global_ref(ref_ as jni::Handle)
 }
 pub unsafe extern "system" fn ExceptionCheck(env: *mut JNIEnv) -> jboolean {
-	todo!();
+	let _vm = gc::in_vm();
+	thread::is_meet_ex() as jboolean
 }
 pub unsafe extern "system" fn NewDirectByteBuffer(
 	env: *mut JNIEnv,
 	address: *mut c_void,
 	capacity: jlong,
 ) -> jobject {
-	todo!();
+	let _vm = gc::in_vm();
+	let cls = require_class3(None, b"java/nio/DirectByteBuffer").unwrap();
+	if oop::class::init_class(&cls).is_err() {
+		return ptr::null_mut();
+	}
+	let buf = Oop::new_inst(cls.clone());
+	let args = vec![
+		buf.clone(),
+		Oop::new_long(address as i64),
+		Oop::new_int(capacity as i32),
+	];
+	invoke::invoke_ctor(cls, new_br("(JI)V"), args);
+	if thread::is_meet_ex() {
+		ptr::null_mut()
+	} else {
+		oop_to_jobject(buf)
+	}
 }
 pub unsafe extern "system" fn GetDirectBufferAddress(
 	env: *mut JNIEnv,
 	buf: jobject,
 ) -> *mut c_void {
-	todo!();
+	let _vm = gc::in_vm();
+	direct_buffer_field(buf, "address", "J")
+		.map_or(ptr::null_mut(), |v| v.extract_long() as *mut c_void)
 }
 pub unsafe extern "system" fn GetDirectBufferCapacity(env: *mut JNIEnv, buf: jobject) -> jlong {
-	todo!();
+	let _vm = gc::in_vm();
+	direct_buffer_field(buf, "capacity", "I").map_or(-1, |v| v.extract_int() as jlong)
 }
 pub unsafe extern "system" fn GetObjectRefType(env: *mut JNIEnv, obj: jobject) -> jobjectRefType {
-	todo!();
+	let _vm = gc::in_vm();
+	match jni::ref_type(obj as jni::Handle) {
+		jni::LOCAL_REF => jobjectRefType::JNILocalRefType,
+		jni::GLOBAL_REF => jobjectRefType::JNIGlobalRefType,
+		jni::WEAK_GLOBAL_REF => jobjectRefType::JNIWeakGlobalRefType,
+		_ => jobjectRefType::JNIInvalidRefType,
+	}
+}
+
+////////helper
+
+//the arguments of Call<Type>Method<V|A>
+enum Args {
+	V(va_list),
+	A(*const jvalue),
+}
+
+enum Dispatch {
+	Virtual,
+	Nonvirtual,
+	Static,
+}
+
+lazy_static! {
+	static ref JNI_ENV: FunctionTable = FunctionTable(new_function_table());
+}
+
+struct FunctionTable(JNINativeInterface_);
+
+unsafe impl Send for FunctionTable {}
+unsafe impl Sync for FunctionTable {}
+
+thread_local! {
+	static ENV: Box<JNIEnv> = Box::new(&JNI_ENV.0 as *const JNINativeInterface_);
+}
+
+//the JNIEnv of the current thread, all the threads share the same function table
+pub fn jni_env() -> *mut JNIEnv {
+	ENV.with(|env| env.as_ref() as *const JNIEnv as *mut JNIEnv)
+}
+
+//the C variadic functions need the unstable c_variadic of rustc, see the feature c_variadic
+#[cfg(feature = "c_variadic")]
+macro_rules! variadic {
+	($f:ident) => {
+		Some($f)
+	};
+}
+
+#[cfg(not(feature = "c_variadic"))]
+macro_rules! variadic {
+	($f:ident) => {
+		None
+	};
+}
+
+fn new_function_table() -> JNINativeInterface_ {
+	use std::ptr::null_mut;
+	JNINativeInterface_ {
+		reserved0: null_mut(),
+		reserved1: null_mut(),
+		reserved2: null_mut(),
+		reserved3: null_mut(),
+		GetVersion: Some(GetVersion),
+		DefineClass: Some(DefineClass),
+		FindClass: Some(FindClass),
+		FromReflectedMethod: Some(FromReflectedMethod),
+		FromReflectedField: Some(FromReflectedField),
+		ToReflectedMethod: Some(ToReflectedMethod),
+		GetSuperclass: Some(GetSuperclass),
+		IsAssignableFrom: Some(IsAssignableFrom),
+		ToReflectedField: Some(ToReflectedField),
+		Throw: Some(Throw),
+		ThrowNew: Some(ThrowNew),
+		ExceptionOccurred: Some(ExceptionOccurred),
+		ExceptionDescribe: Some(ExceptionDescribe),
+		ExceptionClear: Some(ExceptionClear),
+		FatalError: Some(FatalError),
+		PushLocalFrame: Some(PushLocalFrame),
+		PopLocalFrame: Some(PopLocalFrame),
+		NewGlobalRef: Some(NewGlobalRef),
+		DeleteGlobalRef: Some(DeleteGlobalRef),
+		DeleteLocalRef: Some(DeleteLocalRef),
+		IsSameObject: Some(IsSameObject),
+		NewLocalRef: Some(NewLocalRef),
+		EnsureLocalCapacity: Some(EnsureLocalCapacity),
+		AllocObject: Some(AllocObject),
+		NewObject: variadic!(NewObject),
+		NewObjectV: Some(NewObjectV),
+		NewObjectA: Some(NewObjectA),
+		GetObjectClass: Some(GetObjectClass),
+		IsInstanceOf: Some(IsInstanceOf),
+		GetMethodID: Some(GetMethodID),
+		CallObjectMethod: variadic!(CallObjectMethod),
+		CallObjectMethodV: Some(CallObjectMethodV),
+		CallObjectMethodA: Some(CallObjectMethodA),
+		CallBooleanMethod: variadic!(CallBooleanMethod),
+		CallBooleanMethodV: Some(CallBooleanMethodV),
+		CallBooleanMethodA: Some(CallBooleanMethodA),
+		CallByteMethod: variadic!(CallByteMethod),
+		CallByteMethodV: Some(CallByteMethodV),
+		CallByteMethodA: Some(CallByteMethodA),
+		CallCharMethod: variadic!(CallCharMethod),
+		CallCharMethodV: Some(CallCharMethodV),
+		CallCharMethodA: Some(CallCharMethodA),
+		CallShortMethod: variadic!(CallShortMethod),
+		CallShortMethodV: Some(CallShortMethodV),
+		CallShortMethodA: Some(CallShortMethodA),
+		CallIntMethod: variadic!(CallIntMethod),
+		CallIntMethodV: Some(CallIntMethodV),
+		CallIntMethodA: Some(CallIntMethodA),
+		CallLongMethod: variadic!(CallLongMethod),
+		CallLongMethodV: Some(CallLongMethodV),
+		CallLongMethodA: Some(CallLongMethodA),
+		CallFloatMethod: variadic!(CallFloatMethod),
+		CallFloatMethodV: Some(CallFloatMethodV),
+		CallFloatMethodA: Some(CallFloatMethodA),
+		CallDoubleMethod: variadic!(CallDoubleMethod),
+		CallDoubleMethodV: Some(CallDoubleMethodV),
+		CallDoubleMethodA: Some(CallDoubleMethodA),
+		CallVoidMethod: variadic!(CallVoidMethod),
+		CallVoidMethodV: Some(CallVoidMethodV),
+		CallVoidMethodA: Some(CallVoidMethodA),
+		CallNonvirtualObjectMethod: variadic!(CallNonvirtualObjectMethod),
+		CallNonvirtualObjectMethodV: Some(CallNonvirtualObjectMethodV),
+		CallNonvirtualObjectMethodA: Some(CallNonvirtualObjectMethodA),
+		CallNonvirtualBooleanMethod: variadic!(CallNonvirtualBooleanMethod),
+		CallNonvirtualBooleanMethodV: Some(CallNonvirtualBooleanMethodV),
+		CallNonvirtualBooleanMethodA: Some(CallNonvirtualBooleanMethodA),
+		CallNonvirtualByteMethod: variadic!(CallNonvirtualByteMethod),
+		CallNonvirtualByteMethodV: Some(CallNonvirtualByteMethodV),
+		CallNonvirtualByteMethodA: Some(CallNonvirtualByteMethodA),
+		CallNonvirtualCharMethod: variadic!(CallNonvirtualCharMethod),
+		CallNonvirtualCharMethodV: Some(CallNonvirtualCharMethodV),
+		CallNonvirtualCharMethodA: Some(CallNonvirtualCharMethodA),
+		CallNonvirtualShortMethod: variadic!(CallNonvirtualShortMethod),
+		CallNonvirtualShortMethodV: Some(CallNonvirtualShortMethodV),
+		CallNonvirtualShortMethodA: Some(CallNonvirtualShortMethodA),
+		CallNonvirtualIntMethod: variadic!(CallNonvirtualIntMethod),
+		CallNonvirtualIntMethodV: Some(CallNonvirtualIntMethodV),
+		CallNonvirtualIntMethodA: Some(CallNonvirtualIntMethodA),
+		CallNonvirtualLongMethod: variadic!(CallNonvirtualLongMethod),
+		CallNonvirtualLongMethodV: Some(CallNonvirtualLongMethodV),
+		CallNonvirtualLongMethodA: Some(CallNonvirtualLongMethodA),
+		CallNonvirtualFloatMethod: variadic!(CallNonvirtualFloatMethod),
+		CallNonvirtualFloatMethodV: Some(CallNonvirtualFloatMethodV),
+		CallNonvirtualFloatMethodA: Some(CallNonvirtualFloatMethodA),
+		CallNonvirtualDoubleMethod: variadic!(CallNonvirtualDoubleMethod),
+		CallNonvirtualDoubleMethodV: Some(CallNonvirtualDoubleMethodV),
+		CallNonvirtualDoubleMethodA: Some(CallNonvirtualDoubleMethodA),
+		CallNonvirtualVoidMethod: variadic!(CallNonvirtualVoidMethod),
+		CallNonvirtualVoidMethodV: Some(CallNonvirtualVoidMethodV),
+		CallNonvirtualVoidMethodA: Some(CallNonvirtualVoidMethodA),
+		GetFieldID: Some(GetFieldID),
+		GetObjectField: Some(GetObjectField),
+		GetBooleanField: Some(GetBooleanField),
+		GetByteField: Some(GetByteField),
+		GetCharField: Some(GetCharField),
+		GetShortField: Some(GetShortField),
+		GetIntField: Some(GetIntField),
+		GetLongField: Some(GetLongField),
+		GetFloatField: Some(GetFloatField),
+		GetDoubleField: Some(GetDoubleField),
+		SetObjectField: Some(SetObjectField),
+		SetBooleanField: Some(SetBooleanField),
+		SetByteField: Some(SetByteField),
+		SetCharField: Some(SetCharField),
+		SetShortField: Some(SetShortField),
+		SetIntField: Some(SetIntField),
+		SetLongField: Some(SetLongField),
+		SetFloatField: Some(SetFloatField),
+		SetDoubleField: Some(SetDoubleField),
+		GetStaticMethodID: Some(GetStaticMethodID),
+		CallStaticObjectMethod: variadic!(CallStaticObjectMethod),
+		CallStaticObjectMethodV: Some(CallStaticObjectMethodV),
+		CallStaticObjectMethodA: Some(CallStaticObjectMethodA),
+		CallStaticBooleanMethod: variadic!(CallStaticBooleanMethod),
+		CallStaticBooleanMethodV: Some(CallStaticBooleanMethodV),
+		CallStaticBooleanMethodA: Some(CallStaticBooleanMethodA),
+		CallStaticByteMethod: variadic!(CallStaticByteMethod),
+		CallStaticByteMethodV: Some(CallStaticByteMethodV),
+		CallStaticByteMethodA: Some(CallStaticByteMethodA),
+		CallStaticCharMethod: variadic!(CallStaticCharMethod),
+		CallStaticCharMethodV: Some(CallStaticCharMethodV),
+		CallStaticCharMethodA: Some(CallStaticCharMethodA),
+		CallStaticShortMethod: variadic!(CallStaticShortMethod),
+		CallStaticShortMethodV: Some(CallStaticShortMethodV),
+		CallStaticShortMethodA: Some(CallStaticShortMethodA),
+		CallStaticIntMethod: variadic!(CallStaticIntMethod),
+		CallStaticIntMethodV: Some(CallStaticIntMethodV),
+		CallStaticIntMethodA: Some(CallStaticIntMethodA),
+		CallStaticLongMethod: variadic!(CallStaticLongMethod),
+		CallStaticLongMethodV: Some(CallStaticLongMethodV),
+		CallStaticLongMethodA: Some(CallStaticLongMethodA),
+		CallStaticFloatMethod: variadic!(CallStaticFloatMethod),
+		CallStaticFloatMethodV: Some(CallStaticFloatMethodV),
+		CallStaticFloatMethodA: Some(CallStaticFloatMethodA),
+		CallStaticDoubleMethod: variadic!(CallStaticDoubleMethod),
+		CallStaticDoubleMethodV: Some(CallStaticDoubleMethodV),
+		CallStaticDoubleMethodA: Some(CallStaticDoubleMethodA),
+		CallStaticVoidMethod: variadic!(CallStaticVoidMethod),
+		CallStaticVoidMethodV: Some(CallStaticVoidMethodV),
+		CallStaticVoidMethodA: Some(CallStaticVoidMethodA),
+		GetStaticFieldID: Some(GetStaticFieldID),
+		GetStaticObjectField: Some(GetStaticObjectField),
+		GetStaticBooleanField: Some(GetStaticBooleanField),
+		GetStaticByteField: Some(GetStaticByteField),
+		GetStaticCharField: Some(GetStaticCharField),
+		GetStaticShortField: Some(GetStaticShortField),
+		GetStaticIntField: Some(GetStaticIntField),
+		GetStaticLongField: Some(GetStaticLongField),
+		GetStaticFloatField: Some(GetStaticFloatField),
+		GetStaticDoubleField: Some(GetStaticDoubleField),
+		SetStaticObjectField: Some(SetStaticObjectField),
+		SetStaticBooleanField: Some(SetStaticBooleanField),
+		SetStaticByteField: Some(SetStaticByteField),
+		SetStaticCharField: Some(SetStaticCharField),
+		SetStaticShortField: Some(SetStaticShortField),
+		SetStaticIntField: Some(SetStaticIntField),
+		SetStaticLongField: Some(SetStaticLongField),
+		SetStaticFloatField: Some(SetStaticFloatField),
+		SetStaticDoubleField: Some(SetStaticDoubleField),
+		NewString: Some(NewString),
+		GetStringLength: Some(GetStringLength),
+		GetStringChars: Some(GetStringChars),
+		ReleaseStringChars: Some(ReleaseStringChars),
+		NewStringUTF: Some(NewStringUTF),
+		GetStringUTFLength: Some(GetStringUTFLength),
+		GetStringUTFChars: Some(GetStringUTFChars),
+		ReleaseStringUTFChars: Some(ReleaseStringUTFChars),
+		GetArrayLength: Some(GetArrayLength),
+		NewObjectArray: Some(NewObjectArray),
+		GetObjectArrayElement: Some(GetObjectArrayElement),
+		SetObjectArrayElement: Some(SetObjectArrayElement),
+		NewBooleanArray: Some(NewBooleanArray),
+		NewByteArray: Some(NewByteArray),
+		NewCharArray: Some(NewCharArray),
+		NewShortArray: Some(NewShortArray),
+		NewIntArray: Some(NewIntArray),
+		NewLongArray: Some(NewLongArray),
+		NewFloatArray: Some(NewFloatArray),
+		NewDoubleArray: Some(NewDoubleArray),
+		GetBooleanArrayElements: Some(GetBooleanArrayElements),
+		GetByteArrayElements: Some(GetByteArrayElements),
+		GetCharArrayElements: Some(GetCharArrayElements),
+		GetShortArrayElements: Some(GetShortArrayElements),
+		GetIntArrayElements: Some(GetIntArrayElements),
+		GetLongArrayElements: Some(GetLongArrayElements),
+		GetFloatArrayElements: Some(GetFloatArrayElements),
+		GetDoubleArrayElements: Some(GetDoubleArrayElements),
+		ReleaseBooleanArrayElements: Some(ReleaseBooleanArrayElements),
+		ReleaseByteArrayElements: Some(ReleaseByteArrayElements),
+		ReleaseCharArrayElements: Some(ReleaseCharArrayElements),
+		ReleaseShortArrayElements: Some(ReleaseShortArrayElements),
+		ReleaseIntArrayElements: Some(ReleaseIntArrayElements),
+		ReleaseLongArrayElements: Some(ReleaseLongArrayElements),
+		ReleaseFloatArrayElements: Some(ReleaseFloatArrayElements),
+		ReleaseDoubleArrayElements: Some(ReleaseDoubleArrayElements),
+		GetBooleanArrayRegion: Some(GetBooleanArrayRegion),
+		GetByteArrayRegion: Some(GetByteArrayRegion),
+		GetCharArrayRegion: Some(GetCharArrayRegion),
+		GetShortArrayRegion: Some(GetShortArrayRegion),
+		GetIntArrayRegion: Some(GetIntArrayRegion),
+		GetLongArrayRegion: Some(GetLongArrayRegion),
+		GetFloatArrayRegion: Some(GetFloatArrayRegion),
+		GetDoubleArrayRegion: Some(GetDoubleArrayRegion),
+		SetBooleanArrayRegion: Some(SetBooleanArrayRegion),
+		SetByteArrayRegion: Some(SetByteArrayRegion),
+		SetCharArrayRegion: Some(SetCharArrayRegion),
+		SetShortArrayRegion: Some(SetShortArrayRegion),
+		SetIntArrayRegion: Some(SetIntArrayRegion),
+		SetLongArrayRegion: Some(SetLongArrayRegion),
+		SetFloatArrayRegion: Some(SetFloatArrayRegion),
+		SetDoubleArrayRegion: Some(SetDoubleArrayRegion),
+		RegisterNatives: Some(RegisterNatives),
+		UnregisterNatives: Some(UnregisterNatives),
+		MonitorEnter: Some(MonitorEnter),
+		MonitorExit: Some(MonitorExit),
+		GetJavaVM: Some(GetJavaVM),
+		GetStringRegion: Some(GetStringRegion),
+		GetStringUTFRegion: Some(GetStringUTFRegion),
+		GetPrimitiveArrayCritical: Some(GetPrimitiveArrayCritical),
+		ReleasePrimitiveArrayCritical: Some(ReleasePrimitiveArrayCritical),
+		GetStringCritical: Some(GetStringCritical),
+		ReleaseStringCritical: Some(ReleaseStringCritical),
+		NewWeakGlobalRef: Some(NewWeakGlobalRef),
+		DeleteWeakGlobalRef: Some(DeleteWeakGlobalRef),
+		ExceptionCheck: Some(ExceptionCheck),
+		NewDirectByteBuffer: Some(NewDirectByteBuffer),
+		GetDirectBufferAddress: Some(GetDirectBufferAddress),
+		GetDirectBufferCapacity: Some(GetDirectBufferCapacity),
+		GetObjectRefType: Some(GetObjectRefType),
+	}
+}
+
+unsafe fn cstr(s: *const c_char) -> String {
+	CStr::from_ptr(s).to_string_lossy().to_string()
+}
+
+fn br(v: &[u8]) -> BytesRef {
+	Arc::new(Vec::from(v))
+}
+
+fn set_ex(ex: Oop) {
+	let jt = thread::current_java_thread();
+	jt.write().unwrap().set_ex(ex);
+}
+
+fn throw(name: &[u8], msg: Option<String>) {
+	let ex = exception::new(name, msg);
+	if !thread::is_meet_ex() {
+		set_ex(ex);
+	}
+}
+
+unsafe fn set_is_copy(is_copy: *mut jboolean, v: jboolean) {
+	if !is_copy.is_null() {
+		*is_copy = v;
+	}
+}
+
+//copy to a buffer allocated by malloc, freed by the Release functions
+unsafe fn copy_to_c<T: Copy>(v: &[T]) -> *mut T {
+	let size = std::mem::size_of_val(v);
+	let ptr = libc::malloc(size.max(1)) as *mut T;
+	ptr::copy_nonoverlapping(v.as_ptr(), ptr, v.len());
+	ptr
+}
+
+//the class of 'v', array and mirror included
+fn object_class(v: &Oop) -> ClassRef {
+	let rf = v.extract_ref();
+	let ptr = rf.get_raw_ptr();
+	unsafe {
+		match &(*ptr).v {
+			RefKind::Inst(inst) => inst.class.clone(),
+			RefKind::Array(ary) => ary.class.clone(),
+			RefKind::Mirror(_) => require_class3(None, cls_consts::J_CLASS).unwrap(),
+			RefKind::TypeArray(ary) => {
+				let name: &[u8] = match ary {
+					TypeArrayDesc::Byte(_) => b"[B",
+					TypeArrayDesc::Bool(_) => b"[Z",
+					TypeArrayDesc::Char(_) => b"[C",
+					TypeArrayDesc::Short(_) => b"[S",
+					TypeArrayDesc::Float(_) => b"[F",
+					TypeArrayDesc::Double(_) => b"[D",
+					TypeArrayDesc::Int(_) => b"[I",
+					TypeArrayDesc::Long(_) => b"[J",
+				};
+				require_class3(None, name).unwrap()
+			}
+		}
+	}
+}
+
+//the mirrors of primitive types are only assignable to themselves
+fn is_assignable(sub: &Oop, sup: &Oop) -> bool {
+	let target = |mirror: &Oop| mirror.extract_ref().extract_mirror().target.clone();
+	match (target(sub), target(sup)) {
+		(Some(sub), Some(sup)) => cmp::instance_of(sub, sup),
+		_ => OopPtr::is_eq(sub, sup),
+	}
+}
+
+fn class_loader() -> Option<ClassLoader> {
+	//the loader of the class declaring the current native method,
+	//in JNI_OnLoad, the loader of the class loading the library
+	let jt = thread::current_java_thread();
+	let jt = jt.read().unwrap();
+	for frame in jt.frames.iter().rev() {
+		let method = &frame.mir.method;
+		let is_load = method.cls_name.as_slice() == b"java/lang/System"
+			&& (method.name.as_slice() == b"load" || method.name.as_slice() == b"loadLibrary");
+		if !is_load {
+			return frame.class.get_class().class_loader;
+		}
+	}
+	None
+}
+
+fn find_class(name: &str) -> jclass {
+	match require_class3(class_loader(), name.as_bytes()) {
+		Some(cls) => {
+			if oop::class::init_class(&cls).is_err() {
+				return ptr::null_mut();
+			}
+			class_ref_to_jclass(Some(cls))
+		}
+		None => {
+			if !thread::is_meet_ex() {
+				throw(
+					cls_consts::J_NO_CLASS_DEF_FOUND_ERROR,
+					Some(name.to_string()),
+				);
+			}
+			ptr::null_mut()
+		}
+	}
+}
+
+unsafe fn get_method_id(
+	clazz: jclass,
+	name: *const c_char,
+	sig: *const c_char,
+	is_static: bool,
+) -> jmethodID {
+	let name = br(CStr::from_ptr(name).to_bytes());
+	let desc = br(CStr::from_ptr(sig).to_bytes());
+	let cls = jclass_to_class_ref(clazz);
+	if let Some(cls) = &cls {
+		if oop::class::init_class(cls).is_err() {
+			return ptr::null_mut();
+		}
+	}
+	let mir = cls.and_then(|cls| {
+		let cls = cls.get_class();
+		let mir = if cls.is_array() {
+			let object = require_class3(None, cls_consts::J_OBJECT).unwrap();
+			let object = object.get_class();
+			object.get_class_method(&name, &desc)
+		} else if cls.is_interface() {
+			cls.get_interface_method(&name, &desc)
+		} else {
+			cls.get_class_method(&name, &desc)
+				.or_else(|_| cls.get_interface_method(&name, &desc))
+		};
+		mir.ok().filter(|mir| mir.method.is_static() == is_static)
+	});
+
+	match mir {
+		Some(mir) => util::method_id_to_jmethod(mir),
+		None => {
+			let msg = String::from_utf8_lossy(name.as_slice()).to_string();
+			throw(cls_consts::J_NO_SUCH_METHOD_ERROR, Some(msg));
+			ptr::null_mut()
+		}
+	}
+}
+
+unsafe fn get_field_id(
+	clazz: jclass,
+	name: *const c_char,
+	sig: *const c_char,
+	is_static: bool,
+) -> jfieldID {
+	let name = br(CStr::from_ptr(name).to_bytes());
+	let desc = br(CStr::from_ptr(sig).to_bytes());
+	let cls = jclass_to_class_ref(clazz);
+	if let Some(cls) = &cls {
+		if oop::class::init_class(cls).is_err() {
+			return ptr::null_mut();
+		}
+	}
+	let fid = cls.and_then(|cls| {
+		let cls = cls.get_class();
+		cls.find_field_id(&name, &desc, is_static)
+	});
+
+	match fid {
+		Some(fid) => util::field_id_to_jfield(fid),
+		None => {
+			let msg = String::from_utf8_lossy(name.as_slice()).to_string();
+			throw(cls_consts::J_NO_SUCH_FIELD_ERROR, Some(msg));
+			ptr::null_mut()
+		}
+	}
+}
+
+unsafe fn from_reflected_method(method: Oop) -> jmethodID {
+	let ctor_cls = require_class3(None, cls_consts::J_METHOD_CTOR).unwrap();
+	let (clazz, name, desc) = if cmp::instance_of(object_class(&method), ctor_cls) {
+		let clazz = reflect::get_Constructor_clazz(&method);
+		let desc = reflect::get_Constructor_signature(&method);
+		(clazz, vm::util::S_INIT.clone(), new_br(desc.as_str()))
+	} else {
+		let cls = require_class3(None, cls_consts::J_METHOD).unwrap();
+		let cls = cls.get_class();
+		let rf = method.extract_ref();
+		let fid = cls.get_field_id(&vm::util::S_CLAZZ, &vm::util::S_JAVA_LANG_CLASS, false);
+		let clazz = Class::get_field_value(rf.clone(), fid);
+		let fid = cls.get_field_id(&vm::util::S_NAME, &vm::util::S_JAVA_LANG_STRING, false);
+		let name = Class::get_field_value(rf.clone(), fid);
+		let name = OopPtr::java_lang_string(name.extract_ref());
+		let fid = cls.get_field_id(&vm::util::S_SIGNATURE, &vm::util::S_JAVA_LANG_STRING, false);
+		let desc = Class::get_field_value(rf, fid);
+		let desc = OopPtr::java_lang_string(desc.extract_ref());
+		(clazz, new_br(name.as_str()), new_br(desc.as_str()))
+	};
+
+	let cls = clazz.extract_ref().extract_mirror().target.clone().unwrap();
+	let cls = cls.get_class();
+	let mir = cls.get_this_class_method(&name, &desc).unwrap();
+	util::method_id_to_jmethod(mir)
+}
+
+unsafe fn from_reflected_field(field: Oop) -> jfieldID {
+	let cls = require_class3(None, cls_consts::J_FIELD).unwrap();
+	let cls = cls.get_class();
+	let rf = field.extract_ref();
+	let fid = cls.get_field_id(&vm::util::S_CLAZZ, &vm::util::S_JAVA_LANG_CLASS, false);
+	let clazz = Class::get_field_value(rf.clone(), fid);
+	let fid = cls.get_field_id(&vm::util::S_NAME, &vm::util::S_JAVA_LANG_STRING, false);
+	let name = Class::get_field_value(rf.clone(), fid);
+	let name = OopPtr::java_lang_string(name.extract_ref());
+	let fid = cls.get_field_id(&vm::util::S_MODIFIERS, &vm::util::S_I, false);
+	let modifiers = Class::get_field_value(rf, fid).extract_int();
+	let is_static = (modifiers as u16 & ACC_STATIC) != 0;
+
+	let cls = clazz.extract_ref().extract_mirror().target.clone().unwrap();
+	let cls = cls.get_class();
+	let fid = match &cls.kind {
+		ClassKind::Instance(cls_obj) if is_static => cls_obj.static_fields.iter(),
+		ClassKind::Instance(cls_obj) => cls_obj.inst_fields.iter(),
+		_ => unreachable!(),
+	}
+	.find(|(k, _)| k.0 == cls.name && k.1.as_slice() == name.as_bytes())
+	.map(|(_, fid)| fid.clone());
+
+	match fid {
+		Some(fid) => util::field_id_to_jfield(fid),
+		None => ptr::null_mut(),
+	}
+}
+
+fn exception_describe() {
+	let jt = thread::current_java_thread();
+	let ex = jt.write().unwrap().take_ex();
+	if let Some(ex) = ex {
+		let cls = object_class(&ex);
+		let cls = cls.get_class();
+		let mir = cls.get_virtual_method(&new_br("printStackTrace"), &new_br("()V"));
+		if let Ok(mir) = mir {
+			method_handles::call(mir, vec![ex]);
+		}
+		jt.write().unwrap().take_ex();
+	}
+}
+
+unsafe fn alloc_object(clazz: jclass) -> Option<Oop> {
+	let cls = jclass_to_class_ref(clazz).unwrap();
+	{
+		let cls = cls.get_class();
+		if cls.is_interface() || cls.is_abstract() {
+			let name = String::from_utf8_lossy(cls.name.as_slice()).replace('/', ".");
+			throw(cls_consts::J_INSTANTIATION_EXCEPTION, Some(name));
+			return None;
+		}
+	}
+
+	oop::class::init_class(&cls).ok()?;
+	Some(Oop::new_inst(cls))
+}
+
+unsafe fn new_object(clazz: jclass, method_id: jmethodID, args: Args) -> Option<Oop> {
+	let obj = alloc_object(clazz)?;
+	let h = jni::new_local_ref(obj.clone());
+	call_method(h as jobject, method_id, args, Dispatch::Nonvirtual)?;
+	jni::delete_local_ref(h);
+	Some(obj)
+}
+
+unsafe fn build_args(mir: &MethodIdRef, args: Args) -> Vec<Oop> {
+	let sig = &mir.method.signature;
+	match args {
+		#[cfg(not(feature = "c_variadic"))]
+		Args::V(_) => unimplemented!("va_list needs the feature c_variadic"),
+		#[cfg(feature = "c_variadic")]
+		Args::V(args) => {
+			let args = &mut *(args as *mut VaList);
+			//C varargs promote float to double, and the types smaller than int to int
+			sig.args
+				.iter()
+				.map(|t| match t {
+					SignatureType::Long => Oop::new_long(args.arg::<jlong>()),
+					SignatureType::Float => Oop::new_float(args.arg::<jdouble>() as jfloat),
+					SignatureType::Double => Oop::new_double(args.arg::<jdouble>()),
+					SignatureType::Object(_, _, _) | SignatureType::Array(_) => {
+						jobject_to_oop(args.arg::<jobject>())
+					}
+					_ => Oop::new_int(args.arg::<jint>()),
+				})
+				.collect()
+		}
+		Args::A(args) => sig
+			.args
+			.iter()
+			.enumerate()
+			.map(|(i, t)| {
+				let v = &*args.add(i);
+				match t {
+					SignatureType::Boolean => Oop::new_int(v.z as i32),
+					SignatureType::Byte => Oop::new_int(v.b as i32),
+					SignatureType::Char => Oop::new_int(v.c as i32),
+					SignatureType::Short => Oop::new_int(v.s as i32),
+					SignatureType::Int => Oop::new_int(v.i),
+					SignatureType::Long => Oop::new_long(v.j),
+					SignatureType::Float => Oop::new_float(v.f),
+					SignatureType::Double => Oop::new_double(v.d),
+					_ => jobject_to_oop(v.l),
+				}
+			})
+			.collect(),
+	}
+}
+
+//None means an exception occurred
+unsafe fn call_method(
+	obj: jobject,
+	method_id: jmethodID,
+	args: Args,
+	dispatch: Dispatch,
+) -> Option<Oop> {
+	let mir = jmethod_to_method_id(method_id);
+	let mut args = build_args(&mir, args);
+
+	let mir = match dispatch {
+		Dispatch::Static => mir,
+		_ => {
+			let this = jobject_to_oop(obj);
+			if this.is_null() {
+				throw(cls_consts::J_NPE, None);
+				return None;
+			}
+
+			let mir = match dispatch {
+				Dispatch::Virtual => resolve_virtual(&this, mir),
+				_ => mir,
+			};
+			args.insert(0, this);
+			mir
+		}
+	};
+
+	method_handles::call(mir, args)
+}
+
+//select the method by the class of the receiver
+fn resolve_virtual(this: &Oop, mir: MethodIdRef) -> MethodIdRef {
+	let m = &mir.method;
+	if m.is_static() || m.name.as_slice() == b"<init>" || (m.acc_flags & ACC_PRIVATE) != 0 {
+		return mir;
+	}
+
+	let cls = object_class(this);
+	let cls = cls.get_class();
+	if !cls.is_instance() {
+		return mir;
+	}
+	cls.get_virtual_method(&m.name, &m.desc)
+		.or_else(|_| cls.get_interface_method(&m.name, &m.desc))
+		.unwrap_or(mir)
+}
+
+unsafe fn get_field(obj: jobject, field_id: jfieldID) -> Oop {
+	let fid = jfield_to_field_id(field_id);
+	let obj = jobject_to_oop(obj);
+	Class::get_field_value(obj.extract_ref(), fid)
+}
+
+unsafe fn set_field(obj: jobject, field_id: jfieldID, v: Oop) {
+	let fid = jfield_to_field_id(field_id);
+	let obj = jobject_to_oop(obj);
+	Class::put_field_value(obj.extract_ref(), fid, v);
+}
+
+unsafe fn get_static_field(field_id: jfieldID) -> Oop {
+	let fid = jfield_to_field_id(field_id);
+	let cls = fid.field.class.clone();
+	let cls = cls.get_class();
+	cls.get_static_field_value(fid)
+}
+
+unsafe fn set_static_field(field_id: jfieldID, v: Oop) {
+	let fid = jfield_to_field_id(field_id);
+	let cls = fid.field.class.clone();
+	let cls = cls.get_mut_class();
+	cls.put_static_field_value(fid, v);
+}
+
+unsafe fn string_value(s: jstring) -> Vec<u16> {
+	let s = jobject_to_oop(s);
+	OopPtr::java_lang_string_value(s.extract_ref())
+}
+
+fn check_string_bounds(n: usize, start: jsize, len: jsize) -> bool {
+	if start < 0 || len < 0 || start as usize + len as usize > n {
+		let msg = format!("offset {}, count {}, length {}", start, len, n);
+		throw(cls_consts::J_STRING_INDEX_OUT_OF_BOUNDS, Some(msg));
+		false
+	} else {
+		true
+	}
+}
+
+fn check_len(len: jsize) -> bool {
+	if len < 0 {
+		throw(cls_consts::J_NASE, Some(len.to_string()));
+		false
+	} else {
+		true
+	}
+}
+
+unsafe fn new_object_array(len: jsize, clazz: jclass, init: Oop) -> Option<Oop> {
+	if !check_len(len) {
+		return None;
+	}
+
+	let elm = jclass_to_class_ref(clazz).unwrap();
+	let (name, class_loader) = {
+		let elm = elm.get_class();
+		let mut name = vec![b'['];
+		if elm.is_array() {
+			name.extend_from_slice(elm.name.as_slice());
+		} else {
+			name.push(b'L');
+			name.extend_from_slice(elm.name.as_slice());
+			name.push(b';');
+		}
+		(name, elm.class_loader)
+	};
+
+	let cls = require_class3(class_loader, &name)?;
+	oop::class::init_class(&cls).ok()?;
+	Some(Oop::new_ref_ary2(cls, vec![init; len as usize]))
+}
+
+fn array_component(array: &Oop) -> Option<ClassRef> {
+	let cls = object_class(array);
+	let cls = cls.get_class();
+	match &cls.kind {
+		ClassKind::ObjectArray(ary) => ary.component.clone(),
+		_ => None,
+	}
+}
+
+//the address of the elements of a primitive array, the heap doesn't move objects
+//the native code keeps the address, the elements must not be moved
+fn pinned_array_ptr(array: &Oop) -> *mut c_void {
+	gc::pin(&array.extract_ref());
+	type_array_ptr(array)
+}
+
+fn type_array_ptr(array: &Oop) -> *mut c_void {
+	let rf = array.extract_ref();
+	match rf.extract_mut_type_array() {
+		TypeArrayDesc::Byte(ary) => ary.as_mut_ptr() as *mut c_void,
+		TypeArrayDesc::Bool(ary) => ary.as_mut_ptr() as *mut c_void,
+		TypeArrayDesc::Char(ary) => ary.as_mut_ptr() as *mut c_void,
+		TypeArrayDesc::Short(ary) => ary.as_mut_ptr() as *mut c_void,
+		TypeArrayDesc::Float(ary) => ary.as_mut_ptr() as *mut c_void,
+		TypeArrayDesc::Double(ary) => ary.as_mut_ptr() as *mut c_void,
+		TypeArrayDesc::Int(ary) => ary.as_mut_ptr() as *mut c_void,
+		TypeArrayDesc::Long(ary) => ary.as_mut_ptr() as *mut c_void,
+	}
+}
+
+//the address of array[start], None with ArrayIndexOutOfBoundsException thrown
+fn check_bounds(array: &Oop, start: jsize, len: jsize) -> Option<*mut c_void> {
+	let rf = array.extract_ref();
+	let ptr = rf.get_raw_ptr();
+	let (n, elm_size) = unsafe {
+		match &(*ptr).v {
+			RefKind::Array(ary) => (ary.elements.len(), std::mem::size_of::<Oop>()),
+			RefKind::TypeArray(ary) => {
+				let n = ary.len();
+				let size = match ary {
+					TypeArrayDesc::Byte(_) | TypeArrayDesc::Bool(_) => 1,
+					TypeArrayDesc::Char(_) | TypeArrayDesc::Short(_) => 2,
+					TypeArrayDesc::Float(_) | TypeArrayDesc::Int(_) => 4,
+					TypeArrayDesc::Double(_) | TypeArrayDesc::Long(_) => 8,
+				};
+				(n, size)
+			}
+			_ => unreachable!(),
+		}
+	};
+
+	if start < 0 || len < 0 || start as usize + len as usize > n {
+		let msg = format!(
+			"Array index out of range: {}",
+			start.max(0) as usize + len as usize
+		);
+		throw(cls_consts::J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(msg));
+		return None;
+	}
+
+	match unsafe { &(*ptr).v } {
+		RefKind::TypeArray(_) => {
+			let base = type_array_ptr(array) as *mut u8;
+			Some(unsafe { base.add(start as usize * elm_size) } as *mut c_void)
+		}
+		_ => Some(ptr::null_mut()),
+	}
+}
+
+unsafe fn direct_buffer_field(buf: jobject, name: &str, desc: &str) -> Option<Oop> {
+	let buf = jobject_to_oop(buf);
+	let cls = require_class3(None, b"java/nio/Buffer").unwrap();
+	if buf.is_null() || !cmp::instance_of(object_class(&buf), cls) {
+		return None;
+	}
+	Some(method_handles::get_field(
+		&buf,
+		b"java/nio/Buffer",
+		name,
+		desc,
+	))
 }
@@ -1,12 +1,101 @@
 #![allow(unused_imports)]
 
 /// Conversions between jvm and jni api
-use jni_sys::{jclass, JNIEnv};
+use jni_sys::{jclass, jfieldID, jmethodID, jobject, JNIEnv};
+use std::sync::Arc;
+use vm::native::jni;
+use vm::oop::field::FieldId;
+use vm::oop::Oop;
+use vm::runtime::method::MethodId;
+use vm::types::{ClassRef, FieldIdRef, MethodIdRef};
 
-pub fn class_ref_to_jclass(class_ref: Option<vm::types::ClassRef>) -> jclass {
+pub fn class_ref_to_jclass(class_ref: Option<ClassRef>) -> jclass {
 	if let Some(class_ref) = class_ref {
-		class_ref.as_ref() as *const _ as jclass
+		let mirror = class_ref.get_class().get_mirror();
+		oop_to_jobject(mirror)
 	} else {
 		std::ptr::null_mut()
 	}
 }
+
+//None for the mirrors of primitive types
+pub unsafe fn jclass_to_class_ref(clazz: jclass) -> Option<ClassRef> {
+	match jobject_to_oop(clazz) {
+		Oop::Null => None,
+		mirror => mirror.extract_ref().extract_mirror().target.clone(),
+	}
+}
+
+//a new local reference of 'v'
+pub fn oop_to_jobject(v: Oop) -> jobject {
+	jni::new_local_ref(v) as jobject
+}
+
+pub unsafe fn jobject_to_oop(obj: jobject) -> Oop {
+	jni::resolve(obj as jni::Handle)
+}
+
+//the ids hold a count of the Arc, methods and fields live as long as the class
+pub fn method_id_to_jmethod(mir: MethodIdRef) -> jmethodID {
+	Arc::into_raw(mir) as jmethodID
+}
+
+pub unsafe fn jmethod_to_method_id(id: jmethodID) -> MethodIdRef {
+	let ptr = id as *const MethodId;
+	Arc::increment_strong_count(ptr);
+	Arc::from_raw(ptr)
+}
+
+pub fn field_id_to_jfield(fid: FieldIdRef) -> jfieldID {
+	Arc::into_raw(fid) as jfieldID
+}
+
+pub unsafe fn jfield_to_field_id(id: jfieldID) -> FieldIdRef {
+	let ptr = id as *const FieldId;
+	Arc::increment_strong_count(ptr);
+	Arc::from_raw(ptr)
+}
+
+//java strings are encoded in modified UTF-8 by jni
+pub fn to_modified_utf8(chars: &[u16]) -> Vec<u8> {
+	let mut v = Vec::with_capacity(chars.len());
+	for &c in chars {
+		match c {
+			0x0001..=0x007f => v.push(c as u8),
+			0x0000 | 0x0080..=0x07ff => {
+				v.push((0xc0 | (c >> 6)) as u8);
+				v.push((0x80 | (c & 0x3f)) as u8);
+			}
+			_ => {
+				v.push((0xe0 | (c >> 12)) as u8);
+				v.push((0x80 | ((c >> 6) & 0x3f)) as u8);
+				v.push((0x80 | (c & 0x3f)) as u8);
+			}
+		}
+	}
+	v
+}
+
+pub fn from_modified_utf8(bytes: &[u8]) -> Vec<u16> {
+	let mut v = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		let b = bytes[i] as u16;
+		let (c, n) = if b & 0x80 == 0 {
+			(b, 1)
+		} else if b & 0xe0 == 0xc0 && i + 1 < bytes.len() {
+			(((b & 0x1f) << 6) | (bytes[i + 1] as u16 & 0x3f), 2)
+		} else if b & 0xf0 == 0xe0 && i + 2 < bytes.len() {
+			let c = ((b & 0x0f) << 12)
+				| ((bytes[i + 1] as u16 & 0x3f) << 6)
+				| (bytes[i + 2] as u16 & 0x3f);
+			(c, 3)
+		} else {
+			//malformed, take the byte as is
+			(b, 1)
+		};
+		v.push(c);
+		i += n;
+	}
+	v
+}
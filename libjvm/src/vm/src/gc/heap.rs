@@ -0,0 +1,613 @@
+use crate::gc::nursery::Nursery;
+use crate::gc::reference::{self, ReferenceType};
+use crate::gc::space::{Loc, Space, CELL_SIZE};
+use crate::oop::{Oop, OopPtr, RefKind, RefKindDesc, TypeArrayDesc};
+use crate::runtime::stack;
+use rustc_hash::FxHashMap;
+use std::alloc::{self, Layout};
+use std::mem::size_of;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+const M: usize = 1024 * 1024;
+
+//a payload bigger than 1/8 of the nursery is not worth copying
+const BIG_PAYLOAD_RATIO: usize = 8;
+
+//-Xms, -Xmx, -Xmn
+#[derive(Clone, Copy, Debug)]
+pub struct HeapSize {
+    pub initial: usize,
+    pub max: usize,
+    pub young: usize,
+}
+
+impl Default for HeapSize {
+    fn default() -> Self {
+        Self {
+            initial: 64 * M,
+            max: 1024 * M,
+            young: 0,
+        }
+    }
+}
+
+impl HeapSize {
+    //like hotspot NewRatio=2, young is 1/3 of the heap
+    fn young_size(&self) -> usize {
+        if self.young == 0 {
+            self.initial / 3
+        } else {
+            self.young
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Generation {
+    Young,
+    Full,
+}
+
+struct HeapObj {
+    rf: Arc<OopPtr>,
+    //the cell in the old space, None in the nursery
+    loc: Option<Loc>,
+    size: usize,
+}
+
+/*
+The heap holds one handle of every object, the object is freed only by
+a collection.
+
+Roots are all the handles living outside of the heap: class statics and
+mirrors, JNI global refs, the pending exception, and temporaries held by
+native code. They are found without walking frames: an object whose handle
+count is greater than the references found in the scanned objects plus the
+heap's own handle is referenced from outside, so it is a root. The locals
+and operand stacks hold no handles, the value stacks of the threads are
+scanned instead, see runtime::stack.
+
+New objects are young, their header and payload are allocated in the
+nursery by bumping a pointer. A young collection only scans the young
+objects and the old ones remembered by the write barrier. The survivors
+are promoted: copied to the old space, and their handle is updated to
+the new address, then the nursery is empty again. The handle, not the
+address, is the identity of an object.
+
+The old space holds the headers in the cells of Space and the payloads on
+the system allocator, a full collection marks and sweeps it in place.
+
+Big payloads are allocated in the old space directly. So are the objects
+allocated when the nursery is full, before the collection at the next
+safepoint, they are still young until they survive it.
+
+Unreachable objects, include cycles, are swept by clearing their
+references first, and then dropping them.
+*/
+pub struct Heap {
+    size: HeapSize,
+    nursery: Nursery,
+    space: Space,
+
+    young: Vec<HeapObj>,
+    old: Vec<HeapObj>,
+    young_used: usize,
+    old_used: usize,
+
+    //old objects holding young objects, see gc::write_barrier
+    remembered: Vec<usize>,
+
+    //collect the old generation when reached
+    threshold: usize,
+
+    //the references found by the last collection, see reference::enqueue_pending
+    pending_refs: Vec<Arc<OopPtr>>,
+}
+
+impl Heap {
+    pub fn new(size: HeapSize) -> Self {
+        Self {
+            size,
+            nursery: Nursery::new(size.young_size()),
+            space: Space::new(size.initial),
+            young: Vec::new(),
+            old: Vec::new(),
+            young_used: 0,
+            old_used: 0,
+            remembered: Vec::new(),
+            threshold: size.initial,
+            pending_refs: Vec::new(),
+        }
+    }
+
+    pub fn resize(&mut self, size: HeapSize) {
+        self.size = size;
+        if self.nursery.is_empty() {
+            self.nursery = Nursery::new(size.young_size());
+        }
+        self.threshold = std::cmp::max(self.threshold, size.initial);
+    }
+
+    pub fn alloc(&mut self, v: RefKind) -> Arc<OopPtr> {
+        let size = object_size(&v);
+        let desc = RefKindDesc::new(v);
+        let (ptr, loc) = match self.nursery.alloc(Layout::new::<RefKindDesc>()) {
+            Some(ptr) => {
+                let ptr = ptr.cast::<RefKindDesc>().as_ptr();
+                unsafe { ptr::write(ptr, desc) };
+                (ptr, None)
+            }
+            None => {
+                let (ptr, loc) = self.space.alloc(desc);
+                (ptr, Some(loc))
+            }
+        };
+        let rf = OopPtr::from_raw(ptr);
+
+        self.young.push(HeapObj {
+            rf: rf.clone(),
+            loc,
+            size,
+        });
+        self.young_used += size;
+
+        rf
+    }
+
+    //return true if it's in the old space
+    pub fn alloc_payload(&mut self, layout: Layout) -> (NonNull<u8>, bool) {
+        if layout.size() <= self.nursery.capacity() / BIG_PAYLOAD_RATIO {
+            if let Some(ptr) = self.nursery.alloc(layout) {
+                return (ptr, false);
+            }
+        }
+
+        let ptr = unsafe { alloc::alloc(layout) };
+        match NonNull::new(ptr) {
+            Some(ptr) => (ptr, true),
+            None => alloc::handle_alloc_error(layout),
+        }
+    }
+
+    pub fn remember(&mut self, holder: *const RefKindDesc) {
+        self.remembered.push(holder as usize);
+    }
+
+    pub fn need_collect(&self) -> Option<Generation> {
+        if self.old_used + self.young_used > self.threshold {
+            Some(Generation::Full)
+        } else if self.young_used > self.nursery.capacity() {
+            Some(Generation::Young)
+        } else {
+            None
+        }
+    }
+
+    pub fn used(&self) -> usize {
+        self.young_used + self.old_used
+    }
+
+    pub fn max(&self) -> usize {
+        self.size.max
+    }
+
+    pub fn total(&self) -> usize {
+        let v = self.nursery.capacity() + self.space.committed();
+        let v = std::cmp::max(self.size.initial, v);
+        std::cmp::min(std::cmp::max(v, self.used()), self.size.max)
+    }
+
+    pub fn take_pending_refs(&mut self) -> Vec<Arc<OopPtr>> {
+        std::mem::take(&mut self.pending_refs)
+    }
+
+    pub fn count(&self) -> usize {
+        self.young.len() + self.old.len()
+    }
+
+    /*
+    Must be called with the world stopped, return the freed bytes.
+    'clear_soft' clears the softly reachable objects, before OutOfMemoryError.
+    */
+    pub fn collect(&mut self, gen: Generation, clear_soft: bool) -> usize {
+        let before = self.used();
+
+        let mut objects = std::mem::take(&mut self.young);
+        if gen == Generation::Full {
+            objects.append(&mut self.old);
+            self.old_used = 0;
+        }
+
+        let n = objects.len();
+        let mut index = FxHashMap::default();
+        index.reserve(n);
+        for (i, it) in objects.iter().enumerate() {
+            index.insert(it.rf.get_raw_ptr() as usize, i);
+        }
+
+        //references held by scanned objects
+        let mut internal = vec![0usize; n];
+        let mut count = |v: &Arc<OopPtr>| {
+            if let Some(i) = index.get(&(v.get_raw_ptr() as usize)) {
+                internal[*i] += 1;
+            }
+        };
+        for it in objects.iter() {
+            for_each_ref(it.rf.get_raw_ptr(), &mut count);
+        }
+        if gen == Generation::Young {
+            for it in self.remembered.iter() {
+                for_each_ref(*it as *const RefKindDesc, &mut count);
+            }
+        }
+
+        //mark, the active references are discovered, their referents not traced
+        let mut marked = vec![false; n];
+        let mut pending = Vec::new();
+        let mut discovered = Vec::new();
+        for (i, it) in objects.iter().enumerate() {
+            let handles = Arc::strong_count(&it.rf) - 1;
+            if handles > internal[i] {
+                mark(&it.rf, &index, &mut marked, &mut pending);
+            }
+        }
+        let mut handles = FxHashMap::default();
+        handles.reserve(n);
+        for (i, it) in objects.iter().enumerate() {
+            handles.insert(Arc::as_ptr(&it.rf) as usize, i);
+        }
+        stack::scan_roots(|v| {
+            if let Some(&i) = handles.get(&v) {
+                mark(&objects[i].rf, &index, &mut marked, &mut pending);
+            }
+        });
+        if gen == Generation::Young {
+            //old objects are alive in a young collection
+            for it in self.remembered.iter() {
+                let ptr = *it as *const RefKindDesc;
+                trace(ptr, clear_soft, &mut discovered, |v| {
+                    mark(v, &index, &mut marked, &mut pending)
+                });
+            }
+        }
+        while let Some(i) = pending.pop() {
+            let ptr = objects[i].rf.get_raw_ptr();
+            trace(ptr, clear_soft, &mut discovered, |v| {
+                mark(v, &index, &mut marked, &mut pending)
+            });
+        }
+
+        /*
+        The referents not reachable otherwise, soft and weak ones are cleared,
+        final and phantom ones are kept alive with the objects they reach.
+        */
+        for ty in [
+            ReferenceType::Soft,
+            ReferenceType::Weak,
+            ReferenceType::Final,
+            ReferenceType::Phantom,
+        ] {
+            for (ptr, it_ty) in discovered.iter() {
+                if *it_ty != ty {
+                    continue;
+                }
+
+                let referent = match reference::get_referent(*ptr) {
+                    Some(v) => v,
+                    None => continue,
+                };
+                let alive = match index.get(&(referent.get_raw_ptr() as usize)) {
+                    Some(&i) => marked[i],
+                    //old in a young collection
+                    None => true,
+                };
+                if alive {
+                    continue;
+                }
+
+                match ty {
+                    ReferenceType::Soft | ReferenceType::Weak => {
+                        reference::clear_referent(*ptr as *mut RefKindDesc)
+                    }
+                    ReferenceType::Final | ReferenceType::Phantom => {
+                        mark(&referent, &index, &mut marked, &mut pending);
+                        while let Some(i) = pending.pop() {
+                            let ptr = objects[i].rf.get_raw_ptr();
+                            for_each_ref(ptr, |v| mark(v, &index, &mut marked, &mut pending));
+                        }
+                    }
+                }
+
+                //the handle of the heap, an old one is remembered
+                let rf = match index.get(&(*ptr as usize)) {
+                    Some(&i) => objects[i].rf.clone(),
+                    None => self
+                        .old
+                        .iter()
+                        .find(|it| it.rf.get_raw_ptr() == *ptr)
+                        .map(|it| it.rf.clone())
+                        .unwrap(),
+                };
+                reference::set_pending(&rf);
+                self.pending_refs.push(rf);
+            }
+        }
+
+        //all the survivors are old now, no old-to-young reference left
+        for it in self.remembered.drain(..) {
+            let ptr = it as *const RefKindDesc;
+            unsafe { (*ptr).remembered.store(false, Ordering::Relaxed) };
+        }
+
+        //sweep, break references between dead objects first
+        for (i, it) in objects.iter().enumerate() {
+            if !marked[i] {
+                clear_refs(it.rf.get_mut_raw_ptr());
+            }
+        }
+
+        for (i, mut it) in objects.into_iter().enumerate() {
+            if marked[i] {
+                self.promote(&mut it);
+                self.old_used += it.size;
+                self.old.push(it);
+            } else {
+                debug_assert_eq!(Arc::strong_count(&it.rf), 1);
+                let HeapObj { rf, loc, .. } = it;
+                let ptr = rf.get_mut_raw_ptr();
+                drop(rf);
+                match loc {
+                    Some(loc) => self.space.free(loc),
+                    None => unsafe { ptr::drop_in_place(ptr) },
+                }
+            }
+        }
+        self.young_used = 0;
+        self.nursery.reset();
+        self.space.rewind();
+
+        if gen == Generation::Full {
+            let v = std::cmp::max(self.size.initial, self.old_used * 2);
+            self.threshold = std::cmp::min(v, self.size.max);
+        } else if self.old_used > self.threshold {
+            self.threshold = std::cmp::min(self.old_used * 2, self.size.max);
+        }
+
+        before - self.used()
+    }
+
+    //copy a young survivor out of the nursery
+    fn promote(&mut self, it: &mut HeapObj) {
+        let mut ptr = it.rf.get_mut_raw_ptr();
+        if it.loc.is_none() {
+            let desc = unsafe { ptr::read(ptr) };
+            let (new, loc) = self.space.alloc(desc);
+            it.rf.set_raw_ptr(new);
+            it.loc = Some(loc);
+            ptr = new;
+        }
+
+        unsafe {
+            promote_payload(&mut (*ptr).v);
+            (*ptr).old.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+pub fn promote_payload(v: &mut RefKind) {
+    match v {
+        RefKind::Inst(inst) => inst.field_values.promote(),
+        RefKind::Array(ary) => ary.elements.promote(),
+        RefKind::Mirror(mirror) => mirror.field_values.promote(),
+        RefKind::TypeArray(ary) => match ary {
+            TypeArrayDesc::Byte(v) | TypeArrayDesc::Bool(v) => v.promote(),
+            TypeArrayDesc::Char(v) => v.promote(),
+            TypeArrayDesc::Short(v) => v.promote(),
+            TypeArrayDesc::Int(v) => v.promote(),
+            TypeArrayDesc::Float(v) => v.promote(),
+            TypeArrayDesc::Long(v) => v.promote(),
+            TypeArrayDesc::Double(v) => v.promote(),
+        },
+    }
+}
+
+//approximate bytes occupied by an object
+pub fn object_size(v: &RefKind) -> usize {
+    let payload = match v {
+        RefKind::Inst(inst) => inst.field_values.len() * size_of::<Oop>(),
+        RefKind::Array(ary) => ary.elements.len() * size_of::<Oop>(),
+        RefKind::Mirror(mirror) => mirror.field_values.len() * size_of::<Oop>(),
+        RefKind::TypeArray(ary) => match ary {
+            TypeArrayDesc::Byte(v) | TypeArrayDesc::Bool(v) => v.len(),
+            TypeArrayDesc::Char(v) => v.len() * 2,
+            TypeArrayDesc::Short(v) => v.len() * 2,
+            TypeArrayDesc::Int(v) => v.len() * 4,
+            TypeArrayDesc::Float(v) => v.len() * 4,
+            TypeArrayDesc::Long(v) => v.len() * 8,
+            TypeArrayDesc::Double(v) => v.len() * 8,
+        },
+    };
+
+    CELL_SIZE + payload
+}
+
+fn for_each_ref<F: FnMut(&Arc<OopPtr>)>(ptr: *const RefKindDesc, mut f: F) {
+    let values = unsafe {
+        match &(*ptr).v {
+            RefKind::Inst(inst) => inst.field_values.as_slice(),
+            RefKind::Array(ary) => ary.elements.as_slice(),
+            RefKind::Mirror(mirror) => mirror.field_values.as_slice(),
+            RefKind::TypeArray(_) => return,
+        }
+    };
+
+    for v in values {
+        if let Oop::Ref(child) = v {
+            f(child);
+        }
+    }
+}
+
+fn mark(
+    v: &Arc<OopPtr>,
+    index: &FxHashMap<usize, usize>,
+    marked: &mut [bool],
+    pending: &mut Vec<usize>,
+) {
+    if let Some(&i) = index.get(&(v.get_raw_ptr() as usize)) {
+        if !marked[i] {
+            marked[i] = true;
+            pending.push(i);
+        }
+    }
+}
+
+//like for_each_ref, the referent of an active reference is discovered instead
+fn trace<F: FnMut(&Arc<OopPtr>)>(
+    ptr: *const RefKindDesc,
+    clear_soft: bool,
+    discovered: &mut Vec<(*const RefKindDesc, ReferenceType)>,
+    mut f: F,
+) {
+    let ty = match reference::discoverable(ptr) {
+        //strong until the heap is exhausted
+        Some(ReferenceType::Soft) if !clear_soft => None,
+        ty => ty,
+    };
+
+    match ty {
+        Some(ty) => {
+            discovered.push((ptr, ty));
+            let referent = reference::referent_offset();
+            if let RefKind::Inst(inst) = unsafe { &(*ptr).v } {
+                for (i, v) in inst.field_values.iter().enumerate() {
+                    match v {
+                        Oop::Ref(child) if i != referent => f(child),
+                        _ => (),
+                    }
+                }
+            }
+        }
+        None => for_each_ref(ptr, f),
+    }
+}
+
+fn clear_refs(ptr: *mut RefKindDesc) {
+    let values = unsafe {
+        match &mut (*ptr).v {
+            RefKind::Inst(inst) => inst.field_values.as_mut_slice(),
+            RefKind::Array(ary) => ary.elements.as_mut_slice(),
+            RefKind::Mirror(mirror) => mirror.field_values.as_mut_slice(),
+            RefKind::TypeArray(_) => return,
+        }
+    };
+
+    for v in values {
+        *v = Oop::Null;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gc::Payload;
+    use crate::oop::{MirrorOopDesc, ValueType};
+    use std::sync::atomic::Ordering;
+
+    fn new_obj(heap: &mut Heap, field_values: Vec<Oop>) -> Arc<OopPtr> {
+        heap.alloc(RefKind::Mirror(MirrorOopDesc {
+            target: None,
+            field_values: Payload::from_vec(field_values),
+            value_type: ValueType::OBJECT,
+        }))
+    }
+
+    //a store with the write barrier, see gc::write_barrier
+    fn set_field(heap: &mut Heap, rf: &Arc<OopPtr>, v: Oop) {
+        let ptr = rf.get_mut_raw_ptr();
+        unsafe {
+            if let Oop::Ref(v) = &v {
+                let is_young = !(*v.get_raw_ptr()).old.load(Ordering::Relaxed);
+                if (*ptr).old.load(Ordering::Relaxed) && is_young {
+                    (*ptr).remembered.store(true, Ordering::Relaxed);
+                    heap.remember(ptr);
+                }
+            }
+            if let RefKind::Mirror(mirror) = &mut (*ptr).v {
+                mirror.field_values[0] = v;
+            }
+        }
+    }
+
+    #[test]
+    fn t_collect_cycle() {
+        let mut heap = Heap::new(HeapSize::default());
+        let a = new_obj(&mut heap, vec![Oop::Null]);
+        let b = new_obj(&mut heap, vec![Oop::Ref(a.clone())]);
+        set_field(&mut heap, &a, Oop::Ref(b.clone()));
+        drop(a);
+        drop(b);
+
+        heap.collect(Generation::Young, false);
+        assert_eq!(heap.count(), 0);
+        assert_eq!(heap.used(), 0);
+    }
+
+    #[test]
+    fn t_keep_reachable() {
+        let mut heap = Heap::new(HeapSize::default());
+        let child = new_obj(&mut heap, vec![Oop::Null]);
+        let root = new_obj(&mut heap, vec![Oop::Ref(child.clone())]);
+        let garbage = new_obj(&mut heap, vec![Oop::Ref(child.clone())]);
+        drop(child);
+        drop(garbage);
+
+        heap.collect(Generation::Young, false);
+        assert_eq!(heap.count(), 2);
+
+        drop(root);
+        heap.collect(Generation::Young, false);
+        assert_eq!(heap.count(), 2);
+        heap.collect(Generation::Full, false);
+        assert_eq!(heap.count(), 0);
+    }
+
+    #[test]
+    fn t_promote() {
+        let mut heap = Heap::new(HeapSize::default());
+        let child = new_obj(&mut heap, vec![Oop::Int(7)]);
+        let root = new_obj(&mut heap, vec![Oop::Ref(child.clone())]);
+        drop(child);
+        let young = root.get_raw_ptr();
+
+        heap.collect(Generation::Young, false);
+        assert_ne!(root.get_raw_ptr(), young);
+        assert!(heap.nursery.is_empty());
+
+        //the child is reached through the copy of the root
+        let child = root.extract_mirror().field_values[0].extract_ref();
+        assert_eq!(child.extract_mirror().field_values[0].extract_int(), 7);
+        unsafe { assert!((*child.get_raw_ptr()).old.load(Ordering::Relaxed)) };
+    }
+
+    #[test]
+    fn t_remembered_set() {
+        let mut heap = Heap::new(HeapSize::default());
+        let old = new_obj(&mut heap, vec![Oop::Null]);
+        heap.collect(Generation::Young, false);
+
+        //the only reference of 'young' is held by the old object
+        let young = new_obj(&mut heap, vec![Oop::Null]);
+        set_field(&mut heap, &old, Oop::Ref(young.clone()));
+        drop(young);
+
+        heap.collect(Generation::Young, false);
+        assert_eq!(heap.count(), 2);
+
+        set_field(&mut heap, &old, Oop::Null);
+        heap.collect(Generation::Full, false);
+        assert_eq!(heap.count(), 1);
+    }
+}
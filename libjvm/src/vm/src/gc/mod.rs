@@ -0,0 +1,186 @@
+use crate::oop::{Oop, OopPtr, RefKind};
+use crate::runtime::exception;
+use classfile::consts as cls_consts;
+use std::alloc::Layout;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+pub use self::heap::{Generation, Heap, HeapSize};
+pub use self::payload::Payload;
+pub use self::reference::{
+    register_finalizer, set_finalizer_ready, set_offsets as set_reference_offsets, ReferenceType,
+};
+pub use self::safepoint::{
+    attach as attach_thread, blocking, detach as detach_thread, in_vm, InVm,
+};
+
+mod heap;
+mod nursery;
+mod payload;
+mod reference;
+mod safepoint;
+mod space;
+
+lazy_static! {
+    static ref HEAP: Mutex<Heap> = Mutex::new(Heap::new(HeapSize::default()));
+}
+
+//the heap reached a threshold, collect at the next safepoint
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+//heap usage, read without locking the heap by ensure_capacity
+static USED: AtomicUsize = AtomicUsize::new(0);
+static MAX: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+//should be called before any allocation
+pub fn set_heap_size(size: HeapSize) {
+    let mut heap = HEAP.lock().unwrap();
+    heap.resize(size);
+    MAX.store(heap.max(), Ordering::Relaxed);
+}
+
+pub fn new_object(v: RefKind) -> Arc<OopPtr> {
+    let mut heap = HEAP.lock().unwrap();
+    let rf = heap.alloc(v);
+    USED.store(heap.used(), Ordering::Relaxed);
+    if heap.need_collect().is_some() {
+        PENDING.store(true, Ordering::Relaxed);
+    }
+
+    rf
+}
+
+//see Payload, return true if it's in the old space
+fn alloc_payload(layout: Layout) -> (NonNull<u8>, bool) {
+    HEAP.lock().unwrap().alloc_payload(layout)
+}
+
+/*
+The payload of 'rf' is not moved by the collections any more,
+for native code keeping the address of the elements.
+*/
+pub fn pin(rf: &OopPtr) {
+    let ptr = rf.get_mut_raw_ptr();
+    heap::promote_payload(unsafe { &mut (*ptr).v });
+}
+
+/*
+Called after storing 'v' into the object 'holder',
+an old object holding a young one is remembered for the young collection.
+*/
+#[inline]
+pub fn write_barrier(holder: &OopPtr, v: &Oop) {
+    if let Oop::Ref(v) = v {
+        let holder = holder.get_raw_ptr();
+        unsafe {
+            if (*holder).old.load(Ordering::Relaxed)
+                && !(*v.get_raw_ptr()).old.load(Ordering::Relaxed)
+                && !(*holder).remembered.swap(true, Ordering::Relaxed)
+            {
+                HEAP.lock().unwrap().remember(holder);
+            }
+        }
+    }
+}
+
+//for bulk stores, like System.arraycopy
+pub fn write_barrier_slice(holder: &OopPtr, vs: &[Oop]) {
+    let ptr = holder.get_raw_ptr();
+    if unsafe { (*ptr).old.load(Ordering::Relaxed) } {
+        let young = vs.iter().find(|v| match v {
+            Oop::Ref(v) => unsafe { !(*v.get_raw_ptr()).old.load(Ordering::Relaxed) },
+            _ => false,
+        });
+        if let Some(v) = young {
+            write_barrier(holder, v);
+        }
+    }
+}
+
+#[inline]
+pub fn safepoint() {
+    if PENDING.load(Ordering::Relaxed) {
+        PENDING.store(false, Ordering::Relaxed);
+        let gen = HEAP.lock().unwrap().need_collect();
+        if let Some(gen) = gen {
+            collect(gen);
+        }
+    } else if safepoint::is_requested() {
+        safepoint::park();
+    }
+}
+
+//return false, if another thread was collecting, and this one just waited for it
+pub fn collect(gen: Generation) -> bool {
+    collect2(gen, false)
+}
+
+//'clear_soft' for the last collection before OutOfMemoryError
+fn collect2(gen: Generation, clear_soft: bool) -> bool {
+    if !safepoint::stop_world() {
+        return false;
+    }
+
+    let (freed, used, count, refs) = {
+        let mut heap = HEAP.lock().unwrap();
+        let freed = heap.collect(gen, clear_soft);
+        USED.store(heap.used(), Ordering::Relaxed);
+        (freed, heap.used(), heap.count(), heap.take_pending_refs())
+    };
+
+    safepoint::start_world();
+    reference::enqueue_pending(refs);
+
+    info!(
+        "gc: {:?} freed {} bytes, used {} bytes, {} objects",
+        gen, freed, used, count
+    );
+
+    true
+}
+
+/*
+Make sure 'size' bytes can be allocated, otherwise throw OutOfMemoryError.
+The callers should check before allocating big objects (arrays, instances).
+*/
+pub fn ensure_capacity(size: usize) -> Result<(), ()> {
+    let fits = || {
+        let used = USED.load(Ordering::Relaxed);
+        used.saturating_add(size) <= MAX.load(Ordering::Relaxed)
+    };
+
+    if fits() {
+        return Ok(());
+    }
+
+    //the collection of another thread may not have been a full one
+    while !collect(Generation::Full) {
+        if fits() {
+            return Ok(());
+        }
+    }
+    if fits() {
+        return Ok(());
+    }
+
+    while !collect2(Generation::Full, true) {}
+    if fits() {
+        return Ok(());
+    }
+
+    exception::meet_ex(cls_consts::J_OOM, Some("Java heap space".to_string()));
+    Err(())
+}
+
+pub fn used_memory() -> usize {
+    USED.load(Ordering::Relaxed)
+}
+
+pub fn total_memory() -> usize {
+    HEAP.lock().unwrap().total()
+}
+
+pub fn max_memory() -> usize {
+    HEAP.lock().unwrap().max()
+}
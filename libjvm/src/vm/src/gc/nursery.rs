@@ -0,0 +1,74 @@
+use std::alloc::{self, Layout};
+use std::ptr::NonNull;
+
+const ALIGN: usize = 8;
+
+/*
+The young generation, a contiguous region allocated by bumping 'top'.
+
+It holds the headers and the payloads of the objects allocated since the
+last collection. The collection copies the live ones to the old space,
+then the whole region is free again.
+*/
+pub struct Nursery {
+    start: NonNull<u8>,
+    capacity: usize,
+    top: usize,
+}
+
+unsafe impl Send for Nursery {}
+
+impl Nursery {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = std::cmp::max(align_up(capacity), ALIGN);
+        let layout = Layout::from_size_align(capacity, ALIGN).unwrap();
+        let ptr = unsafe { alloc::alloc(layout) };
+        let start = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => alloc::handle_alloc_error(layout),
+        };
+
+        Self {
+            start,
+            capacity,
+            top: 0,
+        }
+    }
+
+    //None if the nursery is full
+    pub fn alloc(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        debug_assert!(layout.align() <= ALIGN);
+        let size = align_up(layout.size());
+        if size > self.capacity - self.top {
+            return None;
+        }
+
+        let ptr = unsafe { self.start.as_ptr().add(self.top) };
+        self.top += size;
+        NonNull::new(ptr)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.top == 0
+    }
+
+    //all the objects in it are promoted or dead
+    pub fn reset(&mut self) {
+        self.top = 0;
+    }
+}
+
+impl Drop for Nursery {
+    fn drop(&mut self) {
+        let layout = Layout::from_size_align(self.capacity, ALIGN).unwrap();
+        unsafe { alloc::dealloc(self.start.as_ptr(), layout) };
+    }
+}
+
+fn align_up(size: usize) -> usize {
+    (size + ALIGN - 1) & !(ALIGN - 1)
+}
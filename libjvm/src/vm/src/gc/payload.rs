@@ -0,0 +1,130 @@
+use crate::gc;
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+
+/*
+The fields of an instance or mirror, the elements of an array.
+
+A payload is placed in the nursery with its object, the nursery memory is
+given back all at once by the collection. When the object is promoted,
+the payload is copied to the system allocator, the old space, and is freed
+with the object.
+*/
+pub struct Payload<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    //in the old space
+    old: bool,
+}
+
+unsafe impl<T: Send> Send for Payload<T> {}
+unsafe impl<T: Sync> Sync for Payload<T> {}
+
+impl<T: Clone> Payload<T> {
+    pub fn new(len: usize, v: T) -> Self {
+        let mut payload = Self::alloc(len);
+        let ptr = payload.ptr.as_ptr();
+        for i in 0..len {
+            unsafe { ptr::write(ptr.add(i), v.clone()) };
+        }
+        payload.len = len;
+        payload
+    }
+}
+
+impl<T> Payload<T> {
+    //no element is initialized yet, len is 0 until they are
+    fn alloc(len: usize) -> Self {
+        let layout = Layout::array::<T>(len).unwrap();
+        let (ptr, old) = if layout.size() == 0 {
+            (NonNull::dangling(), false)
+        } else {
+            let (ptr, old) = gc::alloc_payload(layout);
+            (ptr.cast(), old)
+        };
+
+        Self { ptr, len: 0, old }
+    }
+
+    pub fn from_vec(mut v: Vec<T>) -> Self {
+        let len = v.len();
+        let mut payload = Self::alloc(len);
+        unsafe {
+            ptr::copy_nonoverlapping(v.as_ptr(), payload.ptr.as_ptr(), len);
+            v.set_len(0);
+        }
+        payload.len = len;
+        payload
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.len * std::mem::size_of::<T>()
+    }
+
+    //in the nursery, to be copied when promoted
+    pub(crate) fn is_young(&self) -> bool {
+        !self.old && self.size() != 0
+    }
+
+    /*
+    Move the elements to the old space, they are not moved any more.
+    Called by the collection, or to pin the payload for native code.
+    */
+    pub(crate) fn promote(&mut self) {
+        if !self.is_young() {
+            return;
+        }
+
+        let layout = Layout::array::<T>(self.len).unwrap();
+        let ptr = unsafe { alloc::alloc(layout) } as *mut T;
+        let ptr = match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => alloc::handle_alloc_error(layout),
+        };
+        unsafe { ptr::copy_nonoverlapping(self.ptr.as_ptr(), ptr.as_ptr(), self.len) };
+        self.ptr = ptr;
+        self.old = true;
+    }
+}
+
+impl<T> Deref for Payload<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for Payload<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Drop for Payload<T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.deref_mut() as *mut [T]);
+            if self.old {
+                let layout = Layout::array::<T>(self.len).unwrap();
+                alloc::dealloc(self.ptr.as_ptr() as *mut u8, layout);
+            }
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Payload<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
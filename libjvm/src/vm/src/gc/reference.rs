@@ -0,0 +1,170 @@
+use crate::new_br;
+use crate::oop::{self, Class, Oop, OopPtr, RefKind, RefKindDesc};
+use crate::runtime::JavaCall;
+use classfile::consts::{J_FINALIZER, J_REFERENCE};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/*
+java.lang.ref
+
+The referent of an active reference (Reference.next is null) is not traced
+when marking. After marking, the references whose referents are not
+reachable otherwise are processed in the order of hotspot:
+  soft, weak: the referent is cleared, soft ones only by the collection
+    before OutOfMemoryError, they are strong references otherwise
+  final: the object with a finalize method, kept alive for the Finalizer
+  phantom: kept alive until cleared, like jdk 8, sun.misc.Cleaner runs
+These references become pending (Reference.next is itself), and are then
+linked by Reference.discovered into Reference.pending, the ReferenceHandler
+thread enqueues them.
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReferenceType {
+    Soft,
+    Weak,
+    Final,
+    Phantom,
+}
+
+impl ReferenceType {
+    //the subclasses inherit the type of the super class
+    pub fn of(name: &[u8]) -> Option<Self> {
+        match name {
+            b"java/lang/ref/SoftReference" => Some(ReferenceType::Soft),
+            b"java/lang/ref/WeakReference" => Some(ReferenceType::Weak),
+            b"java/lang/ref/FinalReference" => Some(ReferenceType::Final),
+            b"java/lang/ref/PhantomReference" => Some(ReferenceType::Phantom),
+            _ => None,
+        }
+    }
+}
+
+//Reference.referent, next and discovered, set when Reference is linked
+static REFERENT_OFFSET: AtomicUsize = AtomicUsize::new(0);
+static NEXT_OFFSET: AtomicUsize = AtomicUsize::new(0);
+static DISCOVERED_OFFSET: AtomicUsize = AtomicUsize::new(0);
+
+//the Finalizer thread is started, see init_vm
+static FINALIZER_READY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_offsets(referent: usize, next: usize, discovered: usize) {
+    REFERENT_OFFSET.store(referent, Ordering::Relaxed);
+    NEXT_OFFSET.store(next, Ordering::Relaxed);
+    DISCOVERED_OFFSET.store(discovered, Ordering::Relaxed);
+}
+
+pub fn referent_offset() -> usize {
+    REFERENT_OFFSET.load(Ordering::Relaxed)
+}
+
+//the type of an active reference with a referent, to be discovered
+pub fn discoverable(ptr: *const RefKindDesc) -> Option<ReferenceType> {
+    let inst = match unsafe { &(*ptr).v } {
+        RefKind::Inst(inst) => inst,
+        _ => return None,
+    };
+    let ty = match &inst.class.get_class().kind {
+        oop::ClassKind::Instance(cls_obj) => cls_obj.ref_type?,
+        _ => return None,
+    };
+
+    let values = &inst.field_values;
+    let is_active = values[NEXT_OFFSET.load(Ordering::Relaxed)].is_null();
+    let has_referent = !values[referent_offset()].is_null();
+    if is_active && has_referent {
+        Some(ty)
+    } else {
+        None
+    }
+}
+
+pub fn get_referent(ptr: *const RefKindDesc) -> Option<Arc<OopPtr>> {
+    match unsafe { &(*ptr).v } {
+        RefKind::Inst(inst) => match &inst.field_values[referent_offset()] {
+            Oop::Ref(v) => Some(v.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+//not active any more, Reference.next is itself until enqueued
+pub fn set_pending(rf: &Arc<OopPtr>) {
+    if let RefKind::Inst(inst) = unsafe { &mut (*rf.get_mut_raw_ptr()).v } {
+        inst.field_values[NEXT_OFFSET.load(Ordering::Relaxed)] = Oop::Ref(rf.clone());
+    }
+}
+
+pub fn clear_referent(ptr: *mut RefKindDesc) {
+    if let RefKind::Inst(inst) = unsafe { &mut (*ptr).v } {
+        inst.field_values[referent_offset()] = Oop::Null;
+    }
+}
+
+/*
+Prepend the references found by a collection to Reference.pending, and
+wake the ReferenceHandler thread. Called after the world is started, the
+handler thread takes the list holding Reference.lock too.
+*/
+pub fn enqueue_pending(refs: Vec<Arc<OopPtr>>) {
+    if refs.is_empty() {
+        return;
+    }
+
+    let cls = oop::class::load_and_init(J_REFERENCE);
+    let (lock, pending_fid) = {
+        let cls = cls.get_class();
+        let lock_fid = cls.get_field_id(
+            &new_br("lock"),
+            &new_br("Ljava/lang/ref/Reference$Lock;"),
+            true,
+        );
+        let pending_fid = cls.get_field_id(
+            &new_br("pending"),
+            &new_br("Ljava/lang/ref/Reference;"),
+            true,
+        );
+        (cls.get_static_field_value(lock_fid), pending_fid)
+    };
+
+    let lock = lock.extract_ref();
+    lock.monitor_enter();
+    {
+        let discovered = DISCOVERED_OFFSET.load(Ordering::Relaxed);
+        let cls = cls.get_mut_class();
+        let mut head = cls.get_static_field_value(pending_fid.clone());
+        for it in refs {
+            Class::put_field_value2(it.clone(), discovered, head);
+            head = Oop::Ref(it);
+        }
+        cls.put_static_field_value(pending_fid, head);
+    }
+    let _ = lock.notify_all();
+    let _ = lock.monitor_exit();
+}
+
+pub fn set_finalizer_ready() {
+    FINALIZER_READY.store(true, Ordering::Relaxed);
+}
+
+/*
+Finalizer.register, called when an object of a class with finalize is
+allocated. The objects created before the Finalizer thread is started,
+early in the boot, are never finalized.
+*/
+pub fn register_finalizer(obj: Oop) {
+    if !FINALIZER_READY.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mir = {
+        let cls = oop::class::load_and_init(J_FINALIZER);
+        let cls = cls.get_class();
+        cls.get_static_method(&new_br("register"), &new_br("(Ljava/lang/Object;)V"))
+    };
+    if let Ok(mir) = mir {
+        let mut jc = JavaCall::new_with_args(mir, vec![obj]);
+        jc.invoke(None, false);
+    }
+}
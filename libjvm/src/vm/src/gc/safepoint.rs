@@ -0,0 +1,153 @@
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/*
+Stop the world
+
+Every java thread is attached while it runs java code, and polls at
+safepoints (between bytecodes). The collector raises REQUESTED and waits
+until all other attached threads are parked at a safepoint.
+
+A thread going to block (monitor, wait, park, join, native methods...)
+leaves the running set, it doesn't touch the heap until it comes back,
+and coming back waits for the running collection. Native code calling
+back through JNI comes back the same way for the time of the call.
+
+The collector waits as long as it takes, so every place blocking an
+attached thread has to go through 'blocking'.
+*/
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static ATTACHED: Cell<bool> = const { Cell::new(false) };
+}
+
+struct State {
+    running: usize,
+    parked: usize,
+    collecting: bool,
+}
+
+lazy_static! {
+    static ref STATE: Mutex<State> = Mutex::new(State {
+        running: 0,
+        parked: 0,
+        collecting: false,
+    });
+    static ref COND: Condvar = Condvar::new();
+}
+
+fn is_attached() -> bool {
+    ATTACHED.with(|v| v.get())
+}
+
+pub fn attach() {
+    if is_attached() {
+        return;
+    }
+
+    let mut state = STATE.lock().unwrap();
+    while state.collecting {
+        state = COND.wait(state).unwrap();
+    }
+    state.running += 1;
+    ATTACHED.with(|v| v.set(true));
+}
+
+pub fn detach() {
+    if !is_attached() {
+        return;
+    }
+
+    let mut state = STATE.lock().unwrap();
+    state.running -= 1;
+    ATTACHED.with(|v| v.set(false));
+    COND.notify_all();
+}
+
+#[inline]
+pub fn is_requested() -> bool {
+    REQUESTED.load(Ordering::Acquire)
+}
+
+pub fn park() {
+    if !is_attached() {
+        return;
+    }
+
+    let mut state = STATE.lock().unwrap();
+    if !state.collecting {
+        return;
+    }
+
+    state.parked += 1;
+    COND.notify_all();
+    while state.collecting {
+        state = COND.wait(state).unwrap();
+    }
+    state.parked -= 1;
+}
+
+//run 'f' outside of the running set, 'f' must not touch the heap
+pub fn blocking<T, F: FnOnce() -> T>(f: F) -> T {
+    if !is_attached() {
+        return f();
+    }
+
+    detach();
+    let v = f();
+    attach();
+    v
+}
+
+/*
+Attached for the lifetime of the guard,
+for the JNI functions called by native code run by 'blocking'.
+*/
+pub struct InVm(bool);
+
+pub fn in_vm() -> InVm {
+    let detached = !is_attached();
+    if detached {
+        attach();
+    }
+    InVm(detached)
+}
+
+impl Drop for InVm {
+    fn drop(&mut self) {
+        if self.0 {
+            detach();
+        }
+    }
+}
+
+//return true, if all the other threads are parked
+pub fn stop_world() -> bool {
+    let mut state = STATE.lock().unwrap();
+    if state.collecting {
+        //another thread is collecting, join it
+        drop(state);
+        park();
+        return false;
+    }
+
+    state.collecting = true;
+    REQUESTED.store(true, Ordering::Release);
+
+    let me = if is_attached() { 1 } else { 0 };
+    while state.parked + me < state.running {
+        state = COND.wait(state).unwrap();
+    }
+
+    true
+}
+
+pub fn start_world() {
+    let mut state = STATE.lock().unwrap();
+    state.collecting = false;
+    REQUESTED.store(false, Ordering::Release);
+    COND.notify_all();
+}
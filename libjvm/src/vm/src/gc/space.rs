@@ -0,0 +1,105 @@
+use crate::oop::RefKindDesc;
+use std::mem::MaybeUninit;
+use std::ptr;
+
+const CHUNK_CELLS: usize = 1024;
+
+pub const CELL_SIZE: usize = std::mem::size_of::<RefKindDesc>();
+pub const CHUNK_SIZE: usize = CHUNK_CELLS * CELL_SIZE;
+
+//where a cell lives, (chunk, cell)
+#[derive(Clone, Copy, Debug)]
+pub struct Loc(u32, u32);
+
+struct Chunk {
+    cells: Box<[MaybeUninit<RefKindDesc>]>,
+    used: Vec<bool>,
+    n_used: usize,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        let cells = (0..CHUNK_CELLS)
+            .map(|_| MaybeUninit::uninit())
+            .collect::<Vec<_>>()
+            .into_boxed_slice();
+
+        Self {
+            cells,
+            used: vec![false; CHUNK_CELLS],
+            n_used: 0,
+        }
+    }
+}
+
+/*
+The old space, object headers (RefKindDesc) are placed in fixed size cells.
+
+Allocation bumps 'top' through the chunks, skipping the cells still in use,
+after a collection it is rewound to reuse the freed cells. The headers
+promoted from the nursery are copied here, and are not moved any more.
+*/
+pub struct Space {
+    chunks: Vec<Chunk>,
+    cur: usize,
+    top: usize,
+}
+
+impl Space {
+    pub fn new(initial: usize) -> Self {
+        let n = std::cmp::max(1, initial / CHUNK_SIZE);
+        let chunks = (0..n).map(|_| Chunk::new()).collect();
+        Self {
+            chunks,
+            cur: 0,
+            top: 0,
+        }
+    }
+
+    pub fn alloc(&mut self, v: RefKindDesc) -> (*mut RefKindDesc, Loc) {
+        loop {
+            if self.cur == self.chunks.len() {
+                self.chunks.push(Chunk::new());
+            }
+
+            let chunk = &mut self.chunks[self.cur];
+            if chunk.n_used < CHUNK_CELLS {
+                while self.top < CHUNK_CELLS {
+                    let i = self.top;
+                    self.top += 1;
+
+                    if !chunk.used[i] {
+                        chunk.used[i] = true;
+                        chunk.n_used += 1;
+                        let cell = chunk.cells[i].as_mut_ptr();
+                        unsafe { ptr::write(cell, v) };
+                        return (cell, Loc(self.cur as u32, i as u32));
+                    }
+                }
+            }
+
+            self.cur += 1;
+            self.top = 0;
+        }
+    }
+
+    //drop the object, and give back the cell
+    pub fn free(&mut self, loc: Loc) {
+        let chunk = &mut self.chunks[loc.0 as usize];
+        let i = loc.1 as usize;
+        debug_assert!(chunk.used[i]);
+
+        unsafe { ptr::drop_in_place(chunk.cells[i].as_mut_ptr()) };
+        chunk.used[i] = false;
+        chunk.n_used -= 1;
+    }
+
+    pub fn rewind(&mut self) {
+        self.cur = 0;
+        self.top = 0;
+    }
+
+    pub fn committed(&self) -> usize {
+        self.chunks.len() * CHUNK_SIZE
+    }
+}
@@ -7,6 +7,7 @@ extern crate log;
 #[macro_use]
 pub mod util;
 
+pub mod gc;
 pub mod native;
 pub mod oop;
 pub mod runtime;
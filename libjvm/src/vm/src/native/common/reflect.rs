@@ -266,7 +266,21 @@ pub fn get_Constructor_signature(ctor: &Oop) -> String {
     OopPtr::java_lang_string(v.extract_ref())
 }
 
-fn create_value_type(t: SignatureType) -> Oop {
+//setAccessible(true) was called
+pub fn get_AccessibleObject_override(obj: &Oop) -> bool {
+    let cls = {
+        let rf = obj.extract_ref();
+        let inst = rf.extract_inst();
+        inst.class.clone()
+    };
+
+    let cls = cls.get_class();
+    let id = cls.get_field_id(&util::S_OVERRIDE, &util::S_Z, false);
+    let v = Class::get_field_value(obj.extract_ref(), id);
+    v.extract_int() != 0
+}
+
+pub fn create_value_type(t: SignatureType) -> Oop {
     match t {
         SignatureType::Byte => java_lang_Class::get_primitive_class_mirror("B").unwrap(),
         SignatureType::Char => java_lang_Class::get_primitive_class_mirror("C").unwrap(),
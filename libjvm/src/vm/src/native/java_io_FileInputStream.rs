@@ -1,4 +1,5 @@
 #![allow(non_snake_case)]
+use crate::gc;
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{Class, Oop, OopPtr};
 use crate::runtime::{self, require_class3};
@@ -66,12 +67,15 @@ fn jvm_readBytes(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 
     let n = {
         let rf = byte_ary.extract_ref();
+        //the collection run meanwhile must not move the elements
+        gc::pin(&rf);
         let ary = rf.extract_mut_type_array();
         let ary = ary.extract_mut_bytes();
 
         let (_, ptr) = ary.split_at_mut(off as usize);
         let ptr = ptr.as_mut_ptr() as *mut libc::c_void;
-        let n = unsafe { libc::read(fd, ptr, len as usize) };
+        //may block on a pipe or the console, the array is held by 'args'
+        let n = gc::blocking(|| unsafe { libc::read(fd, ptr, len as usize) });
         // error!("readBytes n = {}", n);
         if n > 0 {
             n as i32
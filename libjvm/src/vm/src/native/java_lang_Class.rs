@@ -167,6 +167,20 @@ pub fn create_mirror(cls: ClassRef) {
 
     if is_fixed {
         let mirror = Oop::new_mirror(cls.clone());
+        //Class.classLoader, initialized by vm, null for the bootstrap loader
+        if let Some(loader) = cls.get_class().class_loader {
+            let loader = loader.get_oop();
+            if !loader.is_null() {
+                let desc = "Ljava/lang/ClassLoader;";
+                runtime::method_handles::put_field(
+                    &mirror,
+                    cls_consts::J_CLASS,
+                    "classLoader",
+                    desc,
+                    loader,
+                );
+            }
+        }
         let cls = cls.get_mut_class();
         trace!("mirror created: {}", unsafe {
             std::str::from_utf8_unchecked(cls.name.as_slice())
@@ -355,12 +369,7 @@ fn jvm_forName0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         arg1.extract_int() != 0
     };
     let java_cls_loader = args.get(2).unwrap();
-    {
-        match java_cls_loader {
-            Oop::Null => (),
-            _ => unimplemented!("app class loader, unimpl"),
-        }
-    }
+    let class_loader = runtime::ClassLoader::from_oop(java_cls_loader);
 
     let _caller_mirror = args.get(3).unwrap();
 
@@ -376,20 +385,21 @@ fn jvm_forName0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
             //fixme: skip, cause jvm start very slow
             None
         } else {
-            require_class3(None, java_name.as_bytes())
+            require_class3(class_loader, java_name.as_bytes())
         }
     };
 
     match cls {
         Some(cls) => {
-            oop::class::init_class(&cls);
-            if initialize {
-                oop::class::init_class_fully(&cls);
+            if initialize && oop::class::init_class(&cls).is_err() {
+                return Ok(None);
             }
 
             let mirror = cls.get_class().get_mirror();
             Ok(Some(mirror))
         }
+        //thrown by ClassLoader.loadClass
+        None if runtime::thread::is_meet_ex() => Ok(None),
         None => {
             // error!("forName0, NotFound: {}", java_name);
             let msg = Some(java_name);
@@ -594,12 +604,13 @@ fn jvm_getEnclosingMethod0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         }
     };
 
-    let (cls_file, em) = match target {
+    let (cls_file, em, class_loader) = match target {
         Some(target) => {
             let cls = target.get_class();
+            let class_loader = cls.class_loader;
             match &cls.kind {
                 ClassKind::Instance(cls) => match &cls.enclosing_method {
-                    Some(em) => (cls.class_file.clone(), em.clone()),
+                    Some(em) => (cls.class_file.clone(), em.clone(), class_loader),
                     None => return Ok(Some(Oop::Null)),
                 },
                 _ => return Ok(Some(Oop::Null)),
@@ -612,7 +623,7 @@ fn jvm_getEnclosingMethod0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     if em.class_index == 0 {
         panic!();
     }
-    let em_class = require_class2(em.class_index, &cls_file.cp).unwrap();
+    let em_class = require_class2(class_loader, em.class_index, &cls_file.cp).unwrap();
     let em_class_mirror = {
         let cls = em_class.get_class();
         cls.get_mirror()
@@ -669,19 +680,22 @@ fn jvm_getDeclaringClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         }
         None => return Ok(Some(Oop::Null)),
     };
+    let class_loader = target.get_class().class_loader;
 
     for it in inner_classes.iter() {
         if it.inner_class_info_index == 0 {
             continue;
         }
 
-        let inner_class = require_class2(it.inner_class_info_index, &cls_file.cp).unwrap();
+        let inner_class =
+            require_class2(class_loader, it.inner_class_info_index, &cls_file.cp).unwrap();
 
         if Arc::ptr_eq(&inner_class, &target) {
             return if it.outer_class_info_index == 0 {
                 Ok(Some(Oop::Null))
             } else {
-                let outer_class = require_class2(it.outer_class_info_index, &cls_file.cp).unwrap();
+                let outer_class =
+                    require_class2(class_loader, it.outer_class_info_index, &cls_file.cp).unwrap();
                 let v = outer_class.get_class();
                 Ok(Some(v.get_mirror()))
             };
@@ -743,7 +757,7 @@ fn jvm_getInterfaces0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
             let mut elms = Vec::with_capacity(inst.class_file.interfaces.len());
             let cp = &inst.class_file.cp;
             inst.class_file.interfaces.iter().for_each(|it| {
-                let cls = require_class2(*it, cp).unwrap();
+                let cls = require_class2(v.class_loader, *it, cp).unwrap();
                 let cls = cls.get_class();
                 elms.push(cls.get_mirror());
             });
@@ -832,7 +846,12 @@ fn jvm_getDeclaredClasses0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
                     let mut inners = Vec::with_capacity(inner_classes.len());
                     for it in inner_classes {
                         if it.outer_class_info_index == this_class {
-                            let inner_clz = require_class2(it.inner_class_info_index, cp).unwrap();
+                            let inner_clz = require_class2(
+                                target_class.class_loader,
+                                it.inner_class_info_index,
+                                cp,
+                            )
+                            .unwrap();
                             let v = inner_clz.get_class().get_mirror();
                             inners.push(v);
                         }
@@ -3,8 +3,10 @@
 
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Oop, OopPtr};
-use crate::runtime;
+use crate::runtime::{self, exception, ClassLoader};
 use crate::util;
+use class_parser::parse_class;
+use classfile::{constant_pool, consts as cls_consts};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -24,6 +26,22 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
             "(Ljava/lang/String;)Ljava/lang/Class;",
             Box::new(jvm_findBootstrapClass),
         ),
+        new_fn(
+            "defineClass0",
+            "(Ljava/lang/String;[BIILjava/security/ProtectionDomain;)Ljava/lang/Class;",
+            Box::new(jvm_defineClass0),
+        ),
+        new_fn(
+            "defineClass1",
+            "(Ljava/lang/String;[BIILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+            Box::new(jvm_defineClass1),
+        ),
+        new_fn(
+            "defineClass2",
+            "(Ljava/lang/String;Ljava/nio/ByteBuffer;IILjava/security/ProtectionDomain;Ljava/lang/String;)Ljava/lang/Class;",
+            Box::new(jvm_defineClass2),
+        ),
+        new_fn("resolveClass0", "(Ljava/lang/Class;)V", Box::new(jvm_resolveClass0)),
     ]
 }
 
@@ -39,11 +57,13 @@ fn jvm_findBuiltinLib(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 }
 
 fn jvm_findLoadedClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
     let name = args.get(1).unwrap();
     let name = OopPtr::java_lang_string(name.extract_ref());
     info!("findLoadedClass0: {}", name);
     let name = name.replace(".", util::FILE_SEP);
-    let v = match runtime::sys_dic_find(name.as_bytes()) {
+    let loader = ClassLoader::from_oop(this).unwrap_or(ClassLoader::Bootstrap);
+    let v = match runtime::sys_dic_find(loader, name.as_bytes()) {
         Some(cls) => {
             let cls = cls.get_class();
             cls.get_mirror()
@@ -53,8 +73,129 @@ fn jvm_findLoadedClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(v))
 }
 
-// fixme: Is this correct? uncertain
 fn jvm_findBootstrapClass(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    info!("findBootstrapClass");
-    jvm_findLoadedClass0(_env, args)
+    let name = args.get(1).unwrap();
+    let name = OopPtr::java_lang_string(name.extract_ref());
+    info!("findBootstrapClass: {}", name);
+    let name = name.replace(".", util::FILE_SEP);
+    let v = match runtime::require_class3(None, name.as_bytes()) {
+        Some(cls) => {
+            let cls = cls.get_class();
+            cls.get_mirror()
+        }
+        None => Oop::Null,
+    };
+    Ok(Some(v))
+}
+
+fn jvm_defineClass0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    jvm_defineClass1(_env, args)
+}
+
+fn jvm_defineClass1(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
+    let name = args.get(1).unwrap();
+    let b = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int() as usize;
+    let len = args.get(4).unwrap().extract_int() as usize;
+
+    let rf = b.extract_ref();
+    let ary = rf.extract_type_array();
+    let ary = ary.extract_bytes();
+    define_class(this, name, &ary[off..(off + len)])
+}
+
+fn jvm_defineClass2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
+    let name = args.get(1).unwrap();
+    let b = args.get(2).unwrap();
+    let off = args.get(3).unwrap().extract_int() as usize;
+    let len = args.get(4).unwrap().extract_int() as usize;
+
+    let hb = runtime::method_handles::get_field(b, b"java/nio/ByteBuffer", "hb", "[B");
+    match hb {
+        //direct buffer
+        Oop::Null => {
+            let address = runtime::method_handles::get_field(b, b"java/nio/Buffer", "address", "J");
+            let address = address.extract_long() as *const u8;
+            let bytes = unsafe { std::slice::from_raw_parts(address.add(off), len) };
+            define_class(this, name, bytes)
+        }
+        hb => {
+            let offset =
+                runtime::method_handles::get_field(b, b"java/nio/ByteBuffer", "offset", "I");
+            let off = off + offset.extract_int() as usize;
+            let rf = hb.extract_ref();
+            let ary = rf.extract_type_array();
+            let ary = ary.extract_bytes();
+            define_class(this, name, &ary[off..(off + len)])
+        }
+    }
+}
+
+//linking is done in defining
+fn jvm_resolveClass0(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+/*
+Define a class by the loader, the class named 'name' is recorded in the
+loader's namespace, 'name' may be null.
+*/
+pub fn define_class(loader: &Oop, name: &Oop, bytes: &[u8]) -> JNIResult {
+    let class_loader = ClassLoader::from_oop(loader);
+
+    let cf = match parse_class(bytes) {
+        Ok((_, cf)) => cf,
+        Err(_) => {
+            let msg = match name {
+                Oop::Null => None,
+                name => Some(OopPtr::java_lang_string(name.extract_ref())),
+            };
+            return Err(exception::new(cls_consts::J_CLASS_FORMAT_ERROR, msg));
+        }
+    };
+
+    if let Err((cls, msg)) = runtime::verifier::check_format(&cf) {
+        return Err(exception::new(cls, Some(msg)));
+    }
+
+    let this_name = constant_pool::get_class_name(&cf.cp, cf.this_class as usize).clone();
+    if !name.is_null() {
+        let name = OopPtr::java_lang_string(name.extract_ref());
+        if name.replace(".", "/").as_bytes() != this_name.as_slice() {
+            let msg = format!(
+                "{} (wrong name: {})",
+                name,
+                String::from_utf8_lossy(this_name.as_slice())
+            );
+            return Err(exception::new(
+                cls_consts::J_NO_CLASS_DEF_FOUND_ERROR,
+                Some(msg),
+            ));
+        }
+    }
+
+    let key = class_loader.unwrap_or(ClassLoader::Bootstrap);
+    if runtime::sys_dic_find(key, this_name.as_slice()).is_some() {
+        let msg = format!(
+            "attempted duplicate class definition for name: \"{}\"",
+            String::from_utf8_lossy(this_name.as_slice())
+        );
+        return Err(exception::new(cls_consts::J_LINKAGE_ERROR, Some(msg)));
+    }
+    if runtime::loader_constraints::check(key, this_name.as_slice(), None).is_err() {
+        return Ok(None);
+    }
+
+    let class = match runtime::define_class(class_loader, this_name.as_slice(), cf, None) {
+        Some(class) => class,
+        None => return Ok(None),
+    };
+    if let Err(msg) = runtime::verifier::verify(&class) {
+        runtime::sys_dic_remove(key, this_name.as_slice());
+        return Err(exception::new(cls_consts::J_VERIFY_ERROR, Some(msg)));
+    }
+    let mirror = { class.get_class().get_mirror() };
+    Ok(Some(mirror))
 }
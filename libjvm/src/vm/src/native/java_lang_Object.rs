@@ -2,6 +2,8 @@
 
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Oop};
+use crate::runtime::{exception, thread};
+use classfile::consts as cls_consts;
 use std::time::Duration;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
@@ -10,6 +12,7 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn("hashCode", "()I", Box::new(jvm_hashCode)),
         new_fn("clone", "()Ljava/lang/Object;", Box::new(jvm_clone)),
         new_fn("getClass", "()Ljava/lang/Class;", Box::new(jvm_getClass)),
+        new_fn("notify", "()V", Box::new(jvm_notify)),
         new_fn("notifyAll", "()V", Box::new(jvm_notifyAll)),
         new_fn("wait", "(J)V", Box::new(jvm_wait)),
     ]
@@ -52,22 +55,63 @@ fn jvm_getClass(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(mirror))
 }
 
+fn not_owner() -> Oop {
+    let msg = "current thread is not owner".to_string();
+    exception::new(cls_consts::J_ILLEGAL_MONITOR_STATE, Some(msg))
+}
+
+fn jvm_notify(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
+    let rf = this.extract_ref();
+    match rf.notify() {
+        Ok(_) => Ok(None),
+        Err(_) => Err(not_owner()),
+    }
+}
+
 fn jvm_notifyAll(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let this = args.get(0).unwrap();
     let rf = this.extract_ref();
-    rf.notify_all();
-    Ok(None)
+    match rf.notify_all() {
+        Ok(_) => Ok(None),
+        Err(_) => Err(not_owner()),
+    }
 }
 
+//wait(0) waits until notified or interrupted
 fn jvm_wait(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let this = args.get(0).unwrap();
     let millis = args.get(1).unwrap().extract_long();
+    if millis < 0 {
+        let msg = "timeout value is negative".to_string();
+        return Err(exception::new(cls_consts::J_ILLEGAL_ARGUMENT, Some(msg)));
+    }
 
     let rf = this.extract_ref();
-    if millis == 0 {
-        rf.wait();
-    } else {
-        rf.wait_timeout(Duration::from_millis(millis as u64));
+    if !rf.is_locked_by_current() {
+        return Err(not_owner());
+    }
+
+    let interrupt = {
+        let jt = thread::current_java_thread();
+        let jt = jt.read().unwrap();
+        jt.interrupt.clone()
+    };
+
+    if interrupt.begin_wait(this.clone()) {
+        let timeout = match millis {
+            0 => None,
+            millis => Some(Duration::from_millis(millis as u64)),
+        };
+        let r = rf.wait(timeout, &interrupt);
+        interrupt.end_wait();
+        if r.is_err() {
+            return Err(not_owner());
+        }
+    }
+
+    if interrupt.is_interrupted(true) {
+        return Err(exception::new(cls_consts::J_INTERRUPTED_EXCEPTION, None));
     }
 
     Ok(None)
@@ -1,7 +1,11 @@
 #![allow(non_snake_case)]
 
+use crate::gc;
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
-use crate::oop::Oop;
+use crate::new_br;
+use crate::oop::{self, Oop};
+use crate::runtime::JavaCall;
+use classfile::consts::J_FINALIZER;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -11,6 +15,10 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
             Box::new(jvm_availableProcessors),
         ),
         new_fn("gc", "()V", Box::new(jvm_gc)),
+        new_fn("runFinalization0", "()V", Box::new(jvm_runFinalization0)),
+        new_fn("freeMemory", "()J", Box::new(jvm_freeMemory)),
+        new_fn("totalMemory", "()J", Box::new(jvm_totalMemory)),
+        new_fn("maxMemory", "()J", Box::new(jvm_maxMemory)),
     ]
 }
 
@@ -20,5 +28,34 @@ fn jvm_availableProcessors(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
 }
 
 fn jvm_gc(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    gc::collect(gc::Generation::Full);
     Ok(None)
 }
+
+//run the pending finalizers in the current thread too
+fn jvm_runFinalization0(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let mir = {
+        let cls = oop::class::load_and_init(J_FINALIZER);
+        let cls = cls.get_class();
+        cls.get_static_method(&new_br("runFinalization"), &new_br("()V"))
+    };
+    if let Ok(mir) = mir {
+        let mut jc = JavaCall::new_with_args(mir, vec![]);
+        jc.invoke(None, false);
+    }
+
+    Ok(None)
+}
+
+fn jvm_freeMemory(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let free = gc::total_memory().saturating_sub(gc::used_memory());
+    Ok(Some(Oop::new_long(free as i64)))
+}
+
+fn jvm_totalMemory(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_long(gc::total_memory() as i64)))
+}
+
+fn jvm_maxMemory(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_long(gc::max_memory() as i64)))
+}
@@ -1,9 +1,11 @@
 #![allow(non_snake_case)]
 
+use crate::gc;
 use crate::native::{self, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::{self, Oop, OopPtr};
-use crate::runtime::{self, thread, JavaCall};
+use crate::runtime::{self, exception, thread, JavaCall};
 use crate::{new_br, util};
+use classfile::consts as cls_consts;
 use std::sync::Arc;
 use std::time::SystemTime;
 
@@ -33,6 +35,7 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
             "(Ljava/lang/String;)V",
             Box::new(jvm_loadLibrary),
         ),
+        new_fn("load", "(Ljava/lang/String;)V", Box::new(jvm_load)),
         new_fn(
             "identityHashCode",
             "(Ljava/lang/Object;)I",
@@ -146,11 +149,20 @@ fn jvm_initProperties(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let v = std::env::var("JAVA_HOME").expect("Please Setup JAVA_HOME env");
     put_props_kv(props_oop, "java.home", v.as_str());
 
+    //java.library.path
+    let v = native::library::default_library_path();
+    put_props_kv(props_oop, "java.library.path", v.as_str());
+
     //test.src for jdk/test/java/lang/Character/CheckProp.java
     if let Ok(v) = std::env::var("TEST_SRC") {
         put_props_kv(props_oop, "test.src", v.as_str());
     }
 
+    //-D options
+    for (k, v) in runtime::vm::properties() {
+        put_props_kv(props_oop, &k, &v);
+    }
+
     if thread::is_meet_ex() {
         unreachable!("jvm_initProperties meet ex");
     }
@@ -181,7 +193,7 @@ fn put_props_kv(props: &Oop, k: &str, v: &str) {
     let args = vec![props.clone(), k, v];
 
     let mut jc = JavaCall::new_with_args(mir, args);
-    let area = runtime::DataArea::new(1);
+    let area = runtime::DataArea::new();
     jc.invoke(Some(&area), false);
 }
 
@@ -217,23 +229,7 @@ fn jvm_mapLibraryName(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let s = OopPtr::java_lang_string(v.extract_ref());
 
     trace!("mapLibraryName libname = {}", s);
-    let mut name = String::new();
-    if cfg!(target_os = "macos") {
-        name.push_str("lib");
-        name.push_str(&s);
-        name.push_str(".dylib");
-    } else if cfg!(target_os = "windows") {
-        unimplemented!();
-    //        name.extend_from_slice(s.as_bytes());
-    //        name.extend_from_slice(".dll".as_bytes());
-    } else if cfg!(target_os = "linux") {
-        unimplemented!();
-    //        name.extend_from_slice("lib".as_bytes());
-    //        name.extend_from_slice(s.as_bytes());
-    //        name.extend_from_slice(".so".as_bytes());
-    } else {
-        unimplemented!()
-    }
+    let name = native::library::map_library_name(&s);
     trace!("mapLibraryName name = {}", name);
 
     let v = util::oop::new_java_lang_string2(&name);
@@ -241,10 +237,96 @@ fn jvm_mapLibraryName(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(v))
 }
 
-fn jvm_loadLibrary(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+/*
+Search the library in java.library.path.
+
+The natives of the jdk are implemented by the vm, so a library missing
+for a class of the bootstrap loader is not an error.
+*/
+fn jvm_loadLibrary(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let v = args.get(0).unwrap();
+    let libname = OopPtr::java_lang_string(v.extract_ref());
+    let name = native::library::map_library_name(&libname);
+
+    let path = match get_property("java.library.path") {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let found = path
+        .split(util::PATH_SEP)
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| std::path::Path::new(dir).join(&name))
+        .find(|it| it.is_file());
+
+    match found {
+        Some(file) => {
+            let file = file.to_string_lossy();
+            native::library::load(&file)?;
+        }
+        None if matches!(
+            caller_class_loader(),
+            None | Some(runtime::ClassLoader::Bootstrap)
+        ) =>
+        {
+            info!("loadLibrary: {} is built in", libname);
+        }
+        None => {
+            let msg = format!("no {} in java.library.path", libname);
+            return Err(exception::new(
+                cls_consts::J_UNSATISFIED_LINK_ERROR,
+                Some(msg),
+            ));
+        }
+    }
+
+    Ok(None)
+}
+
+fn jvm_load(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let v = args.get(0).unwrap();
+    let filename = OopPtr::java_lang_string(v.extract_ref());
+    if !std::path::Path::new(&filename).is_absolute() {
+        let msg = format!("Expecting an absolute path of the library: {}", filename);
+        return Err(exception::new(
+            cls_consts::J_UNSATISFIED_LINK_ERROR,
+            Some(msg),
+        ));
+    }
+
+    native::library::load(&filename)?;
     Ok(None)
 }
 
+//System.getProperty, None if the property is not set or an exception occurred
+fn get_property(key: &str) -> Option<String> {
+    let cls = runtime::require_class3(None, b"java/lang/System").unwrap();
+    let mir = {
+        let cls = cls.get_class();
+        cls.get_static_method(
+            &new_br("getProperty"),
+            &new_br("(Ljava/lang/String;)Ljava/lang/String;"),
+        )
+        .unwrap()
+    };
+
+    let key = util::oop::new_java_lang_string2(key);
+    match runtime::method_handles::call(mir, vec![key]) {
+        Some(Oop::Null) | None => None,
+        Some(v) => Some(OopPtr::java_lang_string(v.extract_ref())),
+    }
+}
+
+//the loader of the class calling System.load or System.loadLibrary
+fn caller_class_loader() -> Option<runtime::ClassLoader> {
+    let jt = runtime::thread::current_java_thread();
+    let jt = jt.read().unwrap();
+    //the last frame is the native method
+    match jt.frames.iter().rev().nth(1) {
+        Some(frame) => frame.class.get_class().class_loader,
+        None => None,
+    }
+}
+
 fn jvm_identityHashCode(env: JNIEnv, args: &[Oop]) -> JNIResult {
     native::java_lang_Object::jvm_hashCode(env, args)
 }
@@ -384,6 +466,7 @@ pub fn arraycopy_diff_obj(
             }
         }
     } else {
+        let dest_rf = dest.clone();
         let src = src.extract_array();
         let dest = dest.extract_mut_array();
 
@@ -391,6 +474,7 @@ pub fn arraycopy_diff_obj(
         let (_, dest_ptr) = dest.elements.split_at_mut(dest_pos);
 
         dest_ptr[..length].clone_from_slice(&src_ptr[..length]);
+        gc::write_barrier_slice(&dest_rf, &src_ptr[..length]);
     }
 }
 
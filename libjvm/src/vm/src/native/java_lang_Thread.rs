@@ -1,10 +1,15 @@
 #![allow(non_snake_case)]
 
-use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::gc;
+use crate::native::{java_lang_Throwable, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::new_br;
 use crate::oop::{Class, Oop, OopPtr};
+use crate::runtime::thread::THREAD_STATUS_RUNNABLE;
 use crate::runtime::vm::get_vm;
-use crate::runtime::{self, vm, JavaCall, JavaThread};
+use crate::runtime::{self, exception, require_class3, vm, JavaCall, JavaThread};
+use crate::types::JavaThreadRef;
+use classfile::consts as cls_consts;
+use std::time::Duration;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -18,6 +23,35 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn("isAlive", "()Z", Box::new(jvm_isAlive)),
         new_fn("start0", "()V", Box::new(jvm_start0)),
         new_fn("isInterrupted", "(Z)Z", Box::new(jvm_isInterrupted)),
+        new_fn("interrupt0", "()V", Box::new(jvm_interrupt0)),
+        new_fn("sleep", "(J)V", Box::new(jvm_sleep)),
+        new_fn("yield", "()V", Box::new(jvm_yield)),
+        new_fn(
+            "holdsLock",
+            "(Ljava/lang/Object;)Z",
+            Box::new(jvm_holdsLock),
+        ),
+        new_fn(
+            "getThreads",
+            "()[Ljava/lang/Thread;",
+            Box::new(jvm_getThreads),
+        ),
+        new_fn(
+            "dumpThreads",
+            "([Ljava/lang/Thread;)[[Ljava/lang/StackTraceElement;",
+            Box::new(jvm_dumpThreads),
+        ),
+        new_fn(
+            "setNativeName",
+            "(Ljava/lang/String;)V",
+            Box::new(jvm_setNativeName),
+        ),
+        //since jdk 9
+        new_fn(
+            "setName0",
+            "(Ljava/lang/String;)V",
+            Box::new(jvm_setNativeName),
+        ),
     ]
 }
 
@@ -35,26 +69,21 @@ fn jvm_setPriority0(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     Ok(None)
 }
 
-//'_jt' is caller's thread context, can't be used here
-//should find by 'eetop' in thread pool
-fn jvm_isAlive(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let this = args.get(0).unwrap();
-    let eetop = OopPtr::java_lang_thread_eetop(this.extract_ref());
+//the thread started and not exited yet, by 'eetop'
+pub fn find_thread(thread_oop: &Oop) -> Option<JavaThreadRef> {
+    let eetop = OopPtr::java_lang_thread_eetop(thread_oop.extract_ref());
     let vm = get_vm();
+    vm.threads.find_java_thread(eetop)
+}
 
-    let r = match vm.threads.find_java_thread(eetop) {
-        Some(jt) => {
-            info!("native thread tag = {}", jt.read().unwrap().tag);
-            if jt.read().unwrap().is_alive {
-                1
-            } else {
-                0
-            }
-        }
-        None => 0,
+fn jvm_isAlive(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
+    let r = match find_thread(this) {
+        Some(jt) => jt.read().unwrap().is_alive,
+        None => false,
     };
 
-    Ok(Some(Oop::new_int(r)))
+    Ok(Some(Oop::new_int(r as i32)))
 }
 
 fn jvm_start0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
@@ -66,54 +95,183 @@ fn jvm_start0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     };
 
     let cls = clazz.get_class();
-    if cls.name.as_slice() == "java/lang/ref/Reference$ReferenceHandler".as_bytes() {
-        Ok(None)
-    } else {
-        let vm = vm::get_vm();
-
-        let jt = JavaThread::new(None, vm.threads.next_id());
-        vm.threads.attach_java_thread(jt.clone());
-
-        let args = vec![thread_oop.clone()];
-        vm.threads.spawn_java_thread(move || {
-            //setup current thread
-            let current_thread = jt.clone();
-            runtime::thread::THREAD.with(|t| {
-                *t.borrow_mut() = current_thread;
-            });
-
-            let cls = clazz.get_class();
-            let mir = {
-                //setup eetop
-                let eetop = jt.read().unwrap().eetop;
-                let fid = cls.get_field_id(&new_br("eetop"), &new_br("J"), false);
-                Class::put_field_value(thread_oop.extract_ref(), fid, Oop::new_long(eetop));
-
-                //obtain 'run' method
-                cls.get_virtual_method(&new_br("run"), &new_br("()V"))
-                    .unwrap()
-            };
-
-            //invoke 'run'
-            let mut jc = JavaCall::new_with_args(mir, args);
-            jt.write().unwrap().is_alive = true;
-            jt.write().unwrap().java_thread_obj = Some(thread_oop.clone());
-            jc.invoke(None, false);
-            jt.write().unwrap().is_alive = false;
-
-            //notify thread that invoke 'join'
-            let v = thread_oop.extract_ref();
-            v.notify_all();
-
-            vm.threads.detach_current_thread();
+    let vm = vm::get_vm();
+
+    let eetop = vm.threads.next_id();
+    let jt = JavaThread::new(None, eetop);
+    let (run, stack_size) = {
+        let rf = thread_oop.extract_ref();
+        let fid = cls.get_field_id(&new_br("eetop"), &new_br("J"), false);
+        Class::put_field_value(rf.clone(), fid, Oop::new_long(eetop));
+        let fid = cls.get_field_id(&new_br("stackSize"), &new_br("J"), false);
+        let stack_size = Class::get_field_value(rf, fid).extract_long();
+
+        //obtain 'run' method
+        let run = cls
+            .get_virtual_method(&new_br("run"), &new_br("()V"))
+            .unwrap();
+        (run, stack_size.max(0) as usize)
+    };
+
+    //alive once started, before the native thread runs
+    let name = {
+        let mut jt = jt.write().unwrap();
+        jt.set_java_thread_obj(thread_oop.clone());
+        jt.is_alive = true;
+        jt.set_status(THREAD_STATUS_RUNNABLE);
+        jt.tag.clone()
+    };
+    vm.threads.attach_java_thread(jt.clone());
+
+    let thread = jt.clone();
+    let args = vec![thread_oop.clone()];
+    let r = vm.threads.spawn_java_thread(name, stack_size, move || {
+        //setup current thread
+        runtime::thread::THREAD.with(|t| {
+            *t.borrow_mut() = thread;
         });
+        gc::attach_thread();
+
+        //invoke 'run'
+        let mut jc = JavaCall::new_with_args(run, args);
+        jc.invoke(None, false);
+
+        //the uncaught exception is dispatched, joining threads are notified
+        let _ = vm::detach_current_thread();
+    });
+
+    match r {
+        Ok(_) => Ok(None),
+        Err(e) => {
+            warn!("start thread failed: {}", e);
+            {
+                let mut jt = jt.write().unwrap();
+                jt.is_alive = false;
+                jt.set_status(0);
+            }
+            vm.threads.detach_java_thread(&jt);
+            let msg = "unable to create new native thread".to_string();
+            Err(exception::new(cls_consts::J_OOM, Some(msg)))
+        }
+    }
+}
+
+//Thread.interrupted clears the status of the current thread
+fn jvm_isInterrupted(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
+    let clear = args.get(1).unwrap().extract_int() != 0;
+    let r = match find_thread(this) {
+        Some(jt) => jt.read().unwrap().interrupt.is_interrupted(clear),
+        None => false,
+    };
+
+    Ok(Some(Oop::new_int(r as i32)))
+}
+
+fn jvm_interrupt0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
+    if let Some(jt) = find_thread(this) {
+        let (interrupt, parker) = {
+            let jt = jt.read().unwrap();
+            (jt.interrupt.clone(), jt.parker.clone())
+        };
+        interrupt.interrupt();
+        //LockSupport.park returns
+        parker.unpark();
+    }
+
+    Ok(None)
+}
 
+fn jvm_sleep(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let millis = args.get(0).unwrap().extract_long();
+    if millis < 0 {
+        let msg = "timeout value is negative".to_string();
+        return Err(exception::new(cls_consts::J_ILLEGAL_ARGUMENT, Some(msg)));
+    }
+
+    let interrupt = {
+        let jt = runtime::thread::current_java_thread();
+        let jt = jt.read().unwrap();
+        jt.interrupt.clone()
+    };
+    if interrupt.sleep(Duration::from_millis(millis as u64)) {
         Ok(None)
+    } else {
+        let msg = "sleep interrupted".to_string();
+        Err(exception::new(
+            cls_consts::J_INTERRUPTED_EXCEPTION,
+            Some(msg),
+        ))
+    }
+}
+
+fn jvm_yield(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    std::thread::yield_now();
+    Ok(None)
+}
+
+fn jvm_holdsLock(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let obj = args.get(0).unwrap();
+    let r = match obj {
+        Oop::Null => return Err(exception::new(cls_consts::J_NPE, None)),
+        obj => obj.extract_ref().is_locked_by_current(),
+    };
+
+    Ok(Some(Oop::new_int(r as i32)))
+}
+
+//the threads started and alive
+fn jvm_getThreads(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    let vm = get_vm();
+    let threads = vm
+        .threads
+        .java_threads()
+        .iter()
+        .filter_map(|jt| {
+            let jt = jt.read().unwrap();
+            if jt.is_alive {
+                jt.java_thread_obj.clone()
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    let ary_cls = require_class3(None, b"[Ljava/lang/Thread;").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, threads)))
+}
+
+//the stack traces of the threads, empty for the thread not alive
+fn jvm_dumpThreads(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let threads = args.get(0).unwrap();
+    let threads = match threads {
+        Oop::Null => return Err(exception::new(cls_consts::J_NPE, None)),
+        threads => threads.extract_ref().extract_array().elements.to_vec(),
+    };
+
+    let mut traces = Vec::with_capacity(threads.len());
+    for it in threads.iter() {
+        let frames = match it {
+            Oop::Null => return Err(exception::new(cls_consts::J_NPE, None)),
+            it => match find_thread(it) {
+                Some(jt) => jt.read().unwrap().frames.clone(),
+                None => Vec::new(),
+            },
+        };
+        traces.push(java_lang_Throwable::build_stack_trace(&frames));
     }
+
+    let ary_cls = require_class3(None, b"[[Ljava/lang/StackTraceElement;").unwrap();
+    Ok(Some(Oop::new_ref_ary2(ary_cls, traces)))
 }
 
-fn jvm_isInterrupted(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
-    //todo: fix me
-    let v = Oop::new_int(0);
-    Ok(Some(v))
+fn jvm_setNativeName(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let this = args.get(0).unwrap();
+    let name = args.get(1).unwrap();
+    if let (Some(jt), Oop::Ref(name)) = (find_thread(this), name) {
+        jt.write().unwrap().tag = OopPtr::java_lang_string(name.clone());
+    }
+
+    Ok(None)
 }
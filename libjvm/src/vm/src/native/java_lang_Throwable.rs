@@ -1,8 +1,9 @@
 #![allow(non_snake_case)]
 
 use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
-use crate::oop::{self, Class, Oop};
-use crate::runtime::{self, require_class3};
+use crate::oop::{self, Class, ClassKind, Oop};
+use crate::runtime::{self, cmp, require_class3};
+use crate::types::FrameRef;
 use crate::{new_br, util};
 use std::sync::atomic::Ordering;
 
@@ -26,60 +27,82 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
     ]
 }
 
-fn jvm_fillInStackTrace(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let jt = runtime::thread::current_java_thread();
+/*
+The frames of the thread, the innermost first, without the ones building
+the throwable, like hotspot:
 
-    let elm_cls = oop::class::load_and_init(b"java/lang/StackTraceElement");
-    let ary_cls = require_class3(None, b"[Ljava/lang/StackTraceElement;").unwrap();
+  Throwable.fillInStackTrace, the native and the java one
+  the <init> of the class of the throwable and its super classes
 
+the exception thrown by the vm is built the same, the frame throwing it
+is the innermost one, or the native method.
+*/
+fn jvm_fillInStackTrace(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let throwable_oop = args.get(0).unwrap();
-    let mut backtrace = Vec::with_capacity(jt.read().unwrap().frames.len());
-
-    let mut found_ex_here = false;
-    let jth = jt.read().unwrap();
-    for it in jth.frames.iter() {
-        let ex_here = {
-            let it = it.try_read().unwrap();
-            it.ex_here.load(Ordering::Relaxed)
-        };
-
-        backtrace.push(it.clone());
+    let throwable_cls = {
+        let rf = throwable_oop.extract_ref();
+        let inst = rf.extract_inst();
+        inst.class.clone()
+    };
 
-        if ex_here {
-            found_ex_here = true;
+    let jt = runtime::thread::current_java_thread();
+    let mut backtrace = { jt.read().unwrap().frames.clone() };
+    while let Some(frame) = backtrace.last() {
+        let m = &frame.mir.method;
+        if m.name.as_slice() == b"fillInStackTrace" {
+            backtrace.pop();
+        } else {
             break;
         }
     }
-    drop(jth);
-
-    /*
-       todo: how handle throw better?
-
-    if no ex_here found, it's:
-      throw new AnnotationFormatError("Unexpected end of annotations.");
-
-    new Throwable
-      Throwable.fillInStackTrace invoked, and come here
-
-    there are stacktraces for build 'Throwable' obj, not necessary for user, need discard
-
-    Exception in thread "main" java.lang.annotation.AnnotationFormatError: Unexpected end of annotations.
-       at java.lang.Throwable.fillInStackTrace(Throwable.java)
-       at java.lang.Throwable.fillInStackTrace(Throwable.java:783)
-       at java.lang.Throwable.<init>(Throwable.java:265)
-       at java.lang.Error.<init>(Error.java:70)
-       */
-    if !found_ex_here {
-        backtrace.pop();
-        backtrace.pop();
-        backtrace.pop();
-        backtrace.pop();
+    while let Some(frame) = backtrace.last() {
+        let m = &frame.mir.method;
+        if m.name.as_slice() == b"<init>"
+            && cmp::instance_of(throwable_cls.clone(), m.class.clone())
+        {
+            backtrace.pop();
+        } else {
+            break;
+        }
+    }
+    backtrace.retain(|it| !is_hidden(it));
+
+    let stack_trace_ary = build_stack_trace(&backtrace);
+    let throwable_cls = require_class3(None, b"java/lang/Throwable").unwrap();
+    {
+        let cls = throwable_cls.get_class();
+        let id = cls.get_field_id(
+            &new_br("stackTrace"),
+            &new_br("[Ljava/lang/StackTraceElement;"),
+            false,
+        );
+        Class::put_field_value(throwable_oop.extract_ref(), id, Oop::Null);
+        let id = cls.get_field_id(&new_br("backtrace"), &new_br("Ljava/lang/Object;"), false);
+        Class::put_field_value(throwable_oop.extract_ref(), id, stack_trace_ary);
     }
 
+    Ok(Some(throwable_oop.clone()))
+}
+
+//the frames of the lambda forms and of the anonymous classes, not shown by hotspot
+fn is_hidden(frame: &FrameRef) -> bool {
+    let m = &frame.mir.method;
+    let is_anonymous = match &m.class.get_class().kind {
+        ClassKind::Instance(cls_obj) => cls_obj.host.is_some(),
+        _ => false,
+    };
+
+    is_anonymous || m.check_annotation(b"Ljava/lang/invoke/LambdaForm$Hidden;")
+}
+
+//the StackTraceElement[] of 'frames', the innermost frame is the last one
+pub fn build_stack_trace(frames: &[FrameRef]) -> Oop {
+    let elm_cls = oop::class::load_and_init(b"java/lang/StackTraceElement");
+    let ary_cls = require_class3(None, b"[Ljava/lang/StackTraceElement;").unwrap();
+
     let mut traces = Vec::new();
-    for caller in backtrace.iter().rev() {
+    for caller in frames.iter().rev() {
         let (mir, pc) = {
-            let caller = caller.try_read().unwrap();
             let pc = caller.pc.load(Ordering::Relaxed);
             (caller.mir.clone(), pc)
         };
@@ -97,9 +120,14 @@ fn jvm_fillInStackTrace(_env: JNIEnv, args: &[Oop]) -> JNIResult {
                 let name = unsafe { std::str::from_utf8_unchecked(name.as_slice()) };
                 util::oop::new_java_lang_string2(name)
             }
-            None => util::oop::new_java_lang_string2(""),
+            None => Oop::Null,
+        };
+        //the pc is of the next instruction, -2 is "Native Method" of StackTraceElement
+        let line_num = if mir.method.is_native() {
+            -2
+        } else {
+            mir.method.get_line_num((pc - 1).max(0) as u16)
         };
-        let line_num = mir.method.get_line_num((pc - 1) as u16);
 
         let elm = Oop::new_inst(elm_cls.clone());
         let args = vec![
@@ -118,21 +146,7 @@ fn jvm_fillInStackTrace(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         traces.push(elm);
     }
 
-    let stack_trace_ary = Oop::new_ref_ary2(ary_cls, traces);
-    let throwable_cls = require_class3(None, b"java/lang/Throwable").unwrap();
-    {
-        let cls = throwable_cls.get_class();
-        let id = cls.get_field_id(
-            &new_br("stackTrace"),
-            &new_br("[Ljava/lang/StackTraceElement;"),
-            false,
-        );
-        Class::put_field_value(throwable_oop.extract_ref(), id, Oop::Null);
-        let id = cls.get_field_id(&new_br("backtrace"), &new_br("Ljava/lang/Object;"), false);
-        Class::put_field_value(throwable_oop.extract_ref(), id, stack_trace_ary);
-    }
-
-    Ok(Some(throwable_oop.clone()))
+    Oop::new_ref_ary2(ary_cls, traces)
 }
 
 fn jvm_getStackTraceDepth(_env: JNIEnv, args: &[Oop]) -> JNIResult {
@@ -0,0 +1,509 @@
+#![allow(non_snake_case)]
+
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::new_br;
+use crate::oop::{self, Oop, OopPtr};
+use crate::runtime::exception;
+use crate::runtime::method_handles::{self as mh, *};
+use crate::types::{ClassRef, FieldIdRef, MethodIdRef};
+use crate::util;
+use classfile::consts as cls_consts;
+use std::sync::Arc;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![
+        new_fn("registerNatives", "()V", Box::new(jvm_registerNatives)),
+        new_fn("getConstant", "(I)I", Box::new(jvm_getConstant)),
+        new_fn(
+            "getNamedCon",
+            "(I[Ljava/lang/Object;)I",
+            Box::new(jvm_getNamedCon),
+        ),
+        new_fn(
+            "init",
+            "(Ljava/lang/invoke/MemberName;Ljava/lang/Object;)V",
+            Box::new(jvm_init),
+        ),
+        new_fn(
+            "expand",
+            "(Ljava/lang/invoke/MemberName;)V",
+            Box::new(jvm_expand),
+        ),
+        new_fn(
+            "resolve",
+            "(Ljava/lang/invoke/MemberName;Ljava/lang/Class;)Ljava/lang/invoke/MemberName;",
+            Box::new(jvm_resolve),
+        ),
+        new_fn(
+            "getMembers",
+            "(Ljava/lang/Class;Ljava/lang/String;Ljava/lang/String;ILjava/lang/Class;I[Ljava/lang/invoke/MemberName;)I",
+            Box::new(jvm_getMembers),
+        ),
+        new_fn(
+            "objectFieldOffset",
+            "(Ljava/lang/invoke/MemberName;)J",
+            Box::new(jvm_objectFieldOffset),
+        ),
+        new_fn(
+            "staticFieldOffset",
+            "(Ljava/lang/invoke/MemberName;)J",
+            Box::new(jvm_objectFieldOffset),
+        ),
+        new_fn(
+            "staticFieldBase",
+            "(Ljava/lang/invoke/MemberName;)Ljava/lang/Object;",
+            Box::new(jvm_staticFieldBase),
+        ),
+        new_fn(
+            "setCallSiteTargetNormal",
+            "(Ljava/lang/invoke/CallSite;Ljava/lang/invoke/MethodHandle;)V",
+            Box::new(jvm_setCallSiteTarget),
+        ),
+        new_fn(
+            "setCallSiteTargetVolatile",
+            "(Ljava/lang/invoke/CallSite;Ljava/lang/invoke/MethodHandle;)V",
+            Box::new(jvm_setCallSiteTarget),
+        ),
+    ]
+}
+
+fn jvm_registerNatives(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(None)
+}
+
+//only used by MethodHandleNatives.verifyConstants, no constants to check
+fn jvm_getConstant(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_int(0)))
+}
+
+fn jvm_getNamedCon(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    Ok(Some(Oop::new_int(0)))
+}
+
+//MemberName(Method), MemberName(Constructor), MemberName(Field)
+fn jvm_init(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    let target = args.get(1).unwrap();
+
+    let target_cls = {
+        let rf = target.extract_ref();
+        let inst = rf.extract_inst();
+        inst.class.get_class().name.clone()
+    };
+    let clazz = mh::get_field(target, target_cls.as_slice(), "clazz", "Ljava/lang/Class;");
+    let slot = mh::get_field(target, target_cls.as_slice(), "slot", "I").extract_int();
+    let defc = mh::mirror_target(&clazz).ok_or_else(|| linkage_error("no class".to_string()))?;
+    let not_found = || {
+        let name = defc.get_class().name.clone();
+        let msg = format!(
+            "no member at slot {} of {}",
+            slot,
+            String::from_utf8_lossy(name.as_slice()).replace('/', ".")
+        );
+        linkage_error(msg)
+    };
+
+    match target_cls.as_slice() {
+        cls_consts::J_METHOD | cls_consts::J_METHOD_CTOR => {
+            let mir = find_method_by_slot(&defc, slot as usize).ok_or_else(not_found)?;
+            let ref_kind = if mir.method.name.as_slice() == b"<init>" {
+                cls_consts::REF_NEW_INVOKE_SPECIAL
+            } else if mir.method.is_static() {
+                cls_consts::REF_INVOKE_STATIC
+            } else if defc.get_class().is_interface() {
+                cls_consts::REF_INVOKE_INTERFACE
+            } else {
+                cls_consts::REF_INVOKE_VIRTUAL
+            };
+            fill_method(mn, mir, ref_kind);
+        }
+        cls_consts::J_FIELD => {
+            let modifiers = mh::get_field(target, cls_consts::J_FIELD, "modifiers", "I");
+            let is_static = (modifiers.extract_int() as u16 & classfile::flags::ACC_STATIC) != 0;
+            let fid = find_field_by_slot(&defc, slot as usize, is_static).ok_or_else(not_found)?;
+            let ref_kind = if is_static {
+                cls_consts::REF_GET_STATIC
+            } else {
+                cls_consts::REF_GET_FIELD
+            };
+            fill_field(mn, fid, ref_kind);
+        }
+        t => {
+            let msg = format!(
+                "not a member: {}",
+                String::from_utf8_lossy(t).replace('/', ".")
+            );
+            return Err(linkage_error(msg));
+        }
+    }
+
+    Ok(None)
+}
+
+//fill in name & type from vmtarget
+fn jvm_expand(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    let flags = get_flags(mn);
+    if flags & (MN_IS_METHOD | MN_IS_CONSTRUCTOR) == 0 {
+        return Ok(None);
+    }
+
+    if let Ok(mir) = mh::get_vmtarget(mn) {
+        let name = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "name", "Ljava/lang/String;");
+        if name.is_null() {
+            let v = util::oop::new_java_lang_string3(mir.method.name.as_slice());
+            mh::put_field(
+                mn,
+                cls_consts::J_MEMBER_NAME,
+                "name",
+                "Ljava/lang/String;",
+                v,
+            );
+        }
+
+        let typ = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "type", "Ljava/lang/Object;");
+        if typ.is_null() {
+            let v = util::oop::new_java_lang_string3(mir.method.desc.as_slice());
+            mh::put_field(
+                mn,
+                cls_consts::J_MEMBER_NAME,
+                "type",
+                "Ljava/lang/Object;",
+                v,
+            );
+        }
+    }
+
+    Ok(None)
+}
+
+fn jvm_resolve(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+
+    let clazz = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "clazz", "Ljava/lang/Class;");
+    let name = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "name", "Ljava/lang/String;");
+    let typ = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "type", "Ljava/lang/Object;");
+    let defc = match mh::mirror_target(&clazz) {
+        Some(defc) if !name.is_null() && !typ.is_null() => defc,
+        _ => {
+            let msg = "nothing to resolve".to_string();
+            return Err(exception::new(cls_consts::J_INTERNAL_ERROR, Some(msg)));
+        }
+    };
+    let name = OopPtr::java_lang_string(name.extract_ref());
+    let name = new_br(name.as_str());
+    let desc = Arc::new(mh::type_desc(&typ));
+
+    let flags = get_flags(mn);
+    let ref_kind = ((flags >> MN_REFERENCE_KIND_SHIFT) & MN_REFERENCE_KIND_MASK) as u8;
+
+    if flags & MN_IS_FIELD != 0 {
+        let is_static =
+            ref_kind == cls_consts::REF_GET_STATIC || ref_kind == cls_consts::REF_PUT_STATIC;
+        match mh::find_field(&defc, &name, &desc, is_static) {
+            Some(fid) => fill_field(mn, fid, ref_kind),
+            None => {
+                let msg = member_str(&defc, &name, &desc);
+                return Err(exception::new(cls_consts::J_NO_SUCH_FIELD_ERROR, Some(msg)));
+            }
+        }
+    } else if flags & (MN_IS_METHOD | MN_IS_CONSTRUCTOR) != 0 {
+        match mh::find_method(&defc, &name, &desc, ref_kind) {
+            Ok(mir) => fill_method(mn, mir, ref_kind),
+            Err(_) => {
+                let msg = member_str(&defc, &name, &desc);
+                return Err(exception::new(
+                    cls_consts::J_NO_SUCH_METHOD_ERROR,
+                    Some(msg),
+                ));
+            }
+        }
+    } else {
+        let msg = "unrecognized MemberName format".to_string();
+        return Err(exception::new(cls_consts::J_INTERNAL_ERROR, Some(msg)));
+    }
+
+    Ok(Some(mn.clone()))
+}
+
+fn jvm_getMembers(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let defc = args.first().unwrap();
+    let match_name = args.get(1).unwrap();
+    let match_sig = args.get(2).unwrap();
+    let match_flags = args.get(3).unwrap().extract_int();
+    let skip = args.get(5).unwrap().extract_int() as usize;
+    let results = args.get(6).unwrap();
+
+    let defc = match mh::mirror_target(defc) {
+        Some(defc) => defc,
+        None => return Ok(Some(Oop::new_int(-1))),
+    };
+    let match_name = match match_name {
+        Oop::Null => None,
+        v => Some(OopPtr::java_lang_string(v.extract_ref()).into_bytes()),
+    };
+    let match_sig = match match_sig {
+        Oop::Null => None,
+        v => Some(OopPtr::java_lang_string(v.extract_ref()).into_bytes()),
+    };
+    let is_match = |name: &[u8], desc: &[u8]| {
+        let name_matched = match &match_name {
+            Some(it) => it.as_slice() == name,
+            None => true,
+        };
+        let sig_matched = match &match_sig {
+            Some(it) => it.as_slice() == desc,
+            None => true,
+        };
+        name_matched && sig_matched
+    };
+
+    let mut found = Vec::new();
+    let mut cur = Some(defc);
+    while let Some(cls) = cur {
+        {
+            let cls = cls.get_class();
+            if let oop::class::ClassKind::Instance(cls_obj) = &cls.kind {
+                if match_flags & MN_IS_FIELD != 0 {
+                    cls_obj
+                        .static_fields
+                        .values()
+                        .chain(cls_obj.inst_fields.values())
+                        .filter(|it| is_match(it.field.name.as_slice(), it.field.desc.as_slice()))
+                        .for_each(|it| found.push(Member::Field(it.clone())));
+                }
+
+                if match_flags & (MN_IS_METHOD | MN_IS_CONSTRUCTOR) != 0 {
+                    cls_obj
+                        .all_methods
+                        .values()
+                        .filter(|it| {
+                            let is_ctor = it.method.name.as_slice() == b"<init>";
+                            let want = if is_ctor {
+                                MN_IS_CONSTRUCTOR
+                            } else {
+                                MN_IS_METHOD
+                            };
+                            match_flags & want != 0
+                                && it.method.name.as_slice() != b"<clinit>"
+                                && is_match(it.method.name.as_slice(), it.method.desc.as_slice())
+                        })
+                        .for_each(|it| found.push(Member::Method(it.clone())));
+                }
+            }
+        }
+
+        cur = if match_flags & MN_SEARCH_SUPERCLASSES != 0 {
+            cls.get_class().super_class.clone()
+        } else {
+            None
+        };
+    }
+
+    let results = {
+        let rf = results.extract_ref();
+        let ary = rf.extract_array();
+        ary.elements.to_vec()
+    };
+    let n = found.len().saturating_sub(skip);
+    for (mn, it) in results.iter().zip(found.into_iter().skip(skip)) {
+        if mn.is_null() {
+            break;
+        }
+
+        match it {
+            Member::Field(fid) => {
+                let ref_kind = if fid.field.is_static() {
+                    cls_consts::REF_GET_STATIC
+                } else {
+                    cls_consts::REF_GET_FIELD
+                };
+                fill_field(mn, fid.clone(), ref_kind);
+                fill_name_type(mn, &fid.field.name, &fid.field.desc);
+            }
+            Member::Method(mir) => {
+                let ref_kind = if mir.method.name.as_slice() == b"<init>" {
+                    cls_consts::REF_NEW_INVOKE_SPECIAL
+                } else if mir.method.is_static() {
+                    cls_consts::REF_INVOKE_STATIC
+                } else {
+                    cls_consts::REF_INVOKE_VIRTUAL
+                };
+                fill_name_type(mn, &mir.method.name, &mir.method.desc);
+                fill_method(mn, mir, ref_kind);
+            }
+        }
+    }
+
+    Ok(Some(Oop::new_int(n as i32)))
+}
+
+fn jvm_objectFieldOffset(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    match resolve_field(mn) {
+        Ok(fid) => Ok(Some(Oop::new_long(fid.offset as i64))),
+        Err(ex) => Err(ex),
+    }
+}
+
+fn jvm_staticFieldBase(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mn = args.first().unwrap();
+    match resolve_field(mn) {
+        Ok(fid) => Ok(Some(fid.field.class.get_class().get_mirror())),
+        Err(ex) => Err(ex),
+    }
+}
+
+fn jvm_setCallSiteTarget(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let call_site = args.first().unwrap();
+    let target = args.get(1).unwrap();
+    mh::put_field(
+        call_site,
+        cls_consts::J_CALL_SITE,
+        "target",
+        "Ljava/lang/invoke/MethodHandle;",
+        target.clone(),
+    );
+    Ok(None)
+}
+
+enum Member {
+    Field(FieldIdRef),
+    Method(MethodIdRef),
+}
+
+fn get_flags(mn: &Oop) -> i32 {
+    mh::get_field(mn, cls_consts::J_MEMBER_NAME, "flags", "I").extract_int()
+}
+
+fn fill_method(mn: &Oop, mir: MethodIdRef, ref_kind: u8) {
+    let kind = if mir.method.name.as_slice() == b"<init>" {
+        MN_IS_CONSTRUCTOR
+    } else {
+        MN_IS_METHOD
+    };
+    let mut flags =
+        mir.method.acc_flags as i32 | kind | ((ref_kind as i32) << MN_REFERENCE_KIND_SHIFT);
+    if mir
+        .method
+        .check_annotation(b"Lsun/reflect/CallerSensitive;")
+    {
+        flags |= MN_CALLER_SENSITIVE;
+    }
+
+    let clazz = mir.method.class.get_class().get_mirror();
+    mh::put_field(
+        mn,
+        cls_consts::J_MEMBER_NAME,
+        "clazz",
+        "Ljava/lang/Class;",
+        clazz,
+    );
+    mh::put_field(
+        mn,
+        cls_consts::J_MEMBER_NAME,
+        "flags",
+        "I",
+        Oop::new_int(flags),
+    );
+    mh::set_vmtarget(mn, mir);
+}
+
+fn fill_field(mn: &Oop, fid: FieldIdRef, ref_kind: u8) {
+    let flags =
+        fid.field.acc_flags as i32 | MN_IS_FIELD | ((ref_kind as i32) << MN_REFERENCE_KIND_SHIFT);
+    let clazz = fid.field.class.get_class().get_mirror();
+    mh::put_field(
+        mn,
+        cls_consts::J_MEMBER_NAME,
+        "clazz",
+        "Ljava/lang/Class;",
+        clazz,
+    );
+    mh::put_field(
+        mn,
+        cls_consts::J_MEMBER_NAME,
+        "flags",
+        "I",
+        Oop::new_int(flags),
+    );
+}
+
+fn fill_name_type(mn: &Oop, name: &[u8], desc: &[u8]) {
+    let name = util::oop::new_java_lang_string3(name);
+    let desc = util::oop::new_java_lang_string3(desc);
+    mh::put_field(
+        mn,
+        cls_consts::J_MEMBER_NAME,
+        "name",
+        "Ljava/lang/String;",
+        name,
+    );
+    mh::put_field(
+        mn,
+        cls_consts::J_MEMBER_NAME,
+        "type",
+        "Ljava/lang/Object;",
+        desc,
+    );
+}
+
+fn resolve_field(mn: &Oop) -> Result<FieldIdRef, Oop> {
+    let clazz = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "clazz", "Ljava/lang/Class;");
+    let name = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "name", "Ljava/lang/String;");
+    let typ = mh::get_field(mn, cls_consts::J_MEMBER_NAME, "type", "Ljava/lang/Object;");
+    let flags = get_flags(mn);
+
+    let defc = mh::mirror_target(&clazz).ok_or_else(|| linkage_error("no class".to_string()))?;
+    let name = OopPtr::java_lang_string(name.extract_ref());
+    let name = new_br(name.as_str());
+    let desc = Arc::new(mh::type_desc(&typ));
+    let is_static = flags & (classfile::flags::ACC_STATIC as i32) != 0;
+
+    mh::find_field(&defc, &name, &desc, is_static).ok_or_else(|| {
+        let msg = member_str(&defc, &name, &desc);
+        exception::new(cls_consts::J_NO_SUCH_FIELD_ERROR, Some(msg))
+    })
+}
+
+//the slot of java/lang/reflect/Method & Constructor is the index of method_info
+fn find_method_by_slot(cls: &ClassRef, slot: usize) -> Option<MethodIdRef> {
+    let cls = cls.get_class();
+    match &cls.kind {
+        oop::class::ClassKind::Instance(cls_obj) => cls_obj
+            .all_methods
+            .values()
+            .find(|it| it.offset == slot)
+            .cloned(),
+        _ => None,
+    }
+}
+
+fn find_field_by_slot(cls: &ClassRef, slot: usize, is_static: bool) -> Option<FieldIdRef> {
+    let cls = cls.get_class();
+    match &cls.kind {
+        oop::class::ClassKind::Instance(cls_obj) => {
+            let fields = if is_static {
+                &cls_obj.static_fields
+            } else {
+                &cls_obj.inst_fields
+            };
+            fields.values().find(|it| it.offset == slot).cloned()
+        }
+        _ => None,
+    }
+}
+
+fn linkage_error(msg: String) -> Oop {
+    exception::new(cls_consts::J_LINKAGE_ERROR, Some(msg))
+}
+
+fn member_str(defc: &ClassRef, name: &[u8], desc: &[u8]) -> String {
+    let cls = defc.get_class();
+    format!(
+        "{}.{}{}",
+        String::from_utf8_lossy(cls.name.as_slice()).replace("/", "."),
+        String::from_utf8_lossy(name),
+        String::from_utf8_lossy(desc)
+    )
+}
@@ -0,0 +1,30 @@
+#![allow(non_snake_case)]
+
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::new_br;
+use crate::oop::Oop;
+use crate::runtime::JavaCall;
+
+pub fn get_native_methods() -> Vec<JNINativeMethod> {
+    vec![new_fn(
+        "invokeFinalizeMethod",
+        "(Ljava/lang/Object;)V",
+        Box::new(jvm_invokeFinalizeMethod),
+    )]
+}
+
+//the exception thrown by finalize is left to Finalizer, which ignores it
+fn jvm_invokeFinalizeMethod(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let obj = args.get(0).unwrap();
+    let mir = {
+        let cls = obj.extract_ref().extract_inst().class.clone();
+        let cls = cls.get_class();
+        cls.get_virtual_method(&new_br("finalize"), &new_br("()V"))
+    };
+    if let Ok(mir) = mir {
+        let mut jc = JavaCall::new_with_args(mir, vec![obj.clone()]);
+        jc.invoke(None, false);
+    }
+
+    Ok(None)
+}
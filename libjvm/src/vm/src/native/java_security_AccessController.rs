@@ -49,12 +49,11 @@ fn jvm_doPrivileged(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 
     let args = vec![v.clone()];
     let mut jc = JavaCall::new_with_args(mir, args);
-    let area = runtime::DataArea::new(1);
+    let area = runtime::DataArea::new();
     jc.invoke(Some(&area), false);
 
     if !thread::is_meet_ex() {
-        let mut stack = area.stack.borrow_mut();
-        let r = stack.pop_ref();
+        let r = area.return_v.borrow_mut().take().unwrap();
         Ok(Some(r))
     } else {
         Ok(None)
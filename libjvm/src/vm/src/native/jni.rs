@@ -0,0 +1,187 @@
+use crate::oop::Oop;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::sync::{Mutex, RwLock};
+
+/*
+JNI references
+
+A reference handed to native code is the address of a boxed Oop, the box
+holds a handle of the object, so the object is a gc root until the
+reference is deleted.
+
+Local references live in frames of the current thread, a frame is pushed
+before calling a native method and popped after it returns. Global
+references live until DeleteGlobalRef.
+*/
+pub type Handle = *mut Oop;
+
+//the function table lives in the common crate, it installs the hooks at startup
+pub struct Hooks {
+    //JNIEnv of the current thread
+    pub env: fn() -> *mut c_void,
+    pub java_vm: fn() -> *mut c_void,
+}
+
+//jobjectRefType
+pub const INVALID_REF: i32 = 0;
+pub const LOCAL_REF: i32 = 1;
+pub const GLOBAL_REF: i32 = 2;
+pub const WEAK_GLOBAL_REF: i32 = 3;
+
+thread_local! {
+    //the base frame, for the threads attached by the invocation api
+    //boxed, the handles are the addresses of the boxes
+    #[allow(clippy::vec_box)]
+    static LOCALS: RefCell<Vec<Vec<Box<Oop>>>> = RefCell::new(vec![Vec::new()]);
+}
+
+lazy_static! {
+    static ref GLOBALS: Mutex<FxHashMap<usize, Box<Oop>>> = Mutex::new(FxHashMap::default());
+    //weak globals are kept strong, they are only told apart by GetObjectRefType
+    static ref WEAKS: Mutex<FxHashSet<usize>> = Mutex::new(FxHashSet::default());
+
+    //(class name, method name, method signature) -> function pointer, by RegisterNatives
+    static ref REGISTERED: RwLock<FxHashMap<(String, String, String), usize>> =
+        RwLock::new(FxHashMap::default());
+
+    static ref HOOKS: RwLock<Option<Hooks>> = RwLock::new(None);
+}
+
+pub fn install(hooks: Hooks) {
+    HOOKS.write().unwrap().replace(hooks);
+}
+
+//null if the jni functions are not installed
+pub fn env() -> *mut c_void {
+    match HOOKS.read().unwrap().as_ref() {
+        Some(hooks) => (hooks.env)(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+pub fn java_vm() -> *mut c_void {
+    match HOOKS.read().unwrap().as_ref() {
+        Some(hooks) => (hooks.java_vm)(),
+        None => std::ptr::null_mut(),
+    }
+}
+
+pub fn new_local_ref(v: Oop) -> Handle {
+    if v.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    let mut v = Box::new(v);
+    let h = v.as_mut() as Handle;
+    LOCALS.with(|frames| frames.borrow_mut().last_mut().unwrap().push(v));
+    h
+}
+
+pub fn delete_local_ref(h: Handle) {
+    LOCALS.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        let frame = frames.last_mut().unwrap();
+        if let Some(pos) = frame.iter().position(|it| std::ptr::eq(it.as_ref(), h)) {
+            frame.swap_remove(pos);
+        }
+    });
+}
+
+pub fn push_local_frame() {
+    LOCALS.with(|frames| frames.borrow_mut().push(Vec::new()));
+}
+
+//pop the top frame, 'result' is kept as a local reference of the previous frame
+pub fn pop_local_frame(result: Oop) -> Handle {
+    LOCALS.with(|frames| {
+        let mut frames = frames.borrow_mut();
+        if frames.len() > 1 {
+            frames.pop();
+        }
+    });
+
+    new_local_ref(result)
+}
+
+pub fn new_global_ref(v: Oop, weak: bool) -> Handle {
+    if v.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    let mut v = Box::new(v);
+    let h = v.as_mut() as Handle;
+    GLOBALS.lock().unwrap().insert(h as usize, v);
+    if weak {
+        WEAKS.lock().unwrap().insert(h as usize);
+    }
+    h
+}
+
+pub fn delete_global_ref(h: Handle) {
+    GLOBALS.lock().unwrap().remove(&(h as usize));
+    WEAKS.lock().unwrap().remove(&(h as usize));
+}
+
+pub fn ref_type(h: Handle) -> i32 {
+    if h.is_null() {
+        return INVALID_REF;
+    }
+
+    if GLOBALS.lock().unwrap().contains_key(&(h as usize)) {
+        return if WEAKS.lock().unwrap().contains(&(h as usize)) {
+            WEAK_GLOBAL_REF
+        } else {
+            GLOBAL_REF
+        };
+    }
+
+    let is_local = LOCALS.with(|frames| {
+        let frames = frames.borrow();
+        frames
+            .iter()
+            .any(|frame| frame.iter().any(|it| std::ptr::eq(it.as_ref(), h)))
+    });
+    if is_local {
+        LOCAL_REF
+    } else {
+        INVALID_REF
+    }
+}
+
+/// The object referred by 'h', Null for a null reference
+///
+/// # Safety
+///
+/// 'h' is null, or a reference not deleted yet
+pub unsafe fn resolve(h: Handle) -> Oop {
+    if h.is_null() {
+        Oop::Null
+    } else {
+        (*h).clone()
+    }
+}
+
+pub fn register_native(cls: &[u8], name: &[u8], desc: &[u8], fnptr: usize) {
+    let k = (
+        String::from_utf8_lossy(cls).to_string(),
+        String::from_utf8_lossy(name).to_string(),
+        String::from_utf8_lossy(desc).to_string(),
+    );
+    REGISTERED.write().unwrap().insert(k, fnptr);
+}
+
+pub fn unregister_natives(cls: &[u8]) {
+    let cls = String::from_utf8_lossy(cls);
+    REGISTERED.write().unwrap().retain(|k, _| k.0 != cls);
+}
+
+pub fn find_registered(cls: &[u8], name: &[u8], desc: &[u8]) -> Option<usize> {
+    let k = (
+        String::from_utf8_lossy(cls).to_string(),
+        String::from_utf8_lossy(name).to_string(),
+        String::from_utf8_lossy(desc).to_string(),
+    );
+    REGISTERED.read().unwrap().get(&k).cloned()
+}
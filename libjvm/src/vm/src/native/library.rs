@@ -0,0 +1,365 @@
+use crate::gc;
+use crate::native::{jni, trampoline, JNIResult};
+use crate::oop::Oop;
+use crate::runtime::exception;
+use crate::types::MethodIdRef;
+use classfile::{consts as cls_consts, SignatureType};
+use rustc_hash::FxHashMap;
+use std::ffi::{c_void, CStr, CString};
+use std::sync::Mutex;
+
+/*
+Native libraries
+
+The libraries loaded by System.load and System.loadLibrary, the native
+methods not implemented by the vm are looked up in them by the JNI names:
+
+  Java_<mangled class name>_<mangled method name>
+  Java_<mangled class name>_<mangled method name>__<mangled argument types>
+
+The functions registered by RegisterNatives take precedence.
+*/
+//(class name, method name, method signature)
+type NativeKey = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+lazy_static! {
+    //(path, dlopen handle)
+    static ref LIBS: Mutex<Vec<(String, usize)>> = Mutex::new(Vec::new());
+
+    //-> function pointer
+    static ref SYMBOLS: Mutex<FxHashMap<NativeKey, usize>> = Mutex::new(FxHashMap::default());
+}
+
+//JNI_VERSION_1_1 ~ JNI_VERSION_1_8
+const JNI_VERSIONS: [i32; 5] = [
+    0x0001_0001,
+    0x0001_0002,
+    0x0001_0004,
+    0x0001_0006,
+    0x0001_0008,
+];
+
+type OnLoad = unsafe extern "system" fn(*mut c_void, *mut c_void) -> i32;
+
+/*
+Load the library at 'path', call its JNI_OnLoad.
+Loading a library again does nothing.
+*/
+pub fn load(path: &str) -> Result<(), Oop> {
+    if LIBS.lock().unwrap().iter().any(|(it, _)| it == path) {
+        return Ok(());
+    }
+
+    let c_path =
+        CString::new(path).map_err(|_| unsatisfied(format!("Can't load library: {}", path)))?;
+    let handle = unsafe { libc::dlopen(c_path.as_ptr(), libc::RTLD_LAZY) };
+    if handle.is_null() {
+        let err = unsafe { libc::dlerror() };
+        let msg = if err.is_null() {
+            format!("Can't load library: {}", path)
+        } else {
+            let err = unsafe { CStr::from_ptr(err) };
+            format!("{}: {}", path, err.to_string_lossy())
+        };
+        return Err(unsatisfied(msg));
+    }
+
+    let on_load = unsafe { libc::dlsym(handle, b"JNI_OnLoad\0".as_ptr() as *const _) };
+    if !on_load.is_null() {
+        let on_load: OnLoad = unsafe { std::mem::transmute(on_load) };
+        let version = unsafe { on_load(jni::java_vm(), std::ptr::null_mut()) };
+        if !JNI_VERSIONS.contains(&version) {
+            unsafe {
+                libc::dlclose(handle);
+            }
+            let msg = format!(
+                "unsupported JNI version 0x{:x} required by {}",
+                version, path
+            );
+            return Err(unsatisfied(msg));
+        }
+    }
+
+    info!("load library: {}", path);
+    LIBS.lock()
+        .unwrap()
+        .push((path.to_string(), handle as usize));
+    Ok(())
+}
+
+//'libname' mapped to the file name of the platform
+pub fn map_library_name(libname: &str) -> String {
+    if cfg!(target_os = "macos") {
+        format!("lib{}.dylib", libname)
+    } else if cfg!(target_os = "windows") {
+        format!("{}.dll", libname)
+    } else {
+        format!("lib{}.so", libname)
+    }
+}
+
+//the value of java.library.path, the search path of the dynamic linker first
+pub fn default_library_path() -> String {
+    let (env, dirs) = if cfg!(target_os = "macos") {
+        ("DYLD_LIBRARY_PATH", ".")
+    } else {
+        (
+            "LD_LIBRARY_PATH",
+            "/usr/java/packages/lib:/usr/lib64:/lib64:/lib:/usr/lib",
+        )
+    };
+
+    match std::env::var(env) {
+        Ok(v) if !v.is_empty() => format!("{}:{}", v, dirs),
+        _ => dirs.to_string(),
+    }
+}
+
+//the function implementing the native method 'mir'
+pub fn find_native(mir: &MethodIdRef) -> Option<usize> {
+    let method = &mir.method;
+    let (cls, name, desc) = (
+        method.cls_name.as_slice(),
+        method.name.as_slice(),
+        method.desc.as_slice(),
+    );
+
+    if let Some(f) = jni::find_registered(cls, name, desc) {
+        return Some(f);
+    }
+
+    let k = (Vec::from(cls), Vec::from(name), Vec::from(desc));
+    if let Some(f) = SYMBOLS.lock().unwrap().get(&k) {
+        return Some(*f);
+    }
+
+    let short_name = format!("Java_{}_{}", mangle(cls), mangle(name));
+    let args = desc
+        .iter()
+        .position(|&c| c == b')')
+        .map(|end| &desc[1..end])
+        .unwrap_or(&[]);
+    let long_name = format!("{}__{}", short_name, mangle(args));
+
+    let f = dlsym_all(&short_name).or_else(|| dlsym_all(&long_name))?;
+    SYMBOLS.lock().unwrap().insert(k, f);
+    Some(f)
+}
+
+/*
+Call the function 'f' implementing 'mir', 'args' includes 'this'.
+
+The object arguments are passed as local references of a new local frame,
+the frame is popped when the function returns.
+*/
+pub fn invoke(mir: &MethodIdRef, f: usize, args: &[Oop]) -> JNIResult {
+    let env = jni::env();
+    if env.is_null() {
+        let msg = Some("JNI is not initialized".to_string());
+        return Err(exception::new(cls_consts::J_INTERNAL_ERROR, msg));
+    }
+
+    let method = &mir.method;
+    jni::push_local_frame();
+
+    let mut c_args = Vec::with_capacity(args.len() + 2);
+    c_args.push(trampoline::Arg::Pointer(env as usize));
+    let args = if method.is_static() {
+        let mirror = method.class.get_class().get_mirror();
+        c_args.push(trampoline::Arg::Pointer(jni::new_local_ref(mirror) as usize));
+        args
+    } else {
+        let this = args.first().unwrap().clone();
+        c_args.push(trampoline::Arg::Pointer(jni::new_local_ref(this) as usize));
+        &args[1..]
+    };
+
+    for (t, v) in method.signature.args.iter().zip(args.iter()) {
+        let arg = match t {
+            SignatureType::Boolean => trampoline::Arg::Boolean(v.extract_int() as u8),
+            SignatureType::Byte => trampoline::Arg::Byte(v.extract_int() as i8),
+            SignatureType::Char => trampoline::Arg::Char(v.extract_int() as u16),
+            SignatureType::Short => trampoline::Arg::Short(v.extract_int() as i16),
+            SignatureType::Long => trampoline::Arg::Long(v.extract_long()),
+            SignatureType::Float => trampoline::Arg::Float(v.extract_float()),
+            SignatureType::Double => trampoline::Arg::Double(v.extract_double()),
+            SignatureType::Object(_, _, _) | SignatureType::Array(_) => {
+                trampoline::Arg::Pointer(jni::new_local_ref(v.clone()) as usize)
+            }
+            _ => trampoline::Arg::Int(v.extract_int()),
+        };
+        c_args.push(arg);
+    }
+
+    //the arguments are held by the local frame, the collector may run meanwhile
+    let retype = &method.signature.retype;
+    let v = gc::blocking(|| unsafe { trampoline::call(f, &c_args, retype) });
+
+    let v = match retype {
+        SignatureType::Void => None,
+        SignatureType::Boolean => Some(Oop::new_int(v as u8 as i32)),
+        SignatureType::Byte => Some(Oop::new_int(v as i8 as i32)),
+        SignatureType::Char => Some(Oop::new_int(v as u16 as i32)),
+        SignatureType::Short => Some(Oop::new_int(v as i16 as i32)),
+        SignatureType::Int => Some(Oop::new_int(v as i32)),
+        SignatureType::Long => Some(Oop::new_long(v as i64)),
+        SignatureType::Float => Some(Oop::new_float(f32::from_bits(v as u32))),
+        SignatureType::Double => Some(Oop::new_double(f64::from_bits(v))),
+        SignatureType::Object(_, _, _) | SignatureType::Array(_) => {
+            Some(unsafe { jni::resolve(v as jni::Handle) })
+        }
+    };
+
+    jni::pop_local_frame(Oop::Null);
+    Ok(v)
+}
+
+//the message of UnsatisfiedLinkError for 'mir'
+pub fn not_found(mir: &MethodIdRef) -> Oop {
+    let method = &mir.method;
+    let msg = format!(
+        "{}.{}{}",
+        String::from_utf8_lossy(method.cls_name.as_slice()).replace('/', "."),
+        String::from_utf8_lossy(method.name.as_slice()),
+        String::from_utf8_lossy(method.desc.as_slice())
+    );
+    unsatisfied(msg)
+}
+
+fn unsatisfied(msg: String) -> Oop {
+    exception::new(cls_consts::J_UNSATISFIED_LINK_ERROR, Some(msg))
+}
+
+fn dlsym_all(name: &str) -> Option<usize> {
+    let name = CString::new(name).ok()?;
+    let libs = LIBS.lock().unwrap();
+    libs.iter().find_map(|(_, handle)| {
+        let f = unsafe { libc::dlsym(*handle as *mut c_void, name.as_ptr()) };
+        if f.is_null() {
+            None
+        } else {
+            Some(f as usize)
+        }
+    })
+}
+
+//JNI spec, "Resolving Native Method Names"
+fn mangle(s: &[u8]) -> String {
+    let mut v = String::with_capacity(s.len());
+    for c in String::from_utf8_lossy(s).encode_utf16() {
+        match c {
+            0x2f => v.push('_'),      // '/'
+            0x5f => v.push_str("_1"), // '_'
+            0x3b => v.push_str("_2"), // ';'
+            0x5b => v.push_str("_3"), // '['
+            c if c < 0x80 && (c as u8).is_ascii_alphanumeric() => v.push(c as u8 as char),
+            c => v.push_str(&format!("_0{:04x}", c)),
+        }
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dlsym_all, load, mangle};
+    use crate::native::trampoline::{call, Arg};
+    use classfile::SignatureType;
+    use std::process::Command;
+
+    const TEST_LIB: &str = r#"
+#include <stdint.h>
+
+double Java_Test_mix(void *env, void *cls, uint8_t z, int8_t b, uint16_t c,
+                     int16_t s, int32_t i, int64_t j, float f, double d,
+                     int32_t i2, float f2, int64_t j2, double d2, int32_t i3,
+                     float f3, double d3, int64_t j3, float f4, double d4,
+                     double d5, int32_t i4) {
+    if (env != (void *)0x10 || cls != (void *)0x20) return -1;
+    return z + b + c + s + i + j + f + d + i2 + f2 + j2 + d2 + i3 + f3 + d3 +
+           j3 + f4 + d4 + d5 + i4;
+}
+
+int8_t Java_Test_neg(void *env, void *cls, int8_t b) { return -b; }
+uint16_t Java_Test_chr(void *env, void *cls, uint16_t c) { return c + 1; }
+float Java_Test_half(void *env, void *cls, float f) { return f / 2; }
+int64_t Java_Test_wide(void *env, void *cls, int32_t i) {
+    return (int64_t)i << 32;
+}
+"#;
+
+    #[test]
+    fn t_mangle() {
+        assert_eq!(mangle(b"java/lang/Object"), "java_lang_Object");
+        assert_eq!(mangle(b"my_method"), "my_1method");
+        assert_eq!(mangle(b"ILjava/lang/String;[I"), "ILjava_lang_String_2_3I");
+        assert_eq!(mangle("caf\u{e9}".as_bytes()), "caf_000e9");
+    }
+
+    #[test]
+    fn t_call() {
+        let dir = std::env::temp_dir().join(format!("vm-native-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src = dir.join("test.c");
+        let lib = dir.join("libtest.so");
+        std::fs::write(&src, TEST_LIB).unwrap();
+        let status = Command::new("cc")
+            .args(&["-shared", "-fPIC", "-o"])
+            .arg(&lib)
+            .arg(&src)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(load(lib.to_str().unwrap()).is_ok());
+
+        let f = |name: &[u8]| dlsym_all(&format!("Java_Test_{}", mangle(name))).unwrap();
+        let env = || vec![Arg::Pointer(0x10), Arg::Pointer(0x20)];
+
+        let mut args = env();
+        args.extend(vec![
+            Arg::Boolean(1),
+            Arg::Byte(-2),
+            Arg::Char(3),
+            Arg::Short(-4),
+            Arg::Int(5),
+            Arg::Long(-6),
+            Arg::Float(7.5),
+            Arg::Double(-8.25),
+            Arg::Int(9),
+            Arg::Float(10.5),
+            Arg::Long(11),
+            Arg::Double(12.25),
+            Arg::Int(13),
+            Arg::Float(14.5),
+            Arg::Double(15.25),
+            Arg::Long(16),
+            Arg::Float(17.5),
+            Arg::Double(18.25),
+            Arg::Double(19.25),
+            Arg::Int(20),
+        ]);
+        let v = unsafe { call(f(b"mix"), &args, &SignatureType::Double) };
+        assert_eq!(f64::from_bits(v), 172.75);
+
+        let mut args = env();
+        args.push(Arg::Byte(5));
+        let v = unsafe { call(f(b"neg"), &args, &SignatureType::Byte) };
+        assert_eq!(v as i8, -5);
+
+        let mut args = env();
+        args.push(Arg::Char(0xfffe));
+        let v = unsafe { call(f(b"chr"), &args, &SignatureType::Char) };
+        assert_eq!(v as u16, 0xffff);
+
+        let mut args = env();
+        args.push(Arg::Float(3.0));
+        let v = unsafe { call(f(b"half"), &args, &SignatureType::Float) };
+        assert_eq!(f32::from_bits(v as u32), 1.5);
+
+        let mut args = env();
+        args.push(Arg::Int(-1));
+        let v = unsafe { call(f(b"wide"), &args, &SignatureType::Long) };
+        assert_eq!(v as i64, -1i64 << 32);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
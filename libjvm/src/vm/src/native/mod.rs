@@ -5,14 +5,17 @@ use crate::types::ClassRef;
 use rustc_hash::FxHashMap;
 use std::sync::{Arc, RwLock};
 
-mod common;
+pub mod common;
+pub mod jni;
+pub mod library;
+mod trampoline;
 
 mod java_io_FileDescriptor;
 mod java_io_FileInputStream;
 mod java_io_FileOutputStream;
 mod java_io_UnixFileSystem;
 pub mod java_lang_Class;
-mod java_lang_ClassLoader;
+pub mod java_lang_ClassLoader;
 mod java_lang_Double;
 mod java_lang_Float;
 mod java_lang_Object;
@@ -21,6 +24,8 @@ mod java_lang_String;
 mod java_lang_System;
 mod java_lang_Thread;
 mod java_lang_Throwable;
+mod java_lang_invoke_MethodHandleNatives;
+mod java_lang_ref_Finalizer;
 mod java_lang_reflect_Array;
 mod java_lang_reflect_Proxy;
 mod java_security_AccessController;
@@ -120,7 +125,15 @@ fn create_native_fn_tables(
         ),
         ("java/lang/Double", java_lang_Double::get_native_methods()),
         ("java/lang/Float", java_lang_Float::get_native_methods()),
+        (
+            "java/lang/invoke/MethodHandleNatives",
+            java_lang_invoke_MethodHandleNatives::get_native_methods(),
+        ),
         ("java/lang/Object", java_lang_Object::get_native_methods()),
+        (
+            "java/lang/ref/Finalizer",
+            java_lang_ref_Finalizer::get_native_methods(),
+        ),
         (
             "java/lang/reflect/Array",
             java_lang_reflect_Array::get_native_methods(),
@@ -1,13 +1,20 @@
 #![allow(non_snake_case)]
 
-use crate::native::{java_lang_System, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::gc;
+use crate::native::{
+    java_lang_System, java_lang_Thread, new_fn, JNIEnv, JNINativeMethod, JNIResult,
+};
+use crate::new_br;
 use crate::oop;
 use crate::oop::{Class, Oop, OopPtr};
-use crate::runtime::require_class3;
+use crate::runtime::{self, exception, require_class3};
 use crate::util;
+use class_parser::parse_class;
 use classfile::flags::ACC_STATIC;
-use std::os::raw::c_void;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use classfile::{constant_pool, consts as cls_consts, ConstantPoolType};
+use std::sync::atomic::{fence, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -98,9 +105,67 @@ pub fn get_native_methods() -> Vec<JNINativeMethod> {
         new_fn("putByte", "(Ljava/lang/Object;JB)V", Box::new(jvm_putByte)),
         new_fn("getByte", "(Ljava/lang/Object;J)B", Box::new(jvm_getByte2)),
         new_fn("park", "(ZJ)V", Box::new(jvm_park)),
+        new_fn("unpark", "(Ljava/lang/Object;)V", Box::new(jvm_unpark)),
+        new_fn(
+            "putIntVolatile",
+            "(Ljava/lang/Object;JI)V",
+            Box::new(jvm_putVolatile),
+        ),
+        new_fn(
+            "putLongVolatile",
+            "(Ljava/lang/Object;JJ)V",
+            Box::new(jvm_putVolatile),
+        ),
+        new_fn(
+            "putObjectVolatile",
+            "(Ljava/lang/Object;JLjava/lang/Object;)V",
+            Box::new(jvm_putVolatile),
+        ),
+        new_fn(
+            "putOrderedInt",
+            "(Ljava/lang/Object;JI)V",
+            Box::new(jvm_putVolatile),
+        ),
+        new_fn(
+            "putOrderedLong",
+            "(Ljava/lang/Object;JJ)V",
+            Box::new(jvm_putVolatile),
+        ),
+        new_fn(
+            "putOrderedObject",
+            "(Ljava/lang/Object;JLjava/lang/Object;)V",
+            Box::new(jvm_putVolatile),
+        ),
+        new_fn("getInt", "(Ljava/lang/Object;J)I", Box::new(jvm_getInt)),
+        new_fn("putInt", "(Ljava/lang/Object;JI)V", Box::new(jvm_putInt)),
+        new_fn("getInt", "(J)I", Box::new(jvm_getInt2)),
+        new_fn("putInt", "(JI)V", Box::new(jvm_putInt2)),
+        new_fn("getLong", "(Ljava/lang/Object;J)J", Box::new(jvm_getLong)),
+        new_fn("putLong", "(Ljava/lang/Object;JJ)V", Box::new(jvm_putLong2)),
+        new_fn("getLong", "(J)J", Box::new(jvm_getLong2)),
+        new_fn(
+            "getObject",
+            "(Ljava/lang/Object;J)Ljava/lang/Object;",
+            Box::new(jvm_getObjectVolatile),
+        ),
+        new_fn("fullFence", "()V", Box::new(jvm_fullFence)),
+        new_fn("loadFence", "()V", Box::new(jvm_loadFence)),
+        new_fn("storeFence", "()V", Box::new(jvm_storeFence)),
+        new_fn(
+            "shouldBeInitialized",
+            "(Ljava/lang/Class;)Z",
+            Box::new(jvm_shouldBeInitialized),
+        ),
+        new_fn(
+            "defineAnonymousClass",
+            "(Ljava/lang/Class;[B[Ljava/lang/Object;)Ljava/lang/Class;",
+            Box::new(jvm_defineAnonymousClass),
+        ),
     ]
 }
 
+static ANONYMOUS_CLASS_SEQ: AtomicUsize = AtomicUsize::new(0);
+
 fn jvm_registerNatives(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     Ok(None)
 }
@@ -123,27 +188,20 @@ fn jvm_objectFieldOffset(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     objectFieldOffset(field, false)
 }
 
-// fixme: The semantic requirement here is atomic operation, which needs to be re-implemented here
 fn jvm_compareAndSwapObject(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let owner = args.get(1).unwrap();
     let offset = args.get(2).unwrap().extract_long();
     let old_data = args.get(3).unwrap();
     let new_data = args.get(4).unwrap();
 
-    let v_at_offset = Class::get_field_value2(owner.extract_ref(), offset as usize);
-
-    if OopPtr::is_eq(&v_at_offset, old_data) {
-        Class::put_field_value2(owner.extract_ref(), offset as usize, new_data.clone());
-        Ok(Some(Oop::new_int(1)))
-    } else {
-        Ok(Some(Oop::new_int(0)))
-    }
+    let r = compare_and_swap(owner, offset, new_data, |v| OopPtr::is_eq(v, old_data));
+    Ok(Some(Oop::new_int(r as i32)))
 }
 
 fn jvm_getIntVolatile(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let owner = args.get(1).unwrap();
     let offset = args.get(2).unwrap().extract_long();
-    let v = Class::get_field_value2(owner.extract_ref(), offset as usize);
+    let v = Class::get_field_value_volatile(owner.extract_ref(), offset as usize);
     Ok(Some(v))
 }
 
@@ -153,17 +211,8 @@ fn jvm_compareAndSwapInt(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let old_data = args.get(3).unwrap().extract_int();
     let new_data = args.get(4).unwrap();
 
-    let v_at_offset = {
-        let v = Class::get_field_value2(owner.extract_ref(), offset as usize);
-        v.extract_int()
-    };
-
-    if v_at_offset == old_data {
-        Class::put_field_value2(owner.extract_ref(), offset as usize, new_data.clone());
-        Ok(Some(Oop::new_int(1)))
-    } else {
-        Ok(Some(Oop::new_int(0)))
-    }
+    let r = compare_and_swap(owner, offset, new_data, |v| v.extract_int() == old_data);
+    Ok(Some(Oop::new_int(r as i32)))
 }
 
 fn jvm_allocateMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
@@ -185,13 +234,9 @@ fn jvm_freeMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 }
 
 fn jvm_putLong(_env: JNIEnv, args: &[Oop]) -> JNIResult {
-    let ptr = args.get(1).unwrap().extract_long() as *mut libc::c_void;
+    let addr = args.get(1).unwrap().extract_long();
     let l = args.get(2).unwrap().extract_long();
-    let v = l.to_be_bytes();
-    unsafe {
-        libc::memcpy(ptr, v.as_ptr() as *const c_void, 8);
-    }
-
+    put_raw_long(addr, l);
     Ok(None)
 }
 
@@ -207,23 +252,14 @@ fn jvm_compareAndSwapLong(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let old_data = args.get(3).unwrap().extract_long();
     let new_data = args.get(4).unwrap();
 
-    let v_at_offset = {
-        let v = Class::get_field_value2(owner.extract_ref(), offset as usize);
-        v.extract_long()
-    };
-
-    if v_at_offset == old_data {
-        Class::put_field_value2(owner.extract_ref(), offset as usize, new_data.clone());
-        Ok(Some(Oop::new_int(1)))
-    } else {
-        Ok(Some(Oop::new_int(0)))
-    }
+    let r = compare_and_swap(owner, offset, new_data, |v| v.extract_long() == old_data);
+    Ok(Some(Oop::new_int(r as i32)))
 }
 
 fn jvm_getObjectVolatile(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let owner = args.get(1).unwrap();
     let offset = args.get(2).unwrap().extract_long();
-    let v_at_offset = Class::get_field_value2(owner.extract_ref(), offset as usize);
+    let v_at_offset = Class::get_field_value_volatile(owner.extract_ref(), offset as usize);
     Ok(Some(v_at_offset))
 }
 
@@ -234,10 +270,112 @@ fn jvm_pageSize(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
 fn jvm_getLongVolatile(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let owner = args.get(1).unwrap();
     let offset = args.get(2).unwrap().extract_long();
-    let v_at_offset = Class::get_field_value2(owner.extract_ref(), offset as usize);
+    let v_at_offset = Class::get_field_value_volatile(owner.extract_ref(), offset as usize);
     Ok(Some(v_at_offset))
 }
 
+//putXxxVolatile and putOrderedXxx, the lock of the slot orders the store
+fn jvm_putVolatile(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let owner = args.get(1).unwrap();
+    let offset = args.get(2).unwrap().extract_long();
+    let x = args.get(3).unwrap();
+    Class::put_field_value_volatile(owner.extract_ref(), offset as usize, x.clone());
+    Ok(None)
+}
+
+//the field of 'obj', or the raw address if 'obj' is null
+fn jvm_getInt(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let obj = args.get(1).unwrap();
+    let offset = args.get(2).unwrap().extract_long();
+    let v = match obj {
+        Oop::Null => Oop::new_int(get_raw_int(offset)),
+        obj => Class::get_field_value_volatile(obj.extract_ref(), offset as usize),
+    };
+    Ok(Some(v))
+}
+
+fn jvm_putInt(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let obj = args.get(1).unwrap();
+    let offset = args.get(2).unwrap().extract_long();
+    let x = args.get(3).unwrap();
+    match obj {
+        Oop::Null => put_raw_int(offset, x.extract_int()),
+        obj => Class::put_field_value_volatile(obj.extract_ref(), offset as usize, x.clone()),
+    }
+    Ok(None)
+}
+
+fn jvm_getInt2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    Ok(Some(Oop::new_int(get_raw_int(addr))))
+}
+
+fn jvm_putInt2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    let x = args.get(2).unwrap().extract_int();
+    put_raw_int(addr, x);
+    Ok(None)
+}
+
+fn jvm_getLong(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let obj = args.get(1).unwrap();
+    let offset = args.get(2).unwrap().extract_long();
+    let v = match obj {
+        Oop::Null => Oop::new_long(get_raw_long(offset)),
+        obj => Class::get_field_value_volatile(obj.extract_ref(), offset as usize),
+    };
+    Ok(Some(v))
+}
+
+fn jvm_putLong2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let obj = args.get(1).unwrap();
+    let offset = args.get(2).unwrap().extract_long();
+    let x = args.get(3).unwrap();
+    match obj {
+        Oop::Null => put_raw_long(offset, x.extract_long()),
+        obj => Class::put_field_value_volatile(obj.extract_ref(), offset as usize, x.clone()),
+    }
+    Ok(None)
+}
+
+fn jvm_getLong2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let addr = args.get(1).unwrap().extract_long();
+    Ok(Some(Oop::new_long(get_raw_long(addr))))
+}
+
+fn jvm_fullFence(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    fence(Ordering::SeqCst);
+    Ok(None)
+}
+
+fn jvm_loadFence(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    fence(Ordering::Acquire);
+    Ok(None)
+}
+
+fn jvm_storeFence(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
+    fence(Ordering::Release);
+    Ok(None)
+}
+
+fn jvm_shouldBeInitialized(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let clazz = args.get(1).unwrap();
+    let target = {
+        let rf = clazz.extract_ref();
+        let mirror = rf.extract_mirror();
+        mirror.target.clone()
+    };
+    //the mirrors of the primitive types have no target
+    let r = match target {
+        Some(target) => {
+            let cls = target.get_class();
+            !matches!(cls.get_class_state(), oop::class::State::FullyIni)
+        }
+        None => false,
+    };
+    Ok(Some(Oop::new_int(r as i32)))
+}
+
 fn jvm_setMemory(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let _this = args.get(0).unwrap();
     let obj = args.get(1).unwrap();
@@ -369,8 +507,7 @@ fn jvm_ensureClassInitialized(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let rf = clazz.extract_ref();
     let mirror = rf.extract_mirror();
     let target = mirror.target.clone().unwrap();
-    oop::class::init_class(&target);
-    oop::class::init_class_fully(&target);
+    let _ = oop::class::init_class(&target);
     Ok(None)
 }
 
@@ -435,28 +572,202 @@ fn jvm_getByte2(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     Ok(Some(v))
 }
 
+/*
+Relative in nanoseconds, 0 to park until unparked, or absolute in
+milliseconds since the epoch. Returns at once if the permit is available,
+or the thread is interrupted.
+*/
 fn jvm_park(_env: JNIEnv, args: &[Oop]) -> JNIResult {
     let _this = args.get(0).unwrap();
     let is_absolute = args.get(1).unwrap().extract_int() != 0;
-    let time = args.get(2).unwrap().extract_long() as u64;
+    let time = args.get(2).unwrap().extract_long();
 
-    if is_absolute {
+    let now = Instant::now();
+    let deadline = if is_absolute {
         let epoch_duration = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let diff = Duration::from_millis(time) - epoch_duration;
-        std::thread::park_timeout(diff);
-    } else {
-        if time != 0 {
-            std::thread::park_timeout(Duration::from_nanos(time));
-        } else {
-            std::thread::park();
+        let at = Duration::from_millis(time.max(0) as u64);
+        //passed already
+        match at.checked_sub(epoch_duration) {
+            Some(diff) => now.checked_add(diff),
+            None => Some(now),
         }
+    } else if time > 0 {
+        now.checked_add(Duration::from_nanos(time as u64))
+    } else if time == 0 {
+        None
+    } else {
+        Some(now)
+    };
+
+    let (interrupt, parker) = {
+        let jt = runtime::thread::current_java_thread();
+        let jt = jt.read().unwrap();
+        (jt.interrupt.clone(), jt.parker.clone())
+    };
+    gc::blocking(|| parker.park(deadline, &interrupt));
+
+    Ok(None)
+}
+
+//the thread not started or exited is ignored
+fn jvm_unpark(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let thread = args.get(1).unwrap();
+    if let Oop::Null = thread {
+        return Ok(None);
+    }
+
+    if let Some(jt) = java_lang_Thread::find_thread(thread) {
+        let parker = jt.read().unwrap().parker.clone();
+        parker.unpark();
     }
 
     Ok(None)
 }
 
+/*
+Used by java.lang.invoke to define the LambdaForm classes.
+
+Like hotspot, the name of the class is made unique, "java/lang/invoke/LambdaForm$MH/1",
+and the i-th constant is replaced by cpPatches[i], if it's not null:
+  String, the patch object is pushed by ldc
+  Utf8, the text is replaced by the String
+*/
+fn jvm_defineAnonymousClass(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let host = args.get(1).unwrap();
+    let data = args.get(2).unwrap();
+    let cp_patches = args.get(3).unwrap();
+
+    let host = {
+        let rf = host.extract_ref();
+        let mirror = rf.extract_mirror();
+        mirror.target.clone().unwrap()
+    };
+    let bytes = {
+        let rf = data.extract_ref();
+        let ary = rf.extract_type_array();
+        ary.extract_bytes().to_vec()
+    };
+    let patches = match cp_patches {
+        Oop::Null => vec![],
+        v => {
+            let rf = v.extract_ref();
+            let ary = rf.extract_array();
+            ary.elements.to_vec()
+        }
+    };
+
+    let mut cf = match parse_class(bytes.as_slice()) {
+        Ok((_, cf)) => cf,
+        Err(_) => {
+            let msg = "defineAnonymousClass: bad class bytes".to_string();
+            return Err(exception::new(cls_consts::J_CLASS_FORMAT_ERROR, Some(msg)));
+        }
+    };
+    if let Err((cls, msg)) = runtime::verifier::check_format(&cf) {
+        return Err(exception::new(cls, Some(msg)));
+    }
+
+    let this_class = cf.this_class as usize;
+    let name = {
+        let name = constant_pool::get_class_name(&cf.cp, this_class);
+        let seq = ANONYMOUS_CLASS_SEQ.fetch_add(1, Ordering::Relaxed);
+        format!("{}/{}", String::from_utf8_lossy(name.as_slice()), seq)
+    };
+
+    let mut constants = Vec::new();
+    {
+        let cp = Arc::make_mut(&mut cf.cp);
+        for (i, it) in patches.iter().enumerate() {
+            if it.is_null() {
+                continue;
+            }
+
+            match &cp[i] {
+                ConstantPoolType::String { .. } => constants.push((i, it.clone())),
+                ConstantPoolType::Utf8 { .. } => {
+                    let s = OopPtr::java_lang_string(it.extract_ref());
+                    cp[i] = ConstantPoolType::Utf8 {
+                        bytes: new_br(s.as_str()),
+                    };
+                }
+                t => warn!("defineAnonymousClass: unsupported cp patch, {:?}", t),
+            }
+        }
+
+        cp.push(ConstantPoolType::Utf8 {
+            bytes: new_br(name.as_str()),
+        });
+        cp[this_class] = ConstantPoolType::Class {
+            name_index: (cp.len() - 1) as u16,
+        };
+    }
+
+    let class_loader = { host.get_class().class_loader };
+    let class = match runtime::define_class(class_loader, name.as_bytes(), cf, Some(host)) {
+        Some(class) => class,
+        None => return Ok(None),
+    };
+    {
+        let cls = class.extract_inst();
+        for (i, v) in constants {
+            cls.cp_cache.put_constant(i, v);
+        }
+    }
+
+    let mirror = { class.get_class().get_mirror() };
+    Ok(Some(mirror))
+}
+
 ////////helper
 
+//the value at 'offset' is replaced by 'new_data', if 'expect' the value
+fn compare_and_swap<F>(owner: &Oop, offset: i64, new_data: &Oop, expect: F) -> bool
+where
+    F: FnOnce(&Oop) -> bool,
+{
+    let mut swapped = false;
+    Class::update_field_value2(owner.extract_ref(), offset as usize, |v| {
+        swapped = expect(v);
+        if swapped {
+            Some(new_data.clone())
+        } else {
+            None
+        }
+    });
+    swapped
+}
+
+//the raw memory is big endian, as Bits.byteOrder detected by putLong
+fn get_raw_int(addr: i64) -> i32 {
+    let mut v = [0u8; 4];
+    unsafe {
+        std::ptr::copy_nonoverlapping(addr as *const u8, v.as_mut_ptr(), 4);
+    }
+    i32::from_be_bytes(v)
+}
+
+fn put_raw_int(addr: i64, x: i32) {
+    let v = x.to_be_bytes();
+    unsafe {
+        std::ptr::copy_nonoverlapping(v.as_ptr(), addr as *mut u8, 4);
+    }
+}
+
+fn get_raw_long(addr: i64) -> i64 {
+    let mut v = [0u8; 8];
+    unsafe {
+        std::ptr::copy_nonoverlapping(addr as *const u8, v.as_mut_ptr(), 8);
+    }
+    i64::from_be_bytes(v)
+}
+
+fn put_raw_long(addr: i64, x: i64) {
+    let v = x.to_be_bytes();
+    unsafe {
+        std::ptr::copy_nonoverlapping(v.as_ptr(), addr as *mut u8, 8);
+    }
+}
+
 fn objectFieldOffset(field: &Oop, is_static: bool) -> JNIResult {
     let cls = require_class3(None, b"java/lang/reflect/Field").unwrap();
 
@@ -2,7 +2,7 @@
 
 use crate::native::{common, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::Oop;
-use crate::{new_br, runtime};
+use crate::{new_br, runtime, util};
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![new_fn(
@@ -25,6 +25,14 @@ fn jvm_newInstance0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 
     let signature = common::reflect::get_Constructor_signature(ctor);
 
+    if !common::reflect::get_AccessibleObject_override(ctor) {
+        let cls = target_cls.get_class();
+        let sig = new_br(signature.as_str());
+        if let Ok(mir) = cls.get_this_class_method(&util::S_INIT, &sig) {
+            runtime::access::check_reflect(&target_cls, mir.method.acc_flags)?;
+        }
+    }
+
     let cls = target_cls.get_class();
     let name = unsafe { std::str::from_utf8_unchecked(cls.name.as_slice()) };
     info!("newInstance0 {}:{}", name, signature);
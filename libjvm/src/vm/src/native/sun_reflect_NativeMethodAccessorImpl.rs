@@ -1,8 +1,8 @@
 #![allow(non_snake_case)]
 
-use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::native::{common, new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::new_br;
-use crate::oop::{self, Class, Oop, OopPtr};
+use crate::oop::{Class, Oop, OopPtr};
 use crate::runtime::{self, require_class3};
 use crate::util;
 use classfile::{consts as cls_consts, SignatureType};
@@ -52,6 +52,10 @@ fn jvm_invoke0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
         clz.get_class_method(&m_name, &m_signature).unwrap()
     };
 
+    if !common::reflect::get_AccessibleObject_override(method) {
+        runtime::access::check_reflect(&clz, mir.method.acc_flags)?;
+    }
+
     // {
     //     let cls = clz.read().unwrap();
     //     let cls_name = cls.name.clone();
@@ -77,35 +81,12 @@ fn jvm_invoke0(_env: JNIEnv, args: &[Oop]) -> JNIResult {
 
     let force_no_resolve = mir.method.name.as_slice() == b"<init>" || mir.method.is_static();
     let mut jc = runtime::invoke::JavaCall::new_with_args(mir, args);
-    let area = runtime::DataArea::new(0);
+    let area = runtime::DataArea::new();
     jc.invoke(Some(&area), force_no_resolve);
 
-    let r = {
-        let mut stack = area.stack.borrow_mut();
-        match jc.mir.method.signature.retype {
-            SignatureType::Byte
-            | SignatureType::Char
-            | SignatureType::Boolean
-            | SignatureType::Short
-            | SignatureType::Int => {
-                let v = stack.pop_int();
-                Some(oop::Oop::new_int(v))
-            }
-            SignatureType::Double => {
-                let v = stack.pop_double();
-                Some(oop::Oop::new_double(v))
-            }
-            SignatureType::Float => {
-                let v = stack.pop_float();
-                Some(oop::Oop::new_float(v))
-            }
-            SignatureType::Long => {
-                let v = stack.pop_long();
-                Some(oop::Oop::new_long(v))
-            }
-            SignatureType::Object(_, _, _) | SignatureType::Array(_) => Some(stack.pop_ref()),
-            SignatureType::Void => Some(Oop::Null),
-        }
+    let r = match jc.mir.method.signature.retype {
+        SignatureType::Void => Some(Oop::Null),
+        _ => area.return_v.borrow_mut().take(),
     };
 
     Ok(r)
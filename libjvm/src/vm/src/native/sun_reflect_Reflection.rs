@@ -1,8 +1,9 @@
 #![allow(non_snake_case)]
 
-use crate::native::{self, new_fn, JNIEnv, JNINativeMethod, JNIResult};
+use crate::native::{new_fn, JNIEnv, JNINativeMethod, JNIResult};
 use crate::oop::Oop;
 use crate::runtime;
+use classfile::flags::*;
 
 pub fn get_native_methods() -> Vec<JNINativeMethod> {
     vec![
@@ -26,7 +27,6 @@ fn jvm_getCallerClass(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     {
         let cur = {
             let cur = callers.pop().unwrap(); //pop cur method
-            let cur = cur.try_read().unwrap();
             cur.mir.clone()
         };
         debug_assert_eq!(cur.method.name.as_slice(), b"getCallerClass");
@@ -35,7 +35,6 @@ fn jvm_getCallerClass(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     loop {
         let caller = {
             let caller = callers.pop().unwrap();
-            let caller = caller.try_read().unwrap();
             caller.mir.clone()
         };
         if caller
@@ -51,6 +50,17 @@ fn jvm_getCallerClass(_env: JNIEnv, _args: &[Oop]) -> JNIResult {
     }
 }
 
-fn jvm_getClassAccessFlags(env: JNIEnv, args: &[Oop]) -> JNIResult {
-    native::java_lang_Class::jvm_getModifiers(env, args)
+//the flags of the class file, not of the InnerClasses attribute as Class.getModifiers
+fn jvm_getClassAccessFlags(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let v = args.get(0).unwrap();
+    let v = {
+        let rf = v.extract_ref();
+        let mirror = rf.extract_mirror();
+        match &mirror.target {
+            Some(target) => target.get_class().acc_flags,
+            None => ACC_ABSTRACT | ACC_FINAL | ACC_PUBLIC,
+        }
+    };
+
+    Ok(Some(Oop::new_int(v as i32)))
 }
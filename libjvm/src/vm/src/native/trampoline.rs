@@ -0,0 +1,86 @@
+/*
+Call a C function with arguments only known at runtime
+
+The call is described to libffi with the exact C types of the JNI
+signature, libffi places the arguments as the platform calling
+convention requires, registers or stack.
+*/
+use classfile::SignatureType;
+use libffi::middle::{self, Cif, CodePtr, Type};
+
+pub enum Arg {
+    Boolean(u8),
+    Byte(i8),
+    Char(u16),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    //JNIEnv *, jobject, jclass
+    Pointer(usize),
+}
+
+impl Arg {
+    fn ffi_type(&self) -> Type {
+        match self {
+            Arg::Boolean(_) => Type::u8(),
+            Arg::Byte(_) => Type::i8(),
+            Arg::Char(_) => Type::u16(),
+            Arg::Short(_) => Type::i16(),
+            Arg::Int(_) => Type::i32(),
+            Arg::Long(_) => Type::i64(),
+            Arg::Float(_) => Type::f32(),
+            Arg::Double(_) => Type::f64(),
+            Arg::Pointer(_) => Type::pointer(),
+        }
+    }
+
+    fn as_ffi(&self) -> middle::Arg {
+        match self {
+            Arg::Boolean(v) => middle::arg(v),
+            Arg::Byte(v) => middle::arg(v),
+            Arg::Char(v) => middle::arg(v),
+            Arg::Short(v) => middle::arg(v),
+            Arg::Int(v) => middle::arg(v),
+            Arg::Long(v) => middle::arg(v),
+            Arg::Float(v) => middle::arg(v),
+            Arg::Double(v) => middle::arg(v),
+            Arg::Pointer(v) => middle::arg(v),
+        }
+    }
+}
+
+/*
+Call 'f' returning 'retype'.
+Returns the raw bits of the return value, a float in the low 32 bits,
+the integral types narrower than 64 bits are to be truncated by the caller.
+*/
+pub unsafe fn call(f: usize, args: &[Arg], retype: &SignatureType) -> u64 {
+    let rtype = match retype {
+        SignatureType::Void => Type::void(),
+        SignatureType::Boolean => Type::u8(),
+        SignatureType::Byte => Type::i8(),
+        SignatureType::Char => Type::u16(),
+        SignatureType::Short => Type::i16(),
+        SignatureType::Int => Type::i32(),
+        SignatureType::Long => Type::i64(),
+        SignatureType::Float => Type::f32(),
+        SignatureType::Double => Type::f64(),
+        SignatureType::Object(_, _, _) | SignatureType::Array(_) => Type::pointer(),
+    };
+    let cif = Cif::new(args.iter().map(|it| it.ffi_type()), rtype);
+    let ffi_args: Vec<middle::Arg> = args.iter().map(|it| it.as_ffi()).collect();
+    let code = CodePtr::from_ptr(f as *const _);
+
+    //libffi widens the integral returns to a full register
+    match retype {
+        SignatureType::Void => {
+            cif.call::<()>(code, &ffi_args);
+            0
+        }
+        SignatureType::Float => cif.call::<f32>(code, &ffi_args).to_bits() as u64,
+        SignatureType::Double => cif.call::<f64>(code, &ffi_args).to_bits(),
+        _ => cif.call::<u64>(code, &ffi_args),
+    }
+}
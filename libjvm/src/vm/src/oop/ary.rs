@@ -1,13 +1,14 @@
+use crate::gc::Payload;
 use crate::oop::{class, Oop};
 use crate::types::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct ArrayOopDesc {
     pub class: ClassRef,
-    pub elements: Vec<Oop>,
+    pub elements: Payload<Oop>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum TypeArrayDesc {
     Byte(ByteAry),
     Bool(BoolAry),
@@ -31,7 +32,7 @@ pub enum TypeArrayEnum {
 }
 
 impl ArrayOopDesc {
-    pub fn new(class: ClassRef, elements: Vec<Oop>) -> Self {
+    pub fn new(class: ClassRef, elements: Payload<Oop>) -> Self {
         {
             debug_assert!(class.get_class().is_array());
         }
@@ -178,6 +179,17 @@ impl TypeArrayDesc {
     }
 }
 
+impl TypeArrayEnum {
+    pub fn elem_size(&self) -> usize {
+        match self {
+            Self::Boolean | Self::Byte => 1,
+            Self::Char | Self::Short => 2,
+            Self::Float | Self::Int => 4,
+            Self::Double | Self::Long => 8,
+        }
+    }
+}
+
 impl From<u8> for TypeArrayEnum {
     fn from(b: u8) -> Self {
         match b {
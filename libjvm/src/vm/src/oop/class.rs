@@ -1,23 +1,29 @@
 use std::fmt::{self, Debug, Error, Formatter};
 use std::ops::{Deref, DerefMut};
 use std::sync::atomic::Ordering;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::ThreadId;
 
 use rustc_hash::FxHashMap;
 
 use classfile::{
     attributes::EnclosingMethod, attributes::InnerClass, constant_pool,
-    constant_pool::get_utf8 as get_cp_utf8, consts, flags::*, AttributeType, BytesRef, U2,
+    constant_pool::get_utf8 as get_cp_utf8, consts, flags::*, AttributeType, BytesRef, OpCode, U2,
 };
 
+use crate::gc;
 use crate::oop::{self, consts as oop_consts, field, Oop, OopPtr, RefKindDesc, ValueType};
 use crate::runtime::method::MethodId;
-use crate::runtime::thread::ReentrantMutex;
 use crate::runtime::{
-    self, method, require_class2, ClassLoader, ConstantPoolCache, JavaCall, JavaThread,
+    self, exception, method, require_class2, ClassLoader, ConstantPoolCache, JavaCall, JavaThread,
 };
 use crate::types::*;
-use crate::{native, util};
+use crate::{native, new_br, util};
+
+lazy_static! {
+    //the locks of the slots accessed by Unsafe, striped by the address and offset
+    static ref SLOT_LOCKS: Vec<Mutex<()>> = (0..64).map(|_| Mutex::new(())).collect();
+}
 
 pub struct ClassPtr(u64);
 
@@ -88,8 +94,8 @@ impl Debug for ClassPtr {
 /////////////////////////////////////////////
 
 pub struct Class {
-    clinit_mutex: Arc<std::sync::Mutex<()>>,
-    mutex: ReentrantMutex,
+    //the thread running <clinit>, waited by the others, see init_class
+    clinit_mutex: Arc<(Mutex<Option<ThreadId>>, Condvar)>,
     state: std::sync::atomic::AtomicU8,
 
     pub name: BytesRef,
@@ -159,9 +165,21 @@ pub struct ClassObject {
 
     pub n_inst_fields: usize,
 
+    //a subclass of java.lang.ref.Reference
+    pub ref_type: Option<gc::ReferenceType>,
+    //the instances are registered to java.lang.ref.Finalizer
+    pub has_finalizer: bool,
+
     //  FxHashMap<(name, desc), MethodIdRef>
     pub all_methods: FxHashMap<(BytesRef, BytesRef), MethodIdRef>,
-    v_table: FxHashMap<(BytesRef, BytesRef), MethodIdRef>,
+    //indexed by MethodId.vtable_index, the indices of the super class are kept,
+    //an interface has its own methods only
+    pub vtable: Vec<MethodIdRef>,
+    //  FxHashMap<(name, desc), index of vtable>
+    v_table: FxHashMap<(BytesRef, BytesRef), usize>,
+    //all the super interfaces, with the methods selected for their vtable,
+    //None if there are conflicting default methods
+    itable: Vec<(ClassRef, Vec<Option<MethodIdRef>>)>,
 
     //  FxHashMap<(package, name, desc), FieldIdRef>
     pub static_fields: FxHashMap<(BytesRef, BytesRef, BytesRef), FieldIdRef>,
@@ -178,6 +196,9 @@ pub struct ClassObject {
     pub enclosing_method: Option<EnclosingMethod>,
     pub inner_classes: Option<Vec<InnerClass>>,
 
+    //the host of an anonymous class, which access it has
+    pub host: Option<ClassRef>,
+
     pub cp_cache: ConstantPoolCache,
 }
 
@@ -193,74 +214,136 @@ pub struct ArrayClassObject {
     pub mirror: Option<Oop>,
 }
 
-pub fn init_class(class: &ClassRef) {
-    let need = { class.get_class().get_class_state() == State::Linked };
-    if need {
-        let mut cls = class.get_mut_class();
-        let clinit_mutex = cls.clinit_mutex.clone();
-        let l = clinit_mutex.lock().unwrap();
-
-        cls.set_class_state(State::BeingIni);
-        if let Some(super_class) = &cls.super_class {
-            init_class(super_class);
-            init_class_fully(super_class);
-        }
-
-        match &mut cls.kind {
-            ClassKind::Instance(class_obj) => {
-                class_obj.init_static_fields();
+/*
+The initialization procedure of JVMS 5.5, the exception is pending if failed.
+The one thrown by <clinit> is wrapped in ExceptionInInitializerError unless
+it's an Error, and the class in error state throws NoClassDefFoundError.
+*/
+pub fn init_class(class: &ClassRef) -> Result<(), ()> {
+    if class.get_class().get_class_state() == State::FullyIni {
+        return Ok(());
+    }
+
+    let clinit_mutex = class.get_class().clinit_mutex.clone();
+    let (lock, cond) = &*clinit_mutex;
+    let current = std::thread::current().id();
+    {
+        let mut initializing = gc::blocking(|| lock.lock().unwrap());
+        loop {
+            match class.get_class().get_class_state() {
+                //a recursive request
+                State::BeingIni if *initializing == Some(current) => return Ok(()),
+                State::BeingIni => {
+                    initializing = gc::blocking(|| cond.wait(initializing).unwrap());
+                }
+                State::FullyIni => return Ok(()),
+                State::IniErr => {
+                    drop(initializing);
+                    let name = class.get_class().name.clone();
+                    let msg = format!(
+                        "Could not initialize class {}",
+                        String::from_utf8_lossy(name.as_slice()).replace('/', ".")
+                    );
+                    exception::meet_ex(consts::J_NO_CLASS_DEF_FOUND_ERROR, Some(msg));
+                    return Err(());
+                }
+                _ => break,
             }
-
-            _ => cls.set_class_state(State::FullyIni),
         }
+
+        *initializing = Some(current);
+        class.get_mut_class().set_class_state(State::BeingIni);
     }
-}
 
-//invoke "<clinit>"
-pub fn init_class_fully(class: &ClassRef) {
-    let need = { class.get_class().get_class_state() == State::BeingIni };
+    let r = run_clinit(class);
 
-    if need {
-        let l = class.get_class().clinit_mutex.lock();
+    let mut initializing = gc::blocking(|| lock.lock().unwrap());
+    *initializing = None;
+    let state = if r.is_ok() {
+        State::FullyIni
+    } else {
+        State::IniErr
+    };
+    class.get_mut_class().set_class_state(state);
+    cond.notify_all();
 
-        let (mir, name) = {
-            let mut class = class.get_mut_class();
-            class.set_class_state(State::FullyIni);
+    r
+}
 
-            let mir = class.get_this_class_method(&util::S_CLINIT, &util::S_CLINIT_SIG);
-            (mir, class.name.clone())
-        };
+//the static fields, the super class and "<clinit>"
+fn run_clinit(class: &ClassRef) -> Result<(), ()> {
+    let (name, super_class) = {
+        let mut cls = class.get_mut_class();
+        let is_interface = cls.is_interface();
+        match &mut cls.kind {
+            ClassKind::Instance(class_obj) => class_obj.init_static_fields(),
+            _ => return Ok(()),
+        }
+        let super_class = cls.super_class.clone().filter(|_| !is_interface);
+        (cls.name.clone(), super_class)
+    };
+
+    if let Some(super_class) = &super_class {
+        init_class(super_class)?;
+    }
 
-        if let Ok(mir) = mir {
-            info!("call {}:<clinit>", unsafe {
-                std::str::from_utf8_unchecked(name.as_slice())
-            });
-            let mut jc = JavaCall::new_with_args(mir, vec![]);
-            jc.invoke(None, true);
+    let mir = {
+        let cls = class.get_class();
+        cls.get_this_class_method(&util::S_CLINIT, &util::S_CLINIT_SIG)
+    };
+    if let Ok(mir) = mir {
+        info!("call {}:<clinit>", unsafe {
+            std::str::from_utf8_unchecked(name.as_slice())
+        });
+        let mut jc = JavaCall::new_with_args(mir, vec![]);
+        jc.invoke(None, true);
+    }
+
+    if runtime::thread::is_meet_ex() {
+        let jt = runtime::thread::current_java_thread();
+        let ex = jt.write().unwrap().take_ex().unwrap();
+        let error = runtime::require_class3(None, consts::J_ERROR).unwrap();
+        let ex_cls = ex.extract_ref().extract_inst().class.clone();
+        if runtime::cmp::instance_of(ex_cls, error) {
+            jt.write().unwrap().set_ex(ex);
+        } else {
+            exception::meet_ex_with_cause(consts::J_EXCEPTION_IN_INITIALIZER_ERROR, ex);
         }
+        return Err(());
     }
+
+    Ok(())
 }
 
+//a class of the bootstrap the vm depends on, the vm exits if not found
 pub fn load_and_init(name: &[u8]) -> ClassRef {
     // trace!("load_and_init 1 name={}", String::from_utf8_lossy(name));
-    let cls_name = unsafe { std::str::from_utf8_unchecked(name) };
-    let class = runtime::require_class3(None, name)
-        .unwrap_or_else(|| panic!("Class not found: {}", cls_name));
+    let class = match runtime::require_class3(None, name) {
+        Some(class) => class,
+        None => {
+            eprintln!("Error occurred during initialization of VM");
+            eprintln!(
+                "java/lang/NoClassDefFoundError: {}",
+                String::from_utf8_lossy(name)
+            );
+            std::process::exit(1);
+        }
+    };
 
-    init_class(&class);
-    init_class_fully(&class);
+    //the exception is pending if failed
+    let _ = init_class(&class);
 
     class
 }
 
 impl Class {
     pub fn get_class_state(&self) -> State {
-        let v = self.state.load(Ordering::Relaxed);
+        let v = self.state.load(Ordering::Acquire);
         State::from(v)
     }
 
     pub fn set_class_state(&mut self, s: State) {
-        self.state.store(s.into(), Ordering::Relaxed);
+        self.state.store(s.into(), Ordering::Release);
     }
 
     pub fn get_name(&self) -> BytesRef {
@@ -299,22 +382,12 @@ impl Class {
         (self.acc_flags & ACC_INTERFACE) == ACC_INTERFACE
     }
 
-    pub fn monitor_enter(&self) {
-        unsafe {
-            self.mutex.lock();
-        }
-    }
-
-    pub fn monitor_exit(&self) {
-        unsafe {
-            self.mutex.unlock();
-        }
-    }
-
-    pub fn link_class(&mut self, self_ref: ClassRef) {
+    //Err, if the exception is thrown, such as NoClassDefFoundError of the super class
+    pub fn link_class(&mut self, self_ref: ClassRef) -> Result<(), ()> {
         match &mut self.kind {
             ClassKind::Instance(class_obj) => {
-                self.super_class = class_obj.link_super_class(self.name.clone(), self.class_loader);
+                self.super_class =
+                    class_obj.link_super_class(self.name.clone(), self.class_loader)?;
                 let n = match &self.super_class {
                     Some(super_cls) => {
                         let super_cls = super_cls.get_class();
@@ -326,9 +399,53 @@ impl Class {
                     None => 0,
                 };
                 class_obj.link_fields(self_ref.clone(), self.name.clone(), n);
-                class_obj.link_interfaces();
-                class_obj.link_methods(self_ref, self.name.clone());
+                //MemberName.vmtarget, invisible to java
+                if self.name.as_slice() == consts::J_MEMBER_NAME {
+                    let offset = class_obj.n_inst_fields;
+                    runtime::method_handles::set_member_name_vmtarget_offset(offset);
+                    class_obj.n_inst_fields += 1;
+                }
+                //Reference.referent, next and discovered, for the collector
+                if self.name.as_slice() == consts::J_REFERENCE {
+                    let cls_name = self.name.clone();
+                    let offset = |name: &str, desc: &str| {
+                        let k = (cls_name.clone(), new_br(name), new_br(desc));
+                        class_obj.inst_fields.get(&k).unwrap().offset
+                    };
+                    gc::set_reference_offsets(
+                        offset("referent", "Ljava/lang/Object;"),
+                        offset("next", "Ljava/lang/ref/Reference;"),
+                        offset("discovered", "Ljava/lang/ref/Reference;"),
+                    );
+                }
+                class_obj.link_interfaces(self.class_loader)?;
+
+                let super_cls = self.super_class.as_ref().map(|it| it.get_class());
+                let super_obj = match super_cls.map(|it| &it.kind) {
+                    Some(ClassKind::Instance(cls_obj)) => Some(cls_obj),
+                    _ => None,
+                };
+                let is_interface = (self.acc_flags & ACC_INTERFACE) == ACC_INTERFACE;
+                class_obj.link_methods(
+                    self_ref,
+                    self.name.clone(),
+                    self.class_loader,
+                    super_obj.filter(|_| !is_interface),
+                );
+                class_obj.link_itable(super_obj.filter(|_| !is_interface));
                 class_obj.link_attributes();
+
+                class_obj.ref_type = gc::ReferenceType::of(self.name.as_slice())
+                    .or_else(|| super_obj.and_then(|it| it.ref_type));
+                //an empty finalize, like Object.finalize, is not called
+                let k = (new_br("finalize"), new_br("()V"));
+                class_obj.has_finalizer = match class_obj.all_methods.get(&k) {
+                    Some(mir) => match &mir.method.code {
+                        Some(code) => code.code.as_slice() != [OpCode::return_void as u8],
+                        None => true,
+                    },
+                    None => super_obj.is_some_and(|it| it.has_finalizer),
+                };
             }
 
             ClassKind::ObjectArray(ary_class_obj) => {
@@ -343,6 +460,7 @@ impl Class {
         }
 
         self.set_class_state(State::Linked);
+        Ok(())
     }
 
     pub fn get_class_kind_type(&self) -> ClassKindType {
@@ -376,13 +494,17 @@ impl Class {
     }
 
     pub fn get_mirror(&self) -> Oop {
+        self.try_get_mirror().unwrap()
+    }
+
+    //None until java.lang.Class is loaded, see java_lang_Class::MIRROR_STATE
+    pub fn try_get_mirror(&self) -> Option<Oop> {
         match &self.kind {
-            ClassKind::Instance(cls_obj) => cls_obj.mirror.clone().unwrap(),
+            ClassKind::Instance(cls_obj) => cls_obj.mirror.clone(),
             //[J
-            ClassKind::TypeArray(typ_ary) => typ_ary.mirror.clone().unwrap(),
+            ClassKind::TypeArray(typ_ary) => typ_ary.mirror.clone(),
             //[Ljava/lang/Object;
-            ClassKind::ObjectArray(obj_ary) => obj_ary.mirror.clone().unwrap(),
-            _ => unreachable!(),
+            ClassKind::ObjectArray(obj_ary) => obj_ary.mirror.clone(),
         }
     }
 
@@ -485,33 +607,48 @@ impl Class {
     }
 
     pub fn get_field_id(&self, name: &BytesRef, desc: &BytesRef, is_static: bool) -> FieldIdRef {
+        self.find_field_id(name, desc, is_static)
+            .unwrap_or_else(|| {
+                panic!(
+                    "field not found: {}.{}:{}",
+                    String::from_utf8_lossy(self.name.as_slice()),
+                    String::from_utf8_lossy(name.as_slice()),
+                    String::from_utf8_lossy(desc.as_slice())
+                )
+            })
+    }
+
+    //look up the class, the super interfaces for a static field, then the super classes
+    pub fn find_field_id(
+        &self,
+        name: &BytesRef,
+        desc: &BytesRef,
+        is_static: bool,
+    ) -> Option<FieldIdRef> {
         let k = (self.name.clone(), name.clone(), desc.clone());
 
-        if is_static {
-            match &self.kind {
-                ClassKind::Instance(cls_obj) => {
-                    if let Some(fid) = cls_obj.static_fields.get(&k) {
-                        return fid.clone();
-                    }
-                }
-                _ => unreachable!(),
-            }
-        } else {
-            match &self.kind {
-                ClassKind::Instance(cls_obj) => {
-                    if let Some(fid) = cls_obj.inst_fields.get(&k) {
-                        return fid.clone();
-                    }
-                }
-                _ => unreachable!(),
+        let fields = match &self.kind {
+            ClassKind::Instance(cls_obj) if is_static => &cls_obj.static_fields,
+            ClassKind::Instance(cls_obj) => &cls_obj.inst_fields,
+            _ => return None,
+        };
+        if let Some(fid) = fields.get(&k) {
+            return Some(fid.clone());
+        }
+
+        if let ClassKind::Instance(cls_obj) = &self.kind {
+            let fid = cls_obj
+                .interfaces
+                .values()
+                .filter(|_| is_static)
+                .find_map(|it| it.get_class().find_field_id(name, desc, true));
+            if fid.is_some() {
+                return fid;
             }
         }
 
-        let super_class = self.super_class.clone();
-        super_class
-            .unwrap()
-            .get_class()
-            .get_field_id(name, desc, is_static)
+        let super_class = self.super_class.as_ref()?;
+        super_class.get_class().find_field_id(name, desc, is_static)
     }
 
     pub fn put_field_value(rf: Arc<OopPtr>, fir: FieldIdRef, v: Oop) {
@@ -519,6 +656,7 @@ impl Class {
     }
 
     pub fn put_field_value2(rf: Arc<OopPtr>, offset: usize, v: Oop) {
+        gc::write_barrier(&rf, &v);
         let ptr = rf.get_mut_raw_ptr();
         unsafe {
             match &mut (*ptr).v {
@@ -549,6 +687,79 @@ impl Class {
         }
     }
 
+    /*
+    The volatile accesses and the atomic operations of Unsafe. The field at
+    'offset', or the element of an array, is read and replaced by the value
+    returned by 'f' if any, holding the lock of the slot. The old value is
+    returned.
+    */
+    pub fn update_field_value2<F>(rf: Arc<OopPtr>, offset: usize, f: F) -> Oop
+    where
+        F: FnOnce(&Oop) -> Option<Oop>,
+    {
+        let slot = Arc::as_ptr(&rf) as usize ^ offset.wrapping_mul(31);
+        let lock = &SLOT_LOCKS[slot % SLOT_LOCKS.len()];
+        let _l = lock.lock().unwrap_or_else(|e| e.into_inner());
+
+        let old = Self::get_slot_value(&rf, offset);
+        if let Some(v) = f(&old) {
+            Self::put_slot_value(&rf, offset, v);
+        }
+        old
+    }
+
+    pub fn get_field_value_volatile(rf: Arc<OopPtr>, offset: usize) -> Oop {
+        Self::update_field_value2(rf, offset, |_| None)
+    }
+
+    pub fn put_field_value_volatile(rf: Arc<OopPtr>, offset: usize, v: Oop) {
+        Self::update_field_value2(rf, offset, |_| Some(v));
+    }
+
+    //like get_field_value2, the elements of the primitive arrays too
+    fn get_slot_value(rf: &OopPtr, offset: usize) -> Oop {
+        let ptr = rf.get_raw_ptr();
+        unsafe {
+            match &(*ptr).v {
+                oop::RefKind::TypeArray(ary) => match ary {
+                    oop::TypeArrayDesc::Byte(v) => Oop::new_int(v[offset] as i8 as i32),
+                    oop::TypeArrayDesc::Bool(v) => Oop::new_int(v[offset] as i32),
+                    oop::TypeArrayDesc::Char(v) => Oop::new_int(v[offset] as i32),
+                    oop::TypeArrayDesc::Short(v) => Oop::new_int(v[offset] as i32),
+                    oop::TypeArrayDesc::Int(v) => Oop::new_int(v[offset]),
+                    oop::TypeArrayDesc::Long(v) => Oop::new_long(v[offset]),
+                    oop::TypeArrayDesc::Float(v) => Oop::new_float(v[offset]),
+                    oop::TypeArrayDesc::Double(v) => Oop::new_double(v[offset]),
+                },
+                oop::RefKind::Inst(inst) => inst.field_values[offset].clone(),
+                oop::RefKind::Mirror(mirror) => mirror.field_values[offset].clone(),
+                oop::RefKind::Array(ary) => ary.elements[offset].clone(),
+            }
+        }
+    }
+
+    fn put_slot_value(rf: &OopPtr, offset: usize, v: Oop) {
+        gc::write_barrier(rf, &v);
+        let ptr = rf.get_mut_raw_ptr();
+        unsafe {
+            match &mut (*ptr).v {
+                oop::RefKind::TypeArray(ary) => match ary {
+                    oop::TypeArrayDesc::Byte(ary) => ary[offset] = v.extract_int() as u8,
+                    oop::TypeArrayDesc::Bool(ary) => ary[offset] = v.extract_int() as u8,
+                    oop::TypeArrayDesc::Char(ary) => ary[offset] = v.extract_int() as u16,
+                    oop::TypeArrayDesc::Short(ary) => ary[offset] = v.extract_int() as i16,
+                    oop::TypeArrayDesc::Int(ary) => ary[offset] = v.extract_int(),
+                    oop::TypeArrayDesc::Long(ary) => ary[offset] = v.extract_long(),
+                    oop::TypeArrayDesc::Float(ary) => ary[offset] = v.extract_float(),
+                    oop::TypeArrayDesc::Double(ary) => ary[offset] = v.extract_double(),
+                },
+                oop::RefKind::Inst(inst) => inst.field_values[offset] = v,
+                oop::RefKind::Mirror(mirror) => mirror.field_values[offset] = v,
+                oop::RefKind::Array(ary) => ary.elements[offset] = v,
+            }
+        }
+    }
+
     pub fn put_static_field_value(&mut self, fid: FieldIdRef, v: Oop) {
         match &mut self.kind {
             ClassKind::Instance(cls_obj) => {
@@ -626,8 +837,11 @@ impl Class {
                     let it = cls.all_methods.get_mut(&k).unwrap();
                     let mut method = it.method.clone();
                     method.acc_flags |= ACC_NATIVE;
-                    let m = method::MethodId::new(it.offset, method);
+                    let m = method::MethodId::new(it.offset, it.vtable_index, method);
                     cls.all_methods.insert(k, m.clone());
+                    if let Some(i) = m.vtable_index {
+                        cls.vtable[i] = m.clone();
+                    }
 
                     m
                 };
@@ -654,8 +868,12 @@ impl Class {
         let class_obj = ClassObject {
             class_file,
             n_inst_fields: 0,
+            ref_type: None,
+            has_finalizer: false,
             all_methods: FxHashMap::default(),
+            vtable: vec![],
             v_table: FxHashMap::default(),
+            itable: vec![],
             static_fields: FxHashMap::default(),
             inst_fields: FxHashMap::default(),
             static_field_values: vec![],
@@ -665,24 +883,18 @@ impl Class {
             source_file: None,
             enclosing_method: None,
             inner_classes: None,
-            cp_cache: ConstantPoolCache::new(cp),
-        };
-
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
+            host: None,
+            cp_cache: ConstantPoolCache::new(class_loader, cp),
         };
 
         Self {
-            clinit_mutex: Arc::new(Mutex::new(())),
+            clinit_mutex: Arc::new((Mutex::new(None), Condvar::new())),
             name,
             state: std::sync::atomic::AtomicU8::new(State::Allocated.into()),
             acc_flags,
             super_class: None,
             class_loader,
             kind: ClassKind::Instance(class_obj),
-            mutex,
         }
     }
 
@@ -697,21 +909,14 @@ impl Class {
             mirror: None,
         };
 
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
         Self {
-            clinit_mutex: Arc::new(Mutex::new(())),
+            clinit_mutex: Arc::new((Mutex::new(None), Condvar::new())),
             name,
             state: std::sync::atomic::AtomicU8::new(State::Allocated.into()),
             acc_flags: 0, //todo: should be 0?
             super_class: None,
             class_loader: Some(class_loader),
             kind: ClassKind::ObjectArray(ary_cls_obj),
-            mutex,
         }
     }
 
@@ -727,21 +932,14 @@ impl Class {
         name.push(b'[');
         name.extend_from_slice(value_type.into());
 
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
         Self {
-            clinit_mutex: Arc::new(Mutex::new(())),
+            clinit_mutex: Arc::new((Mutex::new(None), Condvar::new())),
             name: Arc::new(name),
             state: std::sync::atomic::AtomicU8::new(State::Allocated.into()),
             acc_flags: 0, //todo: should be 0?
             super_class: None,
             class_loader: Some(class_loader),
             kind: ClassKind::TypeArray(ary_cls_obj),
-            mutex,
         }
     }
 
@@ -779,32 +977,26 @@ impl Class {
             }
         };
 
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
         Self {
-            clinit_mutex: Arc::new(Mutex::new(())),
+            clinit_mutex: Arc::new((Mutex::new(None), Condvar::new())),
             name: Arc::new(name2),
             state: std::sync::atomic::AtomicU8::new(State::Allocated.into()),
             acc_flags: 0, //todo: should be 0?
             super_class: None,
             class_loader: Some(class_loader),
             kind,
-            mutex,
         }
     }
 }
 
 //inner api for link
 impl ClassObject {
+    //Err, if the exception is thrown
     fn link_super_class(
         &mut self,
         name: BytesRef,
         class_loader: Option<ClassLoader>,
-    ) -> Option<ClassRef> {
+    ) -> Result<Option<ClassRef>, ()> {
         let class_file = &self.class_file;
         let cp = &class_file.cp;
 
@@ -813,18 +1005,27 @@ impl ClassObject {
                 unreachable!("should be java/lang/Object");
             }
 
-            None
+            Ok(None)
         } else {
-            let name = constant_pool::get_class_name(cp, class_file.super_class as usize);
-            let super_class = runtime::require_class(class_loader, name).unwrap();
-
-            {
-                let c = super_class.get_class();
-                debug_assert!(c.is_instance());
-                debug_assert!(!c.is_final(), "should not final");
+            let super_class = require_class2(class_loader, class_file.super_class, cp).ok_or(())?;
+
+            let c = super_class.get_class();
+            if c.is_interface() {
+                let msg = format!(
+                    "class {} has interface {} as super class",
+                    String::from_utf8_lossy(name.as_slice()).replace('/', "."),
+                    String::from_utf8_lossy(c.name.as_slice()).replace('/', ".")
+                );
+                exception::meet_ex(consts::J_INCOMPATIBLE_CLASS_CHANGE_ERROR, Some(msg));
+                return Err(());
+            }
+            if c.is_final() {
+                let msg = "Cannot inherit from final class".to_string();
+                exception::meet_ex(consts::J_VERIFY_ERROR, Some(msg));
+                return Err(());
             }
 
-            Some(super_class)
+            Ok(Some(super_class))
         }
     }
 
@@ -862,29 +1063,40 @@ impl ClassObject {
         self.static_field_values = vec![Oop::Null; n_static];
     }
 
-    fn link_interfaces(&mut self) {
+    //Err, if the exception is thrown
+    fn link_interfaces(&mut self, class_loader: Option<ClassLoader>) -> Result<(), ()> {
         let class_file = self.class_file.clone();
         let cp = &class_file.cp;
 
-        class_file
-            .interfaces
-            .iter()
-            .for_each(|it| match runtime::require_class2(*it, cp) {
-                Some(class) => {
-                    let name = class.get_class().name.clone();
-                    self.interfaces.insert(name, class);
-                }
-                None => {
-                    let name = constant_pool::get_class_name(cp, *it as usize);
-                    error!("link interface failed {:?}", name);
-                }
-            });
+        for it in class_file.interfaces.iter() {
+            let class = require_class2(class_loader, *it, cp).ok_or(())?;
+            let name = class.get_class().name.clone();
+            if !class.get_class().is_interface() {
+                let msg = "Implementing class".to_string();
+                exception::meet_ex(consts::J_INCOMPATIBLE_CLASS_CHANGE_ERROR, Some(msg));
+                return Err(());
+            }
+            self.interfaces.insert(name, class);
+        }
+
+        Ok(())
     }
 
-    fn link_methods(&mut self, this_ref: ClassRef, cls_name: BytesRef) {
+    fn link_methods(
+        &mut self,
+        this_ref: ClassRef,
+        cls_name: BytesRef,
+        class_loader: Option<ClassLoader>,
+        super_obj: Option<&ClassObject>,
+    ) {
         let class_file = self.class_file.clone();
         let cp = &class_file.cp;
 
+        if let Some(super_obj) = super_obj {
+            self.vtable = super_obj.vtable.clone();
+            self.v_table = super_obj.v_table.clone();
+        }
+
         class_file.methods.iter().enumerate().for_each(|(i, it)| {
             let method = method::Method::new(
                 cp,
@@ -894,18 +1106,91 @@ impl ClassObject {
                 i,
                 cls_name.clone(),
             );
-            let method_id = method::MethodId::new(i, method);
-            let name = method_id.method.name.clone();
-            let desc = method_id.method.desc.clone();
+            let name = method.name.clone();
+            let desc = method.desc.clone();
             let k = (name, desc);
+
+            let selectable =
+                !method.is_static() && !method.is_private() && method.name.as_slice() != b"<init>";
+            let overridden: Vec<usize> = if selectable {
+                self.vtable
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| m.method.name == k.0 && m.method.desc == k.1)
+                    .filter(|(_, m)| can_override(&m.method, &cls_name, class_loader))
+                    .map(|(i, _)| i)
+                    .collect()
+            } else {
+                vec![]
+            };
+            let vtable_index = match overridden.first() {
+                Some(&i) => Some(i),
+                None if selectable => Some(self.vtable.len()),
+                None => None,
+            };
+
+            let method_id = method::MethodId::new(i, vtable_index, method);
             self.all_methods.insert(k.clone(), method_id.clone());
 
-            if !method_id.method.is_static() {
-                self.v_table.insert(k, method_id);
+            if let Some(index) = vtable_index {
+                overridden
+                    .iter()
+                    .for_each(|&i| self.vtable[i] = method_id.clone());
+                if overridden.is_empty() {
+                    self.vtable.push(method_id);
+                }
+                self.v_table.insert(k, index);
             }
         });
     }
 
+    //select the methods of all the super interfaces, JVMS 5.4.6
+    fn link_itable(&mut self, super_obj: Option<&ClassObject>) {
+        let mut interfaces: Vec<ClassRef> = match super_obj {
+            Some(super_obj) => super_obj.itable.iter().map(|(it, _)| it.clone()).collect(),
+            None => vec![],
+        };
+        self.interfaces
+            .values()
+            .for_each(|it| collect_interfaces(it, &mut interfaces));
+
+        self.itable = interfaces
+            .iter()
+            .map(|intf| {
+                let methods = match &intf.get_class().kind {
+                    ClassKind::Instance(intf_obj) => intf_obj
+                        .vtable
+                        .iter()
+                        .map(|m| self.select_interface_method(&interfaces, m))
+                        .collect(),
+                    _ => unreachable!(),
+                };
+                (intf.clone(), methods)
+            })
+            .collect();
+    }
+
+    fn select_interface_method(
+        &self,
+        interfaces: &[ClassRef],
+        mir: &MethodIdRef,
+    ) -> Option<MethodIdRef> {
+        let k = (mir.method.name.clone(), mir.method.desc.clone());
+        if let Some(&i) = self.v_table.get(&k) {
+            return Some(self.vtable[i].clone());
+        }
+
+        let candidates = max_specific_methods(interfaces, &k.0, &k.1);
+        let mut defaults = candidates.iter().filter(|m| !m.method.is_abstract());
+        match (defaults.next(), defaults.next()) {
+            (Some(m), None) => Some(m.clone()),
+            //AbstractMethodError when invoked
+            (None, _) => Some(mir.clone()),
+            //IncompatibleClassChangeError when invoked
+            _ => None,
+        }
+    }
+
     fn link_attributes(&mut self) {
         let class_file = self.class_file.clone();
         let cp = &class_file.cp;
@@ -986,19 +1271,36 @@ impl Class {
         let k = (name.clone(), desc.clone());
         match &self.kind {
             ClassKind::Instance(cls_obj) => {
-                if let Some(m) = cls_obj.v_table.get(&k) {
-                    return Ok(m.clone());
+                if let Some(&i) = cls_obj.v_table.get(&k) {
+                    return Ok(cls_obj.vtable[i].clone());
+                }
+
+                //a default method
+                if !self.is_interface() {
+                    let selected = cls_obj.itable.iter().find_map(|(_, methods)| {
+                        methods.iter().flatten().find(|m| {
+                            m.method.name.as_slice() == name.as_slice()
+                                && m.method.desc.as_slice() == desc.as_slice()
+                        })
+                    });
+                    if let Some(m) = selected {
+                        return Ok(m.clone());
+                    }
                 }
             }
             _ => unreachable!(),
         }
 
+        //the methods of java.lang.Object for an interface
         match self.super_class.as_ref() {
-            Some(super_class) => super_class.get_class().get_virtual_method_inner(name, desc),
-            None => Err(()),
+            Some(super_class) if self.is_interface() => {
+                super_class.get_class().get_virtual_method_inner(name, desc)
+            }
+            _ => Err(()),
         }
     }
 
+    //JVMS 5.4.3.4, the interface, java.lang.Object, then the super interfaces
     pub fn get_interface_method_inner(
         &self,
         name: &BytesRef,
@@ -1006,26 +1308,170 @@ impl Class {
     ) -> Result<MethodIdRef, ()> {
         let k = (name.clone(), desc.clone());
         match &self.kind {
-            ClassKind::Instance(cls_obj) => match cls_obj.v_table.get(&k) {
-                Some(m) => return Ok(m.clone()),
-                None => {
-                    for (_, itf) in cls_obj.interfaces.iter() {
-                        let cls = itf.get_class();
-                        let m = cls.get_interface_method(name, desc);
-                        if m.is_ok() {
-                            return m;
-                        }
-                    }
+            ClassKind::Instance(cls_obj) => {
+                if let Some(m) = cls_obj.all_methods.get(&k) {
+                    return Ok(m.clone());
                 }
-            },
+            }
             _ => unreachable!(),
         }
 
-        match self.super_class.as_ref() {
+        let m = match self.super_class.as_ref() {
             Some(super_class) => super_class
                 .get_class()
-                .get_interface_method_inner(name, desc),
+                .get_class_method_inner(name, desc, true),
             None => Err(()),
+        };
+        m.or_else(|_| self.get_super_interface_method(name, desc))
+    }
+
+    //the maximally-specific super interface method, a default method is preferred, JVMS 5.4.3.3
+    pub fn get_super_interface_method(
+        &self,
+        name: &BytesRef,
+        desc: &BytesRef,
+    ) -> Result<MethodIdRef, ()> {
+        let interfaces: Vec<ClassRef> = match &self.kind {
+            ClassKind::Instance(cls_obj) => {
+                cls_obj.itable.iter().map(|(it, _)| it.clone()).collect()
+            }
+            _ => return Err(()),
+        };
+        let candidates = max_specific_methods(&interfaces, name, desc);
+        let mut defaults = candidates.iter().filter(|m| !m.method.is_abstract());
+        match (defaults.next(), defaults.next()) {
+            (Some(m), None) => Ok(m.clone()),
+            _ => candidates.first().cloned().ok_or(()),
         }
     }
+
+    //the method to invoke on a receiver of this class, JVMS 5.4.6
+    pub fn select_method(
+        &self,
+        resolved: &MethodIdRef,
+    ) -> Result<MethodIdRef, (&'static [u8], String)> {
+        let (index, cls_obj) = match (resolved.vtable_index, &self.kind) {
+            (Some(index), ClassKind::Instance(cls_obj)) => (index, cls_obj),
+            _ => return Ok(resolved.clone()),
+        };
+
+        let intf = &resolved.method.class;
+        let selected = if intf.get_class().is_interface() {
+            match cls_obj.itable.iter().find(|(it, _)| Arc::ptr_eq(it, intf)) {
+                Some((_, methods)) => methods[index].clone(),
+                None => {
+                    let msg = format!(
+                        "Class {} does not implement the requested interface {}",
+                        String::from_utf8_lossy(self.name.as_slice()).replace('/', "."),
+                        String::from_utf8_lossy(intf.get_class().name.as_slice()).replace('/', ".")
+                    );
+                    return Err((consts::J_INCOMPATIBLE_CLASS_CHANGE_ERROR, msg));
+                }
+            }
+        } else {
+            cls_obj.vtable.get(index).cloned()
+        };
+
+        match selected {
+            Some(m) if m.method.is_abstract() => {
+                let msg = format!(
+                    "{}.{}{}",
+                    String::from_utf8_lossy(self.name.as_slice()).replace('/', "."),
+                    String::from_utf8_lossy(m.method.name.as_slice()),
+                    String::from_utf8_lossy(m.method.desc.as_slice())
+                );
+                Err((consts::J_ABSTRACT_METHOD_ERROR, msg))
+            }
+            Some(m) => Ok(m),
+            None => {
+                let interfaces: Vec<ClassRef> =
+                    cls_obj.itable.iter().map(|(it, _)| it.clone()).collect();
+                let name = &resolved.method.name;
+                let desc = &resolved.method.desc;
+                let conflicting: Vec<String> = max_specific_methods(&interfaces, name, desc)
+                    .iter()
+                    .filter(|m| !m.method.is_abstract())
+                    .map(|m| {
+                        format!(
+                            "{}.{}",
+                            String::from_utf8_lossy(m.method.cls_name.as_slice()).replace('/', "."),
+                            String::from_utf8_lossy(name.as_slice())
+                        )
+                    })
+                    .collect();
+                let msg = format!("Conflicting default methods: {}", conflicting.join(" "));
+                Err((consts::J_INCOMPATIBLE_CLASS_CHANGE_ERROR, msg))
+            }
+        }
+    }
+}
+
+//JVMS 5.4.5, a package private method is overridden in the same runtime package only
+fn can_override(
+    m: &method::Method,
+    cls_name: &BytesRef,
+    class_loader: Option<ClassLoader>,
+) -> bool {
+    if m.is_public() || m.is_protected() {
+        return true;
+    }
+
+    let loader = m.class.get_class().class_loader;
+    is_same_package(
+        (loader, m.cls_name.as_slice()),
+        (class_loader, cls_name.as_slice()),
+    )
+}
+
+pub fn is_same_package(a: (Option<ClassLoader>, &[u8]), b: (Option<ClassLoader>, &[u8])) -> bool {
+    let package = |name: &[u8]| match name.iter().rposition(|&c| c == b'/') {
+        Some(pos) => name[..pos].to_vec(),
+        None => vec![],
+    };
+    let loader = |it: Option<ClassLoader>| it.unwrap_or(ClassLoader::Bootstrap);
+
+    loader(a.0) == loader(b.0) && package(a.1) == package(b.1)
+}
+
+//the interface and its super interfaces, the duplicates are skipped
+fn collect_interfaces(intf: &ClassRef, out: &mut Vec<ClassRef>) {
+    if out.iter().any(|it| Arc::ptr_eq(it, intf)) {
+        return;
+    }
+    out.push(intf.clone());
+
+    let cls = intf.get_class();
+    if let ClassKind::Instance(cls_obj) = &cls.kind {
+        cls_obj
+            .interfaces
+            .values()
+            .for_each(|it| collect_interfaces(it, out));
+    }
+}
+
+//the methods of the interfaces not overridden by a sub interface of them, JVMS 5.4.3.3
+fn max_specific_methods(
+    interfaces: &[ClassRef],
+    name: &BytesRef,
+    desc: &BytesRef,
+) -> Vec<MethodIdRef> {
+    let k = (name.clone(), desc.clone());
+    let candidates: Vec<(&ClassRef, MethodIdRef)> = interfaces
+        .iter()
+        .filter_map(|intf| match &intf.get_class().kind {
+            ClassKind::Instance(cls_obj) => cls_obj.all_methods.get(&k).map(|m| (intf, m.clone())),
+            _ => None,
+        })
+        .filter(|(_, m)| !m.method.is_static() && !m.method.is_private())
+        .collect();
+
+    candidates
+        .iter()
+        .filter(|(intf, _)| {
+            !candidates.iter().any(|(other, _)| {
+                !Arc::ptr_eq(other, intf) && other.get_class().check_interface((*intf).clone())
+            })
+        })
+        .map(|(_, m)| m.clone())
+        .collect()
 }
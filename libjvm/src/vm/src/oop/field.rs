@@ -1,5 +1,6 @@
+use crate::gc::Payload;
 use crate::oop::{self, consts as oop_consts, Oop, ValueType};
-use crate::runtime::require_class2;
+use crate::runtime::{self, exception, require_class2, ClassLoader};
 use crate::types::ClassRef;
 use crate::types::*;
 use crate::util;
@@ -12,28 +13,58 @@ use std::fmt;
 use std::ops::Deref;
 use std::sync::Arc;
 
-pub fn get_field_ref(cp: &ConstantPool, idx: usize, is_static: bool) -> FieldIdRef {
+//'accessor' owns the pool 'cp'
+pub fn get_field_ref(
+    accessor: &ClassRef,
+    cp: &ConstantPool,
+    idx: usize,
+    is_static: bool,
+) -> Result<FieldIdRef, ()> {
     let (class_index, name_and_type_index) = constant_pool::get_field_ref(cp, idx);
+    let class_loader = accessor.get_class().class_loader;
 
     //load Field's Class, then init it
-    let class = require_class2(class_index, cp).unwrap_or_else(|| {
-        panic!(
-            "Unknown field class {:?}",
-            cp.get(class_index as usize)
-                .expect("Missing item")
-                .as_cp_item(cp)
-        )
-    });
-
-    oop::class::init_class(&class);
-    oop::class::init_class_fully(&class);
+    let class = require_class2(class_loader, class_index, cp).ok_or(())?;
+
+    oop::class::init_class(&class)?;
 
     let (name, desc) = constant_pool::get_name_and_type(cp, name_and_type_index as usize);
-    let class = class.get_class();
-    class.get_field_id(name, desc, is_static)
+    let fid = {
+        let class = class.get_class();
+        match class.find_field_id(name, desc, is_static) {
+            Some(fid) => fid,
+            None => {
+                let (ex, msg) = match class.find_field_id(name, desc, !is_static) {
+                    Some(_) => {
+                        let expected = if is_static { "static" } else { "non-static" };
+                        let msg = format!(
+                            "Expected {} field {}.{}",
+                            expected,
+                            String::from_utf8_lossy(class.name.as_slice()).replace('/', "."),
+                            String::from_utf8_lossy(name.as_slice())
+                        );
+                        (consts::J_INCOMPATIBLE_CLASS_CHANGE_ERROR, msg)
+                    }
+                    None => {
+                        let msg = String::from_utf8_lossy(name.as_slice()).to_string();
+                        (consts::J_NO_SUCH_FIELD_ERROR, msg)
+                    }
+                };
+                exception::meet_ex(ex, Some(msg));
+                return Err(());
+            }
+        }
+    };
+
+    runtime::access::check_field(accessor, &class, &fid)?;
+
+    let defining = fid.field.class.get_class().class_loader;
+    runtime::loader_constraints::add_for_desc(desc.as_slice(), class_loader, defining)?;
+
+    Ok(fid)
 }
 
-pub fn build_inited_field_values(class: ClassRef) -> Vec<Oop> {
+pub fn build_inited_field_values(class: ClassRef) -> Payload<Oop> {
     let n = {
         let class = class.get_class();
         match &class.kind {
@@ -41,7 +72,7 @@ pub fn build_inited_field_values(class: ClassRef) -> Vec<Oop> {
             _ => unreachable!(),
         }
     };
-    let mut field_values = vec![Oop::Null; n];
+    let mut field_values = Payload::new(n, Oop::Null);
     let mut cur_cls = class;
 
     loop {
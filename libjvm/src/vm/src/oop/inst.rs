@@ -1,10 +1,11 @@
+use crate::gc::Payload;
 use crate::oop::{field, Oop};
 use crate::types::ClassRef;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct InstOopDesc {
     pub class: ClassRef,
-    pub field_values: Vec<Oop>,
+    pub field_values: Payload<Oop>,
 }
 
 impl InstOopDesc {
@@ -1,10 +1,11 @@
+use crate::gc::Payload;
 use crate::oop::{Oop, ValueType};
 use crate::types::ClassRef;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct MirrorOopDesc {
     pub target: Option<ClassRef>,
-    pub field_values: Vec<Oop>,
+    pub field_values: Payload<Oop>,
     pub value_type: ValueType,
 }
 
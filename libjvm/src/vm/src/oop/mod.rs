@@ -1,12 +1,15 @@
 #![allow(unused)]
 
 use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Condvar, Mutex, RwLock};
 
 use classfile::{BytesRef, ClassFile};
 
+use crate::gc::{self, Payload};
 use crate::new_br;
 use crate::oop::class::ClassObject;
+use crate::runtime::thread::Monitor;
 use crate::runtime::{require_class3, ClassLoader};
 use crate::types::*;
 use crate::util::oop::{get_java_lang_integer_value_offset, get_java_lang_string_value_offset};
@@ -44,8 +47,9 @@ pub enum Oop {
     Ref(Arc<OopPtr>),
 }
 
+//the handle of an object, the address is updated when the object is moved
 #[derive(Debug)]
-pub struct OopPtr(u64);
+pub struct OopPtr(AtomicUsize);
 
 impl fmt::Debug for Oop {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -115,90 +119,82 @@ impl Oop {
     }
 
     pub fn new_byte_ary(len: usize) -> Oop {
-        let elms = vec![0; len];
-        Self::new_byte_ary2(elms)
+        let v = TypeArrayDesc::Byte(Payload::new(len, 0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     fn new_bool_ary(len: usize) -> Oop {
-        let elms = vec![0; len];
-        Self::new_bool_ary2(elms)
+        let v = TypeArrayDesc::Bool(Payload::new(len, 0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     fn new_char_ary(len: usize) -> Oop {
-        let elms = vec![0; len];
-        Self::new_char_ary2(elms)
+        let v = TypeArrayDesc::Char(Payload::new(len, 0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     fn new_short_ary(len: usize) -> Oop {
-        let elms = vec![0; len];
-        Self::new_short_ary2(elms)
+        let v = TypeArrayDesc::Short(Payload::new(len, 0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     fn new_int_ary(len: usize) -> Oop {
-        let elms = vec![0; len];
-        Self::new_int_ary2(elms)
+        let v = TypeArrayDesc::Int(Payload::new(len, 0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     fn new_float_ary(len: usize) -> Oop {
-        let elms = vec![0.0; len];
-        Self::new_float_ary2(elms)
+        let v = TypeArrayDesc::Float(Payload::new(len, 0.0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     fn new_double_ary(len: usize) -> Oop {
-        let elms = vec![0.0; len];
-        Self::new_double_ary2(elms)
+        let v = TypeArrayDesc::Double(Payload::new(len, 0.0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     fn new_long_ary(len: usize) -> Oop {
-        let elms = vec![0; len];
-        Self::new_long_ary2(elms)
+        let v = TypeArrayDesc::Long(Payload::new(len, 0));
+        Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_byte_ary2(elms: Vec<u8>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Byte(ary);
+        let v = TypeArrayDesc::Byte(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_bool_ary2(elms: Vec<u8>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Bool(ary);
+        let v = TypeArrayDesc::Bool(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_char_ary2(elms: Vec<u16>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Char(ary);
+        let v = TypeArrayDesc::Char(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_short_ary2(elms: Vec<i16>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Short(ary);
+        let v = TypeArrayDesc::Short(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_int_ary2(elms: Vec<i32>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Int(ary);
+        let v = TypeArrayDesc::Int(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_float_ary2(elms: Vec<f32>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Float(ary);
+        let v = TypeArrayDesc::Float(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_double_ary2(elms: Vec<f64>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Double(ary);
+        let v = TypeArrayDesc::Double(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 
     pub fn new_long_ary2(elms: Vec<i64>) -> Oop {
-        let ary = Box::new(elms);
-        let v = TypeArrayDesc::Long(ary);
+        let v = TypeArrayDesc::Long(Payload::from_vec(elms));
         Self::new_ref(RefKind::TypeArray(v))
     }
 }
@@ -250,7 +246,7 @@ impl Oop {
         let field_values = field::build_inited_field_values(java_lang_class);
         let v = MirrorOopDesc {
             target: Some(target),
-            field_values: vec![],
+            field_values: Payload::new(0, Oop::Null),
             value_type,
         };
 
@@ -261,12 +257,12 @@ impl Oop {
 //array reference factory
 impl Oop {
     pub fn new_ref_ary(ary_cls_obj: ClassRef, len: usize) -> Oop {
-        let elements = vec![Oop::Null; len];
-        Self::new_ref_ary2(ary_cls_obj, elements)
+        let v = ArrayOopDesc::new(ary_cls_obj, Payload::new(len, Oop::Null));
+        Self::new_ref(RefKind::Array(v))
     }
 
     pub fn new_ref_ary2(ary_cls_obj: ClassRef, elms: Vec<Oop>) -> Oop {
-        let v = ArrayOopDesc::new(ary_cls_obj, elms);
+        let v = ArrayOopDesc::new(ary_cls_obj, Payload::from_vec(elms));
         Self::new_ref(RefKind::Array(v))
     }
 }
@@ -274,10 +270,7 @@ impl Oop {
 //private helper
 impl Oop {
     fn new_ref(v: RefKind) -> Oop {
-        let v = RefKindDesc::new(v);
-        let v = Box::new(v);
-        let ptr = Box::into_raw(v) as u64;
-        let rf = Arc::new(OopPtr(ptr));
+        let rf = gc::new_object(v);
         Oop::Ref(rf)
     }
 }
@@ -289,7 +282,7 @@ impl Oop {
                 if OopPtr::is_java_lang_string(rf.clone()) {
                     OopPtr::java_lang_string_hash(rf.clone())
                 } else {
-                    rf.0 as i32
+                    Arc::as_ptr(rf) as usize as i32
                 }
             }
             Oop::Null => 0,
@@ -348,12 +341,22 @@ impl Oop {
 }
 
 impl OopPtr {
+    //the object is owned by the heap, see gc::new_object
+    pub(crate) fn from_raw(ptr: *mut RefKindDesc) -> Arc<Self> {
+        Arc::new(OopPtr(AtomicUsize::new(ptr as usize)))
+    }
+
+    //by the collection, with the world stopped
+    pub(crate) fn set_raw_ptr(&self, ptr: *mut RefKindDesc) {
+        self.0.store(ptr as usize, Ordering::Relaxed);
+    }
+
     pub fn get_raw_ptr(&self) -> *const RefKindDesc {
-        self.0 as *const RefKindDesc
+        self.0.load(Ordering::Relaxed) as *const RefKindDesc
     }
 
     pub fn get_mut_raw_ptr(&self) -> *mut RefKindDesc {
-        self.0 as *mut RefKindDesc
+        self.0.load(Ordering::Relaxed) as *mut RefKindDesc
     }
 }
 
@@ -389,30 +392,45 @@ impl OopPtr {
     }
 }
 
+//the monitor is boxed, it stays in place while the object is moved
 impl OopPtr {
-    pub fn monitor_enter(&self) {
+    fn monitor(&self) -> &Monitor {
         let ptr = self.get_raw_ptr();
-        unsafe { (*ptr).monitor_enter() };
+        unsafe { &*((*ptr).monitor() as *const Monitor) }
     }
 
-    pub fn monitor_exit(&self) {
-        let ptr = self.get_raw_ptr();
-        unsafe { (*ptr).monitor_exit() };
+    pub fn monitor_enter(&self) {
+        self.monitor().enter();
     }
 
-    pub fn notify_all(&self) {
-        let ptr = self.get_raw_ptr();
-        unsafe { (*ptr).notify_all() }
+    pub fn monitor_exit(&self) -> Result<(), ()> {
+        self.monitor().exit()
     }
 
-    pub fn wait(&self) {
+    pub fn notify(&self) -> Result<(), ()> {
+        self.monitor().notify()
+    }
+
+    pub fn notify_all(&self) -> Result<(), ()> {
+        self.monitor().notify_all()
+    }
+
+    pub fn is_locked_by_current(&self) -> bool {
         let ptr = self.get_raw_ptr();
-        unsafe { (*ptr).wait() }
+        unsafe { (*ptr).is_locked_by_current() }
+    }
+
+    pub fn wait(
+        &self,
+        timeout: Option<std::time::Duration>,
+        interrupt: &crate::runtime::thread::Interrupt,
+    ) -> Result<(), ()> {
+        self.monitor().wait(timeout, interrupt)
     }
 
-    pub fn wait_timeout(&self, duration: std::time::Duration) {
+    pub fn wake_waiters(&self) {
         let ptr = self.get_raw_ptr();
-        unsafe { (*ptr).wait_timeout(duration) }
+        unsafe { (*ptr).wake_waiters() }
     }
 }
 
@@ -431,7 +449,7 @@ impl OopPtr {
         let l = l.extract_ref();
         let r = r.extract_ref();
 
-        if l.0 == r.0 {
+        if Arc::ptr_eq(&l, &r) {
             true
         } else if Self::is_java_lang_string(l.clone()) && Self::is_java_lang_string(r.clone()) {
             Self::is_java_lang_string_eq(l, r)
@@ -466,7 +484,7 @@ impl OopPtr {
         let chars1 = rf1.extract_type_array().extract_chars();
         let chars2 = rf2.extract_type_array().extract_chars();
 
-        chars1 == chars2
+        chars1.as_slice() == chars2.as_slice()
     }
 }
 
@@ -519,12 +537,6 @@ impl OopPtr {
     }
 }
 
-impl Drop for OopPtr {
-    fn drop(&mut self) {
-        let _v = unsafe { Box::from_raw(self.0 as *mut RefKindDesc) };
-    }
-}
-
 pub fn init() {
     consts::init();
 }
@@ -1,8 +1,9 @@
 use crate::oop::{ArrayOopDesc, InstOopDesc, MirrorOopDesc, TypeArrayDesc};
-use crate::runtime::thread::{Condvar, ReentrantMutex};
+use crate::runtime::thread::Monitor;
 use std::fmt;
 use std::fmt::Formatter;
-use std::time::Duration;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
 
 #[derive(Debug)]
 pub enum RefKind {
@@ -16,61 +17,60 @@ pub struct RefKindDesc {
     pub v: RefKind,
     pub hash_code: Option<i32>,
 
-    mutex: ReentrantMutex,
-    cond_var: Condvar,
+    //gc state, see gc::write_barrier
+    pub(crate) old: AtomicBool,
+    pub(crate) remembered: AtomicBool,
+
+    monitor: AtomicPtr<Monitor>,
 }
 
 impl RefKindDesc {
     pub fn new(v: RefKind) -> Self {
-        let mutex = unsafe {
-            let mut mutex = ReentrantMutex::uninitialized();
-            mutex.init();
-            mutex
-        };
-
-        let cond_var = unsafe {
-            let mut cond = Condvar::new();
-            // cond.init();
-            cond
-        };
-
         Self {
             v,
             hash_code: None,
-            mutex,
-            cond_var,
+            old: AtomicBool::new(false),
+            remembered: AtomicBool::new(false),
+            monitor: AtomicPtr::new(ptr::null_mut()),
         }
     }
 }
 
 impl RefKindDesc {
-    pub fn monitor_enter(&self) {
-        unsafe {
-            self.mutex.lock();
-        }
-    }
-
-    pub fn monitor_exit(&self) {
-        unsafe {
-            self.mutex.unlock();
+    //inflated on the first use, most objects are never locked
+    pub(crate) fn monitor(&self) -> &Monitor {
+        let mut ptr = self.monitor.load(Ordering::Acquire);
+        if ptr.is_null() {
+            let new = Box::into_raw(Box::new(Monitor::new()));
+            ptr = match self.monitor.compare_exchange(
+                ptr::null_mut(),
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => new,
+                Err(exist) => {
+                    unsafe {
+                        drop(Box::from_raw(new));
+                    }
+                    exist
+                }
+            };
         }
-    }
 
-    pub fn wait(&self) {
-        unsafe {
-            self.cond_var.wait(&self.mutex);
-        }
+        unsafe { &*ptr }
     }
 
-    pub fn wait_timeout(&self, duration: Duration) {
-        unsafe {
-            self.cond_var.wait_timeout(&self.mutex, duration);
-        }
+    //Thread.holdsLock
+    pub fn is_locked_by_current(&self) -> bool {
+        let ptr = self.monitor.load(Ordering::Acquire);
+        !ptr.is_null() && unsafe { (*ptr).is_owned_by_current() }
     }
 
-    pub fn notify_all(&self) {
-        unsafe {
-            self.cond_var.notify_all();
+    pub fn wake_waiters(&self) {
+        let ptr = self.monitor.load(Ordering::Acquire);
+        if !ptr.is_null() {
+            unsafe { (*ptr).wake_waiters() }
         }
     }
 }
@@ -123,8 +123,11 @@ impl RefKind {
 
 impl Drop for RefKindDesc {
     fn drop(&mut self) {
-        unsafe {
-            self.mutex.destroy();
+        let ptr = *self.monitor.get_mut();
+        if !ptr.is_null() {
+            unsafe {
+                drop(Box::from_raw(ptr));
+            }
         }
     }
 }
@@ -134,7 +137,6 @@ impl fmt::Debug for RefKindDesc {
         f.debug_struct("RefKindDesc")
             .field("v", &self.v)
             .field("hash_code", &self.hash_code)
-            .field("mutex", &"mutex")
             .finish()
     }
 }
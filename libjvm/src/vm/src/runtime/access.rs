@@ -0,0 +1,277 @@
+use crate::oop::class::{self, ClassKind};
+use crate::oop::Oop;
+use crate::runtime::{self, exception};
+use crate::types::{ClassRef, FieldIdRef, MethodIdRef};
+use classfile::{constant_pool, consts as cls_consts, flags::*, U2};
+use std::sync::Arc;
+
+/*
+Access control, JVM spec 5.4.4
+
+A class is accessible to the class D, if it's public, or in the runtime
+package of D. A member declared in the class C is accessible to D if
+  public,
+  protected, and D is a subclass of C or in the package of C,
+  package private, and D is in the package of C,
+  private, and D is C or a nestmate of C (the class files since 11).
+
+An anonymous class (Unsafe.defineAnonymousClass, the spun lambda class)
+has the access of its host.
+
+The reflection (Method.invoke, Constructor.newInstance) is checked the
+same, against the caller out of the reflection implementation, unless
+setAccessible(true) was called.
+*/
+
+//the nests appear in the class files of version 55 (Java 11)
+const NEST_VERSION: U2 = 55;
+
+pub fn is_class_accessible(accessor: &ClassRef, cls: &ClassRef) -> bool {
+    let cls = match element_class(cls) {
+        Some(cls) => cls,
+        None => return true,
+    };
+    if Arc::ptr_eq(accessor, &cls) || cls.get_class().is_public() {
+        return true;
+    }
+
+    let accessor = host_of(accessor);
+    Arc::ptr_eq(&accessor, &cls) || is_same_package(&accessor, &cls)
+}
+
+//'declaring' is the class declaring the member with the 'acc_flags'
+pub fn is_member_accessible(accessor: &ClassRef, declaring: &ClassRef, acc_flags: U2) -> bool {
+    if acc_flags & ACC_PUBLIC != 0 || Arc::ptr_eq(accessor, declaring) {
+        return true;
+    }
+
+    let accessor = host_of(accessor);
+    if Arc::ptr_eq(&accessor, declaring) {
+        return true;
+    }
+
+    if acc_flags & ACC_PRIVATE != 0 {
+        return Arc::ptr_eq(&nest_host(&accessor), &nest_host(declaring));
+    }
+
+    if acc_flags & ACC_PROTECTED != 0 && is_subclass(&accessor, declaring) {
+        return true;
+    }
+
+    is_same_package(&accessor, declaring)
+}
+
+//'referenced' is the class named by the symbolic reference, Err with IllegalAccessError
+pub fn check_method(
+    accessor: &ClassRef,
+    referenced: &ClassRef,
+    mir: &MethodIdRef,
+) -> Result<(), ()> {
+    check_class(accessor, referenced)?;
+
+    let m = &mir.method;
+    if is_member_accessible(accessor, &m.class, m.acc_flags) {
+        return Ok(());
+    }
+
+    let msg = format!(
+        "tried to access method {}.{}{} from class {}",
+        external_name(m.cls_name.as_slice()),
+        String::from_utf8_lossy(m.name.as_slice()),
+        String::from_utf8_lossy(m.desc.as_slice()),
+        external_name(accessor.get_class().name.as_slice())
+    );
+    exception::meet_ex(cls_consts::J_ILLEGAL_ACCESS_ERROR, Some(msg));
+    Err(())
+}
+
+//'referenced' is the class named by the symbolic reference, Err with IllegalAccessError
+pub fn check_field(accessor: &ClassRef, referenced: &ClassRef, fir: &FieldIdRef) -> Result<(), ()> {
+    check_class(accessor, referenced)?;
+
+    let f = &fir.field;
+    if is_member_accessible(accessor, &f.class, f.acc_flags) {
+        return Ok(());
+    }
+
+    let msg = format!(
+        "tried to access field {}.{} from class {}",
+        external_name(f.cls_name.as_slice()),
+        String::from_utf8_lossy(f.name.as_slice()),
+        external_name(accessor.get_class().name.as_slice())
+    );
+    exception::meet_ex(cls_consts::J_ILLEGAL_ACCESS_ERROR, Some(msg));
+    Err(())
+}
+
+//Err with IllegalAccessException, the native returns it
+pub fn check_reflect(declaring: &ClassRef, acc_flags: U2) -> Result<(), Oop> {
+    let caller = match reflect_caller() {
+        Some(caller) => caller,
+        None => return Ok(()),
+    };
+    if is_class_accessible(&caller, declaring)
+        && is_member_accessible(&caller, declaring, acc_flags)
+    {
+        return Ok(());
+    }
+
+    let msg = format!(
+        "Class {} can not access a member of class {} with modifiers \"{}\"",
+        external_name(caller.get_class().name.as_slice()),
+        external_name(declaring.get_class().name.as_slice()),
+        modifiers_name(acc_flags)
+    );
+    Err(exception::new(cls_consts::J_ILLEGAL_ACCESS, Some(msg)))
+}
+
+//the class calling Method.invoke or Constructor.newInstance
+fn reflect_caller() -> Option<ClassRef> {
+    let jt = runtime::thread::current_java_thread();
+    let jt = jt.read().unwrap();
+    jt.frames.iter().rev().find_map(|frame| {
+        let name = frame.class.get_class().name.clone();
+        if name.starts_with(b"sun/reflect/") || name.starts_with(b"java/lang/reflect/") {
+            None
+        } else {
+            Some(frame.class.clone())
+        }
+    })
+}
+
+//java.lang.reflect.Modifier.toString, for the methods
+fn modifiers_name(acc_flags: U2) -> String {
+    let names = [
+        (ACC_PUBLIC, "public"),
+        (ACC_PROTECTED, "protected"),
+        (ACC_PRIVATE, "private"),
+        (ACC_ABSTRACT, "abstract"),
+        (ACC_STATIC, "static"),
+        (ACC_FINAL, "final"),
+        (ACC_SYNCHRONIZED, "synchronized"),
+        (ACC_NATIVE, "native"),
+        (ACC_STRICT, "strictfp"),
+    ];
+    let names: Vec<&str> = names
+        .iter()
+        .filter(|(flag, _)| acc_flags & flag != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    names.join(" ")
+}
+
+fn check_class(accessor: &ClassRef, cls: &ClassRef) -> Result<(), ()> {
+    if is_class_accessible(accessor, cls) {
+        return Ok(());
+    }
+
+    let msg = format!(
+        "tried to access class {} from class {}",
+        external_name(cls.get_class().name.as_slice()),
+        external_name(accessor.get_class().name.as_slice())
+    );
+    exception::meet_ex(cls_consts::J_ILLEGAL_ACCESS_ERROR, Some(msg));
+    Err(())
+}
+
+//the host of an anonymous class, which may be anonymous too, or the class itself
+fn host_of(cls: &ClassRef) -> ClassRef {
+    let mut cur = cls.clone();
+    loop {
+        let host = match &cur.get_class().kind {
+            ClassKind::Instance(cls_obj) => cls_obj.host.clone(),
+            _ => None,
+        };
+        match host {
+            Some(host) => cur = host,
+            None => return cur,
+        }
+    }
+}
+
+//the element class of an array class, None for the primitive arrays
+fn element_class(cls: &ClassRef) -> Option<ClassRef> {
+    let mut cur = cls.clone();
+    loop {
+        let next = match &cur.get_class().kind {
+            ClassKind::Instance(_) => return Some(cur.clone()),
+            ClassKind::ObjectArray(ary) => ary.component.clone()?,
+            ClassKind::TypeArray(_) => return None,
+        };
+        cur = next;
+    }
+}
+
+//JVM spec 5.4.4, the nest host is verified to list the class as a member,
+//otherwise the class is the host of its own nest
+fn nest_host(cls: &ClassRef) -> ClassRef {
+    let this = cls.get_class();
+    let cls_obj = match &this.kind {
+        ClassKind::Instance(cls_obj) => cls_obj,
+        _ => return cls.clone(),
+    };
+    let cf = &cls_obj.class_file;
+    if cf.version.major < NEST_VERSION {
+        return cls.clone();
+    }
+    let host_index = match cf.nest_host() {
+        Some(idx) => idx,
+        None => return cls.clone(),
+    };
+
+    let name = constant_pool::get_class_name(&cf.cp, host_index);
+    let host = match runtime::require_class3(this.class_loader, name.as_slice()) {
+        Some(host) => host,
+        None => {
+            //the nest host is not loadable, the class is not in any other nest
+            let _ = runtime::thread::current_java_thread()
+                .write()
+                .unwrap()
+                .take_ex();
+            return cls.clone();
+        }
+    };
+
+    let is_member = match &host.get_class().kind {
+        ClassKind::Instance(host_obj) => {
+            let host_cf = &host_obj.class_file;
+            let members = host_cf.nest_members().map(|it| it.as_slice());
+            members.unwrap_or_default().iter().any(|&idx| {
+                constant_pool::get_class_name(&host_cf.cp, idx as usize).as_slice()
+                    == this.name.as_slice()
+            })
+        }
+        _ => false,
+    };
+
+    if is_member && is_same_package(cls, &host) {
+        host
+    } else {
+        cls.clone()
+    }
+}
+
+fn is_subclass(cls: &ClassRef, sup: &ClassRef) -> bool {
+    let mut cur = Some(cls.clone());
+    while let Some(it) = cur {
+        if Arc::ptr_eq(&it, sup) {
+            return true;
+        }
+        cur = it.get_class().super_class.clone();
+    }
+
+    false
+}
+
+fn is_same_package(a: &ClassRef, b: &ClassRef) -> bool {
+    let a = a.get_class();
+    let b = b.get_class();
+    class::is_same_package(
+        (a.class_loader, a.name.as_slice()),
+        (b.class_loader, b.name.as_slice()),
+    )
+}
+
+fn external_name(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).replace('/', ".")
+}
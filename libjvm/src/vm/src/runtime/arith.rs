@@ -0,0 +1,303 @@
+/*
+The arithmetic of the instructions, JVM spec 6.5, with the semantics of Java
+
+  int, long      wrap around, Integer.MIN_VALUE / -1 is Integer.MIN_VALUE,
+                 the division by zero is None, an ArithmeticException
+  float, double  IEEE 754, NaN and the signed zero kept, the remainder
+                 truncates like fmod, not the IEEE remainder
+  f2i, d2l, ...  round toward zero, saturate at the bounds, NaN is 0
+  i2b, l2i, ...  truncate, i2f, d2f round to the nearest
+  shifts         the distance masked by the width, 0x1f or 0x3f
+
+The float to int conversions of Rust ('as') have been the same since 1.45.
+*/
+
+#[inline]
+pub fn idiv(v1: i32, v2: i32) -> Option<i32> {
+    if v2 == 0 {
+        None
+    } else {
+        Some(v1.wrapping_div(v2))
+    }
+}
+
+#[inline]
+pub fn ldiv(v1: i64, v2: i64) -> Option<i64> {
+    if v2 == 0 {
+        None
+    } else {
+        Some(v1.wrapping_div(v2))
+    }
+}
+
+#[inline]
+pub fn irem(v1: i32, v2: i32) -> Option<i32> {
+    if v2 == 0 {
+        None
+    } else {
+        Some(v1.wrapping_rem(v2))
+    }
+}
+
+#[inline]
+pub fn lrem(v1: i64, v2: i64) -> Option<i64> {
+    if v2 == 0 {
+        None
+    } else {
+        Some(v1.wrapping_rem(v2))
+    }
+}
+
+#[inline]
+pub fn frem(v1: f32, v2: f32) -> f32 {
+    v1 % v2
+}
+
+#[inline]
+pub fn drem(v1: f64, v2: f64) -> f64 {
+    v1 % v2
+}
+
+//never throws, the division by zero is an infinity or NaN
+#[inline]
+pub fn fdiv(v1: f32, v2: f32) -> f32 {
+    v1 / v2
+}
+
+#[inline]
+pub fn ddiv(v1: f64, v2: f64) -> f64 {
+    v1 / v2
+}
+
+//flips the sign, also of zero, not 0.0 - v
+#[inline]
+pub fn fneg(v: f32) -> f32 {
+    -v
+}
+
+#[inline]
+pub fn dneg(v: f64) -> f64 {
+    -v
+}
+
+#[inline]
+pub fn ishl(v1: i32, v2: i32) -> i32 {
+    v1.wrapping_shl(v2 as u32)
+}
+
+#[inline]
+pub fn lshl(v1: i64, v2: i32) -> i64 {
+    v1.wrapping_shl(v2 as u32)
+}
+
+#[inline]
+pub fn ishr(v1: i32, v2: i32) -> i32 {
+    v1.wrapping_shr(v2 as u32)
+}
+
+#[inline]
+pub fn lshr(v1: i64, v2: i32) -> i64 {
+    v1.wrapping_shr(v2 as u32)
+}
+
+#[inline]
+pub fn iushr(v1: i32, v2: i32) -> i32 {
+    (v1 as u32).wrapping_shr(v2 as u32) as i32
+}
+
+#[inline]
+pub fn lushr(v1: i64, v2: i32) -> i64 {
+    (v1 as u64).wrapping_shr(v2 as u32) as i64
+}
+
+#[inline]
+pub fn lcmp(v1: i64, v2: i64) -> i32 {
+    match v1.cmp(&v2) {
+        std::cmp::Ordering::Greater => 1,
+        std::cmp::Ordering::Less => -1,
+        std::cmp::Ordering::Equal => 0,
+    }
+}
+
+//'nan' is the result if any is NaN, -1 for fcmpl, 1 for fcmpg
+#[inline]
+pub fn fcmp(v1: f32, v2: f32, nan: i32) -> i32 {
+    dcmp(v1 as f64, v2 as f64, nan)
+}
+
+//'nan' is the result if any is NaN, -1 for dcmpl, 1 for dcmpg
+#[inline]
+pub fn dcmp(v1: f64, v2: f64, nan: i32) -> i32 {
+    match v1.partial_cmp(&v2) {
+        Some(std::cmp::Ordering::Greater) => 1,
+        Some(std::cmp::Ordering::Less) => -1,
+        Some(std::cmp::Ordering::Equal) => 0,
+        None => nan,
+    }
+}
+
+#[inline]
+pub fn i2b(v: i32) -> i32 {
+    v as i8 as i32
+}
+
+#[inline]
+pub fn i2c(v: i32) -> i32 {
+    v as u16 as i32
+}
+
+#[inline]
+pub fn i2s(v: i32) -> i32 {
+    v as i16 as i32
+}
+
+#[inline]
+pub fn l2i(v: i64) -> i32 {
+    v as i32
+}
+
+//round to the nearest
+#[inline]
+pub fn i2f(v: i32) -> f32 {
+    v as f32
+}
+
+#[inline]
+pub fn d2f(v: f64) -> f32 {
+    v as f32
+}
+
+#[inline]
+pub fn f2i(v: f32) -> i32 {
+    v as i32
+}
+
+#[inline]
+pub fn f2l(v: f32) -> i64 {
+    v as i64
+}
+
+#[inline]
+pub fn d2i(v: f64) -> i32 {
+    v as i32
+}
+
+#[inline]
+pub fn d2l(v: f64) -> i64 {
+    v as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_int() {
+        assert_eq!(idiv(7, -2), Some(-3));
+        assert_eq!(idiv(i32::MIN, -1), Some(i32::MIN));
+        assert_eq!(idiv(1, 0), None);
+        assert_eq!(irem(-7, 2), Some(-1));
+        assert_eq!(irem(7, -2), Some(1));
+        assert_eq!(irem(i32::MIN, -1), Some(0));
+        assert_eq!(irem(1, 0), None);
+
+        assert_eq!(ldiv(i64::MIN, -1), Some(i64::MIN));
+        assert_eq!(ldiv(-7, 2), Some(-3));
+        assert_eq!(ldiv(1, 0), None);
+        assert_eq!(lrem(i64::MIN, -1), Some(0));
+        assert_eq!(lrem(-7, 2), Some(-1));
+        assert_eq!(lrem(1, 0), None);
+    }
+
+    #[test]
+    fn t_shift() {
+        assert_eq!(ishl(1, 33), 2);
+        assert_eq!(ishl(1, -1), i32::MIN);
+        assert_eq!(ishr(-8, 1), -4);
+        assert_eq!(ishr(-8, 32), -8);
+        assert_eq!(iushr(-1, 28), 0xf);
+        assert_eq!(iushr(-1, 32), -1);
+
+        assert_eq!(lshl(1, 65), 2);
+        assert_eq!(lshl(1, 63), i64::MIN);
+        assert_eq!(lshr(-8, 1), -4);
+        assert_eq!(lshr(-8, 64), -8);
+        assert_eq!(lushr(-1, 60), 0xf);
+        assert_eq!(lushr(-1, 64), -1);
+    }
+
+    #[test]
+    fn t_float() {
+        assert_eq!(frem(5.5, 2.0), 1.5);
+        assert_eq!(frem(-5.5, 2.0), -1.5);
+        assert_eq!(frem(5.5, -2.0), 1.5);
+        assert!(frem(1.0, 0.0).is_nan());
+        assert!(frem(f32::INFINITY, 1.0).is_nan());
+        assert_eq!(frem(1.0, f32::INFINITY), 1.0);
+        assert!(frem(-0.0, 1.0).is_sign_negative());
+
+        assert_eq!(drem(5.5, 2.0), 1.5);
+        assert_eq!(drem(-5.5, 2.0), -1.5);
+        assert!(drem(1.0, 0.0).is_nan());
+        assert!(drem(-0.0, 1.0).is_sign_negative());
+
+        assert!(fneg(0.0).is_sign_negative());
+        assert!(dneg(0.0).is_sign_negative());
+        assert_eq!(dneg(-0.0).to_bits(), 0.0f64.to_bits());
+        assert!(dneg(f64::NAN).is_nan());
+
+        assert_eq!(fdiv(1.0, 0.0), f32::INFINITY);
+        assert_eq!(fdiv(1.0, -0.0), f32::NEG_INFINITY);
+        assert_eq!(ddiv(1.0, -0.0), f64::NEG_INFINITY);
+        assert!(ddiv(0.0, 0.0).is_nan());
+    }
+
+    #[test]
+    fn t_cmp() {
+        assert_eq!(lcmp(1, 2), -1);
+        assert_eq!(lcmp(2, 1), 1);
+        assert_eq!(lcmp(i64::MIN, i64::MAX), -1);
+        assert_eq!(lcmp(3, 3), 0);
+
+        assert_eq!(fcmp(1.0, 2.0, -1), -1);
+        assert_eq!(fcmp(2.0, 1.0, -1), 1);
+        assert_eq!(fcmp(0.0, -0.0, 1), 0);
+        assert_eq!(fcmp(f32::NAN, 1.0, -1), -1);
+        assert_eq!(fcmp(1.0, f32::NAN, 1), 1);
+
+        assert_eq!(dcmp(1.0, 2.0, 1), -1);
+        assert_eq!(dcmp(f64::INFINITY, f64::MAX, 1), 1);
+        assert_eq!(dcmp(-0.0, 0.0, -1), 0);
+        assert_eq!(dcmp(f64::NAN, f64::NAN, -1), -1);
+        assert_eq!(dcmp(f64::NAN, 1.0, 1), 1);
+    }
+
+    #[test]
+    fn t_convert() {
+        assert_eq!(f2i(f32::NAN), 0);
+        assert_eq!(f2i(1e10), i32::MAX);
+        assert_eq!(f2i(-1e10), i32::MIN);
+        assert_eq!(f2i(f32::NEG_INFINITY), i32::MIN);
+        assert_eq!(f2i(-1.9), -1);
+        assert_eq!(f2l(f32::INFINITY), i64::MAX);
+        assert_eq!(f2l(f32::NAN), 0);
+
+        assert_eq!(d2i(f64::NAN), 0);
+        assert_eq!(d2i(2147483648.0), i32::MAX);
+        assert_eq!(d2i(-2147483649.0), i32::MIN);
+        assert_eq!(d2i(2.9), 2);
+        assert_eq!(d2l(1e30), i64::MAX);
+        assert_eq!(d2l(-1e30), i64::MIN);
+        assert_eq!(d2l(-0.5), 0);
+
+        assert_eq!(i2b(0x1ff), -1);
+        assert_eq!(i2b(0x80), -128);
+        assert_eq!(i2c(-1), 0xffff);
+        assert_eq!(i2s(0x18000), -0x8000);
+        assert_eq!(l2i(0x1_0000_0001), 1);
+        assert_eq!(l2i(i64::MAX), -1);
+        assert_eq!(d2f(1e300), f32::INFINITY);
+        assert_eq!(d2f(1e-50), 0.0);
+        assert_eq!(i2f(16777217), 16777216.0);
+    }
+}
@@ -0,0 +1,453 @@
+use crate::oop::Oop;
+use crate::types::MethodIdRef;
+use classfile::{OpCode, U1};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/*
+The code of a method decoded before its first run: an instruction at the pc
+of each bytecode, with its operands read, the branches to an absolute pc,
+and 'wide' folded in the instruction it modifies. The pcs inside an
+instruction are never fetched, so the pcs of the exception table, the line
+number table and the stack trace are the pcs of the bytecode.
+
+An instruction resolving a symbolic reference is rewritten to its quick form
+after the first execution, like the fast bytecodes of hotspot:
+
+  getfield, putfield       -> the offset and the type of the field
+  invokevirtual            -> the method resolved
+  ldc, ldc_w, ldc2_w       -> the constant resolved
+
+The quick forms depend on the layout of the classes they were resolved
+against, and on the classes of other methods too. When a class is redefined,
+deoptimize_all makes all of them stale: a frame running stale code finds the
+decoded forms back on its next quick instruction and resolves again, the
+method is decoded again for its next call.
+*/
+
+pub const GETFIELD_QUICK: u8 = 203;
+pub const PUTFIELD_QUICK: u8 = 204;
+pub const INVOKEVIRTUAL_QUICK: u8 = 205;
+pub const LDC_QUICK: u8 = 206;
+
+//bumped by deoptimize_all, the code decoded before is stale
+static EPOCH: AtomicUsize = AtomicUsize::new(0);
+
+pub fn deoptimize_all() {
+    EPOCH.fetch_add(1, Ordering::SeqCst);
+}
+
+//a decoded instruction
+#[derive(Clone, Copy)]
+pub struct Insn {
+    pub op: u8,
+    pub pc: i32,
+    //the pc of the next instruction, not for a switch
+    pub next: i32,
+    /*
+    cp index, local index, newarray type, or for the quick forms:
+      getfield, putfield: the descriptor of the field
+    */
+    pub a: usize,
+    /*
+    immediate, iinc constant, branch target, switch index, dimensions,
+    the count of invokeinterface, the slot of ldc and invokevirtual, or
+      getfield, putfield: the offset of the field
+    */
+    pub b: i32,
+}
+
+pub enum Switch {
+    Table {
+        low: i32,
+        targets: Vec<i32>,
+        default: i32,
+    },
+    //sorted by the key, JVM spec 6.5 lookupswitch
+    Lookup {
+        pairs: Vec<(i32, i32)>,
+        default: i32,
+    },
+}
+
+impl Switch {
+    pub fn target(&self, key: i32) -> i32 {
+        match self {
+            Switch::Table {
+                low,
+                targets,
+                default,
+            } => {
+                let i = key as i64 - *low as i64;
+                if i >= 0 && (i as usize) < targets.len() {
+                    targets[i as usize]
+                } else {
+                    *default
+                }
+            }
+            Switch::Lookup { pairs, default } => {
+                match pairs.binary_search_by_key(&key, |it| it.0) {
+                    Ok(i) => pairs[i].1,
+                    Err(_) => *default,
+                }
+            }
+        }
+    }
+}
+
+//the value an instruction resolved to, kept by its quick form
+pub enum Resolved {
+    Method(MethodIdRef),
+    Constant(Oop),
+}
+
+pub struct Code {
+    insns: Vec<AtomicU64>,
+    //the decoded forms, for deoptimize
+    decoded: Vec<u64>,
+    switches: Vec<Switch>,
+    slots: Vec<OnceLock<Resolved>>,
+    epoch: usize,
+    //quicken and deoptimize, true once deoptimized
+    deoptimized: Mutex<bool>,
+}
+
+impl Code {
+    pub fn empty() -> Self {
+        Self::new(&[])
+    }
+
+    pub fn new(code: &[U1]) -> Self {
+        let epoch = EPOCH.load(Ordering::SeqCst);
+        let mut decoder = Decoder {
+            code,
+            pc: 0,
+            switches: Vec::new(),
+            n_slots: 0,
+        };
+        let mut decoded = vec![0; code.len()];
+        while decoder.pc < code.len() {
+            let pc = decoder.pc;
+            let insn = decoder.decode();
+            decoded[pc] = pack(insn.op, insn.next as u8, insn.a, insn.b);
+        }
+
+        let insns = decoded.iter().map(|it| AtomicU64::new(*it)).collect();
+        let slots = (0..decoder.n_slots).map(|_| OnceLock::new()).collect();
+        Self {
+            insns,
+            decoded,
+            switches: decoder.switches,
+            slots,
+            epoch,
+            deoptimized: Mutex::new(false),
+        }
+    }
+
+    #[inline]
+    pub fn fetch(&self, pc: i32) -> Insn {
+        let v = self.insns[pc as usize].load(Ordering::Acquire);
+        if is_quick(v as u8) && self.is_stale() {
+            self.deoptimize();
+            return unpack(pc, self.decoded[pc as usize]);
+        }
+        unpack(pc, v)
+    }
+
+    //decoded before deoptimize_all
+    #[inline]
+    pub fn is_stale(&self) -> bool {
+        self.epoch != EPOCH.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn switch(&self, i: i32) -> &Switch {
+        &self.switches[i as usize]
+    }
+
+    //the value of a quick form, set before the quick form is visible
+    #[inline]
+    pub fn resolved(&self, slot: i32) -> &Resolved {
+        self.slots[slot as usize].get().unwrap()
+    }
+
+    //rewrite the instruction at 'pc', the value kept in its slot if any
+    pub fn quicken(&self, pc: i32, op: u8, a: usize, b: i32, v: Option<Resolved>) {
+        let deoptimized = self.deoptimized.lock().unwrap();
+        if *deoptimized || self.is_stale() {
+            return;
+        }
+
+        let insn = self.insns[pc as usize].load(Ordering::Acquire);
+        if let Some(v) = v {
+            //set once, by the first thread resolving it
+            let slot = unpack(pc, insn).b;
+            let _ = self.slots[slot as usize].set(v);
+        }
+        let quick = pack(op, (insn >> 8) as u8, a, b);
+        self.insns[pc as usize].store(quick, Ordering::Release);
+    }
+
+    //the decoded forms back, never quickened again
+    fn deoptimize(&self) {
+        let mut deoptimized = self.deoptimized.lock().unwrap();
+        *deoptimized = true;
+        for (insn, v) in self.insns.iter().zip(self.decoded.iter()) {
+            insn.store(*v, Ordering::Release);
+        }
+    }
+}
+
+#[inline]
+fn is_quick(op: u8) -> bool {
+    (GETFIELD_QUICK..=LDC_QUICK).contains(&op)
+}
+
+/*
+op: 8 bits, the length: 8 bits, a: 16 bits, b: 32 bits
+
+The length of a switch doesn't fit, it always branches.
+*/
+fn pack(op: u8, len: u8, a: usize, b: i32) -> u64 {
+    (op as u64) | ((len as u64) << 8) | ((a as u16 as u64) << 16) | ((b as u32 as u64) << 32)
+}
+
+#[inline]
+fn unpack(pc: i32, v: u64) -> Insn {
+    Insn {
+        op: v as u8,
+        pc,
+        next: pc + ((v >> 8) as u8 as i32),
+        a: (v >> 16) as u16 as usize,
+        b: (v >> 32) as u32 as i32,
+    }
+}
+
+struct Decoder<'a> {
+    code: &'a [U1],
+    pc: usize,
+    switches: Vec<Switch>,
+    n_slots: usize,
+}
+
+impl<'a> Decoder<'a> {
+    //the instruction at pc, 'next' is its length until packed
+    fn decode(&mut self) -> Insn {
+        let pc = self.pc;
+        let op = self.u1();
+        let mut insn = Insn {
+            op,
+            pc: pc as i32,
+            next: 0,
+            a: 0,
+            b: 0,
+        };
+
+        match OpCode::from(op) {
+            OpCode::bipush => insn.b = self.u1() as i8 as i32,
+            OpCode::sipush => insn.b = self.u2() as i16 as i32,
+            OpCode::ldc => {
+                insn.a = self.u1() as usize;
+                insn.b = self.new_slot();
+            }
+            OpCode::ldc_w | OpCode::ldc2_w => {
+                insn.a = self.u2() as usize;
+                insn.b = self.new_slot();
+            }
+            OpCode::iload
+            | OpCode::lload
+            | OpCode::fload
+            | OpCode::dload
+            | OpCode::aload
+            | OpCode::istore
+            | OpCode::lstore
+            | OpCode::fstore
+            | OpCode::dstore
+            | OpCode::astore
+            | OpCode::ret => insn.a = self.u1() as usize,
+            OpCode::iinc => {
+                insn.a = self.u1() as usize;
+                insn.b = self.u1() as i8 as i32;
+            }
+            OpCode::ifeq
+            | OpCode::ifne
+            | OpCode::iflt
+            | OpCode::ifge
+            | OpCode::ifgt
+            | OpCode::ifle
+            | OpCode::if_icmpeq
+            | OpCode::if_icmpne
+            | OpCode::if_icmplt
+            | OpCode::if_icmpge
+            | OpCode::if_icmpgt
+            | OpCode::if_icmple
+            | OpCode::if_acmpeq
+            | OpCode::if_acmpne
+            | OpCode::goto
+            | OpCode::jsr
+            | OpCode::ifnull
+            | OpCode::ifnonnull => insn.b = pc as i32 + self.u2() as i16 as i32,
+            OpCode::goto_w | OpCode::jsr_w => insn.b = pc as i32 + self.u4(),
+            OpCode::tableswitch => insn.b = self.table_switch(pc),
+            OpCode::lookupswitch => insn.b = self.lookup_switch(pc),
+            OpCode::getstatic
+            | OpCode::putstatic
+            | OpCode::getfield
+            | OpCode::putfield
+            | OpCode::invokespecial
+            | OpCode::invokestatic
+            | OpCode::new
+            | OpCode::anewarray
+            | OpCode::checkcast
+            | OpCode::instanceof => insn.a = self.u2() as usize,
+            OpCode::invokevirtual => {
+                insn.a = self.u2() as usize;
+                insn.b = self.new_slot();
+            }
+            OpCode::invokeinterface => {
+                insn.a = self.u2() as usize;
+                insn.b = self.u1() as i32;
+                let _zero = self.u1();
+            }
+            OpCode::invokedynamic => {
+                insn.a = self.u2() as usize;
+                let _zero = self.u2();
+            }
+            OpCode::newarray => insn.a = self.u1() as usize,
+            OpCode::multianewarray => {
+                insn.a = self.u2() as usize;
+                insn.b = self.u1() as i32;
+            }
+            OpCode::wide => {
+                insn.op = self.u1();
+                insn.a = self.u2() as usize;
+                if insn.op == OpCode::iinc as u8 {
+                    insn.b = self.u2() as i16 as i32;
+                }
+            }
+            _ => (),
+        }
+
+        insn.next = (self.pc - pc) as i32;
+        insn
+    }
+
+    fn table_switch(&mut self, pc: usize) -> i32 {
+        self.align();
+        let default = pc as i32 + self.u4();
+        let low = self.u4();
+        let high = self.u4();
+        let targets = (low..=high).map(|_| pc as i32 + self.u4()).collect();
+        self.switches.push(Switch::Table {
+            low,
+            targets,
+            default,
+        });
+        self.switches.len() as i32 - 1
+    }
+
+    fn lookup_switch(&mut self, pc: usize) -> i32 {
+        self.align();
+        let default = pc as i32 + self.u4();
+        let n = self.u4();
+        let mut pairs: Vec<(i32, i32)> = (0..n)
+            .map(|_| {
+                let key = self.u4();
+                (key, pc as i32 + self.u4())
+            })
+            .collect();
+        pairs.sort_by_key(|it| it.0);
+        self.switches.push(Switch::Lookup { pairs, default });
+        self.switches.len() as i32 - 1
+    }
+
+    //the padding of a switch, its operands are 4 bytes aligned
+    fn align(&mut self) {
+        self.pc = (self.pc + 3) & !3;
+    }
+
+    fn new_slot(&mut self) -> i32 {
+        self.n_slots += 1;
+        self.n_slots as i32 - 1
+    }
+
+    fn u1(&mut self) -> u8 {
+        let v = self.code[self.pc];
+        self.pc += 1;
+        v
+    }
+
+    fn u2(&mut self) -> u16 {
+        let v = u16::from_be_bytes([self.code[self.pc], self.code[self.pc + 1]]);
+        self.pc += 2;
+        v
+    }
+
+    fn u4(&mut self) -> i32 {
+        let v = &self.code[self.pc..self.pc + 4];
+        self.pc += 4;
+        i32::from_be_bytes([v[0], v[1], v[2], v[3]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_decode() {
+        let code = [
+            0x10, 0xfe, // 0: bipush -2
+            0xc4, 0x84, 0x01, 0x00, 0xff, 0xff, // 2: wide iinc 256, -1
+            0x99, 0xff, 0xfa, // 8: ifeq 2
+            0xab, 0x00, 0x00, 0x00, 0x0f, // 11: lookupswitch, default 26
+            0x00, 0x00, 0x00, 0x02, // npairs
+            0x00, 0x00, 0x00, 0x07, 0x00, 0x00, 0x00, 0x0e, // 7 -> 25
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, // -1 -> 11
+            0xb1, // 36: return
+        ];
+        let code = Code::new(&code);
+
+        let insn = code.fetch(0);
+        assert_eq!((insn.op, insn.b, insn.next), (OpCode::bipush as u8, -2, 2));
+        let insn = code.fetch(2);
+        assert_eq!(insn.op, OpCode::iinc as u8);
+        assert_eq!((insn.a, insn.b, insn.next), (256, -1, 8));
+        let insn = code.fetch(8);
+        assert_eq!((insn.b, insn.next), (2, 11));
+
+        let insn = code.fetch(11);
+        let switch = code.switch(insn.b);
+        assert_eq!(switch.target(7), 25);
+        assert_eq!(switch.target(-1), 11);
+        assert_eq!(switch.target(0), 26);
+        assert_eq!(code.fetch(36).op, OpCode::return_void as u8);
+    }
+
+    #[test]
+    fn t_quicken() {
+        //ldc 1; ldc 2
+        let code = Code::new(&[0x12, 0x01, 0x12, 0x02]);
+        code.quicken(
+            2,
+            LDC_QUICK,
+            0,
+            1,
+            Some(Resolved::Constant(Oop::new_int(3))),
+        );
+        let insn = code.fetch(2);
+        assert_eq!((insn.op, insn.next), (LDC_QUICK, 4));
+        match code.resolved(insn.b) {
+            Resolved::Constant(v) => assert_eq!(v.extract_int(), 3),
+            _ => unreachable!(),
+        }
+
+        deoptimize_all();
+        assert!(code.is_stale());
+        let insn = code.fetch(2);
+        assert_eq!((insn.op, insn.a), (OpCode::ldc as u8, 2));
+        //not quickened again
+        code.quicken(0, LDC_QUICK, 0, 0, Some(Resolved::Constant(Oop::Null)));
+        assert_eq!(code.fetch(0).op, OpCode::ldc as u8);
+    }
+}
@@ -1,27 +1,72 @@
 use crate::native;
+use crate::new_br;
 use crate::oop::class::ClassPtr;
-use crate::oop::{self, Class, ValueType};
-use crate::runtime::{self, ClassPathResult};
+use crate::oop::{self, Class, Oop, ValueType};
+use crate::runtime::{self, exception, thread, ClassPathResult, DataArea, JavaCall};
 use crate::types::*;
 use crate::util;
 use class_parser::parse_class;
-use classfile::{constant_pool, BytesRef, ConstantPool, U2};
-use std::sync::{Arc, Mutex};
+use classfile::{constant_pool, consts as cls_consts, BytesRef, ClassFile, ConstantPool, U2};
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex, RwLock};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum ClassLoader {
     Base,
     Bootstrap,
+    //an instance of java.lang.ClassLoader, the index in LOADERS
+    User(u32),
+}
+
+#[derive(Default)]
+struct Loaders {
+    oops: Vec<Oop>,
+    //by the address of the handle, the object may be moved
+    ids: FxHashMap<usize, u32>,
+}
+
+lazy_static! {
+    //the loaders are never unloaded, so are the classes
+    static ref LOADERS: RwLock<Loaders> = RwLock::new(Loaders::default());
 }
 
 pub fn require_class(class_loader: Option<ClassLoader>, name: &BytesRef) -> Option<ClassRef> {
     require_class3(class_loader, name.as_slice())
 }
 
-pub fn require_class2(index: U2, cp: &ConstantPool) -> Option<ClassRef> {
+//'class_loader' is the loader of the class which the 'cp' belongs to,
+//NoClassDefFoundError is thrown if not found
+pub fn require_class2(
+    class_loader: Option<ClassLoader>,
+    index: U2,
+    cp: &ConstantPool,
+) -> Option<ClassRef> {
     let class = constant_pool::get_class_name(cp, index as usize);
     // trace!("require_class2 class = {}", String::from_utf8_lossy(class.as_slice()));
-    require_class3(None, class.as_slice())
+    let r = require_class3(class_loader, class.as_slice());
+    if r.is_none() {
+        no_class_def_found(class.as_slice());
+    }
+    r
+}
+
+//the ClassNotFoundException thrown by ClassLoader.loadClass is replaced too
+pub fn no_class_def_found(name: &[u8]) {
+    let jt = thread::current_java_thread();
+    let replace = match &jt.read().unwrap().ex {
+        Some(ex) => {
+            let rf = ex.extract_ref();
+            let cls = rf.extract_inst().class.get_class();
+            cls.name.as_slice() == cls_consts::J_CLASS_NOT_FOUND
+        }
+        None => true,
+    };
+
+    if replace {
+        let _ = jt.write().unwrap().take_ex();
+        let msg = String::from_utf8_lossy(name).to_string();
+        exception::meet_ex(cls_consts::J_NO_CLASS_DEF_FOUND_ERROR, Some(msg));
+    }
 }
 
 pub fn require_class3(class_loader: Option<ClassLoader>, name: &[u8]) -> Option<ClassRef> {
@@ -29,19 +74,90 @@ pub fn require_class3(class_loader: Option<ClassLoader>, name: &[u8]) -> Option<
     class_loader.load_class(name)
 }
 
+//define a class built in memory, such as the spun lambda class, the anonymous class,
+//the anonymous class has the access of its 'host',
+//None if failed to link, the exception is pending
+pub fn define_class(
+    class_loader: Option<ClassLoader>,
+    name: &[u8],
+    cf: ClassFile,
+    host: Option<ClassRef>,
+) -> Option<ClassRef> {
+    let cfr = Arc::new(Box::new(cf));
+    let mut class = Class::new_class(cfr, class_loader);
+    if let oop::class::ClassKind::Instance(cls_obj) = &mut class.kind {
+        cls_obj.host = host;
+    }
+    let class = ClassPtr::new(class);
+
+    //the class refers to itself by name
+    runtime::sys_dic_put(
+        class_loader.unwrap_or(ClassLoader::Bootstrap),
+        name,
+        class.clone(),
+    );
+    {
+        let this_ref = class.clone();
+        let cls = class.get_mut_class();
+        cls.set_class_state(oop::class::State::Loaded);
+        if cls.link_class(this_ref).is_err() {
+            runtime::sys_dic_remove(class_loader.unwrap_or(ClassLoader::Bootstrap), name);
+            return None;
+        }
+    }
+    native::java_lang_Class::create_mirror(class.clone());
+
+    Some(class)
+}
+
 impl ClassLoader {
+    //null is the bootstrap loader
+    pub fn from_oop(loader: &Oop) -> Option<ClassLoader> {
+        let rf = match loader {
+            Oop::Null => return None,
+            v => v.extract_ref(),
+        };
+
+        let ptr = Arc::as_ptr(&rf) as usize;
+        if let Some(id) = LOADERS.read().unwrap().ids.get(&ptr) {
+            return Some(ClassLoader::User(*id));
+        }
+
+        //another thread may have registered it in the meantime
+        let mut loaders = LOADERS.write().unwrap();
+        if let Some(id) = loaders.ids.get(&ptr) {
+            return Some(ClassLoader::User(*id));
+        }
+        let id = loaders.oops.len() as u32;
+        loaders.oops.push(loader.clone());
+        loaders.ids.insert(ptr, id);
+        Some(ClassLoader::User(id))
+    }
+
+    //the java.lang.ClassLoader instance, null for the bootstrap loader
+    pub fn get_oop(&self) -> Oop {
+        match self {
+            ClassLoader::User(id) => {
+                let loaders = LOADERS.read().unwrap();
+                loaders.oops[*id as usize].clone()
+            }
+            _ => Oop::Null,
+        }
+    }
+
     fn load_class(&self, name: &[u8]) -> Option<ClassRef> {
         debug_assert!(!name.contains(&b'.'));
         //        error!("load_class name = {}", String::from_utf8_lossy(name));
         match self {
             ClassLoader::Base => (),
             ClassLoader::Bootstrap => {
-                let it = runtime::sys_dic_find(name);
+                let it = runtime::sys_dic_find(*self, name);
                 if it.is_some() {
                     //                    info!("load_class in dic: {}", String::from_utf8_lossy(name));
                     return it;
                 }
             }
+            ClassLoader::User(_) => return self.load_class_by_user(name),
         }
 
         if is_array(name) {
@@ -51,14 +167,24 @@ impl ClassLoader {
             if let Some(class) = &class {
                 match self {
                     ClassLoader::Base => (),
-                    ClassLoader::Bootstrap => {
-                        runtime::sys_dic_put(name, class.clone());
+                    ClassLoader::Bootstrap | ClassLoader::User(_) => {
+                        runtime::sys_dic_put(*self, name, class.clone());
                         let this_ref = class.clone();
 
-                        {
+                        let linked = {
                             let mut cls = class.get_mut_class();
                             cls.set_class_state(oop::class::State::Loaded);
-                            cls.link_class(this_ref);
+                            cls.link_class(this_ref)
+                        };
+                        if linked.is_err() {
+                            runtime::sys_dic_remove(*self, name);
+                            return None;
+                        }
+
+                        if let Err(msg) = runtime::verifier::verify(class) {
+                            runtime::sys_dic_remove(*self, name);
+                            exception::meet_ex(cls_consts::J_VERIFY_ERROR, Some(msg));
+                            return None;
                         }
 
                         native::java_lang_Class::create_mirror(class.clone());
@@ -85,13 +211,14 @@ impl ClassLoader {
                                 {
                                     let this_ref = class.clone();
                                     let mut class = class.get_mut_class();
-                                    class.link_class(this_ref);
+                                    //not registered, the LinkageError is pending
+                                    if class.link_class(this_ref).is_err() {
+                                        return None;
+                                    }
                                 }
                                 match self {
                                     ClassLoader::Base => (),
-                                    ClassLoader::Bootstrap => {
-                                        runtime::sys_dic_put(name, class.clone());
-                                    }
+                                    _ => runtime::sys_dic_put(*self, name, class.clone()),
                                 }
 
                                 native::java_lang_Class::create_mirror(class.clone());
@@ -111,14 +238,14 @@ impl ClassLoader {
                         {
                             let this_ref = class.clone();
                             let mut class = class.get_mut_class();
-                            class.link_class(this_ref);
+                            if class.link_class(this_ref).is_err() {
+                                return None;
+                            }
                         }
 
                         match self {
                             ClassLoader::Base => (),
-                            ClassLoader::Bootstrap => {
-                                runtime::sys_dic_put(name, class.clone());
-                            }
+                            _ => runtime::sys_dic_put(*self, name, class.clone()),
                         }
 
                         //mirror has been created when vm inited
@@ -139,9 +266,7 @@ impl ClassLoader {
                         let class = ClassPtr::new(class);
                         match self {
                             ClassLoader::Base => (),
-                            ClassLoader::Bootstrap => {
-                                runtime::sys_dic_put(name, class.clone());
-                            }
+                            _ => runtime::sys_dic_put(*self, name, class.clone()),
                         }
 
                         native::java_lang_Class::create_mirror(class.clone());
@@ -155,17 +280,106 @@ impl ClassLoader {
         }
     }
 
+    /*
+    The user loader is the initiating loader, the class is defined by
+    ClassLoader.loadClass, which may delegate to the parent loaders.
+    An array class is defined by the loader of its element class.
+    */
+    fn load_class_by_user(&self, name: &[u8]) -> Option<ClassRef> {
+        if let Some(class) = runtime::sys_dic_find(*self, name) {
+            return Some(class);
+        }
+
+        let class = if is_array(name) {
+            let elm = name.iter().position(|&c| c != b'[').unwrap();
+            let defining = match name[elm] {
+                b'L' => {
+                    let elm = self.load_class(&name[elm + 1..name.len() - 1])?;
+                    let elm = elm.get_class();
+                    elm.class_loader.unwrap_or(ClassLoader::Bootstrap)
+                }
+                _ => ClassLoader::Bootstrap,
+            };
+
+            if defining == *self {
+                self.load_array_class(name)
+            } else {
+                defining.load_class(name)
+            }
+        } else {
+            self.call_load_class(name)
+        }?;
+
+        if runtime::loader_constraints::check(*self, name, Some(&class)).is_err() {
+            return None;
+        }
+        runtime::sys_dic_put(*self, name, class.clone());
+
+        Some(class)
+    }
+
+    //ClassLoader.loadClass(String)
+    fn call_load_class(&self, name: &[u8]) -> Option<ClassRef> {
+        let loader = self.get_oop();
+        let mir = {
+            let cls = {
+                let rf = loader.extract_ref();
+                let inst = rf.extract_inst();
+                inst.class.clone()
+            };
+            let cls = cls.get_class();
+            cls.get_virtual_method(
+                &new_br("loadClass"),
+                &new_br("(Ljava/lang/String;)Ljava/lang/Class;"),
+            )
+            .ok()?
+        };
+
+        let name = String::from_utf8_lossy(name).replace('/', ".");
+        let name = util::oop::new_java_lang_string2(&name);
+        let area = DataArea::new();
+        let mut jc = JavaCall::new_with_args(mir, vec![loader, name]);
+        jc.invoke(Some(&area), true);
+        if thread::is_meet_ex() {
+            return None;
+        }
+
+        let v = area.return_v.borrow_mut().take().unwrap();
+        match v {
+            Oop::Null => None,
+            v => {
+                let rf = v.extract_ref();
+                let mirror = rf.extract_mirror();
+                mirror.target.clone()
+            }
+        }
+    }
+
     fn load_class_from_path(&self, name: &[u8]) -> Option<ClassRef> {
         let name = unsafe { std::str::from_utf8_unchecked(name) };
         match runtime::find_class_in_classpath(name) {
             Ok(ClassPathResult(_, buf)) => match parse_class(&buf) {
-                Ok(cf) => {
-                    let cfr = Arc::new(Box::new(cf.1));
+                Ok((_, cf)) => {
+                    if let Err((cls, msg)) = runtime::verifier::check_format(&cf) {
+                        exception::meet_ex(cls, Some(msg));
+                        return None;
+                    }
+
+                    let cfr = Arc::new(Box::new(cf));
                     let class = Class::new_class(cfr, Some(*self));
                     Some(ClassPtr::new(class))
                 }
 
-                Err(e) => unreachable!("name={}, {}", name, e),
+                Err(e) => {
+                    let reason = if e.is_incomplete() {
+                        "Truncated class file"
+                    } else {
+                        "Malformed class file"
+                    };
+                    let msg = format!("{} in class file {}", reason, name);
+                    exception::meet_ex(cls_consts::J_CLASS_FORMAT_ERROR, Some(msg));
+                    None
+                }
             },
 
             Err(_) => None,
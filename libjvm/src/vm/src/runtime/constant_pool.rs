@@ -4,13 +4,18 @@ use rustc_hash::FxHashMap;
 
 use classfile::ConstantPool;
 
-use crate::oop::field;
-use crate::types::{FieldIdRef, MethodIdRef};
+use crate::oop::{field, Oop};
+use crate::runtime::indy::{self, CallSiteRef};
+use crate::runtime::ClassLoader;
+use crate::types::{ClassRef, FieldIdRef, MethodIdRef};
 use crate::{oop, runtime};
+use std::sync::Arc;
 
 enum CacheType {
     Field(FieldIdRef),
     Method(MethodIdRef),
+    //resolved MethodHandle & MethodType, or the patched String of anonymous class
+    Constant(Oop),
 }
 
 impl CacheType {
@@ -27,31 +32,51 @@ impl CacheType {
             _ => unreachable!(),
         }
     }
+
+    fn extract_constant(&self) -> Oop {
+        match self {
+            CacheType::Constant(v) => v.clone(),
+            _ => unreachable!(),
+        }
+    }
 }
 
 pub struct ConstantPoolCache {
+    //the defining loader of the class, resolves the symbolic references
+    class_loader: Option<ClassLoader>,
     cp: ConstantPool,
     cache: RefCell<FxHashMap<usize, CacheType>>,
+    //(method offset, pc of invokedynamic) -> CallSite
+    call_sites: RefCell<FxHashMap<(usize, usize), CallSiteRef>>,
 }
 
 impl ConstantPoolCache {
-    pub fn new(cp: ConstantPool) -> Self {
+    pub fn new(class_loader: Option<ClassLoader>, cp: ConstantPool) -> Self {
         Self {
+            class_loader,
             cp,
             cache: RefCell::new(FxHashMap::default()),
+            call_sites: RefCell::new(FxHashMap::default()),
         }
     }
 
-    pub fn get_field(&self, idx: usize, is_static: bool) -> FieldIdRef {
+    //'class' owns the pool, and accesses the resolved members,
+    //Err, if an exception occurred in resolving
+    pub fn get_field(
+        &self,
+        class: &ClassRef,
+        idx: usize,
+        is_static: bool,
+    ) -> Result<FieldIdRef, ()> {
         let cache = self.cache.borrow();
         let it = cache.get(&idx);
         match it {
-            Some(it) => it.extract_field(),
+            Some(it) => Ok(it.extract_field()),
             None => {
                 drop(cache);
-                let fid = field::get_field_ref(&self.cp, idx, is_static);
+                let fid = field::get_field_ref(class, &self.cp, idx, is_static)?;
                 self.cache_field(idx, fid.clone());
-                fid
+                Ok(fid)
             }
         }
     }
@@ -62,16 +87,18 @@ impl ConstantPoolCache {
         cache.insert(k, v);
     }
 
-    pub fn get_method(&self, idx: usize) -> MethodIdRef {
+    //'class' owns the pool, and accesses the resolved members,
+    //Err, if an exception occurred in resolving
+    pub fn get_method(&self, class: &ClassRef, idx: usize) -> Result<MethodIdRef, ()> {
         let cache = self.cache.borrow();
         let it = cache.get(&idx);
         match it {
-            Some(it) => it.extract_method(),
+            Some(it) => Ok(it.extract_method()),
             None => {
                 drop(cache);
-                let m = runtime::method::get_method_ref(&self.cp, idx).unwrap();
+                let m = runtime::method::get_method_ref(class, &self.cp, idx)?;
                 self.cache_method(idx, m.clone());
-                m
+                Ok(m)
             }
         }
     }
@@ -81,4 +108,49 @@ impl ConstantPoolCache {
         let v = CacheType::Method(v);
         cache.insert(k, v);
     }
+
+    pub fn find_constant(&self, idx: usize) -> Option<Oop> {
+        let cache = self.cache.borrow();
+        cache.get(&idx).map(|it| it.extract_constant())
+    }
+
+    //None, if an exception occurred in resolving
+    pub fn get_constant<F>(&self, idx: usize, resolve: F) -> Option<Oop>
+    where
+        F: FnOnce() -> Option<Oop>,
+    {
+        match self.find_constant(idx) {
+            Some(v) => Some(v),
+            None => {
+                let v = resolve()?;
+                self.put_constant(idx, v.clone());
+                Some(v)
+            }
+        }
+    }
+
+    pub fn put_constant(&self, k: usize, v: Oop) {
+        let mut cache = self.cache.borrow_mut();
+        cache.insert(k, CacheType::Constant(v));
+    }
+
+    //every invokedynamic instruction is a distinct call site, JVM spec 6.5
+    pub fn get_call_site(
+        &self,
+        host: &ClassRef,
+        k: (usize, usize),
+        idx: usize,
+    ) -> Result<CallSiteRef, ()> {
+        let call_sites = self.call_sites.borrow();
+        match call_sites.get(&k) {
+            Some(it) => Ok(it.clone()),
+            None => {
+                drop(call_sites);
+                let site = Arc::new(indy::resolve(host, &self.cp, idx)?);
+                let mut call_sites = self.call_sites.borrow_mut();
+                call_sites.insert(k, site.clone());
+                Ok(site)
+            }
+        }
+    }
 }
@@ -1,2 +1,7 @@
-pub const THREAD_MAX_STACK_FRAMES: usize = 512;
-// pub const WARN_THREAD_MAX_STACK_FRAMES: usize = 512;
+//the stack left for creating StackOverflowError, at most
+pub const THREAD_STACK_RESERVED_ZONE: usize = 128 * 1024;
+//the stack size of a thread when it can't be known, the default of hotspot
+pub const THREAD_DEFAULT_STACK_SIZE: usize = 1024 * 1024;
+//the stack accounted for a java frame, the java stack of a thread is bounded
+//by its native stack, but the frames are not on it
+pub const THREAD_STACK_FRAME_SIZE: usize = 128;
@@ -1,8 +1,6 @@
 use crate::oop::Oop;
-use crate::runtime::local::Local;
-use crate::runtime::stack::Stack;
+use crate::runtime::stack::{self, Stack};
 use std::cell::RefCell;
-use std::sync::{Arc, RwLock};
 
 /*
 The origin of DataArea
@@ -15,26 +13,33 @@ If an exception occurs, jvm_fillInStackTrace traverses the current thread frames
 extract the class name, method name, and pc (pc for LineNumberTable Attributes from each frame)
 Locate the error line of code) and construct an exception stack.
 
-The DataArea in the Frame is wrapped with RefCell, so that java_call::invoke_java can execute Java
-Method, you can use the read-only frame to execute bytecode; when there is an exception, you can also let
-jvm_fillInStackTrace traverse the frames to get the necessary information.
-The nature of RefCell makes this possible.
-In a read-only Frame context, to modify the DataArea, borrow_mut is fine.
+The locals and the operand stack of a frame are on the value stack of the
+thread, see runtime::stack. The operand stack of the running frame is the
+top of it, the args are popped and the return value pushed there, also for
+the areas of the natives calling java, they are balanced.
 */
 pub struct DataArea {
-    pub stack: RefCell<Stack>,
     pub return_v: RefCell<Option<Oop>>,
 }
 
 unsafe impl Sync for DataArea {}
 
 impl DataArea {
-    pub fn new(max_stack: usize) -> Self {
-        let stack = RefCell::new(Stack::new(max_stack));
-
+    pub fn new() -> Self {
         Self {
-            stack,
             return_v: RefCell::new(None),
         }
     }
+
+    //the operand stack of the running frame
+    #[inline]
+    pub fn stack(&self) -> &'static mut Stack {
+        stack::current()
+    }
+}
+
+impl Default for DataArea {
+    fn default() -> Self {
+        Self::new()
+    }
 }
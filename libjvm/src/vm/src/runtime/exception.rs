@@ -2,7 +2,24 @@ use crate::oop::{self, Oop};
 use crate::runtime::{self, require_class3};
 use crate::types::JavaThreadRef;
 use crate::{new_br, util};
+use classfile::consts as cls_consts;
 use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+lazy_static! {
+    //thrown when creating a StackOverflowError overflows too
+    static ref STACK_OVERFLOW_ERROR: Mutex<Oop> = Mutex::new(Oop::Null);
+}
+
+//the errors which can't be created when they are thrown, at the start of the vm
+pub fn preallocate() {
+    let ex = new(cls_consts::J_SOE, None);
+    *STACK_OVERFLOW_ERROR.lock().unwrap() = ex;
+}
+
+pub fn preallocated_stack_overflow_error() -> Oop {
+    STACK_OVERFLOW_ERROR.lock().unwrap().clone()
+}
 
 pub fn new(name: &[u8], msg: Option<String>) -> Oop {
     let cls = match require_class3(None, name) {
@@ -10,8 +27,7 @@ pub fn new(name: &[u8], msg: Option<String>) -> Oop {
         None => panic!("ClassNotFound: {}", String::from_utf8_lossy(name)),
     };
 
-    oop::class::init_class(&cls);
-    oop::class::init_class_fully(&cls);
+    let _ = oop::class::init_class(&cls);
 
     let ex = Oop::new_inst(cls.clone());
 
@@ -33,15 +49,30 @@ pub fn new(name: &[u8], msg: Option<String>) -> Oop {
     ex
 }
 
+//with the 'Throwable' arg ctor, such as ExceptionInInitializerError
+pub fn new_with_cause(name: &[u8], cause: Oop) -> Oop {
+    let cls = match require_class3(None, name) {
+        Some(cls) => cls,
+        None => panic!("ClassNotFound: {}", String::from_utf8_lossy(name)),
+    };
+
+    let _ = oop::class::init_class(&cls);
+
+    let ex = Oop::new_inst(cls.clone());
+    let args = vec![ex.clone(), cause];
+    runtime::invoke::invoke_ctor(cls, new_br("(Ljava/lang/Throwable;)V"), args);
+
+    ex
+}
+
 pub fn meet_ex(cls_name: &'static [u8], msg: Option<String>) {
     let jt = runtime::thread::current_java_thread();
-    {
-        let jt = jt.read().unwrap();
-        let frame = jt.frames.last().unwrap();
-        let frame = frame.try_read().unwrap();
-        frame.ex_here.store(true, Ordering::Relaxed);
-    }
-
     let ex = new(cls_name, msg);
     jt.write().unwrap().set_ex(ex);
 }
+
+pub fn meet_ex_with_cause(cls_name: &'static [u8], cause: Oop) {
+    let jt = runtime::thread::current_java_thread();
+    let ex = new_with_cause(cls_name, cause);
+    jt.write().unwrap().set_ex(ex);
+}
@@ -1,7 +1,8 @@
 use crate::oop;
+use crate::runtime::bytecode::Code;
 use crate::runtime::DataArea;
 use crate::types::*;
-use classfile::{ConstantPool, U1};
+use classfile::ConstantPool;
 use std::sync::Arc;
 
 pub struct Frame {
@@ -10,10 +11,9 @@ pub struct Frame {
     //avoid lock class to access cp
     pub cp: ConstantPool,
     pub mir: MethodIdRef,
-    pub code: Arc<Vec<U1>>,
+    pub code: Arc<Code>,
 
     pub pc: std::sync::atomic::AtomicI32,
-    pub ex_here: std::sync::atomic::AtomicBool,
 
     // The variable part of Frame is placed here
     pub area: DataArea,
@@ -30,37 +30,17 @@ impl Frame {
             cls_obj.class_file.cp.clone()
         };
         let pc = std::sync::atomic::AtomicI32::new(0);
-        let ex_here = std::sync::atomic::AtomicBool::new(false);
 
-        // trace!("method.code.is_some = {}", mir.method.code.is_some());
-        match &mir.method.code {
-            Some(code) => {
-                // trace!("max_locals = {}, max_stack = {}", code.max_locals, code.max_stack);
-                let area = DataArea::new(code.max_stack as usize);
-                let code = code.code.clone();
+        let code = mir.method.get_decoded_code();
 
-                Self {
-                    frame_id,
-                    class,
-                    cp,
-                    mir,
-                    code,
-                    pc,
-                    ex_here,
-                    area,
-                }
-            }
-
-            None => Self {
-                frame_id,
-                class,
-                cp: Arc::new(Vec::new()),
-                mir,
-                code: Arc::new(vec![]),
-                pc,
-                ex_here,
-                area: DataArea::new(0),
-            },
+        Self {
+            frame_id,
+            class,
+            cp,
+            mir,
+            code,
+            pc,
+            area: DataArea::new(),
         }
     }
 }
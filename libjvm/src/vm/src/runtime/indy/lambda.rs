@@ -0,0 +1,665 @@
+use super::{static_arg_int, static_arg_method_type, BootstrapInfo};
+use crate::oop::{self, Class, Oop};
+use crate::runtime::{self, exception, invoke, DataArea};
+use crate::types::{ClassRef, FieldIdRef};
+use class_parser::MethodSignature;
+use classfile::attributes::Code;
+use classfile::{
+    constant_pool, consts as cls_const, flags::*, AttributeType, BytesRef, ClassFile, ConstantPool,
+    ConstantPoolType, FieldInfo, MethodInfo, OpCode, SignatureType, Version, U2,
+};
+use rustc_hash::FxHashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+//LambdaMetafactory.altMetafactory flags
+const FLAG_SERIALIZABLE: i32 = 1;
+const FLAG_MARKERS: i32 = 1 << 1;
+const FLAG_BRIDGES: i32 = 1 << 2;
+
+static LAMBDA_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+/*
+Like InnerClassLambdaMetafactory, a class is spun for every lambda call site:
+
+final class Host$$Lambda$N implements Itf {
+    private final T1 arg$1; //captured args
+    ...
+    public R sam(P1 p1, ...) {
+        return impl(arg$1, ..., p1, ...);
+    }
+}
+
+The captured args are stored into the fields directly when the call site
+is invoked, so no constructor is generated.
+*/
+pub struct LambdaSite {
+    factory: MethodSignature,
+    class: ClassRef,
+    fields: Vec<FieldIdRef>,
+    //non-capturing lambda, all evaluations share one instance
+    instance: Option<Oop>,
+}
+
+impl LambdaSite {
+    pub fn invoke(&self, caller: &DataArea) {
+        let v = match &self.instance {
+            Some(v) => v.clone(),
+            None => {
+                let args = invoke::build_args_from_caller_stack(caller, &self.factory);
+                let v = Oop::new_inst(self.class.clone());
+                let rf = v.extract_ref();
+                for (fid, arg) in self.fields.iter().zip(args) {
+                    Class::put_field_value(rf.clone(), fid.clone(), arg);
+                }
+                v
+            }
+        };
+
+        caller.stack().push_ref(v);
+    }
+}
+
+//the implementation method, a CONSTANT_MethodHandle_info
+struct Target {
+    kind: u8,
+    class: BytesRef,
+    name: BytesRef,
+    desc: BytesRef,
+    is_interface: bool,
+    sig: MethodSignature,
+}
+
+pub fn resolve(info: &BootstrapInfo, is_alt: bool) -> Result<LambdaSite, ()> {
+    let cp = info.cp;
+    let args = info.args;
+
+    let sam_desc = args.first().and_then(|it| static_arg_method_type(cp, *it));
+    let inst_desc = args.get(2).and_then(|it| static_arg_method_type(cp, *it));
+    let target = args.get(1).and_then(|it| match cp.get(*it as usize) {
+        Some(ConstantPoolType::MethodHandle {
+            ref_kind,
+            ref_index,
+        }) => {
+            let (tag, class_index, nt_idx) = constant_pool::get_method_ref(cp, *ref_index as usize);
+            let class = constant_pool::get_class_name(cp, class_index as usize).clone();
+            let (name, desc) = constant_pool::get_name_and_type(cp, nt_idx as usize);
+            Some(Target {
+                kind: *ref_kind,
+                class,
+                name: name.clone(),
+                desc: desc.clone(),
+                is_interface: tag == cls_const::CONSTANT_INTERFACE_METHOD_REF_TAG,
+                sig: MethodSignature::new(desc.as_slice()),
+            })
+        }
+        _ => None,
+    });
+
+    let (sam_desc, inst_desc, target) = match (sam_desc, inst_desc, target) {
+        (Some(sam_desc), Some(inst_desc), Some(target)) => (sam_desc, inst_desc, target),
+        _ => return link_error("LambdaMetafactory: bad static arguments".to_string()),
+    };
+
+    match target.kind {
+        cls_const::REF_INVOKE_VIRTUAL
+        | cls_const::REF_INVOKE_STATIC
+        | cls_const::REF_INVOKE_SPECIAL
+        | cls_const::REF_NEW_INVOKE_SPECIAL
+        | cls_const::REF_INVOKE_INTERFACE => (),
+        kind => return link_error(format!("LambdaMetafactory: bad ref_kind {}", kind)),
+    }
+
+    let factory = MethodSignature::new(info.desc.as_slice());
+    let itf = match &factory.retype {
+        SignatureType::Object(desc, _, _) => Arc::new(Vec::from(&desc[1..desc.len() - 1])),
+        _ => return link_error("LambdaMetafactory: bad factory type".to_string()),
+    };
+
+    let mut interfaces = vec![itf];
+    let mut descs = vec![sam_desc];
+    if is_alt {
+        match parse_alt_args(cp, args) {
+            Some((markers, bridges)) => {
+                interfaces.extend(markers);
+                descs.extend(bridges);
+            }
+            None => {
+                return link_error("LambdaMetafactory: bad altMetafactory arguments".to_string())
+            }
+        }
+    }
+
+    let host_name = { info.host.get_class().name.clone() };
+    let seq = LAMBDA_SEQ.fetch_add(1, Ordering::Relaxed);
+    let name = format!(
+        "{}$$Lambda${}",
+        String::from_utf8_lossy(host_name.as_slice()),
+        seq
+    );
+
+    let inst = MethodSignature::new(inst_desc.as_slice());
+    let cf = spin_class(
+        name.as_bytes(),
+        &interfaces,
+        &factory.args,
+        info.name,
+        &descs,
+        &target,
+        &inst,
+    )?;
+
+    let class = define_class(info.host, name.as_bytes(), cf).ok_or(())?;
+    let fields = {
+        let cls = class.get_class();
+        factory
+            .args
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let name = Arc::new(field_name(i));
+                let desc = Arc::new(to_desc(t));
+                cls.get_field_id(&name, &desc, false)
+            })
+            .collect()
+    };
+    let instance = if factory.args.is_empty() {
+        Some(Oop::new_inst(class.clone()))
+    } else {
+        None
+    };
+
+    Ok(LambdaSite {
+        factory,
+        class,
+        fields,
+        instance,
+    })
+}
+
+fn link_error<T>(msg: String) -> Result<T, ()> {
+    exception::meet_ex(cls_const::J_BOOTSTRAP_METHOD_ERROR, Some(msg));
+    Err(())
+}
+
+//altMetafactory(..., int flags, int markerCount, Class... markers, int bridgeCount, MethodType... bridges)
+fn parse_alt_args(cp: &ConstantPool, args: &[U2]) -> Option<(Vec<BytesRef>, Vec<BytesRef>)> {
+    let mut markers = Vec::new();
+    let mut bridges = Vec::new();
+
+    let flags = static_arg_int(cp, *args.get(3)?)?;
+    let mut pos = 4;
+    if flags & FLAG_MARKERS != 0 {
+        let n = static_arg_int(cp, *args.get(pos)?)?;
+        pos += 1;
+        for _ in 0..n {
+            let idx = *args.get(pos)? as usize;
+            markers.push(constant_pool::get_class_name(cp, idx).clone());
+            pos += 1;
+        }
+    }
+    if flags & FLAG_BRIDGES != 0 {
+        let n = static_arg_int(cp, *args.get(pos)?)?;
+        pos += 1;
+        for _ in 0..n {
+            bridges.push(static_arg_method_type(cp, *args.get(pos)?)?);
+            pos += 1;
+        }
+    }
+    if flags & FLAG_SERIALIZABLE != 0 {
+        markers.push(Arc::new(Vec::from(cls_const::J_SERIALIZABLE)));
+    }
+
+    Some((markers, bridges))
+}
+
+fn define_class(host: &ClassRef, name: &[u8], cf: ClassFile) -> Option<ClassRef> {
+    let class_loader = { host.get_class().class_loader };
+    let class = runtime::define_class(class_loader, name, cf, Some(host.clone()))?;
+
+    oop::class::init_class(&class).ok()?;
+
+    Some(class)
+}
+
+fn spin_class(
+    name: &[u8],
+    interfaces: &[BytesRef],
+    captured: &[SignatureType],
+    sam_name: &BytesRef,
+    descs: &[BytesRef],
+    target: &Target,
+    inst: &MethodSignature,
+) -> Result<ClassFile, ()> {
+    let mut sp = ClassSpinner::new();
+    let this_class = sp.class(name);
+    let super_class = sp.class(cls_const::J_OBJECT);
+    let interfaces = interfaces
+        .iter()
+        .map(|it| sp.class(it.as_slice()))
+        .collect();
+
+    let fields = captured
+        .iter()
+        .enumerate()
+        .map(|(i, t)| FieldInfo {
+            acc_flags: ACC_PRIVATE | ACC_FINAL,
+            name_index: sp.utf8(field_name(i).as_slice()),
+            desc_index: sp.utf8(to_desc(t).as_slice()),
+            attrs: vec![],
+        })
+        .collect();
+
+    let mut methods = Vec::with_capacity(descs.len());
+    for desc in descs {
+        let code = spin_method_body(&mut sp, name, captured, desc, target, inst)?;
+        methods.push(MethodInfo {
+            acc_flags: ACC_PUBLIC,
+            name_index: sp.utf8(sam_name.as_slice()),
+            desc_index: sp.utf8(desc.as_slice()),
+            attrs: vec![AttributeType::Code(code)],
+        });
+    }
+
+    Ok(ClassFile {
+        version: Version {
+            minor: 0,
+            major: 52,
+        },
+        cp: Arc::new(sp.cp),
+        acc_flags: ACC_FINAL | ACC_SUPER | ACC_SYNTHETIC,
+        this_class,
+        super_class,
+        interfaces,
+        fields,
+        methods,
+        attrs: vec![],
+    })
+}
+
+fn spin_method_body(
+    sp: &mut ClassSpinner,
+    this_name: &[u8],
+    captured: &[SignatureType],
+    desc: &BytesRef,
+    target: &Target,
+    inst: &MethodSignature,
+) -> Result<Code, ()> {
+    let sam = MethodSignature::new(desc.as_slice());
+    let mut code = Vec::new();
+
+    //the params of implementation method, 'this' included
+    let mut params = Vec::with_capacity(target.sig.args.len() + 1);
+    match target.kind {
+        cls_const::REF_INVOKE_VIRTUAL
+        | cls_const::REF_INVOKE_SPECIAL
+        | cls_const::REF_INVOKE_INTERFACE => {
+            params.push(object_type(target.class.as_slice()));
+        }
+        cls_const::REF_NEW_INVOKE_SPECIAL => {
+            let idx = sp.class(target.class.as_slice());
+            emit_u2(&mut code, OpCode::new, idx);
+            code.push(OpCode::dup as u8);
+        }
+        _ => (),
+    }
+    params.extend_from_slice(target.sig.args.as_slice());
+
+    if captured.len() + sam.args.len() != params.len() {
+        return link_error(format!(
+            "LambdaMetafactory: type mismatch, {} => {}",
+            String::from_utf8_lossy(desc.as_slice()),
+            String::from_utf8_lossy(target.desc.as_slice())
+        ));
+    }
+
+    let mut params = params.iter();
+
+    //captured args
+    for (i, t) in captured.iter().enumerate() {
+        code.push(OpCode::aload_0 as u8);
+        let idx = sp.field_ref(this_name, field_name(i).as_slice(), to_desc(t).as_slice());
+        emit_u2(&mut code, OpCode::getfield, idx);
+        convert(sp, &mut code, t, params.next().unwrap());
+    }
+
+    //sam args, instantiated type used as the hint of cast & unbox
+    let mut slot = 1;
+    for (i, t) in sam.args.iter().enumerate() {
+        code.push(load_op(t) as u8);
+        code.push(slot as u8);
+        slot += slots(t);
+
+        let hint = inst.args.get(i).unwrap_or(t);
+        convert(sp, &mut code, t, hint);
+        convert(sp, &mut code, hint, params.next().unwrap());
+    }
+
+    //invoke implementation method
+    let idx = sp.method_ref(
+        target.class.as_slice(),
+        target.name.as_slice(),
+        target.desc.as_slice(),
+        target.is_interface,
+    );
+    match target.kind {
+        cls_const::REF_INVOKE_STATIC => emit_u2(&mut code, OpCode::invokestatic, idx),
+        cls_const::REF_INVOKE_VIRTUAL => emit_u2(&mut code, OpCode::invokevirtual, idx),
+        cls_const::REF_INVOKE_INTERFACE => {
+            emit_u2(&mut code, OpCode::invokeinterface, idx);
+            let count: usize = 1 + target.sig.args.iter().map(slots).sum::<usize>();
+            code.push(count as u8);
+            code.push(0);
+        }
+        _ => emit_u2(&mut code, OpCode::invokespecial, idx),
+    }
+
+    //return
+    let ret = if target.kind == cls_const::REF_NEW_INVOKE_SPECIAL {
+        object_type(target.class.as_slice())
+    } else {
+        target.sig.retype.clone()
+    };
+    match &sam.retype {
+        SignatureType::Void => {
+            match slots(&ret) {
+                1 => code.push(OpCode::pop as u8),
+                2 => code.push(OpCode::pop2 as u8),
+                _ => (),
+            }
+            code.push(OpCode::return_void as u8);
+        }
+        sam_ret => {
+            if ret == SignatureType::Void {
+                return link_error("LambdaMetafactory: void implementation".to_string());
+            }
+            let hint = match &inst.retype {
+                SignatureType::Void => &ret,
+                t => t,
+            };
+            convert(sp, &mut code, &ret, hint);
+            convert(sp, &mut code, hint, sam_ret);
+            code.push(return_op(sam_ret) as u8);
+        }
+    }
+
+    let n_params: usize = target.sig.args.iter().map(slots).sum();
+    Ok(Code {
+        max_stack: (n_params + 4) as U2,
+        max_locals: slot as U2,
+        code: Arc::new(code),
+        exceptions: vec![],
+        attrs: vec![],
+    })
+}
+
+struct ClassSpinner {
+    cp: Vec<ConstantPoolType>,
+    utf8s: FxHashMap<Vec<u8>, U2>,
+}
+
+impl ClassSpinner {
+    fn new() -> Self {
+        Self {
+            cp: vec![ConstantPoolType::Nop],
+            utf8s: FxHashMap::default(),
+        }
+    }
+
+    fn push(&mut self, it: ConstantPoolType) -> U2 {
+        self.cp.push(it);
+        (self.cp.len() - 1) as U2
+    }
+
+    fn utf8(&mut self, v: &[u8]) -> U2 {
+        if let Some(idx) = self.utf8s.get(v) {
+            return *idx;
+        }
+
+        let idx = self.push(ConstantPoolType::Utf8 {
+            bytes: Arc::new(Vec::from(v)),
+        });
+        self.utf8s.insert(Vec::from(v), idx);
+        idx
+    }
+
+    fn class(&mut self, name: &[u8]) -> U2 {
+        let name_index = self.utf8(name);
+        self.push(ConstantPoolType::Class { name_index })
+    }
+
+    fn name_and_type(&mut self, name: &[u8], desc: &[u8]) -> U2 {
+        let name_index = self.utf8(name);
+        let desc_index = self.utf8(desc);
+        self.push(ConstantPoolType::NameAndType {
+            name_index,
+            desc_index,
+        })
+    }
+
+    fn field_ref(&mut self, class: &[u8], name: &[u8], desc: &[u8]) -> U2 {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, desc);
+        self.push(ConstantPoolType::FieldRef {
+            class_index,
+            name_and_type_index,
+        })
+    }
+
+    fn method_ref(&mut self, class: &[u8], name: &[u8], desc: &[u8], is_interface: bool) -> U2 {
+        let class_index = self.class(class);
+        let name_and_type_index = self.name_and_type(name, desc);
+        if is_interface {
+            self.push(ConstantPoolType::InterfaceMethodRef {
+                class_index,
+                name_and_type_index,
+            })
+        } else {
+            self.push(ConstantPoolType::MethodRef {
+                class_index,
+                name_and_type_index,
+            })
+        }
+    }
+}
+
+fn emit_u2(code: &mut Vec<u8>, op: OpCode, v: U2) {
+    code.push(op as u8);
+    code.extend_from_slice(&v.to_be_bytes());
+}
+
+/*
+Adapt the value on top of stack, 'from' => 'to':
+  primitive widening, boxing, unboxing, reference cast
+*/
+fn convert(sp: &mut ClassSpinner, code: &mut Vec<u8>, from: &SignatureType, to: &SignatureType) {
+    if from == to {
+        return;
+    }
+
+    match (is_primitive(from), is_primitive(to)) {
+        (true, true) => widen(code, from, to),
+        (true, false) => {
+            let (wrapper, _, _, box_desc) = wrapper_of(from);
+            let idx = sp.method_ref(wrapper, b"valueOf", box_desc, false);
+            emit_u2(code, OpCode::invokestatic, idx);
+            let to = class_name(to);
+            if to != cls_const::J_OBJECT && to != wrapper {
+                let idx = sp.class(to);
+                emit_u2(code, OpCode::checkcast, idx);
+            }
+        }
+        (false, true) => {
+            //unbox as the source wrapper if known, then widen
+            let prim = match prim_of_wrapper(class_name(from)) {
+                Some(prim) => prim,
+                None => to.clone(),
+            };
+            let (wrapper, unbox_name, unbox_desc, _) = wrapper_of(&prim);
+            let idx = sp.class(wrapper);
+            emit_u2(code, OpCode::checkcast, idx);
+            let idx = sp.method_ref(wrapper, unbox_name, unbox_desc, false);
+            emit_u2(code, OpCode::invokevirtual, idx);
+            widen(code, &prim, to);
+        }
+        (false, false) => {
+            let to = class_name(to);
+            if to != cls_const::J_OBJECT {
+                let idx = sp.class(to);
+                emit_u2(code, OpCode::checkcast, idx);
+            }
+        }
+    }
+}
+
+fn widen(code: &mut Vec<u8>, from: &SignatureType, to: &SignatureType) {
+    let op = match (prim_kind(from), prim_kind(to)) {
+        (b'I', b'J') => OpCode::i2l,
+        (b'I', b'F') => OpCode::i2f,
+        (b'I', b'D') => OpCode::i2d,
+        (b'J', b'F') => OpCode::l2f,
+        (b'J', b'D') => OpCode::l2d,
+        (b'F', b'D') => OpCode::f2d,
+        _ => return,
+    };
+    code.push(op as u8);
+}
+
+fn prim_kind(t: &SignatureType) -> u8 {
+    match t {
+        SignatureType::Long => b'J',
+        SignatureType::Float => b'F',
+        SignatureType::Double => b'D',
+        _ => b'I',
+    }
+}
+
+fn is_primitive(t: &SignatureType) -> bool {
+    !matches!(t, SignatureType::Object(_, _, _) | SignatureType::Array(_))
+}
+
+//(wrapper class, unbox method, unbox desc, box desc)
+fn wrapper_of(t: &SignatureType) -> (&'static [u8], &'static [u8], &'static [u8], &'static [u8]) {
+    match t {
+        SignatureType::Boolean => (
+            b"java/lang/Boolean",
+            b"booleanValue",
+            b"()Z",
+            b"(Z)Ljava/lang/Boolean;",
+        ),
+        SignatureType::Byte => (
+            b"java/lang/Byte",
+            b"byteValue",
+            b"()B",
+            b"(B)Ljava/lang/Byte;",
+        ),
+        SignatureType::Char => (
+            b"java/lang/Character",
+            b"charValue",
+            b"()C",
+            b"(C)Ljava/lang/Character;",
+        ),
+        SignatureType::Short => (
+            b"java/lang/Short",
+            b"shortValue",
+            b"()S",
+            b"(S)Ljava/lang/Short;",
+        ),
+        SignatureType::Int => (
+            b"java/lang/Integer",
+            b"intValue",
+            b"()I",
+            b"(I)Ljava/lang/Integer;",
+        ),
+        SignatureType::Long => (
+            b"java/lang/Long",
+            b"longValue",
+            b"()J",
+            b"(J)Ljava/lang/Long;",
+        ),
+        SignatureType::Float => (
+            b"java/lang/Float",
+            b"floatValue",
+            b"()F",
+            b"(F)Ljava/lang/Float;",
+        ),
+        SignatureType::Double => (
+            b"java/lang/Double",
+            b"doubleValue",
+            b"()D",
+            b"(D)Ljava/lang/Double;",
+        ),
+        t => unreachable!("t = {:?}", t),
+    }
+}
+
+fn prim_of_wrapper(name: &[u8]) -> Option<SignatureType> {
+    match name {
+        b"java/lang/Boolean" => Some(SignatureType::Boolean),
+        b"java/lang/Byte" => Some(SignatureType::Byte),
+        b"java/lang/Character" => Some(SignatureType::Char),
+        b"java/lang/Short" => Some(SignatureType::Short),
+        b"java/lang/Integer" => Some(SignatureType::Int),
+        b"java/lang/Long" => Some(SignatureType::Long),
+        b"java/lang/Float" => Some(SignatureType::Float),
+        b"java/lang/Double" => Some(SignatureType::Double),
+        _ => None,
+    }
+}
+
+fn slots(t: &SignatureType) -> usize {
+    match t {
+        SignatureType::Long | SignatureType::Double => 2,
+        SignatureType::Void => 0,
+        _ => 1,
+    }
+}
+
+fn load_op(t: &SignatureType) -> OpCode {
+    match t {
+        SignatureType::Long => OpCode::lload,
+        SignatureType::Float => OpCode::fload,
+        SignatureType::Double => OpCode::dload,
+        SignatureType::Object(_, _, _) | SignatureType::Array(_) => OpCode::aload,
+        _ => OpCode::iload,
+    }
+}
+
+fn return_op(t: &SignatureType) -> OpCode {
+    match t {
+        SignatureType::Long => OpCode::lreturn,
+        SignatureType::Float => OpCode::freturn,
+        SignatureType::Double => OpCode::dreturn,
+        SignatureType::Object(_, _, _) | SignatureType::Array(_) => OpCode::areturn,
+        SignatureType::Void => OpCode::return_void,
+        _ => OpCode::ireturn,
+    }
+}
+
+fn object_type(name: &[u8]) -> SignatureType {
+    let mut desc = Vec::with_capacity(name.len() + 2);
+    desc.push(b'L');
+    desc.extend_from_slice(name);
+    desc.push(b';');
+    SignatureType::Object(Arc::new(desc), None, None)
+}
+
+//the name used by checkcast
+fn class_name(t: &SignatureType) -> &[u8] {
+    match t {
+        SignatureType::Object(desc, _, _) => &desc[1..desc.len() - 1],
+        SignatureType::Array(desc) => desc.as_slice(),
+        _ => unreachable!(),
+    }
+}
+
+fn to_desc(t: &SignatureType) -> Vec<u8> {
+    match t {
+        SignatureType::Object(desc, _, _) | SignatureType::Array(desc) => desc.to_vec(),
+        t => format!("{:?}", t).into_bytes(),
+    }
+}
+
+fn field_name(i: usize) -> Vec<u8> {
+    format!("arg${}", i + 1).into_bytes()
+}
@@ -0,0 +1,85 @@
+use super::BootstrapInfo;
+use crate::oop::Oop;
+use crate::runtime::{self, exception, invoke, method_handles, DataArea};
+use crate::types::MethodIdRef;
+use class_parser::MethodSignature;
+use classfile::{constant_pool, consts as cls_const, ConstantPoolType, SignatureType, U2};
+
+/*
+The bootstrap method is run by java, MethodHandleNatives.linkCallSite,
+the result is an invoker with an appendix (the CallSite target), like hotspot:
+
+  invoker(dynamic args..., appendix)
+*/
+pub struct LinkedSite {
+    factory: MethodSignature,
+    invoker: MethodIdRef,
+    appendix: Oop,
+}
+
+impl LinkedSite {
+    pub fn invoke(&self, caller: &DataArea) {
+        let mut args = invoke::build_args_from_caller_stack(caller, &self.factory);
+        args.push(self.appendix.clone());
+
+        if let Some(v) = method_handles::call(self.invoker.clone(), args) {
+            if self.factory.retype != SignatureType::Void {
+                invoke::set_return(caller, &self.factory.retype, v);
+            }
+        }
+    }
+}
+
+pub fn resolve(info: &BootstrapInfo, bsm_ref: U2) -> Result<LinkedSite, ()> {
+    let cp = info.cp;
+    let bsm = match cp.get(bsm_ref as usize) {
+        Some(ConstantPoolType::MethodHandle {
+            ref_kind,
+            ref_index,
+        }) => method_handles::new_method_handle(info.host, cp, *ref_kind, *ref_index),
+        _ => {
+            let msg = format!("bootstrap method #{} is not a method handle", bsm_ref);
+            exception::meet_ex(cls_const::J_LINKAGE_ERROR, Some(msg));
+            return Err(());
+        }
+    };
+    let bsm = bsm.ok_or(())?;
+
+    let mut static_args = Vec::with_capacity(info.args.len());
+    for it in info.args.iter() {
+        static_args.push(resolve_static_arg(info, *it).ok_or(())?);
+    }
+
+    let (invoker, appendix) =
+        method_handles::link_call_site(info.host, bsm, info.name, info.desc, static_args)
+            .ok_or(())?;
+    let factory = MethodSignature::new(info.desc.as_slice());
+
+    Ok(LinkedSite {
+        factory,
+        invoker,
+        appendix,
+    })
+}
+
+//loadable constants, boxed
+fn resolve_static_arg(info: &BootstrapInfo, idx: U2) -> Option<Oop> {
+    let cp = info.cp;
+    match cp.get(idx as usize) {
+        Some(ConstantPoolType::Class { .. }) => {
+            let class_loader = info.host.get_class().class_loader;
+            let cls = runtime::require_class2(class_loader, idx, cp)?;
+            let cls = cls.get_class();
+            Some(cls.get_mirror())
+        }
+        Some(ConstantPoolType::MethodType { desc_index }) => {
+            let desc = constant_pool::get_utf8(cp, *desc_index as usize);
+            method_handles::new_method_type(desc.as_slice())
+        }
+        Some(ConstantPoolType::MethodHandle {
+            ref_kind,
+            ref_index,
+        }) => method_handles::new_method_handle(info.host, cp, *ref_kind, *ref_index),
+        _ => method_handles::box_value(super::static_arg_value(cp, idx)),
+    }
+}
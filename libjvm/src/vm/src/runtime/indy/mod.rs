@@ -0,0 +1,161 @@
+use crate::oop::Oop;
+use crate::runtime::{exception, DataArea};
+use crate::types::ClassRef;
+use classfile::{constant_pool, consts as cls_const, BytesRef, ConstantPool, ConstantPoolType, U2};
+use std::sync::Arc;
+
+mod lambda;
+mod linked;
+mod string_concat;
+
+pub type CallSiteRef = Arc<CallSite>;
+
+/*
+The resolved result of an invokedynamic instruction.
+
+JVM spec, 6.5 invokedynamic: "each specific invokedynamic instruction is
+a dynamically-computed call site". The bootstrap method is run once, the result
+is cached by ConstantPoolCache, and then every execution dispatches through
+the target.
+
+The well-known bootstrap methods are linked natively:
+  java/lang/invoke/LambdaMetafactory (lambda & method reference),
+    a class implementing the functional interface is spun for the call site
+  java/lang/invoke/StringConcatFactory (javac 9+ string concat),
+    the recipe is interpreted directly
+
+Others are linked by java, see linked.rs
+*/
+pub enum CallSite {
+    Lambda(lambda::LambdaSite),
+    StringConcat(string_concat::ConcatSite),
+    Linked(linked::LinkedSite),
+}
+
+impl CallSite {
+    //pop the dynamic args from caller's stack, and push the result
+    pub fn invoke(&self, caller: &DataArea) {
+        match self {
+            CallSite::Lambda(site) => site.invoke(caller),
+            CallSite::StringConcat(site) => site.invoke(caller),
+            CallSite::Linked(site) => site.invoke(caller),
+        }
+    }
+}
+
+//the static arguments of a bootstrap method
+pub struct BootstrapInfo<'a> {
+    pub host: &'a ClassRef,
+    pub cp: &'a ConstantPool,
+    pub name: &'a BytesRef,
+    pub desc: &'a BytesRef,
+    pub args: &'a [U2],
+}
+
+pub fn resolve(host: &ClassRef, cp: &ConstantPool, idx: usize) -> Result<CallSite, ()> {
+    let (bsm_idx, nt_idx) = match cp.get(idx) {
+        Some(ConstantPoolType::InvokeDynamic {
+            bootstrap_method_attr_index,
+            name_and_type_index,
+        }) => (*bootstrap_method_attr_index, *name_and_type_index),
+        _ => {
+            let msg = format!("#{} is not an InvokeDynamic constant", idx);
+            exception::meet_ex(cls_const::J_LINKAGE_ERROR, Some(msg));
+            return Err(());
+        }
+    };
+    let (name, desc) = constant_pool::get_name_and_type(cp, nt_idx as usize);
+
+    let bsm = {
+        let cls = host.extract_inst();
+        cls.class_file
+            .bootstrap_methods()
+            .and_then(|methods| methods.get(bsm_idx as usize))
+            .cloned()
+    };
+    let bsm = match bsm {
+        Some(bsm) => bsm,
+        None => {
+            let msg = format!("no bootstrap method #{}", bsm_idx);
+            exception::meet_ex(cls_const::J_BOOTSTRAP_METHOD_ERROR, Some(msg));
+            return Err(());
+        }
+    };
+
+    let (bsm_cls, bsm_name) = match cp.get(bsm.method_ref as usize) {
+        Some(ConstantPoolType::MethodHandle { ref_index, .. }) => {
+            let (_, class_index, nt_idx) = constant_pool::get_method_ref(cp, *ref_index as usize);
+            let cls = constant_pool::get_class_name(cp, class_index as usize);
+            let (name, _) = constant_pool::get_name_and_type(cp, nt_idx as usize);
+            (cls.clone(), name.clone())
+        }
+        _ => {
+            let msg = format!("bootstrap method #{} is not a method handle", bsm_idx);
+            exception::meet_ex(cls_const::J_LINKAGE_ERROR, Some(msg));
+            return Err(());
+        }
+    };
+
+    trace!(
+        "indy resolve {}.{}, name={}, desc={}",
+        String::from_utf8_lossy(bsm_cls.as_slice()),
+        String::from_utf8_lossy(bsm_name.as_slice()),
+        String::from_utf8_lossy(name.as_slice()),
+        String::from_utf8_lossy(desc.as_slice())
+    );
+
+    let info = BootstrapInfo {
+        host,
+        cp,
+        name,
+        desc,
+        args: bsm.args.as_slice(),
+    };
+
+    match (bsm_cls.as_slice(), bsm_name.as_slice()) {
+        (cls_const::J_LAMBDA_METAFACTORY, b"metafactory") => {
+            lambda::resolve(&info, false).map(CallSite::Lambda)
+        }
+        (cls_const::J_LAMBDA_METAFACTORY, b"altMetafactory") => {
+            lambda::resolve(&info, true).map(CallSite::Lambda)
+        }
+        (cls_const::J_STRING_CONCAT_FACTORY, b"makeConcat") => {
+            string_concat::resolve(&info, false).map(CallSite::StringConcat)
+        }
+        (cls_const::J_STRING_CONCAT_FACTORY, b"makeConcatWithConstants") => {
+            string_concat::resolve(&info, true).map(CallSite::StringConcat)
+        }
+        _ => linked::resolve(&info, bsm.method_ref).map(CallSite::Linked),
+    }
+}
+
+//static argument => Oop
+fn static_arg_value(cp: &ConstantPool, idx: U2) -> Oop {
+    match cp.get(idx as usize) {
+        Some(ConstantPoolType::Integer { v }) => Oop::new_int(i32::from_be_bytes(*v)),
+        Some(ConstantPoolType::Float { v }) => Oop::new_float(f32::from_be_bytes(*v)),
+        Some(ConstantPoolType::Long { v }) => Oop::new_long(i64::from_be_bytes(*v)),
+        Some(ConstantPoolType::Double { v }) => Oop::new_double(f64::from_be_bytes(*v)),
+        Some(ConstantPoolType::String { string_index }) => {
+            let s = constant_pool::get_utf8(cp, *string_index as usize);
+            crate::util::oop::new_java_lang_string3(s.as_slice())
+        }
+        _ => Oop::Null,
+    }
+}
+
+fn static_arg_int(cp: &ConstantPool, idx: U2) -> Option<i32> {
+    match cp.get(idx as usize) {
+        Some(ConstantPoolType::Integer { v }) => Some(i32::from_be_bytes(*v)),
+        _ => None,
+    }
+}
+
+fn static_arg_method_type(cp: &ConstantPool, idx: U2) -> Option<BytesRef> {
+    match cp.get(idx as usize) {
+        Some(ConstantPoolType::MethodType { desc_index }) => {
+            Some(constant_pool::get_utf8(cp, *desc_index as usize).clone())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,186 @@
+use super::{static_arg_value, BootstrapInfo};
+use crate::new_br;
+use crate::oop::{Oop, OopPtr};
+use crate::runtime::{self, invoke, thread, DataArea, JavaCall};
+use crate::util;
+use class_parser::MethodSignature;
+use classfile::{constant_pool, consts as cls_const, ConstantPoolType, SignatureType};
+
+//StringConcatFactory recipe tags
+const TAG_ARG: u16 = 1;
+const TAG_CONST: u16 = 2;
+
+enum Elem {
+    Arg,
+    Const(Vec<u16>),
+}
+
+pub struct ConcatSite {
+    factory: MethodSignature,
+    recipe: Vec<Elem>,
+}
+
+impl ConcatSite {
+    pub fn invoke(&self, caller: &DataArea) {
+        let args = invoke::build_args_from_caller_stack(caller, &self.factory);
+        let mut args = args.iter().zip(self.factory.args.iter());
+
+        let mut buf = Vec::new();
+        for it in self.recipe.iter() {
+            match it {
+                Elem::Arg => {
+                    let (v, t) = args.next().unwrap();
+                    match stringify(v, t) {
+                        Some(s) => buf.extend_from_slice(s.as_slice()),
+                        //the exception of toString() is pending
+                        None => return,
+                    }
+                }
+                Elem::Const(s) => buf.extend_from_slice(s.as_slice()),
+            }
+        }
+
+        let v = util::oop::new_java_lang_string4(buf.as_slice());
+        caller.stack().push_ref(v);
+    }
+}
+
+/*
+makeConcat(Lookup, String name, MethodType concatType)
+  all args are concatenated
+
+makeConcatWithConstants(Lookup, String name, MethodType concatType, String recipe, Object... constants)
+  \1 in recipe, an arg
+  \2 in recipe, the next constant
+*/
+pub fn resolve(info: &BootstrapInfo, with_constants: bool) -> Result<ConcatSite, ()> {
+    let factory = MethodSignature::new(info.desc.as_slice());
+    let cp = info.cp;
+
+    if !with_constants {
+        let recipe = factory.args.iter().map(|_| Elem::Arg).collect();
+        return Ok(ConcatSite { factory, recipe });
+    }
+
+    let raw = match info.args.first().map(|it| cp.get(*it as usize)) {
+        Some(Some(ConstantPoolType::String { string_index })) => {
+            let s = constant_pool::get_utf8(cp, *string_index as usize);
+            constant_pool::construct_string_raw(s.as_slice())
+        }
+        _ => {
+            let msg = "StringConcatFactory: bad recipe".to_string();
+            runtime::exception::meet_ex(cls_const::J_BOOTSTRAP_METHOD_ERROR, Some(msg));
+            return Err(());
+        }
+    };
+
+    let mut constants = info.args[1..].iter();
+    let mut recipe = Vec::new();
+    let mut acc = Vec::new();
+    let mut n_args = 0;
+    for c in raw {
+        match c {
+            TAG_ARG | TAG_CONST => {
+                if !acc.is_empty() {
+                    recipe.push(Elem::Const(std::mem::take(&mut acc)));
+                }
+
+                if c == TAG_ARG {
+                    recipe.push(Elem::Arg);
+                    n_args += 1;
+                } else {
+                    let v = match constants.next() {
+                        Some(idx) => static_arg_value(cp, *idx),
+                        None => Oop::Null,
+                    };
+                    let t = match &v {
+                        Oop::Int(_) => SignatureType::Int,
+                        Oop::Long(_) => SignatureType::Long,
+                        Oop::Float(_) => SignatureType::Float,
+                        Oop::Double(_) => SignatureType::Double,
+                        _ => SignatureType::Object(util::S_JAVA_LANG_OBJECT.clone(), None, None),
+                    };
+                    match stringify(&v, &t) {
+                        Some(s) => acc.extend_from_slice(s.as_slice()),
+                        None => return Err(()),
+                    }
+                }
+            }
+            c => acc.push(c),
+        }
+    }
+    if !acc.is_empty() {
+        recipe.push(Elem::Const(acc));
+    }
+
+    if n_args != factory.args.len() {
+        let msg = "StringConcatFactory: mismatched recipe".to_string();
+        runtime::exception::meet_ex(cls_const::J_BOOTSTRAP_METHOD_ERROR, Some(msg));
+        return Err(());
+    }
+
+    Ok(ConcatSite { factory, recipe })
+}
+
+//the same as String.valueOf(v)
+fn stringify(v: &Oop, t: &SignatureType) -> Option<Vec<u16>> {
+    if let Oop::Null = v {
+        return Some("null".encode_utf16().collect());
+    }
+
+    let desc = match t {
+        SignatureType::Boolean => "(Z)Ljava/lang/String;",
+        SignatureType::Char => "(C)Ljava/lang/String;",
+        SignatureType::Byte | SignatureType::Short | SignatureType::Int => "(I)Ljava/lang/String;",
+        SignatureType::Long => "(J)Ljava/lang/String;",
+        SignatureType::Float => "(F)Ljava/lang/String;",
+        SignatureType::Double => "(D)Ljava/lang/String;",
+        _ => {
+            let rf = v.extract_ref();
+            if OopPtr::is_java_lang_string(rf.clone()) {
+                return Some(OopPtr::java_lang_string_value(rf));
+            }
+
+            "(Ljava/lang/Object;)Ljava/lang/String;"
+        }
+    };
+
+    let cls = match runtime::require_class3(None, cls_const::J_STRING) {
+        Some(cls) => cls,
+        None => {
+            let msg = String::from_utf8_lossy(cls_const::J_STRING).to_string();
+            runtime::exception::meet_ex(cls_const::J_NO_CLASS_DEF_FOUND_ERROR, Some(msg));
+            return None;
+        }
+    };
+    let value_of = {
+        let cls = cls.get_class();
+        cls.get_static_method(&new_br("valueOf"), &new_br(desc))
+    };
+    let mir = match value_of {
+        Ok(mir) => mir,
+        Err(_) => {
+            let msg = format!("java.lang.String.valueOf{}", desc);
+            runtime::exception::meet_ex(cls_const::J_NO_SUCH_METHOD_ERROR, Some(msg));
+            return None;
+        }
+    };
+    let mut jc = JavaCall::new_with_args(mir, vec![v.clone()]);
+    let area = DataArea::new();
+    jc.invoke(Some(&area), true);
+
+    if thread::is_meet_ex() {
+        return None;
+    }
+
+    let s = area.return_v.borrow_mut().take();
+    match s {
+        Some(Oop::Null) => Some("null".encode_utf16().collect()),
+        Some(s) => Some(OopPtr::java_lang_string_value(s.extract_ref())),
+        None => {
+            let msg = "String.valueOf returned no value".to_string();
+            runtime::exception::meet_ex(cls_const::J_INTERNAL_ERROR, Some(msg));
+            None
+        }
+    }
+}
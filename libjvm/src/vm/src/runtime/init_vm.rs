@@ -1,3 +1,4 @@
+use crate::gc;
 use crate::oop;
 use crate::oop::{Class, Oop};
 use crate::runtime::{self, require_class3};
@@ -5,12 +6,22 @@ use crate::types::JavaThreadRef;
 use crate::util;
 use crate::{native, new_br};
 use classfile::consts::{
-    J_ARRAY_INDEX_OUT_OF_BOUNDS, J_CLASS, J_CLASS_NOT_FOUND, J_CLONEABLE, J_FIELD, J_INPUT_STREAM,
-    J_INTERNAL_ERROR, J_IOEXCEPTION, J_METHOD_CTOR, J_NPE, J_OBJECT, J_PRINT_STREAM,
-    J_SECURITY_MANAGER, J_SERIALIZABLE, J_STRING, J_SYSTEM, J_THREAD, J_THREAD_GROUP, J_THROWABLE,
+    J_ARRAY_INDEX_OUT_OF_BOUNDS, J_CLASS, J_CLASS_NOT_FOUND, J_CLONEABLE, J_FIELD, J_FINALIZER,
+    J_INPUT_STREAM, J_INTERNAL_ERROR, J_IOEXCEPTION, J_METHOD_CTOR, J_NPE, J_OBJECT,
+    J_PRINT_STREAM, J_SECURITY_MANAGER, J_SERIALIZABLE, J_STRING, J_SYSTEM, J_THREAD,
+    J_THREAD_GROUP, J_THROWABLE,
 };
 use std::borrow::BorrowMut;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+
+lazy_static! {
+    //the group of the threads attached by the invocation api
+    static ref MAIN_THREAD_GROUP: Mutex<Oop> = Mutex::new(Oop::Null);
+}
+
+pub fn main_thread_group() -> Oop {
+    MAIN_THREAD_GROUP.lock().unwrap().clone()
+}
 
 pub fn initialize_jvm() {
     initialize_vm_structs();
@@ -21,9 +32,9 @@ pub fn initialize_jvm() {
     let init_thread_oop = oop::Oop::new_inst(thread_cls.clone());
     {
         let mut cls = thread_cls.get_mut_class();
-        //todo: getNativeHandler
-        //        let id = util::new_field_id(J_THREAD, b"eetop", b"J");
-        //        cls.put_field_value2(init_thread_oop.clone(), id, oop::OopDesc::new_long(0));
+        let eetop = runtime::thread::current_java_thread().read().unwrap().eetop;
+        let id = cls.get_field_id(&new_br("eetop"), &new_br("J"), false);
+        Class::put_field_value(init_thread_oop.extract_ref(), id, oop::Oop::new_long(eetop));
         //todo: define java::lang::ThreadPriority::NORMAL_PRIORITY
         let id = cls.get_field_id(&new_br("priority"), &new_br("I"), false);
         Class::put_field_value(init_thread_oop.extract_ref(), id, oop::Oop::new_int(5));
@@ -70,6 +81,8 @@ pub fn initialize_jvm() {
     //need to impl java_security_accesscontroller
     //    let sun_debug_cls = do_init(b"sun/security/util/Debug", jt);
 
+    *MAIN_THREAD_GROUP.lock().unwrap() = main_thread_group.clone();
+
     let args = vec![
         init_thread_oop,
         main_thread_group,
@@ -81,6 +94,12 @@ pub fn initialize_jvm() {
         args,
     );
 
+    //starts the ReferenceHandler and Finalizer threads, like hotspot
+    let _ = oop::class::load_and_init(J_FINALIZER);
+    gc::set_finalizer_ready();
+
+    runtime::exception::preallocate();
+
     hack_classes();
 
     let init_system_classes_method = {
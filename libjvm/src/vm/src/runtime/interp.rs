@@ -1,11 +1,13 @@
+use crate::gc;
 use crate::oop::{
-    self, consts as oop_consts, field, Class, ClassKind, Oop, OopPtr, TypeArrayDesc, ValueType,
+    self, consts as oop_consts, field, Class, ClassKind, Oop, OopPtr, TypeArrayDesc, TypeArrayEnum,
+    ValueType,
 };
-use crate::runtime::local::Local;
-use crate::runtime::stack::Stack;
+use crate::runtime::bytecode::{self, Code, Insn, Resolved};
+use crate::runtime::invoke::{self, Callee};
 use crate::runtime::{
-    self, cmp, exception, require_class, require_class2, require_class3, thread, DataArea, Frame,
-    JavaCall,
+    self, arith, cmp, exception, require_class, require_class2, require_class3, thread, DataArea,
+    Frame, JavaCall,
 };
 use crate::types::*;
 use crate::util;
@@ -16,10 +18,9 @@ use classfile::{
 use nix::sys::socket::SockType::Datagram;
 use std::borrow::BorrowMut;
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::ops::Deref;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLockReadGuard};
+use std::sync::Arc;
 
 macro_rules! array_store {
     ($ary:ident, $pos:ident, $v:ident) => {
@@ -37,7 +38,6 @@ macro_rules! iarray_load {
     ($area:ident, $ary:ident, $pos:ident) => {
         let len = $ary.len();
         if ($pos < 0) || ($pos as usize >= len) {
-            drop($area);
             let msg = format!("length is {}, but index is {}", len, $pos);
             exception::meet_ex(cls_const::J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(msg));
         } else {
@@ -46,149 +46,108 @@ macro_rules! iarray_load {
     };
 }
 
-macro_rules! read_byte {
-    ($pc:expr, $code:expr) => {{
-        let pc = $pc.fetch_add(1, Ordering::Relaxed);
-        $code[pc as usize]
-    }};
-}
-
-macro_rules! read_i2 {
-    ($pc:expr, $code:expr) => {{
-        let h = read_byte!($pc, $code) as i16;
-        let l = read_byte!($pc, $code) as i16;
-        (h << 8 | l) as i32
-    }};
-}
-
-macro_rules! read_u1 {
-    ($pc:expr, $code:expr) => {{
-        let pc = $pc.fetch_add(1, Ordering::Relaxed);
-        $code[pc as usize] as usize
-    }};
-}
-
-macro_rules! read_u2 {
-    ($pc:expr, $code:expr) => {{
-        read_u1!($pc, $code) << 8 | read_u1!($pc, $code)
-    }};
-}
-
 macro_rules! opcode_const {
     (null, $interp:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         stack.push_null();
     };
-    (m1, $interp:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_const_m1();
-    };
-    (0, $interp:ident, $with_nop:expr) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_const0($with_nop);
+    (int, $interp:ident, $v:expr) => {
+        let stack = $interp.frame.area.stack();
+        stack.push_int($v);
     };
-    (1, $interp:ident, $with_nop:expr) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_const1($with_nop);
+    (long, $interp:ident, $v:expr) => {
+        let stack = $interp.frame.area.stack();
+        stack.push_long($v);
     };
-    (2, $interp:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_const2();
+    (float, $interp:ident, $v:expr) => {
+        let stack = $interp.frame.area.stack();
+        stack.push_float($v);
     };
-    (3, $interp:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_const3();
-    };
-    (4, $interp:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_const4();
-    };
-    (5, $interp:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_const5();
+    (double, $interp:ident, $v:expr) => {
+        let stack = $interp.frame.area.stack();
+        stack.push_double($v);
     };
 }
 
 macro_rules! opcode_load {
     (int, $interp:ident, $pos:expr) => {
-        let v = $interp.local.get_int($pos);
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
+        let v = stack.get_int($interp.base + $pos);
         stack.push_int(v);
     };
     (long, $interp:ident, $pos:expr) => {
-        let v = $interp.local.get_long($pos);
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
+        let v = stack.get_long($interp.base + $pos);
         stack.push_long(v);
     };
     (float, $interp:ident, $pos:expr) => {
-        let v = $interp.local.get_float($pos);
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
+        let v = stack.get_float($interp.base + $pos);
         stack.push_float(v);
     };
     (double, $interp:ident, $pos:expr) => {
-        let v = $interp.local.get_double($pos);
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
+        let v = stack.get_double($interp.base + $pos);
         stack.push_double(v);
     };
     (a, $interp:ident, $pos:expr) => {
-        let v = $interp.local.get_ref($pos);
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        stack.push_ref(v, false);
+        let stack = $interp.frame.area.stack();
+        stack.push_from($interp.base + $pos);
     };
 }
 
 macro_rules! opcode_store {
     (int, $interp:ident, $pos:expr) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v = stack.pop_int();
-        $interp.local.set_int($pos, v);
+        stack.set_int($interp.base + $pos, v);
     };
     (long, $interp:ident, $pos:expr) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v = stack.pop_long();
-        $interp.local.set_long($pos, v);
+        stack.set_long($interp.base + $pos, v);
     };
     (float, $interp:ident, $pos:expr) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v = stack.pop_float();
-        $interp.local.set_float($pos, v);
+        stack.set_float($interp.base + $pos, v);
     };
     (double, $interp:ident, $pos:expr) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v = stack.pop_double();
-        $interp.local.set_double($pos, v);
+        stack.set_double($interp.base + $pos, v);
     };
+    //a reference, or the returnAddress of jsr
     (a, $interp:ident, $pos:expr) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
-        let v = stack.pop_ref();
-        $interp.local.set_ref($pos, v);
+        let stack = $interp.frame.area.stack();
+        stack.pop_to($interp.base + $pos);
     };
 }
 
 macro_rules! opcode_math_op {
     (int, $interp:ident, $op:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_int();
         let v = v1.$op(v2);
         stack.push_int(v);
     };
     (long, $interp:ident, $op:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v2 = stack.pop_long();
         let v1 = stack.pop_long();
         let v = v1.$op(v2);
         stack.push_long(v);
     };
     (float, $interp:ident, $op:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v2 = stack.pop_float();
         let v1 = stack.pop_float();
         let v = v1.$op(v2);
         stack.push_float(v);
     };
     (double, $interp:ident, $op:ident) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+        let stack = $interp.frame.area.stack();
         let v2 = stack.pop_double();
         let v1 = stack.pop_double();
         let v = v1.$op(v2);
@@ -197,53 +156,126 @@ macro_rules! opcode_math_op {
 }
 
 macro_rules! opcode_if {
-    ($interp:ident, $op:tt) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+    ($interp:ident, $insn:ident, $op:tt) => {
+        let stack = $interp.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_int();
         if v1 $op v2 {
-            drop(stack);
-            $interp.goto_by_offset_hardcoded(2);
-        } else {
-            let _ = $interp.frame.pc.fetch_add(2, Ordering::Relaxed);
+            $interp.goto_abs($insn.b);
         }
     };
-    ($interp:ident, $op:tt, 0) => {
-        let mut stack = $interp.frame.area.stack.borrow_mut();
+    ($interp:ident, $insn:ident, $op:tt, 0) => {
+        let stack = $interp.frame.area.stack();
         let v = stack.pop_int();
         if v $op 0 {
-            drop(stack);
-            $interp.goto_by_offset_hardcoded(2);
-        } else {
-            let _ = $interp.frame.pc.fetch_add(2, Ordering::Relaxed);
+            $interp.goto_abs($insn.b);
         }
     };
 }
 
-pub struct Interp<'a> {
-    frame: RwLockReadGuard<'a, Box<Frame>>,
-    local: Local,
+/*
+The frames of a thread called by java are run in a single dispatch loop:
+invoke pushes the frame of the callee, and return pops it back to the
+caller, an exception is unwound frame by frame until a handler is found.
+A new Interp is run only for a java method called by the vm or a native
+method, the entry frame, its return value is left in the frame.
+*/
+pub struct Interp {
+    //the current frame
+    frame: FrameRef,
+    //the locals on the thread stack, the operands above them
+    base: usize,
+    cp: ConstantPool,
+    code: Arc<Code>,
+    locked: Option<Oop>,
+
+    //the callers of the current frame in the loop, the entry frame first
+    callers: Vec<Activation>,
+}
+
+struct Activation {
+    frame: FrameRef,
+    base: usize,
     cp: ConstantPool,
-    code: Arc<Vec<U1>>,
-    op_widen: bool,
+    code: Arc<Code>,
+    locked: Option<Oop>,
 }
 
-impl<'a> Interp<'a> {
-    pub fn new(frame: RwLockReadGuard<'a, Box<Frame>>, local: Local) -> Self {
-        let cp = frame.cp.clone();
-        let code = frame.code.clone();
-        let op_widen = false;
+impl Activation {
+    fn new(callee: Callee) -> Self {
+        let cp = callee.frame.cp.clone();
+        let code = callee.frame.code.clone();
         Self {
-            frame,
-            local,
+            frame: callee.frame,
+            base: callee.base,
             cp,
             code,
-            op_widen,
+            locked: callee.locked,
+        }
+    }
+}
+
+impl Interp {
+    pub fn new(callee: Callee) -> Self {
+        let act = Activation::new(callee);
+        Self {
+            frame: act.frame,
+            base: act.base,
+            cp: act.cp,
+            code: act.code,
+            locked: act.locked,
+            callers: Vec::new(),
         }
     }
+
+    fn swap_frame(&mut self, act: &mut Activation) {
+        std::mem::swap(&mut self.frame, &mut act.frame);
+        std::mem::swap(&mut self.base, &mut act.base);
+        std::mem::swap(&mut self.cp, &mut act.cp);
+        std::mem::swap(&mut self.code, &mut act.code);
+        std::mem::swap(&mut self.locked, &mut act.locked);
+    }
+
+    fn push_frame(&mut self, callee: Callee) {
+        let mut act = Activation::new(callee);
+        self.swap_frame(&mut act);
+        self.callers.push(act);
+    }
+
+    //leave the current frame for its caller, false if it's the entry frame
+    fn pop_frame(&mut self) -> bool {
+        invoke::fin_sync(self.locked.take());
+        let jt = runtime::thread::current_java_thread();
+        let _ = jt.write().unwrap().frames.pop();
+        self.frame.area.stack().truncate(self.base);
+
+        match self.callers.pop() {
+            Some(mut act) => {
+                self.swap_frame(&mut act);
+                true
+            }
+            None => false,
+        }
+    }
+
+    //the return value is pushed to the caller, false if the entry frame returned
+    fn return_to_caller(&mut self) -> bool {
+        if self.callers.is_empty() {
+            self.pop_frame();
+            return false;
+        }
+
+        let callee = self.frame.clone();
+        self.pop_frame();
+        if let Some(v) = callee.area.return_v.borrow_mut().take() {
+            let retype = &callee.mir.method.signature.retype;
+            invoke::set_return(&self.frame.area, retype, v);
+        }
+        true
+    }
 }
 
-impl<'a> Interp<'a> {
+impl Interp {
     fn debug_op(&self, code: u8, op: OpCode) {
         let frame_id = self.frame.frame_id;
 
@@ -257,113 +289,119 @@ impl<'a> Interp<'a> {
     }
 }
 
-impl<'a> Interp<'a> {
+impl Interp {
     pub fn run(&mut self) {
         let jt = runtime::thread::current_java_thread();
-        let codes = self.code.clone();
 
         loop {
-            let code = read_byte!(self.frame.pc, codes);
-            let code = OpCode::from(code);
-            match code {
-                OpCode::athrow => {
-                    self.athrow(jt);
-                    break;
-                }
+            gc::safepoint();
+
+            let pc = self.frame.pc.load(Ordering::Relaxed);
+            let insn = self.code.fetch(pc);
+            self.frame.pc.store(insn.next, Ordering::Relaxed);
+            match OpCode::from(insn.op) {
+                OpCode::athrow => self.athrow(&jt),
                 OpCode::ireturn => {
                     self.ireturn();
-                    break;
+                    if !self.return_to_caller() {
+                        break;
+                    }
                 }
                 OpCode::lreturn => {
                     self.lreturn();
-                    break;
+                    if !self.return_to_caller() {
+                        break;
+                    }
                 }
                 OpCode::freturn => {
                     self.freturn();
-                    break;
+                    if !self.return_to_caller() {
+                        break;
+                    }
                 }
                 OpCode::dreturn => {
                     self.dreturn();
-                    break;
+                    if !self.return_to_caller() {
+                        break;
+                    }
                 }
                 OpCode::areturn => {
                     self.areturn();
-                    break;
+                    if !self.return_to_caller() {
+                        break;
+                    }
                 }
                 OpCode::return_void => {
                     self.return_void();
-                    break;
+                    if !self.return_to_caller() {
+                        break;
+                    }
                 }
                 OpCode::nop => (),
                 OpCode::aconst_null => {
                     opcode_const!(null, self);
                 }
                 OpCode::iconst_m1 => {
-                    opcode_const!(m1, self);
+                    opcode_const!(int, self, -1);
                 }
                 OpCode::iconst_0 => {
-                    opcode_const!(0, self, false);
+                    opcode_const!(int, self, 0);
                 }
                 OpCode::iconst_1 => {
-                    opcode_const!(1, self, false);
+                    opcode_const!(int, self, 1);
                 }
                 OpCode::iconst_2 => {
-                    opcode_const!(2, self);
+                    opcode_const!(int, self, 2);
                 }
                 OpCode::iconst_3 => {
-                    opcode_const!(3, self);
+                    opcode_const!(int, self, 3);
                 }
                 OpCode::iconst_4 => {
-                    opcode_const!(4, self);
+                    opcode_const!(int, self, 4);
                 }
                 OpCode::iconst_5 => {
-                    opcode_const!(5, self);
+                    opcode_const!(int, self, 5);
                 }
                 OpCode::lconst_0 => {
-                    opcode_const!(0, self, true);
+                    opcode_const!(long, self, 0);
                 }
                 OpCode::lconst_1 => {
-                    opcode_const!(1, self, true);
+                    opcode_const!(long, self, 1);
                 }
                 OpCode::fconst_0 => {
-                    opcode_const!(0, self, false);
+                    opcode_const!(float, self, 0.0);
                 }
                 OpCode::fconst_1 => {
-                    opcode_const!(1, self, false);
+                    opcode_const!(float, self, 1.0);
                 }
                 OpCode::fconst_2 => {
-                    opcode_const!(2, self);
+                    opcode_const!(float, self, 2.0);
                 }
                 OpCode::dconst_0 => {
-                    opcode_const!(0, self, true);
+                    opcode_const!(double, self, 0.0);
                 }
                 OpCode::dconst_1 => {
-                    opcode_const!(1, self, true);
+                    opcode_const!(double, self, 1.0);
                 }
-                OpCode::bipush => self.bipush(),
-                OpCode::sipush => self.sipush(),
-                OpCode::ldc => self.ldc(),
-                OpCode::ldc_w => self.ldc_w(),
-                OpCode::ldc2_w => self.ldc2_w(),
+                OpCode::bipush => self.bipush(insn),
+                OpCode::sipush => self.sipush(insn),
+                OpCode::ldc => self.ldc(insn),
+                OpCode::ldc_w => self.ldc_w(insn),
+                OpCode::ldc2_w => self.ldc2_w(insn),
                 OpCode::iload => {
-                    let pos = self.opcode_pos();
-                    opcode_load!(int, self, pos);
+                    opcode_load!(int, self, insn.a);
                 }
                 OpCode::lload => {
-                    let pos = self.opcode_pos();
-                    opcode_load!(long, self, pos);
+                    opcode_load!(long, self, insn.a);
                 }
                 OpCode::fload => {
-                    let pos = self.opcode_pos();
-                    opcode_load!(float, self, pos);
+                    opcode_load!(float, self, insn.a);
                 }
                 OpCode::dload => {
-                    let pos = self.opcode_pos();
-                    opcode_load!(double, self, pos);
+                    opcode_load!(double, self, insn.a);
                 }
                 OpCode::aload => {
-                    let pos = self.opcode_pos();
-                    opcode_load!(a, self, pos);
+                    opcode_load!(a, self, insn.a);
                 }
                 OpCode::iload_0 => {
                     opcode_load!(int, self, 0);
@@ -434,24 +472,19 @@ impl<'a> Interp<'a> {
                 OpCode::caload => self.caload(),
                 OpCode::saload => self.saload(),
                 OpCode::istore => {
-                    let pos = self.opcode_pos();
-                    opcode_store!(int, self, pos);
+                    opcode_store!(int, self, insn.a);
                 }
                 OpCode::lstore => {
-                    let pos = self.opcode_pos();
-                    opcode_store!(long, self, pos);
+                    opcode_store!(long, self, insn.a);
                 }
                 OpCode::fstore => {
-                    let pos = self.opcode_pos();
-                    opcode_store!(float, self, pos);
+                    opcode_store!(float, self, insn.a);
                 }
                 OpCode::dstore => {
-                    let pos = self.opcode_pos();
-                    opcode_store!(double, self, pos);
+                    opcode_store!(double, self, insn.a);
                 }
                 OpCode::astore => {
-                    let pos = self.opcode_pos();
-                    opcode_store!(a, self, pos);
+                    opcode_store!(a, self, insn.a);
                 }
                 OpCode::istore_0 => {
                     opcode_store!(int, self, 0);
@@ -614,7 +647,7 @@ impl<'a> Interp<'a> {
                     use std::ops::BitXor;
                     opcode_math_op!(long, self, bitxor);
                 }
-                OpCode::iinc => self.iinc(),
+                OpCode::iinc => self.iinc(insn),
                 OpCode::i2l => self.i2l(),
                 OpCode::i2f => self.i2f(),
                 OpCode::i2d => self.i2d(),
@@ -636,118 +669,100 @@ impl<'a> Interp<'a> {
                 OpCode::dcmpl => self.dcmpl(),
                 OpCode::dcmpg => self.dcmpg(),
                 OpCode::ifeq => {
-                    opcode_if!(self, ==, 0);
+                    opcode_if!(self, insn, ==, 0);
                 }
                 OpCode::ifne => {
-                    opcode_if!(self, !=, 0);
+                    opcode_if!(self, insn, !=, 0);
                 }
                 OpCode::iflt => {
-                    opcode_if!(self, <, 0);
+                    opcode_if!(self, insn, <, 0);
                 }
                 OpCode::ifge => {
-                    opcode_if!(self, >=, 0);
+                    opcode_if!(self, insn, >=, 0);
                 }
                 OpCode::ifgt => {
-                    opcode_if!(self, >, 0);
+                    opcode_if!(self, insn, >, 0);
                 }
                 OpCode::ifle => {
-                    opcode_if!(self, <=, 0);
+                    opcode_if!(self, insn, <=, 0);
                 }
                 OpCode::if_icmpeq => {
-                    opcode_if!(self, ==);
+                    opcode_if!(self, insn, ==);
                 }
                 OpCode::if_icmpne => {
-                    opcode_if!(self, !=);
+                    opcode_if!(self, insn, !=);
                 }
                 OpCode::if_icmplt => {
-                    opcode_if!(self, <);
+                    opcode_if!(self, insn, <);
                 }
                 OpCode::if_icmpge => {
-                    opcode_if!(self, >=);
+                    opcode_if!(self, insn, >=);
                 }
                 OpCode::if_icmpgt => {
-                    opcode_if!(self, >);
+                    opcode_if!(self, insn, >);
                 }
                 OpCode::if_icmple => {
-                    opcode_if!(self, <=);
-                }
-                OpCode::if_acmpeq => self.if_acmpeq(),
-                OpCode::if_acmpne => self.if_acmpne(),
-                OpCode::goto => self.goto(),
-                OpCode::jsr => self.jsr(),
-                OpCode::ret => self.ret(),
-                OpCode::tableswitch => self.table_switch(),
-                OpCode::lookupswitch => self.lookup_switch(),
-                OpCode::getstatic => self.get_static(),
-                OpCode::putstatic => self.put_static(),
-                OpCode::getfield => self.get_field(),
-                OpCode::putfield => self.put_field(),
-                OpCode::invokevirtual => self.invoke_virtual(),
-                OpCode::invokespecial => self.invoke_special(),
-                OpCode::invokestatic => self.invoke_static(),
-                OpCode::invokeinterface => self.invoke_interface(),
-                OpCode::invokedynamic => self.invoke_dynamic(),
-                OpCode::new => self.new_(),
-                OpCode::newarray => self.new_array(),
-                OpCode::anewarray => self.anew_array(),
+                    opcode_if!(self, insn, <=);
+                }
+                OpCode::if_acmpeq => self.if_acmpeq(insn),
+                OpCode::if_acmpne => self.if_acmpne(insn),
+                OpCode::goto => self.goto(insn),
+                OpCode::jsr => self.jsr(insn),
+                OpCode::ret => self.ret(insn),
+                OpCode::tableswitch => self.table_switch(insn),
+                OpCode::lookupswitch => self.lookup_switch(insn),
+                OpCode::getstatic => self.get_static(insn),
+                OpCode::putstatic => self.put_static(insn),
+                OpCode::getfield => self.get_field(insn),
+                OpCode::putfield => self.put_field(insn),
+                OpCode::invokevirtual => self.invoke_virtual(insn),
+                OpCode::invokespecial => self.invoke_special(insn),
+                OpCode::invokestatic => self.invoke_static(insn),
+                OpCode::invokeinterface => self.invoke_interface(insn),
+                OpCode::invokedynamic => self.invoke_dynamic(insn),
+                OpCode::new => self.new_(insn),
+                OpCode::newarray => self.new_array(insn),
+                OpCode::anewarray => self.anew_array(insn),
                 OpCode::arraylength => self.array_length(),
-                OpCode::checkcast => self.check_cast(),
-                OpCode::instanceof => self.instance_of(),
+                OpCode::checkcast => self.check_cast(insn),
+                OpCode::instanceof => self.instance_of(insn),
                 OpCode::monitorenter => self.monitor_enter(),
                 OpCode::monitorexit => self.monitor_exit(),
-                OpCode::wide => self.wide(),
-                OpCode::multianewarray => self.multi_anew_array(),
-                OpCode::ifnull => self.if_null(),
-                OpCode::ifnonnull => self.if_non_null(),
-                OpCode::goto_w => self.goto_w(),
-                OpCode::jsr_w => self.jsr_w(),
-                _ => unreachable!(),
+                OpCode::multianewarray => self.multi_anew_array(insn),
+                OpCode::ifnull => self.if_null(insn),
+                OpCode::ifnonnull => self.if_non_null(insn),
+                OpCode::goto_w => self.goto_w(insn),
+                OpCode::jsr_w => self.jsr_w(insn),
+                _ => self.quick(insn),
             }
 
-            let is_meet_ex = thread::is_meet_ex();
-            if is_meet_ex {
-                let mut th = jt.write().unwrap();
-                let ex = th.take_ex().unwrap();
-                drop(th);
-                match self.try_handle_exception(ex) {
-                    Ok(_) => (),
-                    Err(ex) => {
-                        let mut th = jt.write().unwrap();
-                        th.set_ex(ex);
-                        break;
-                    }
-                }
+            if thread::is_meet_ex() && !self.unwind(&jt) {
+                break;
             }
         }
     }
 }
 
 //helper methods
-impl<'a> Interp<'a> {
-    fn load_constant(&self, pos: usize) {
+impl Interp {
+    //the constant of ldc, None if an exception occurred in resolving
+    fn resolve_constant(&self, pos: usize) -> Option<Oop> {
         match &self.cp[pos] {
-            ConstantPoolType::Integer { v } => {
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.push_int2(v)
-            }
-            ConstantPoolType::Float { v } => {
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.push_float2(v)
-            }
-            ConstantPoolType::Long { v } => {
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.push_long2(v)
-            }
-            ConstantPoolType::Double { v } => {
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.push_double2(v)
-            }
+            ConstantPoolType::Integer { v } => Some(Oop::new_int(i32::from_be_bytes(*v))),
+            ConstantPoolType::Float { v } => Some(Oop::new_float(f32::from_be_bytes(*v))),
+            ConstantPoolType::Long { v } => Some(Oop::new_long(i64::from_be_bytes(*v))),
+            ConstantPoolType::Double { v } => Some(Oop::new_double(f64::from_be_bytes(*v))),
             ConstantPoolType::String { string_index } => {
-                let s = get_cp_utf8(&self.cp, *string_index as usize);
-                let s = util::oop::new_java_lang_string3(s.as_slice());
-
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.push_ref(s, false);
+                //the String may be patched by Unsafe.defineAnonymousClass
+                let class = self.frame.class.extract_inst();
+                let s = match class.cp_cache.find_constant(pos) {
+                    Some(v) => v,
+                    None => {
+                        let s = get_cp_utf8(&self.cp, *string_index as usize);
+                        util::oop::new_java_lang_string3(s.as_slice())
+                    }
+                };
+                Some(s)
             }
             ConstantPoolType::Class { name_index } => {
                 let name = get_cp_utf8(&self.cp, *name_index as usize);
@@ -755,12 +770,30 @@ impl<'a> Interp<'a> {
                 let cl = { self.frame.class.get_class().class_loader };
                 trace!("load_constant name={}, cl={:?}", name, cl);
                 let class = runtime::require_class3(cl, name.as_bytes()).unwrap();
-                oop::class::init_class(&class);
-                oop::class::init_class_fully(&class);
 
                 let mirror = { class.get_class().get_mirror() };
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.push_ref(mirror, false);
+                Some(mirror)
+            }
+            ConstantPoolType::MethodType { desc_index } => {
+                let class = self.frame.class.extract_inst();
+                let desc = get_cp_utf8(&self.cp, *desc_index as usize);
+                class.cp_cache.get_constant(pos, || {
+                    runtime::method_handles::new_method_type(desc.as_slice())
+                })
+            }
+            ConstantPoolType::MethodHandle {
+                ref_kind,
+                ref_index,
+            } => {
+                let class = self.frame.class.extract_inst();
+                class.cp_cache.get_constant(pos, || {
+                    runtime::method_handles::new_method_handle(
+                        &self.frame.class,
+                        &self.cp,
+                        *ref_kind,
+                        *ref_index,
+                    )
+                })
             }
             _ => unreachable!(),
         }
@@ -771,32 +804,9 @@ impl<'a> Interp<'a> {
         self.frame.pc.store(pc, Ordering::Relaxed);
     }
 
-    #[inline]
-    fn goto_by_offset(&self, branch: i32) {
-        let _ = self.frame.pc.fetch_add(branch, Ordering::Relaxed);
-    }
-
-    #[inline]
-    fn goto_by_offset_with_occupied(&self, branch: i32, occupied: i32) {
-        self.goto_by_offset(branch);
-        self.goto_by_offset(-(occupied - 1));
-    }
-
-    #[inline]
-    fn goto_by_offset_hardcoded(&self, occupied: i32) {
-        let codes = &self.code;
-        let pc = self.frame.pc.load(Ordering::Relaxed);
-        let high = codes[pc as usize] as i16;
-        let low = codes[(pc + 1) as usize] as i16;
-        let branch = (high << 8) | low;
-
-        self.goto_by_offset_with_occupied(branch as i32, occupied);
-    }
-
-    #[inline]
-    fn goto_abs_with_occupied(&self, pc: i32, occupied: i32) {
-        self.goto_abs(pc);
-        self.goto_by_offset(-(occupied - 1));
+    //the defining loader of the current class
+    fn class_loader(&self) -> Option<runtime::ClassLoader> {
+        self.frame.class.get_class().class_loader
     }
 
     fn set_return(&self, v: Option<Oop>) {
@@ -804,29 +814,51 @@ impl<'a> Interp<'a> {
         *return_v = v;
     }
 
-    fn get_field_helper(&self, receiver: Oop, idx: usize, is_static: bool) {
+    fn get_field_helper(&self, receiver: Oop, insn: Insn, is_static: bool) {
         let class = self.frame.class.extract_inst();
-        let fir = class.cp_cache.get_field(idx, is_static);
+        let fir = match class
+            .cp_cache
+            .get_field(&self.frame.class, insn.a, is_static)
+        {
+            Ok(fir) => fir,
+            Err(_) => return,
+        };
         debug_assert_eq!(fir.field.is_static(), is_static);
         trace!("get_field_helper={:?}, is_static={}", fir.field, is_static);
         let value_type = fir.field.value_type;
         let v = if is_static {
-            let class = fir.field.class.get_class();
-            class.get_static_field_value(fir.clone())
+            let v = {
+                let class = fir.field.class.get_class();
+                class.get_static_field_value(fir.clone())
+            };
+            match v {
+                //the ConstantValue of a String, the String is built at the first read
+                Oop::ConstUtf8(s) => {
+                    let v = util::oop::new_java_lang_string3(s.as_slice());
+                    let mut class = fir.field.class.get_mut_class();
+                    class.put_static_field_value(fir.clone(), v.clone());
+                    v
+                }
+                v => v,
+            }
+        } else if fir.field.is_volatile() {
+            //serialized with the atomic operations of Unsafe
+            Class::get_field_value_volatile(receiver.extract_ref(), fir.offset)
         } else {
+            let desc = fir.field.desc[0] as usize;
+            let op = bytecode::GETFIELD_QUICK;
+            self.code
+                .quicken(insn.pc, op, desc, fir.offset as i32, None);
+
             let rf = receiver.extract_ref();
             Class::get_field_value2(rf, fir.offset)
         };
-        let with_nop = match value_type {
-            ValueType::DOUBLE | ValueType::LONG => true,
-            _ => false,
-        };
-        let mut stack = self.frame.area.stack.borrow_mut();
-        stack.push_ref(v, with_nop);
+        let stack = self.frame.area.stack();
+        stack.push_value(v);
     }
 
     fn pop_value(&self, vt: ValueType) -> Oop {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         match vt {
             ValueType::INT
             | ValueType::SHORT
@@ -848,14 +880,20 @@ impl<'a> Interp<'a> {
                 let v = stack.pop_long();
                 Oop::new_long(v)
             }
-            ValueType::ARRAY | ValueType::OBJECT => stack.pop_ref(),
+            ValueType::ARRAY | ValueType::OBJECT => stack.pop_ref().clone(),
             _ => unreachable!(),
         }
     }
 
-    fn put_field_helper(&self, idx: usize, is_static: bool) {
+    fn put_field_helper(&self, insn: Insn, is_static: bool) {
         let class = self.frame.class.extract_inst();
-        let fir = class.cp_cache.get_field(idx, is_static);
+        let fir = match class
+            .cp_cache
+            .get_field(&self.frame.class, insn.a, is_static)
+        {
+            Ok(fir) => fir,
+            Err(_) => return,
+        };
         debug_assert_eq!(fir.field.is_static(), is_static);
         trace!("put_field_helper={:?}, is_static={}", fir.field, is_static);
         let value_type = fir.field.value_type;
@@ -866,40 +904,71 @@ impl<'a> Interp<'a> {
             class.put_static_field_value(fir.clone(), v);
         } else {
             let receiver = {
-                let mut stack = self.frame.area.stack.borrow_mut();
+                let stack = self.frame.area.stack();
                 stack.pop_ref()
             };
-            match receiver {
+            match &*receiver {
                 Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
-                _ => Class::put_field_value2(receiver.extract_ref(), fir.offset, v),
+                _ if fir.field.is_volatile() => {
+                    Class::put_field_value_volatile(receiver.extract_ref(), fir.offset, v)
+                }
+                _ => {
+                    let desc = fir.field.desc[0] as usize;
+                    let op = bytecode::PUTFIELD_QUICK;
+                    self.code
+                        .quicken(insn.pc, op, desc, fir.offset as i32, None);
+
+                    Class::put_field_value2(receiver.extract_ref(), fir.offset, v)
+                }
             }
         }
     }
 
-    fn invoke_helper(&self, is_static: bool, idx: usize, force_no_resolve: bool) {
+    fn invoke_helper(&mut self, is_static: bool, idx: usize, force_no_resolve: bool) {
         let class = self.frame.class.extract_inst();
-        let mir = class.cp_cache.get_method(idx);
-        let caller = match &mir.method.signature.retype {
-            classfile::SignatureType::Void => None,
-            _ => Some(&self.frame.area),
+        let mir = match class.cp_cache.get_method(&self.frame.class, idx) {
+            Ok(mir) => mir,
+            Err(_) => return,
         };
-        debug_assert_eq!(mir.method.is_static(), is_static);
-        if let Ok(mut jc) = runtime::invoke::JavaCall::new(&self.frame.area, mir) {
-            jc.invoke(caller, force_no_resolve);
+        self.invoke_resolved(is_static, mir, force_no_resolve);
+    }
+
+    fn invoke_resolved(&mut self, is_static: bool, mir: MethodIdRef, force_no_resolve: bool) {
+        if mir.method.is_static() != is_static {
+            let expected = if is_static {
+                "Expected static"
+            } else {
+                "Expecting non-static"
+            };
+            let msg = format!(
+                "{} method {}.{}{}",
+                expected,
+                String::from_utf8_lossy(mir.method.cls_name.as_slice()).replace('/', "."),
+                String::from_utf8_lossy(mir.method.name.as_slice()),
+                String::from_utf8_lossy(mir.method.desc.as_slice())
+            );
+            exception::meet_ex(cls_const::J_INCOMPATIBLE_CLASS_CHANGE_ERROR, Some(msg));
+            return;
+        }
+
+        if let Ok(mut jc) = JavaCall::new(&self.frame.area, mir) {
+            if let Some(callee) = jc.invoke_from_interp(&self.frame.area, force_no_resolve) {
+                self.push_frame(callee);
+            }
         }
     }
 
-    pub fn check_cast_helper(&self, is_cast: bool) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let cp_idx = read_i2!(pc, codes);
-        let target_cls = require_class2(cp_idx as U2, &self.cp).unwrap();
+    pub fn check_cast_helper(&self, insn: Insn, is_cast: bool) {
+        let target_cls = match require_class2(self.class_loader(), insn.a as U2, &self.cp) {
+            Some(class) => class,
+            None => return,
+        };
         let obj_rf = self.pop_value(ValueType::OBJECT);
         let obj_rf_clone = obj_rf.clone();
         let op_check_cast = |r: bool, obj_cls: ClassRef, target_cls: ClassRef| {
             if r {
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.push_ref(obj_rf_clone, false);
+                let stack = self.frame.area.stack();
+                stack.push_ref(obj_rf_clone);
             } else {
                 let obj_name = { obj_cls.get_class().name.clone() };
                 let target_name = { target_cls.get_class().name.clone() };
@@ -912,21 +981,21 @@ impl<'a> Interp<'a> {
             }
         };
         let op_instance_of = |r: bool| {
-            let mut stack = self.frame.area.stack.borrow_mut();
+            let stack = self.frame.area.stack();
             if r {
-                stack.push_const1(false);
+                stack.push_int(1);
             } else {
-                stack.push_const0(false);
+                stack.push_int(0);
             }
         };
 
         match obj_rf {
             Oop::Null => {
-                let mut stack = self.frame.area.stack.borrow_mut();
+                let stack = self.frame.area.stack();
                 if is_cast {
-                    stack.push_ref(obj_rf, false);
+                    stack.push_ref(obj_rf);
                 } else {
-                    stack.push_const0(false);
+                    stack.push_int(0);
                 }
             }
             Oop::Ref(rf) => {
@@ -977,23 +1046,31 @@ impl<'a> Interp<'a> {
             _ => unreachable!(),
         }
     }
+}
 
-    #[inline]
-    fn opcode_pos(&mut self) -> usize {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let op_widen = self.op_widen;
-        if op_widen {
-            self.op_widen = false;
-            read_u2!(pc, codes)
-        } else {
-            read_u1!(pc, codes)
+//handle exception
+impl Interp {
+    //find the handler of the pending exception, false if not in the loop
+    fn unwind(&mut self, jt: &JavaThreadRef) -> bool {
+        let mut ex = jt.write().unwrap().take_ex().unwrap();
+        loop {
+            match self.try_handle_exception(ex) {
+                Ok(_) => return true,
+                Err(v) => ex = v,
+            }
+
+            let has_caller = self.pop_frame();
+            //IllegalMonitorStateException of a synchronized method replaces it
+            if thread::is_meet_ex() {
+                ex = jt.write().unwrap().take_ex().unwrap();
+            }
+            if !has_caller {
+                jt.write().unwrap().set_ex(ex);
+                return false;
+            }
         }
     }
-}
 
-//handle exception
-impl<'a> Interp<'a> {
     fn try_handle_exception(&self, ex: Oop) -> Result<(), Oop> {
         let ex_cls = {
             let rf = ex.extract_ref();
@@ -1011,10 +1088,10 @@ impl<'a> Interp<'a> {
 
         match handler {
             Some(pc) => {
-                let mut stack = self.frame.area.stack.borrow_mut();
-                stack.clear();
-                stack.push_ref(ex, false);
-                drop(stack);
+                let stack = self.frame.area.stack();
+                let max_locals = self.frame.mir.method.get_max_locals();
+                stack.truncate(self.base + max_locals);
+                stack.push_ref(ex);
 
                 let line_num = self.frame.mir.method.get_line_num(pc);
 
@@ -1043,52 +1120,48 @@ impl<'a> Interp<'a> {
 }
 
 //byte code impl
-impl<'a> Interp<'a> {
+impl Interp {
     #[inline]
-    fn sipush(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let v = read_i2!(pc, codes);
-        let mut stack = self.frame.area.stack.borrow_mut();
-        stack.push_int(v);
+    fn sipush(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        stack.push_int(insn.b);
     }
 
     #[inline]
-    fn bipush(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let v = (read_byte!(pc, codes) as i8) as i32;
-        let mut stack = self.frame.area.stack.borrow_mut();
-        stack.push_int(v);
+    fn bipush(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        stack.push_int(insn.b);
     }
 
     #[inline]
-    fn ldc(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let pos = read_u1!(pc, codes);
-        self.load_constant(pos);
+    fn ldc(&self, insn: Insn) {
+        if let Some(v) = self.resolve_constant(insn.a) {
+            let resolved = Resolved::Constant(v.clone());
+            let op = bytecode::LDC_QUICK;
+            self.code
+                .quicken(insn.pc, op, insn.a, insn.b, Some(resolved));
+
+            let stack = self.frame.area.stack();
+            stack.push_value(v);
+        }
     }
 
     #[inline]
-    fn ldc_w(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let pos = read_u2!(pc, codes);
-        self.load_constant(pos);
+    fn ldc_w(&self, insn: Insn) {
+        self.ldc(insn);
     }
 
     #[inline]
-    fn ldc2_w(&self) {
-        self.ldc_w();
+    fn ldc2_w(&self, insn: Insn) {
+        self.ldc(insn);
     }
 
     #[inline]
     fn iaload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_type_array();
@@ -1101,10 +1174,10 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn saload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_type_array();
@@ -1117,10 +1190,10 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn caload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_type_array();
@@ -1133,10 +1206,10 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn baload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let mut rf = (*rf).get_raw_ptr();
@@ -1168,10 +1241,10 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn laload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_type_array();
@@ -1191,10 +1264,10 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn faload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_type_array();
@@ -1214,10 +1287,10 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn daload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_type_array();
@@ -1237,10 +1310,10 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn aaload(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_array();
@@ -1252,7 +1325,7 @@ impl<'a> Interp<'a> {
                     exception::meet_ex(cls_const::J_ARRAY_INDEX_OUT_OF_BOUNDS, Some(msg));
                 } else {
                     let v = ary[pos as usize].clone();
-                    stack.push_ref(v, false);
+                    stack.push_ref(v);
                 }
             }
             _ => unreachable!(),
@@ -1261,13 +1334,12 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn bastore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let mut rf = (*rf).get_mut_raw_ptr();
@@ -1292,13 +1364,12 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn castore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_mut_type_array();
@@ -1312,13 +1383,12 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn sastore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_mut_type_array();
@@ -1332,13 +1402,12 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn iastore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_mut_type_array();
@@ -1351,13 +1420,12 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn lastore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_long();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_mut_type_array();
@@ -1370,13 +1438,12 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn fastore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_float();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_mut_type_array();
@@ -1389,13 +1456,12 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn dastore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_double();
         let pos = stack.pop_int();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let ary = rf.extract_mut_type_array();
@@ -1408,17 +1474,18 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn aastore(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_ref();
         let pos = stack.pop_int();
         let ary_rf = stack.pop_ref();
-        drop(stack);
 
-        match ary_rf {
+        match &*ary_rf {
             Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
+                gc::write_barrier(rf, &v);
                 let ary = rf.extract_mut_array();
                 let ary = &mut ary.elements;
+                let v = v.clone();
                 array_store!(ary, pos, v);
             }
             _ => unreachable!(),
@@ -1427,697 +1494,457 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn pop(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.drop_top();
     }
 
     #[inline]
     fn pop2(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.drop_top();
         stack.drop_top();
     }
 
     #[inline]
     fn dup(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.dup();
     }
 
     #[inline]
     fn dup_x1(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.dup_x1();
     }
 
     #[inline]
     fn dup_x2(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.dup_x2();
     }
 
     #[inline]
     fn dup2(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.dup2();
     }
 
     #[inline]
     fn dup2_x1(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.dup2_x1();
     }
 
     #[inline]
     fn dup2_x2(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.dup2_x2();
     }
 
     #[inline]
     fn swap(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         stack.swap();
     }
 
     #[inline]
     fn idiv(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_int();
-        if v2 == 0 {
-            drop(stack);
-            exception::meet_ex(
-                cls_const::J_ARITHMETIC_EX,
-                Some("divide by zero".to_string()),
-            );
-        } else {
-            stack.push_int(v1 / v2);
+        match arith::idiv(v1, v2) {
+            Some(v) => stack.push_int(v),
+            None => exception::meet_ex(cls_const::J_ARITHMETIC_EX, Some("/ by zero".to_string())),
         }
     }
 
     #[inline]
     fn ldiv(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_long();
         let v1 = stack.pop_long();
-        if v2 == 0 {
-            drop(stack);
-            exception::meet_ex(
-                cls_const::J_ARITHMETIC_EX,
-                Some("divide by zero".to_string()),
-            );
-        } else {
-            stack.push_long(v1 / v2);
+        match arith::ldiv(v1, v2) {
+            Some(v) => stack.push_long(v),
+            None => exception::meet_ex(cls_const::J_ARITHMETIC_EX, Some("/ by zero".to_string())),
         }
     }
 
     #[inline]
     fn fdiv(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_float();
         let v1 = stack.pop_float();
-        if v2 == 0.0 {
-            drop(stack);
-            exception::meet_ex(
-                cls_const::J_ARITHMETIC_EX,
-                Some("divide by zero".to_string()),
-            );
-        } else {
-            stack.push_float(v1 / v2);
-        }
+        stack.push_float(arith::fdiv(v1, v2));
     }
 
     #[inline]
     fn ddiv(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_double();
         let v1 = stack.pop_double();
-        if v2 == 0.0 {
-            drop(stack);
-            exception::meet_ex(
-                cls_const::J_ARITHMETIC_EX,
-                Some("divide by zero".to_string()),
-            );
-        } else {
-            stack.push_double(v1 / v2);
-        }
+        stack.push_double(arith::ddiv(v1, v2));
     }
 
     #[inline]
     fn irem(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_int();
-        if v2 == 0 {
-            drop(stack);
-            exception::meet_ex(
-                cls_const::J_ARITHMETIC_EX,
-                Some("divide by zero".to_string()),
-            );
-        } else {
-            stack.push_int(v1 - (v1 / v2) * v2);
+        match arith::irem(v1, v2) {
+            Some(v) => stack.push_int(v),
+            None => exception::meet_ex(cls_const::J_ARITHMETIC_EX, Some("/ by zero".to_string())),
         }
     }
 
     #[inline]
     fn lrem(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_long();
         let v1 = stack.pop_long();
-        if v2 == 0 {
-            drop(stack);
-            exception::meet_ex(
-                cls_const::J_ARITHMETIC_EX,
-                Some("divide by zero".to_string()),
-            );
-        } else {
-            stack.push_long(v1 - (v1 / v2) * v2);
+        match arith::lrem(v1, v2) {
+            Some(v) => stack.push_long(v),
+            None => exception::meet_ex(cls_const::J_ARITHMETIC_EX, Some("/ by zero".to_string())),
         }
     }
 
     #[inline]
     fn frem(&self) {
-        panic!("Use of deprecated instruction frem, please check your Java compiler");
+        let stack = self.frame.area.stack();
+        let v2 = stack.pop_float();
+        let v1 = stack.pop_float();
+        stack.push_float(arith::frem(v1, v2));
     }
 
     #[inline]
     fn drem(&self) {
-        panic!("Use of deprecated instruction drem, please check your Java compiler");
+        let stack = self.frame.area.stack();
+        let v2 = stack.pop_double();
+        let v1 = stack.pop_double();
+        stack.push_double(arith::drem(v1, v2));
     }
 
     #[inline]
     fn ineg(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
-        stack.push_int(-v);
+        stack.push_int(v.wrapping_neg());
     }
 
     #[inline]
     fn lneg(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_long();
-        stack.push_long(-v);
+        stack.push_long(v.wrapping_neg());
     }
 
     #[inline]
     fn fneg(&self) {
-        panic!("Use of deprecated instruction fneg, please check your Java compiler");
+        let stack = self.frame.area.stack();
+        let v = stack.pop_float();
+        stack.push_float(arith::fneg(v));
     }
 
     #[inline]
     fn dneg(&self) {
-        panic!("Use of deprecated instruction dneg, please check your Java compiler");
+        let stack = self.frame.area.stack();
+        let v = stack.pop_double();
+        stack.push_double(arith::dneg(v));
     }
 
     #[inline]
     fn ishl(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_int();
-        let s = v2 & 0x1F;
-        stack.push_int(v1 << s);
+        stack.push_int(arith::ishl(v1, v2));
     }
 
     #[inline]
     fn lshl(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_long();
-        let s = (v2 & 0x3F) as i64;
-        stack.push_long(v1 << s);
+        stack.push_long(arith::lshl(v1, v2));
     }
 
     #[inline]
     fn ishr(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_int();
-        let s = v2 & 0x1F;
-        stack.push_int(v1 >> s);
+        stack.push_int(arith::ishr(v1, v2));
     }
 
     #[inline]
     fn lshr(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
         let v1 = stack.pop_long();
-        let s = (v2 & 0x3F) as i64;
-        stack.push_long(v1 >> s);
+        stack.push_long(arith::lshr(v1, v2));
     }
 
     #[inline]
     fn iushr(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
-        let v1 = stack.pop_int() as u32;
-        let s = (v2 & 0x1F) as u32;
-        stack.push_int((v1 >> s) as i32);
+        let v1 = stack.pop_int();
+        stack.push_int(arith::iushr(v1, v2));
     }
 
     #[inline]
     fn lushr(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_int();
-        let v1 = stack.pop_long() as u64;
-        let s = (v2 & 0x3F) as u64;
-        stack.push_long((v1 >> s) as i64);
-    }
-
-    #[inline]
-    fn iinc(&mut self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let op_widen = self.op_widen;
-        let pos;
-        let factor;
-        if op_widen {
-            self.op_widen = false;
-            pos = read_u2!(pc, codes);
-            factor = (read_u2!(pc, codes) as i16) as i32
-        } else {
-            pos = read_u1!(pc, codes);
-            factor = (read_byte!(pc, codes) as i8) as i32
-        };
+        let v1 = stack.pop_long();
+        stack.push_long(arith::lushr(v1, v2));
+    }
 
-        let v = self.local.get_int(pos);
-        let v = v.wrapping_add(factor);
-        self.local.set_int(pos, v);
+    #[inline]
+    fn iinc(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        let v = stack.get_int(self.base + insn.a);
+        stack.set_int(self.base + insn.a, v.wrapping_add(insn.b));
     }
 
     #[inline]
     fn i2l(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
         stack.push_long(v as i64);
     }
 
     #[inline]
     fn i2f(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
-        stack.push_float(v as f32);
+        stack.push_float(arith::i2f(v));
     }
 
     #[inline]
     fn i2d(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
         stack.push_double(v as f64);
     }
 
     #[inline]
     fn l2i(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_long();
-        stack.push_int(v as i32);
+        stack.push_int(arith::l2i(v));
     }
 
     #[inline]
     fn l2f(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_long();
         stack.push_float(v as f32);
     }
 
     #[inline]
     fn l2d(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_long();
         stack.push_double(v as f64);
     }
 
     #[inline]
     fn f2i(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_float();
-        if v.is_nan() {
-            stack.push_int(0);
-        } else if v.is_infinite() {
-            if v.is_sign_positive() {
-                stack.push_int(i32::MAX);
-            } else {
-                stack.push_int(i32::MIN);
-            }
-        } else {
-            stack.push_int(v as i32);
-        }
+        stack.push_int(arith::f2i(v));
     }
 
     #[inline]
     fn f2l(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_float();
-        if v.is_nan() {
-            stack.push_long(0);
-        } else if v.is_infinite() {
-            if v.is_sign_positive() {
-                stack.push_long(i64::MAX);
-            } else {
-                stack.push_long(i64::MIN);
-            }
-        } else {
-            stack.push_long(v as i64);
-        }
+        stack.push_long(arith::f2l(v));
     }
 
     #[inline]
     fn f2d(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_float();
         stack.push_double(v as f64);
     }
 
     #[inline]
     fn d2i(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_double();
-        if v.is_nan() {
-            stack.push_int(0);
-        } else if v.is_infinite() {
-            if v.is_sign_positive() {
-                stack.push_int(i32::MAX);
-            } else {
-                stack.push_int(i32::MIN);
-            }
-        } else {
-            stack.push_int(v as i32);
-        }
+        stack.push_int(arith::d2i(v));
     }
 
     #[inline]
     fn d2l(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_double();
-        if v.is_nan() {
-            stack.push_long(0);
-        } else if v.is_infinite() {
-            if v.is_sign_positive() {
-                stack.push_long(i64::MAX);
-            } else {
-                stack.push_long(i64::MIN);
-            }
-        } else {
-            stack.push_long(v as i64);
-        }
+        stack.push_long(arith::d2l(v));
     }
 
     #[inline]
     fn d2f(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_double();
-        stack.push_float(v as f32);
+        stack.push_float(arith::d2f(v));
     }
 
     #[inline]
     fn i2b(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
-        let v = v as i8;
-        stack.push_int(v as i32);
+        stack.push_int(arith::i2b(v));
     }
 
     #[inline]
     fn i2c(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
-        let v = v as u16;
-        stack.push_int(v as i32);
+        stack.push_int(arith::i2c(v));
     }
 
     #[inline]
     fn i2s(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
-        let v = v as i16;
-        stack.push_int(v as i32);
+        stack.push_int(arith::i2s(v));
     }
 
     #[inline]
     fn lcmp(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let v1 = stack.pop_long();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_long();
-        let v = match v1.cmp(&v2) {
-            std::cmp::Ordering::Greater => -1,
-            std::cmp::Ordering::Less => 1,
-            std::cmp::Ordering::Equal => 0,
-        };
-        stack.push_int(v);
+        let v1 = stack.pop_long();
+        stack.push_int(arith::lcmp(v1, v2));
     }
 
     #[inline]
     fn fcmpl(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let v1 = stack.pop_float();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_float();
-        let v = if v1.is_nan() || v2.is_nan() {
-            -1
-        } else if v1 > v2 {
-            -1
-        } else if v1 < v2 {
-            1
-        } else {
-            0
-        };
-        stack.push_int(v);
+        let v1 = stack.pop_float();
+        stack.push_int(arith::fcmp(v1, v2, -1));
     }
 
     #[inline]
     fn fcmpg(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let v1 = stack.pop_float();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_float();
-        let v = if v1.is_nan() || v2.is_nan() {
-            1
-        } else if v1 > v2 {
-            -1
-        } else if v1 < v2 {
-            1
-        } else {
-            0
-        };
-        stack.push_int(v);
+        let v1 = stack.pop_float();
+        stack.push_int(arith::fcmp(v1, v2, 1));
     }
 
     #[inline]
     fn dcmpl(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let v1 = stack.pop_double();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_double();
-        let v = if v1.is_nan() || v2.is_nan() {
-            -1
-        } else if v1 > v2 {
-            -1
-        } else if v1 < v2 {
-            1
-        } else {
-            0
-        };
-        stack.push_int(v);
+        let v1 = stack.pop_double();
+        stack.push_int(arith::dcmp(v1, v2, -1));
     }
 
     #[inline]
     fn dcmpg(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let v1 = stack.pop_double();
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_double();
-        if v1.is_nan() || v2.is_nan() {
-            stack.push_int(1);
-        } else if v1 > v2 {
-            stack.push_int(-1);
-        } else if v1 < v2 {
-            stack.push_int(1);
-        } else {
-            stack.push_int(0);
-        }
+        let v1 = stack.pop_double();
+        stack.push_int(arith::dcmp(v1, v2, 1));
     }
 
     #[inline]
-    fn if_acmpeq(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+    fn if_acmpeq(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_ref();
         let v1 = stack.pop_ref();
         if OopPtr::is_eq(&v1, &v2) {
-            drop(stack);
-            self.goto_by_offset_hardcoded(2);
-        } else {
-            let _ = self.frame.pc.fetch_add(2, Ordering::Relaxed);
+            self.goto_abs(insn.b);
         }
     }
 
     #[inline]
-    fn if_acmpne(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+    fn if_acmpne(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
         let v2 = stack.pop_ref();
         let v1 = stack.pop_ref();
         if !OopPtr::is_eq(&v1, &v2) {
-            drop(stack);
-            self.goto_by_offset_hardcoded(2);
-        } else {
-            let _ = self.frame.pc.fetch_add(2, Ordering::Relaxed);
+            self.goto_abs(insn.b);
         }
     }
 
     #[inline]
-    fn goto(&self) {
-        self.goto_by_offset_hardcoded(2);
+    fn goto(&self, insn: Insn) {
+        self.goto_abs(insn.b);
     }
 
+    //the returnAddress is the pc of the next instruction, JVM spec 6.5 jsr
     #[inline]
-    fn jsr(&self) {
-        let _ = self.frame.pc.fetch_add(2, Ordering::Relaxed);
-        panic!("Use of deprecated instruction jsr, please check your Java compiler");
+    fn jsr(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        stack.push_int(insn.next);
+        self.goto_abs(insn.b);
     }
 
     #[inline]
-    fn ret(&mut self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let op_widen = self.op_widen;
-        let new_pc = if op_widen {
-            self.op_widen = false;
-            read_u2!(pc, codes)
-        } else {
-            read_u1!(pc, codes)
-        };
-        pc.store(new_pc as i32, Ordering::Relaxed);
+    fn ret(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        let pc = stack.get_int(self.base + insn.a);
+        self.goto_abs(pc);
     }
 
     #[inline]
-    fn table_switch(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let mut bc = pc.load(Ordering::Relaxed) - 1;
-
-        let origin_bc = bc;
-        if bc % 4 != 0 {
-            bc += (4 - bc % 4);
-        } else {
-            bc += 4;
-        }
-        let mut ptr = bc as usize;
-
-        let default_byte = [codes[ptr], codes[ptr + 1], codes[ptr + 2], codes[ptr + 3]];
-        let default_byte = i32::from_be_bytes(default_byte);
-        let low_byte = [
-            codes[ptr + 4],
-            codes[ptr + 5],
-            codes[ptr + 6],
-            codes[ptr + 7],
-        ];
-        let low_byte = i32::from_be_bytes(low_byte);
-        let high_byte = [
-            codes[ptr + 8],
-            codes[ptr + 9],
-            codes[ptr + 10],
-            codes[ptr + 11],
-        ];
-        let high_byte = i32::from_be_bytes(high_byte);
-        let num = high_byte - low_byte + 1;
-        ptr += 12;
-
-        // switch-case jump table
-        let mut jump_table = Vec::with_capacity(num as usize);
-        for pos in 0..num {
-            let pos = [codes[ptr], codes[ptr + 1], codes[ptr + 2], codes[ptr + 3]];
-            let pos = i32::from_be_bytes(pos);
-            let jump_pos = pos + origin_bc;
-            ptr += 4;
-            jump_table.push(jump_pos);
-        }
-        // default
-        jump_table.push(default_byte + origin_bc);
-
-        let top_value = {
-            let mut stack = self.frame.area.stack.borrow_mut();
-            stack.pop_int()
-        };
-        if (top_value > (jump_table.len() as i32 - 1 + low_byte)) || top_value < low_byte {
-            self.goto_abs_with_occupied(*jump_table.last().unwrap() as i32, 1);
-        } else {
-            self.goto_abs_with_occupied(
-                jump_table[(top_value - low_byte as i32) as usize] as i32,
-                1,
-            );
-        }
+    fn table_switch(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        let v = stack.pop_int();
+        self.goto_abs(self.code.switch(insn.b).target(v));
     }
 
     #[inline]
-    fn lookup_switch(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let mut bc = pc.load(Ordering::Relaxed) - 1;
-
-        let origin_bc = bc;
-        if bc % 4 != 0 {
-            bc += (4 - bc % 4);
-        } else {
-            bc += 4;
-        }
-        let mut ptr = bc as usize;
-
-        let default_byte = [codes[ptr], codes[ptr + 1], codes[ptr + 2], codes[ptr + 3]];
-        let default_byte = u32::from_be_bytes(default_byte);
-        let count = [
-            codes[ptr + 4],
-            codes[ptr + 5],
-            codes[ptr + 6],
-            codes[ptr + 7],
-        ];
-        let count = u32::from_be_bytes(count);
-        ptr += 8;
-
-        let mut jump_table: HashMap<u32, u32> = HashMap::new();
-        for i in 0..count {
-            let value = [codes[ptr], codes[ptr + 1], codes[ptr + 2], codes[ptr + 3]];
-            let value = u32::from_be_bytes(value);
-            let position = [
-                codes[ptr + 4],
-                codes[ptr + 5],
-                codes[ptr + 6],
-                codes[ptr + 7],
-            ];
-            let position = u32::from_be_bytes(position) + origin_bc as u32;
-            ptr += 8;
-            jump_table.insert(value, position);
-        }
-
-        let top_value = {
-            let mut stack = self.frame.area.stack.borrow_mut();
-            stack.pop_int()
-        };
-        match jump_table.get(&(top_value as u32)) {
-            Some(position) => self.goto_abs_with_occupied(*position as i32, 1),
-            None => self.goto_abs_with_occupied(default_byte as i32 + origin_bc, 1),
-        }
+    fn lookup_switch(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        let v = stack.pop_int();
+        self.goto_abs(self.code.switch(insn.b).target(v));
     }
 
     #[inline]
     fn ireturn(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_int();
         let v = Oop::new_int(v);
-        drop(stack);
 
         self.set_return(Some(v));
     }
 
     #[inline]
     fn lreturn(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_long();
         let v = Oop::new_long(v);
-        drop(stack);
 
         self.set_return(Some(v));
     }
 
     #[inline]
     fn freturn(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_float();
         let v = Oop::new_float(v);
-        drop(stack);
 
         self.set_return(Some(v));
     }
 
     #[inline]
     fn dreturn(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_double();
         let v = Oop::new_double(v);
-        drop(stack);
 
         self.set_return(Some(v));
     }
 
     #[inline]
     fn areturn(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let v = stack.pop_ref();
-        drop(stack);
+        let stack = self.frame.area.stack();
+        let v = stack.pop_ref().clone();
 
         self.set_return(Some(v));
     }
@@ -2128,208 +1955,204 @@ impl<'a> Interp<'a> {
     }
 
     #[inline]
-    fn get_static(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let cp_idx = read_u2!(pc, codes);
-        self.get_field_helper(Oop::Null, cp_idx, true);
+    fn get_static(&self, insn: Insn) {
+        self.get_field_helper(Oop::Null, insn, true);
     }
 
     #[inline]
-    fn put_static(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let cp_idx = read_u2!(pc, codes);
-        self.put_field_helper(cp_idx, true);
+    fn put_static(&self, insn: Insn) {
+        self.put_field_helper(insn, true);
     }
 
     #[inline]
-    fn get_field(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let idx = read_u2!(pc, codes);
-
-        let mut stack = self.frame.area.stack.borrow_mut();
+    fn get_field(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
         let rf = stack.pop_ref();
-        drop(stack);
 
-        match rf {
+        match &*rf {
             Oop::Null => {
                 exception::meet_ex(cls_const::J_NPE, None);
             }
             _ => {
-                self.get_field_helper(rf, idx, false);
+                self.get_field_helper(rf.clone(), insn, false);
             }
         }
     }
 
     #[inline]
-    fn put_field(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let idx = read_u2!(pc, codes);
-        self.put_field_helper(idx, false);
+    fn put_field(&self, insn: Insn) {
+        self.put_field_helper(insn, false);
     }
 
     #[inline]
-    fn invoke_virtual(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let idx = read_u2!(pc, codes);
-        self.invoke_helper(false, idx, false);
+    fn invoke_virtual(&mut self, insn: Insn) {
+        let class = self.frame.class.extract_inst();
+        let mir = match class.cp_cache.get_method(&self.frame.class, insn.a) {
+            Ok(mir) => mir,
+            Err(_) => return,
+        };
+
+        let resolved = Resolved::Method(mir.clone());
+        let op = bytecode::INVOKEVIRTUAL_QUICK;
+        self.code
+            .quicken(insn.pc, op, insn.a, insn.b, Some(resolved));
+        self.invoke_resolved(false, mir, false);
     }
 
     #[inline]
-    fn invoke_special(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let idx = read_u2!(pc, codes);
-        self.invoke_helper(false, idx, true);
+    fn invoke_special(&mut self, insn: Insn) {
+        self.invoke_helper(false, insn.a, true);
     }
 
     #[inline]
-    fn invoke_static(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let idx = read_u2!(pc, codes);
-        self.invoke_helper(true, idx, true);
+    fn invoke_static(&mut self, insn: Insn) {
+        self.invoke_helper(true, insn.a, true);
     }
 
     #[inline]
-    fn invoke_interface(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let cp_idx = read_u2!(pc, codes);
-        let _count = read_u1!(pc, codes);
-        let zero = read_u1!(pc, codes);
-        if zero != 0 {
-            warn!("interpreter: invalid invokeinterface: the value of the fourth operand byte must always be zero.");
-        }
-
-        self.invoke_helper(false, cp_idx, false);
+    fn invoke_interface(&mut self, insn: Insn) {
+        self.invoke_helper(false, insn.a, false);
     }
 
     #[inline]
-    fn invoke_dynamic(&self) {
-        //todo: impl
-        unimplemented!()
+    fn invoke_dynamic(&self, insn: Insn) {
+        let class = self.frame.class.extract_inst();
+        let k = (self.frame.mir.offset, insn.pc as usize);
+        if let Ok(site) = class.cp_cache.get_call_site(&self.frame.class, k, insn.a) {
+            site.invoke(&self.frame.area);
+        }
     }
 
     #[inline]
-    fn new_(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let idx = read_u2!(pc, codes);
+    fn new_(&self, insn: Insn) {
+        let idx = insn.a;
 
         let class = {
-            match runtime::require_class2(idx as u16, &self.cp) {
+            match runtime::require_class2(self.class_loader(), idx as u16, &self.cp) {
                 Some(class) => {
-                    oop::class::init_class(&class);
-                    oop::class::init_class_fully(&class);
+                    if oop::class::init_class(&class).is_err() {
+                        return;
+                    }
 
                     class
                 }
-                None => unreachable!("Cannot get class info from constant pool"),
+                None => return,
             }
         };
 
+        let size = class.extract_inst().n_inst_fields * std::mem::size_of::<Oop>();
+        if gc::ensure_capacity(size).is_err() {
+            return;
+        }
+
+        let has_finalizer = class.extract_inst().has_finalizer;
         let v = oop::Oop::new_inst(class);
-        let mut stack = self.frame.area.stack.borrow_mut();
-        stack.push_ref(v, false);
+        if has_finalizer {
+            gc::register_finalizer(v.clone());
+            if thread::is_meet_ex() {
+                return;
+            }
+        }
+
+        let stack = self.frame.area.stack();
+        stack.push_ref(v);
     }
 
     #[inline]
-    fn new_array(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let ary_type = read_byte!(pc, codes);
-        let mut stack = self.frame.area.stack.borrow_mut();
+    fn new_array(&self, insn: Insn) {
+        let ary_type = insn.a as U1;
+        let stack = self.frame.area.stack();
         let len = stack.pop_int();
         if len < 0 {
-            drop(stack);
-            exception::meet_ex(cls_const::J_NASE, Some("length < 0".to_string()));
+            exception::meet_ex(cls_const::J_NASE, Some(len.to_string()));
         } else {
             let len = len as usize;
+            let size = len.saturating_mul(TypeArrayEnum::from(ary_type).elem_size());
+            if gc::ensure_capacity(size).is_err() {
+                return;
+            }
+
             let ary = Oop::new_type_ary(ary_type, len);
-            stack.push_ref(ary, false);
+            let stack = self.frame.area.stack();
+            stack.push_ref(ary);
         }
     }
 
     #[inline]
-    fn anew_array(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let cp_idx = read_i2!(pc, codes);
+    fn anew_array(&self, insn: Insn) {
+        let cp_idx = insn.a;
 
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let length = stack.pop_int();
-        drop(stack);
 
-        //        info!("anew_array length={}", length);
-        if length < 0 {
-            exception::meet_ex(cls_const::J_NASE, Some("length < 0".to_string()));
-        } else {
-            let class = match runtime::require_class2(cp_idx as u16, &self.cp) {
-                Some(class) => class,
-                None => panic!("Cannot get class info from constant pool"),
-            };
+        //the class is resolved before the length is checked, JVM spec 6.5 anewarray
+        let class = match runtime::require_class2(self.class_loader(), cp_idx as u16, &self.cp) {
+            Some(class) => class,
+            None => return,
+        };
 
-            oop::class::init_class(&class);
-            oop::class::init_class_fully(&class);
-
-            let (name, cl) = {
-                let class = class.get_class();
-                let t = class.get_class_kind_type();
-                let name = match t {
-                    oop::class::ClassKindType::Instance | oop::class::ClassKindType::ObjectAry => {
-                        let mut v = Vec::with_capacity(class.name.len() + 3);
-                        v.push(b'[');
-                        v.push(b'L');
-                        v.extend_from_slice(class.name.as_slice());
-                        v.push(b';');
-
-                        v
-                    }
-                    oop::class::ClassKindType::TypAry => {
-                        let mut v = Vec::with_capacity(class.name.len() + 1);
-                        v.push(b'[');
-                        v.extend_from_slice(class.name.as_slice());
+        let (name, cl) = {
+            let class = class.get_class();
+            let t = class.get_class_kind_type();
+            let name = match t {
+                oop::class::ClassKindType::Instance | oop::class::ClassKindType::ObjectAry => {
+                    let mut v = Vec::with_capacity(class.name.len() + 3);
+                    v.push(b'[');
+                    v.push(b'L');
+                    v.extend_from_slice(class.name.as_slice());
+                    v.push(b';');
 
-                        v
-                    }
-                };
+                    v
+                }
+                oop::class::ClassKindType::TypAry => {
+                    let mut v = Vec::with_capacity(class.name.len() + 1);
+                    v.push(b'[');
+                    v.extend_from_slice(class.name.as_slice());
 
-                let name = Arc::new(name);
-                (name, class.class_loader)
+                    v
+                }
             };
 
-            trace!("anew_array name={}", unsafe {
-                std::str::from_utf8_unchecked(name.as_slice())
-            });
-            match runtime::require_class(cl, &name) {
-                Some(ary_cls_obj) => {
-                    oop::class::init_class(&ary_cls_obj);
-                    oop::class::init_class_fully(&ary_cls_obj);
+            let name = Arc::new(name);
+            (name, class.class_loader)
+        };
 
-                    let ary = Oop::new_ref_ary(ary_cls_obj, length as usize);
-                    let mut stack = self.frame.area.stack.borrow_mut();
-                    stack.push_ref(ary, false);
-                }
-                None => unreachable!(),
+        trace!("anew_array name={}", unsafe {
+            std::str::from_utf8_unchecked(name.as_slice())
+        });
+        let ary_cls_obj = match runtime::require_class(cl, &name) {
+            Some(ary_cls_obj) => ary_cls_obj,
+            None => {
+                //the exception of the loader is kept, NoClassDefFoundError otherwise
+                runtime::no_class_def_found(name.as_slice());
+                return;
             }
+        };
+
+        if length < 0 {
+            exception::meet_ex(cls_const::J_NASE, Some(length.to_string()));
+            return;
+        }
+
+        let _ = oop::class::init_class(&ary_cls_obj);
+
+        let size = (length as usize).saturating_mul(std::mem::size_of::<Oop>());
+        if gc::ensure_capacity(size).is_err() {
+            return;
         }
+
+        let ary = Oop::new_ref_ary(ary_cls_obj, length as usize);
+        let stack = self.frame.area.stack();
+        stack.push_ref(ary);
     }
 
     #[inline]
     fn array_length(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+        let stack = self.frame.area.stack();
         let v = stack.pop_ref();
 
-        match v {
-            Oop::Null => {
-                drop(stack);
-                exception::meet_ex(cls_const::J_NPE, None)
-            }
+        match &*v {
+            Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
             Oop::Ref(rf) => {
                 let v = rf.get_raw_ptr();
                 unsafe {
@@ -2351,29 +2174,27 @@ impl<'a> Interp<'a> {
     }
 
     #[inline]
-    fn athrow(&self, jt: JavaThreadRef) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let ex = stack.pop_ref();
-        drop(stack);
+    fn athrow(&self, jt: &JavaThreadRef) {
+        let stack = self.frame.area.stack();
+        let ex = stack.pop_ref().clone();
 
         jt.write().unwrap().set_ex(ex);
     }
 
     #[inline]
-    fn check_cast(&self) {
-        self.check_cast_helper(true);
+    fn check_cast(&self, insn: Insn) {
+        self.check_cast_helper(insn, true);
     }
 
     #[inline]
-    fn instance_of(&self) {
-        self.check_cast_helper(false);
+    fn instance_of(&self, insn: Insn) {
+        self.check_cast_helper(insn, false);
     }
 
     #[inline]
     fn monitor_enter(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let v = stack.pop_ref();
-        drop(stack);
+        let stack = self.frame.area.stack();
+        let v = stack.pop_ref().clone();
 
         match v {
             Oop::Null => {
@@ -2386,129 +2207,577 @@ impl<'a> Interp<'a> {
 
     #[inline]
     fn monitor_exit(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
-        let mut v = stack.pop_ref();
-        drop(stack);
+        let stack = self.frame.area.stack();
+        let v = stack.pop_ref();
 
-        match v {
+        match &*v {
             Oop::Null => {
                 exception::meet_ex(cls_const::J_NPE, None);
             }
-            Oop::Ref(v) => v.monitor_exit(),
+            Oop::Ref(v) => {
+                if v.monitor_exit().is_err() {
+                    exception::meet_ex(cls_const::J_ILLEGAL_MONITOR_STATE, None);
+                }
+            }
             _ => unreachable!(),
         }
     }
 
     #[inline]
-    fn wide(&mut self) {
-        self.op_widen = true;
-    }
+    fn multi_anew_array(&self, insn: Insn) {
+        let cp_idx = insn.a;
+        let dimension = insn.b as usize;
 
-    #[inline]
-    fn multi_anew_array(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.code;
-        let cp_idx = read_u2!(pc, codes);
-        let dimension = read_u1!(pc, codes);
+        //the count of the first dimension is the deepest
+        let stack = self.frame.area.stack();
+        let mut lens: Vec<i32> = (0..dimension).map(|_| stack.pop_int()).collect();
+        lens.reverse();
+
+        //resolution errors come before the size checks, JVMS multianewarray
+        let cls = match require_class2(self.class_loader(), cp_idx as u16, &self.cp) {
+            Some(cls) => cls,
+            None => return,
+        };
+
+        if let Some(len) = lens.iter().find(|it| **it < 0) {
+            exception::meet_ex(cls_const::J_NASE, Some(len.to_string()));
+            return;
+        }
 
-        let mut lens = Vec::new();
-        let mut stack = self.frame.area.stack.borrow_mut();
-        for _ in 0..dimension {
-            let sub = stack.pop_int();
-            //todo: check java/lang/NegativeArraySizeException
-            lens.push(sub);
+        let n = lens
+            .iter()
+            .fold(1usize, |acc, it| acc.saturating_mul(*it as usize));
+        if gc::ensure_capacity(n.saturating_mul(std::mem::size_of::<Oop>())).is_err() {
+            return;
         }
-        drop(stack);
 
-        let cls = require_class2(cp_idx as u16, &self.cp).unwrap();
-        let ary = new_multi_object_array_helper(cls, &lens, 0);
+        let ary = new_multi_array(cls, &lens);
 
-        let mut stack = self.frame.area.stack.borrow_mut();
-        stack.push_ref(ary, false);
+        let stack = self.frame.area.stack();
+        stack.push_ref(ary);
     }
 
     #[inline]
-    fn if_null(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+    fn if_null(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
         let v = stack.pop_ref();
 
-        match v {
-            Oop::Null => {
-                drop(stack);
-                self.goto_by_offset_hardcoded(2)
-            }
-            _ => {
-                let pc = &self.frame.pc;
-                let _ = pc.fetch_add(2, Ordering::Relaxed);
-            }
+        if let Oop::Null = *v {
+            self.goto_abs(insn.b);
         }
     }
 
     #[inline]
-    fn if_non_null(&self) {
-        let mut stack = self.frame.area.stack.borrow_mut();
+    fn if_non_null(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
         let v = stack.pop_ref();
 
-        match v {
-            Oop::Null => {
-                let pc = &self.frame.pc;
-                let _ = pc.fetch_add(2, Ordering::Relaxed);
-            }
-            _ => {
-                drop(stack);
-                self.goto_by_offset_hardcoded(2)
+        if let Oop::Ref(_) = *v {
+            self.goto_abs(insn.b);
+        }
+    }
+
+    #[inline]
+    fn goto_w(&self, insn: Insn) {
+        self.goto_abs(insn.b);
+    }
+
+    #[inline]
+    fn jsr_w(&self, insn: Insn) {
+        self.jsr(insn);
+    }
+
+    #[inline]
+    fn other_wise(&self, insn: Insn) {
+        panic!("Use of undefined bytecode: {} at {}", insn.op, insn.pc);
+    }
+}
+
+//the quick forms, see bytecode
+impl Interp {
+    fn quick(&mut self, insn: Insn) {
+        match insn.op {
+            bytecode::GETFIELD_QUICK => self.get_field_quick(insn),
+            bytecode::PUTFIELD_QUICK => self.put_field_quick(insn),
+            bytecode::INVOKEVIRTUAL_QUICK => self.invoke_virtual_quick(insn),
+            bytecode::LDC_QUICK => self.ldc_quick(insn),
+            _ => self.other_wise(insn),
+        }
+    }
+
+    #[inline]
+    fn get_field_quick(&self, insn: Insn) {
+        let stack = self.frame.area.stack();
+        match &*stack.pop_ref() {
+            Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
+            rf => {
+                let v = Class::get_field_value2(rf.extract_ref(), insn.b as usize);
+                stack.push_value(v);
             }
         }
     }
 
     #[inline]
-    fn goto_w(&self) {
-        let pc = &self.frame.pc;
-        let _ = pc.fetch_add(4, Ordering::Relaxed);
-        panic!("Use of deprecated instruction goto_w, please check your Java compiler")
+    fn put_field_quick(&self, insn: Insn) {
+        let v = self.pop_value(ValueType::from(&(insn.a as u8)));
+        let stack = self.frame.area.stack();
+        match &*stack.pop_ref() {
+            Oop::Null => exception::meet_ex(cls_const::J_NPE, None),
+            rf => Class::put_field_value2(rf.extract_ref(), insn.b as usize, v),
+        }
     }
 
     #[inline]
-    fn jsr_w(&self) {
-        let pc = &self.frame.pc;
-        let _ = pc.fetch_add(4, Ordering::Relaxed);
-        panic!("Use of deprecated instruction jsr_w, please check your Java compiler")
+    fn invoke_virtual_quick(&mut self, insn: Insn) {
+        let mir = match self.code.resolved(insn.b) {
+            Resolved::Method(mir) => mir.clone(),
+            _ => unreachable!(),
+        };
+        self.invoke_resolved(false, mir, false);
     }
 
     #[inline]
-    fn other_wise(&self) {
-        let pc = &self.frame.pc;
-        let codes = &self.frame.code;
-        let pc = pc.load(Ordering::Relaxed);
-        let pc = pc - 1;
-        panic!(
-            "Use of undefined bytecode: {} at {}",
-            codes[pc as usize], pc
-        );
+    fn ldc_quick(&self, insn: Insn) {
+        let v = match self.code.resolved(insn.b) {
+            Resolved::Constant(v) => v.clone(),
+            _ => unreachable!(),
+        };
+        let stack = self.frame.area.stack();
+        stack.push_value(v);
     }
 }
 
-fn new_multi_object_array_helper(cls: ClassRef, lens: &[i32], idx: usize) -> Oop {
-    let length = lens[idx] as usize;
+//JVM spec 6.5 multianewarray, a count of 0 allocates no deeper dimension
+fn new_multi_array(cls: ClassRef, lens: &[i32]) -> Oop {
+    let length = lens[0] as usize;
 
-    let down_type = {
+    let (down_type, prime) = {
         let cls = cls.get_class();
         match &cls.kind {
-            oop::ClassKind::Instance(_) => unreachable!(),
-            ClassKind::ObjectArray(obj_ary) => obj_ary.down_type.clone().unwrap(),
-            ClassKind::TypeArray(typ_ary) => typ_ary.down_type.clone().unwrap(),
+            ClassKind::Instance(_) => unreachable!(),
+            ClassKind::ObjectArray(ary) => (ary.down_type.clone(), None),
+            //[I, not [[I
+            ClassKind::TypeArray(ary) if ary.down_type.is_none() => (None, Some(ary.value_type)),
+            ClassKind::TypeArray(ary) => (ary.down_type.clone(), None),
         }
     };
 
-    if idx < lens.len() - 1 {
-        let mut elms = Vec::with_capacity(length);
-        for i in 0..length {
-            let e = new_multi_object_array_helper(down_type.clone(), lens, idx + 1);
-            elms.push(e);
-        }
-
+    if lens.len() > 1 {
+        let down_type = down_type.unwrap();
+        let elms = (0..length)
+            .map(|_| new_multi_array(down_type.clone(), &lens[1..]))
+            .collect();
         Oop::new_ref_ary2(cls, elms)
     } else {
-        Oop::new_ref_ary(cls, length)
+        match prime {
+            Some(value_type) => Oop::new_type_ary(atype(value_type), length),
+            None => Oop::new_ref_ary(cls, length),
+        }
+    }
+}
+
+//the atype of newarray of the primitive type, JVM spec 6.5
+fn atype(value_type: ValueType) -> U1 {
+    match value_type {
+        ValueType::BOOLEAN => 4,
+        ValueType::CHAR => 5,
+        ValueType::FLOAT => 6,
+        ValueType::DOUBLE => 7,
+        ValueType::BYTE => 8,
+        ValueType::SHORT => 9,
+        ValueType::INT => 10,
+        ValueType::LONG => 11,
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::new_br;
+    use crate::runtime::define_class;
+    use classfile::attributes::{Code as CodeAttr, Type as AttrType};
+    use classfile::flags::{ACC_PUBLIC, ACC_STATIC};
+    use classfile::{FieldInfo, MethodInfo, Version};
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Once;
+
+    fn utf8(s: &str) -> ConstantPoolType {
+        ConstantPoolType::Utf8 {
+            bytes: Arc::new(s.as_bytes().to_vec()),
+        }
+    }
+
+    fn class_file(
+        cp: Vec<ConstantPoolType>,
+        super_class: U2,
+        methods: Vec<MethodInfo>,
+    ) -> ClassFile {
+        ClassFile {
+            version: Version {
+                minor: 0,
+                major: 52,
+            },
+            cp: Arc::new(cp),
+            acc_flags: ACC_PUBLIC,
+            this_class: 2,
+            super_class,
+            interfaces: vec![],
+            fields: vec![],
+            methods,
+            attrs: vec![],
+        }
+    }
+
+    /*
+    A class of the bootstrap loader with the field 'field', set by the
+    ctor '<init>(desc)V', the String and the exceptions thrown by the tests.
+    */
+    fn define_holder(name: &[u8], field: &str, desc: &str) {
+        let cp = vec![
+            ConstantPoolType::Nop,
+            utf8(std::str::from_utf8(name).unwrap()),
+            ConstantPoolType::Class { name_index: 1 },
+            utf8("java/lang/Object"),
+            ConstantPoolType::Class { name_index: 3 },
+            utf8("<init>"),
+            utf8(&format!("({})V", desc)),
+            utf8(field),
+            utf8(desc),
+            ConstantPoolType::NameAndType {
+                name_index: 7,
+                desc_index: 8,
+            },
+            ConstantPoolType::FieldRef {
+                class_index: 2,
+                name_and_type_index: 9,
+            },
+        ];
+        let code = CodeAttr {
+            max_stack: 2,
+            max_locals: 2,
+            //aload_0; aload_1; putfield #10; return
+            code: Arc::new(vec![0x2a, 0x2b, 0xb5, 0x00, 0x0a, 0xb1]),
+            exceptions: vec![],
+            attrs: vec![],
+        };
+        let ctor = MethodInfo {
+            acc_flags: ACC_PUBLIC,
+            name_index: 5,
+            desc_index: 6,
+            attrs: vec![AttrType::Code(code)],
+        };
+        let field = FieldInfo {
+            acc_flags: ACC_PUBLIC,
+            name_index: 7,
+            desc_index: 8,
+            attrs: vec![],
+        };
+        let cf = ClassFile {
+            fields: vec![field],
+            ..class_file(cp, 4, vec![ctor])
+        };
+        define_class(None, name, cf, None).unwrap();
+    }
+
+    /*
+    Run 'code' as the static method 'run' of 'desc' in a new class.
+
+    There's no class library, a bare java/lang/Object is defined in the
+    bootstrap loader for the classes and the arrays created here, and a
+    String and the exceptions of arith and arrays for the code throwing,
+    see run_ex. The constant pool has the class [[[I at 7, and the int
+    Integer.MIN_VALUE at 9.
+    */
+    fn run(desc: &str, max: (u16, u16), code: &[u8]) -> Option<Oop> {
+        run2(desc, max, code, vec![])
+    }
+
+    fn run2(desc: &str, max: (u16, u16), code: &[u8], args: Vec<Oop>) -> Option<Oop> {
+        match call(desc, max, code, args) {
+            Ok(v) => v,
+            Err(ex) => panic!("ex = {}", ex_name(&ex)),
+        }
+    }
+
+    //the exception thrown by 'code', (class name, detail message)
+    fn run_ex(desc: &str, max: (u16, u16), code: &[u8]) -> (String, String) {
+        let ex = match call(desc, max, code, vec![]) {
+            Ok(v) => panic!("no exception, v = {:?}", v),
+            Err(ex) => ex,
+        };
+
+        let rf = ex.extract_ref();
+        let msg = Class::get_field_value2(rf, 0);
+        let msg = OopPtr::java_lang_string(msg.extract_ref());
+        (ex_name(&ex), msg)
+    }
+
+    fn ex_name(ex: &Oop) -> String {
+        let rf = ex.extract_ref();
+        let cls = rf.extract_inst().class.clone();
+        let cls = cls.get_class();
+        String::from_utf8_lossy(cls.name.as_slice()).to_string()
+    }
+
+    fn call(desc: &str, max: (u16, u16), code: &[u8], args: Vec<Oop>) -> Result<Option<Oop>, Oop> {
+        static OBJECT: Once = Once::new();
+        static N: AtomicUsize = AtomicUsize::new(0);
+
+        OBJECT.call_once(|| {
+            let cp = vec![
+                ConstantPoolType::Nop,
+                utf8("java/lang/Object"),
+                ConstantPoolType::Class { name_index: 1 },
+            ];
+            let cf = class_file(cp, 0, vec![]);
+            define_class(None, cls_const::J_OBJECT, cf, None).unwrap();
+
+            //the value at 0, see util::oop::get_java_lang_string_value_offset
+            define_holder(cls_const::J_STRING, "value", "[C");
+            define_holder(
+                cls_const::J_ARITHMETIC_EX,
+                "detailMessage",
+                "Ljava/lang/String;",
+            );
+            define_holder(cls_const::J_NASE, "detailMessage", "Ljava/lang/String;");
+        });
+
+        let name = format!("Test{}", N.fetch_add(1, Ordering::Relaxed));
+        let cp = vec![
+            ConstantPoolType::Nop,
+            utf8(&name),
+            ConstantPoolType::Class { name_index: 1 },
+            utf8("java/lang/Object"),
+            ConstantPoolType::Class { name_index: 3 },
+            utf8("run"),
+            utf8(desc),
+            ConstantPoolType::Class { name_index: 8 },
+            utf8("[[[I"),
+            ConstantPoolType::Integer {
+                v: i32::MIN.to_be_bytes(),
+            },
+        ];
+        let code = CodeAttr {
+            max_stack: max.0,
+            max_locals: max.1,
+            code: Arc::new(code.to_vec()),
+            exceptions: vec![],
+            attrs: vec![],
+        };
+        let method = MethodInfo {
+            acc_flags: ACC_PUBLIC | ACC_STATIC,
+            name_index: 5,
+            desc_index: 6,
+            attrs: vec![AttrType::Code(code)],
+        };
+        let cf = class_file(cp, 4, vec![method]);
+        let cls = define_class(None, name.as_bytes(), cf, None).unwrap();
+        let mir = {
+            let cls = cls.get_class();
+            cls.get_static_method(&new_br("run"), &new_br(desc))
+                .unwrap()
+        };
+
+        let caller = DataArea::new();
+        let mut jc = JavaCall::new_with_args(mir, args);
+        jc.invoke(Some(&caller), true);
+        if thread::is_meet_ex() {
+            let jt = thread::current_java_thread();
+            let ex = jt.write().unwrap().take_ex().unwrap();
+            return Err(ex);
+        }
+        let v = caller.return_v.borrow_mut().take();
+        Ok(v)
+    }
+
+    #[test]
+    fn t_float_ops() {
+        let code = [
+            0x10, 0x07, // bipush 7
+            0x86, // i2f
+            0x10, 0xfd, // bipush -3
+            0x86, // i2f
+            0x72, // frem
+            0xae, // freturn
+        ];
+        let v = run("()F", (2, 0), &code).unwrap();
+        assert_eq!(v.extract_float(), 1.0);
+
+        //fconst_1; fconst_0; frem; freturn
+        let v = run("()F", (2, 0), &[0x0c, 0x0b, 0x72, 0xae]).unwrap();
+        assert!(v.extract_float().is_nan());
+
+        let code = [
+            0x10, 0xf9, // bipush -7
+            0x87, // i2d
+            0x05, // iconst_2
+            0x87, // i2d
+            0x73, // drem
+            0xaf, // dreturn
+        ];
+        let v = run("()D", (4, 0), &code).unwrap();
+        assert_eq!(v.extract_double(), -1.0);
+
+        //fconst_0; fneg; freturn
+        let v = run("()F", (1, 0), &[0x0b, 0x76, 0xae]).unwrap();
+        assert_eq!(v.extract_float().to_bits(), (-0.0f32).to_bits());
+
+        //dconst_1; dneg; dreturn
+        let v = run("()D", (2, 0), &[0x0f, 0x77, 0xaf]).unwrap();
+        assert_eq!(v.extract_double(), -1.0);
+    }
+
+    #[test]
+    fn t_div_by_zero() {
+        let ex = (
+            "java/lang/ArithmeticException".to_string(),
+            "/ by zero".to_string(),
+        );
+
+        //iconst_1; iconst_0; idiv; ireturn
+        assert_eq!(run_ex("()I", (2, 0), &[0x04, 0x03, 0x6c, 0xac]), ex);
+        //lconst_1; lconst_0; ldiv; lreturn
+        assert_eq!(run_ex("()J", (4, 0), &[0x0a, 0x09, 0x6d, 0xad]), ex);
+        //iconst_1; iconst_0; irem; ireturn
+        assert_eq!(run_ex("()I", (2, 0), &[0x04, 0x03, 0x70, 0xac]), ex);
+
+        //Integer.MIN_VALUE / -1 doesn't throw
+        let code = [
+            0x12, 0x09, // ldc Integer.MIN_VALUE
+            0x02, // iconst_m1
+            0x6c, // idiv
+            0xac, // ireturn
+        ];
+        let v = run("()I", (2, 0), &code).unwrap();
+        assert_eq!(v.extract_int(), i32::MIN);
+    }
+
+    #[test]
+    fn t_negative_array_size() {
+        let nase = |len: &str| {
+            (
+                "java/lang/NegativeArraySizeException".to_string(),
+                len.to_string(),
+            )
+        };
+
+        //iconst_m1; newarray int; areturn
+        let code = [0x02, 0xbc, 0x0a, 0xb0];
+        assert_eq!(run_ex("()Ljava/lang/Object;", (1, 0), &code), nase("-1"));
+
+        //bipush -3; anewarray java/lang/Object; areturn
+        let code = [0x10, 0xfd, 0xbd, 0x00, 0x04, 0xb0];
+        assert_eq!(run_ex("()Ljava/lang/Object;", (1, 0), &code), nase("-3"));
+
+        //the length 0 is fine
+        let code = [0x03, 0xbd, 0x00, 0x04, 0xb0];
+        let v = run("()Ljava/lang/Object;", (1, 0), &code).unwrap();
+        assert_eq!(v.extract_ref().extract_array().elements.len(), 0);
+    }
+
+    #[test]
+    fn t_subroutine() {
+        let code = [
+            0x03, // 0: iconst_0
+            0x3b, // 1: istore_0
+            0xa8, 0x00, 0x0d, // 2: jsr 15
+            0xa8, 0x00, 0x0a, // 5: jsr 15
+            0xc8, 0x00, 0x00, 0x00, 0x0d, // 8: goto_w 21
+            0x02, // 13: iconst_m1
+            0xac, // 14: ireturn
+            0x4c, // 15: astore_1
+            0x84, 0x00, 0x01, // 16: iinc 0, 1
+            0xa9, 0x01, // 19: ret 1
+            0x1a, // 21: iload_0
+            0xac, // 22: ireturn
+        ];
+        let v = run("()I", (1, 2), &code).unwrap();
+        assert_eq!(v.extract_int(), 2);
+    }
+
+    #[test]
+    fn t_multi_anew_array() {
+        let code = [
+            0x05, // iconst_2
+            0x06, // iconst_3
+            0x07, // iconst_4
+            0xc5, 0x00, 0x07, 0x03, // multianewarray [[[I, 3
+            0xb0, // areturn
+        ];
+        let v = run("()Ljava/lang/Object;", (3, 0), &code).unwrap();
+        let outer = v.extract_ref();
+        let outer = outer.extract_array();
+        assert_eq!(outer.elements.len(), 2);
+        for it in outer.elements.iter() {
+            let rf = it.extract_ref();
+            let ary = rf.extract_array();
+            assert_eq!(ary.elements.len(), 3);
+            let rf = ary.elements[0].extract_ref();
+            assert_eq!(rf.extract_type_array().extract_ints().len(), 4);
+        }
+
+        //the last dimension is left null
+        let code = [
+            0x05, // iconst_2
+            0x06, // iconst_3
+            0xc5, 0x00, 0x07, 0x02, // multianewarray [[[I, 2
+            0xb0, // areturn
+        ];
+        let v = run("()Ljava/lang/Object;", (2, 0), &code).unwrap();
+        let outer = v.extract_ref();
+        let inner = outer.extract_array().elements[1].extract_ref();
+        let inner = inner.extract_array();
+        assert_eq!(inner.elements.len(), 3);
+        assert!(inner.elements.iter().all(|it| it.is_null()));
+    }
+
+    /*
+    The loop of jvm/sample/ArrayListLoop.java without the class library, the
+    list is an Object[] growing like ArrayList, the boxed values are int[1].
+
+        cargo test --release -p vm t_bench_list_add -- --ignored --nocapture
+    */
+    #[test]
+    #[ignore]
+    fn t_bench_list_add() {
+        let code = [
+            0x10, 0x0a, // 0: bipush 10
+            0xbd, 0x00, 0x04, // 2: anewarray java/lang/Object
+            0x4c, // 5: astore_1, the elements
+            0x03, 0x3d, // 6: iconst_0; istore_2, the size
+            0x03, 0x3e, // 8: iconst_0; istore_3
+            0x1d, 0x1a, // 10: iload_3; iload_0
+            0xa2, 0x00, 0x3d, // 12: if_icmpge 73
+            0x1c, 0x2b, 0xbe, // 15: iload_2; aload_1; arraylength
+            0xa0, 0x00, 0x28, // 18: if_icmpne 58
+            0x1c, 0x1c, 0x04, 0x7a, 0x60, // 21: iload_2; iload_2; iconst_1; ishr; iadd
+            0xbd, 0x00, 0x04, // 26: anewarray java/lang/Object
+            0x3a, 0x04, // 29: astore 4
+            0x03, 0x36, 0x05, // 31: iconst_0; istore 5
+            0x15, 0x05, 0x1c, // 34: iload 5; iload_2
+            0xa2, 0x00, 0x12, // 37: if_icmpge 55
+            0x19, 0x04, 0x15, 0x05, // 40: aload 4; iload 5
+            0x2b, 0x15, 0x05, 0x32, // 44: aload_1; iload 5; aaload
+            0x53, // 48: aastore
+            0x84, 0x05, 0x01, // 49: iinc 5, 1
+            0xa7, 0xff, 0xee, // 52: goto 34
+            0x19, 0x04, 0x4c, // 55: aload 4; astore_1
+            0x2b, 0x1c, // 58: aload_1; iload_2
+            0x84, 0x02, 0x01, // 60: iinc 2, 1
+            0x04, 0xbc, 0x0a, // 63: iconst_1; newarray int
+            0x53, // 66: aastore
+            0x84, 0x03, 0x01, // 67: iinc 3, 1
+            0xa7, 0xff, 0xc4, // 70: goto 10
+            0x1c, 0xac, // 73: iload_2; ireturn
+        ];
+
+        for _ in 0..5 {
+            let begin = std::time::Instant::now();
+            let args = vec![Oop::new_int(50000)];
+            let v = run2("(I)I", (4, 6), &code, args).unwrap();
+            assert_eq!(v.extract_int(), 50000);
+            println!("list_add = {} us", begin.elapsed().as_micros());
+        }
     }
 }
@@ -1,8 +1,7 @@
 use crate::native;
 use crate::native::JNINativeMethodStruct;
-use crate::oop::{self, Oop, ValueType};
-use crate::runtime::local::Local;
-use crate::runtime::{self, exception, frame::Frame, thread, DataArea, Interp};
+use crate::oop::{self, Oop, OopPtr, ValueType};
+use crate::runtime::{self, exception, frame::Frame, stack, thread, DataArea, Interp};
 use crate::types::{ClassRef, FrameRef, JavaThreadRef, MethodIdRef};
 use crate::util;
 use class_parser::MethodSignature;
@@ -43,10 +42,7 @@ impl JavaCall {
         //insert 'this' value
         let has_this = !mir.method.is_static();
         if has_this {
-            let this = {
-                let mut stack = caller.stack.borrow_mut();
-                stack.pop_ref()
-            };
+            let this = caller.stack().pop_ref().clone();
 
             //check NPE
             if let Oop::Null = this {
@@ -75,7 +71,7 @@ impl JavaCall {
 }
 
 impl JavaCall {
-    //the 'caller' for store return value
+    //the return value is stored to 'caller.return_v'
     pub fn invoke(&mut self, caller: Option<&DataArea>, force_no_resolve: bool) {
         /*
         Do resolve again first, because you can override in a native way such as:
@@ -84,60 +80,110 @@ impl JavaCall {
 
             public native boolean checkAccess(File f, int access);
         */
-        self.resolve_virtual_method(force_no_resolve);
+        if self.resolve_virtual_method(force_no_resolve).is_err() {
+            return;
+        }
         self.debug();
 
-        if self.mir.method.is_native() {
-            self.invoke_native(caller);
+        //no frame pushed if the call is refused, such as StackOverflowError
+        let jt = runtime::thread::current_java_thread();
+        let depth = { jt.read().unwrap().frames.len() };
+
+        let v = if self.mir.method.is_native() {
+            self.invoke_native()
         } else {
-            self.invoke_java(caller);
+            self.invoke_java()
+        };
+
+        jt.write().unwrap().frames.truncate(depth);
+
+        if let (Some(caller), Some(v)) = (caller, v) {
+            *caller.return_v.borrow_mut() = Some(v);
         }
+    }
 
-        let jt = runtime::thread::current_java_thread();
-        let _ = jt.write().unwrap().frames.pop();
+    /*
+    Called by the interpreter, a java method is run in the dispatch loop of
+    the caller: its frame is pushed to the thread and returned. A native
+    method is invoked here. None if there's no frame to run, the native
+    method returned, or the call is refused with an exception.
+    */
+    pub fn invoke_from_interp(
+        &mut self,
+        caller: &DataArea,
+        force_no_resolve: bool,
+    ) -> Option<Callee> {
+        self.resolve_virtual_method(force_no_resolve).ok()?;
+        self.debug();
+
+        if self.mir.method.is_native() {
+            let jt = runtime::thread::current_java_thread();
+            let depth = { jt.read().unwrap().frames.len() };
+            let v = self.invoke_native();
+            jt.write().unwrap().frames.truncate(depth);
+            if let Some(v) = v {
+                set_return(caller, &self.mir.method.signature.retype, v);
+            }
+            None
+        } else {
+            self.enter_java()
+        }
     }
 }
 
+//the frame of a java method to run, its locals at 'base' of the thread stack
+pub struct Callee {
+    pub frame: FrameRef,
+    pub base: usize,
+    //the object locked by a synchronized method, see fin_sync
+    pub locked: Option<Oop>,
+}
+
 impl JavaCall {
-    fn invoke_java(&mut self, caller: Option<&DataArea>) {
-        self.prepare_sync();
+    //the frames called by the entry frame are run in the same Interp
+    fn invoke_java(&mut self) -> Option<Oop> {
+        let callee = self.enter_java()?;
+
+        let frame = callee.frame.clone();
+        let mut interp = Interp::new(callee);
+        interp.run();
+
+        //if return void, not need set return value
+        if !self.is_return_void && !thread::is_meet_ex() {
+            frame.area.return_v.borrow_mut().take()
+        } else {
+            None
+        }
+    }
+
+    fn enter_java(&mut self) -> Option<Callee> {
+        let locked = self.prepare_sync();
 
-        let jt = runtime::thread::current_java_thread();
         match self.prepare_frame() {
             Ok(frame) => {
-                {
-                    jt.write().unwrap().frames.push(frame.clone());
-                }
-
-                let local = self.build_local();
-                let frame_h = frame.try_read().unwrap();
-                let mut interp = Interp::new(frame_h, local);
-                interp.run();
-
-                //if return void, not need set return value
-                if !self.is_return_void && !thread::is_meet_ex() {
-                    let return_v = {
-                        let frame = frame.try_read().unwrap();
-                        let area = frame.area.return_v.borrow();
-                        area.clone()
-                    };
-
-                    let caller = caller.unwrap();
-                    let return_v = return_v.unwrap();
-                    set_return(caller, &self.mir.method.signature.retype, return_v);
-                }
+                let jt = runtime::thread::current_java_thread();
+                jt.write().unwrap().frames.push(frame.clone());
+
+                let base = self.build_local();
+                Some(Callee {
+                    frame,
+                    base,
+                    locked,
+                })
             }
 
             Err(ex) => {
+                let jt = runtime::thread::current_java_thread();
                 jt.write().unwrap().set_ex(ex);
+                fin_sync(locked);
+                None
             }
         }
-
-        self.fin_sync();
     }
 
-    fn invoke_native(&mut self, caller: Option<&DataArea>) {
-        self.prepare_sync();
+    //the return value, None if void or an exception is thrown
+    fn invoke_native(&mut self) -> Option<Oop> {
+        let locked = self.prepare_sync();
 
         let jt = runtime::thread::current_java_thread();
         let v = match self.prepare_frame() {
@@ -151,160 +197,108 @@ impl JavaCall {
                         let env = native::new_jni_env(class);
                         method.invoke(env, &self.args)
                     }
-                    None => {
-                        let package = self.mir.method.class.get_class().name.as_slice();
-                        let desc = self.mir.method.desc.as_slice();
-                        let name = self.mir.method.name.as_slice();
-                        panic!(
-                            "Native method not found: {}:{}:{}",
-                            unsafe { std::str::from_utf8_unchecked(package) },
-                            unsafe { std::str::from_utf8_unchecked(name) },
-                            unsafe { std::str::from_utf8_unchecked(desc) },
-                        )
-                    }
+                    None => match native::library::find_native(&self.mir) {
+                        Some(f) => native::library::invoke(&self.mir, f, &self.args),
+                        None => Err(native::library::not_found(&self.mir)),
+                    },
                 }
             }
             Err(ex) => Err(ex),
         };
 
-        match v {
-            Ok(v) => {
-                if !self.is_return_void && !thread::is_meet_ex() {
-                    let caller = caller.unwrap();
-                    let return_v = v.unwrap();
-                    set_return(caller, &self.mir.method.signature.retype, return_v);
-                }
+        let v = match v {
+            Ok(v) if !self.is_return_void && !thread::is_meet_ex() => Some(v.unwrap()),
+            Ok(_) => None,
+            Err(ex) => {
+                jt.write().unwrap().set_ex(ex);
+                None
             }
-            Err(ex) => jt.write().unwrap().set_ex(ex),
-        }
+        };
 
-        self.fin_sync();
+        fin_sync(locked);
+        v
     }
 
-    fn prepare_sync(&mut self) {
+    //the object locked, the mirror of the class for a static method
+    fn prepare_sync(&mut self) -> Option<Oop> {
         if self.mir.method.is_synchronized() {
-            if self.mir.method.is_static() {
+            let v = if self.mir.method.is_static() {
+                //no mirror early in the boot, a single thread
                 let class = self.mir.method.class.get_class();
-                class.monitor_enter();
+                class.try_get_mirror()
             } else {
-                let v = self.args.first().unwrap();
-                let v = v.extract_ref();
-                v.monitor_enter();
-            }
-        }
-    }
+                self.args.first().cloned()
+            };
 
-    fn fin_sync(&mut self) {
-        if self.mir.method.is_synchronized() {
-            if self.mir.method.is_static() {
-                let class = self.mir.method.class.get_class();
-                class.monitor_exit();
-            } else {
-                let v = self.args.first().unwrap();
-                let v = v.extract_ref();
-                v.monitor_exit();
+            if let Some(v) = &v {
+                v.extract_ref().monitor_enter();
             }
+            v
+        } else {
+            None
         }
     }
 
     fn prepare_frame(&mut self) -> Result<FrameRef, Oop> {
         let jt = runtime::thread::current_java_thread();
         let frame_len = { jt.read().unwrap().frames.len() };
-        if frame_len >= runtime::consts::THREAD_MAX_STACK_FRAMES {
-            let ex = exception::new(cls_const::J_SOE, None);
-            return Err(ex);
+
+        if thread::is_stack_overflow(frame_len) {
+            return Err(thread::new_stack_overflow_error());
         }
 
         let frame_id = frame_len + 1;
         let frame = Frame::new(self.mir.clone(), frame_id);
-        let frame_ref = new_sync_ref!(frame);
-        Ok(frame_ref)
+        Ok(Arc::new(frame))
     }
 
-    fn build_local(&self) -> Local {
-        //JVM spec, 2.6.1
+    //the locals on the thread stack, JVM spec, 2.6.1
+    fn build_local(&self) -> usize {
         let max_locals = self.mir.method.get_max_locals();
-        let mut local = Local::new(max_locals);
-        let mut slot_pos: usize = 0;
-        for v in self.args.iter() {
-            let step = match v {
-                Oop::Int(v) => {
-                    local.set_int(slot_pos, *v);
-                    1
-                }
-                Oop::Float(v) => {
-                    local.set_float(slot_pos, *v);
-                    1
-                }
-                Oop::Double(v) => {
-                    local.set_double(slot_pos, *v);
-                    2
-                }
-                Oop::Long((v)) => {
-                    local.set_long(slot_pos, *v);
-                    2
-                }
-                _ => {
-                    local.set_ref(slot_pos, v.clone());
-                    1
+        let stack = stack::current();
+        let base = stack.alloc(max_locals);
+        let sig = &self.mir.method.signature;
+        let has_this = self.args.len() > sig.args.len();
+
+        let mut slot_pos = base;
+        for (i, v) in self.args.iter().enumerate() {
+            let t = if has_this { i.checked_sub(1) } else { Some(i) };
+            let v = match (v, t.map(|t| &sig.args[t])) {
+                //boxed by reflection
+                (Oop::Ref(rf), Some(SignatureType::Int)) => {
+                    Oop::new_int(OopPtr::java_lang_integer_value(rf.clone()))
                 }
+                (v, _) => v.clone(),
             };
-
-            slot_pos += step;
+            slot_pos += stack.set_value(slot_pos, v);
         }
 
-        local
+        base
     }
 
-    fn resolve_virtual_method(&mut self, force_no_resolve: bool) {
-        let resolve_again = if force_no_resolve {
-            false
-        } else {
-            //todo: why is the value of 0 possible in acc_flags?
-            /*
-            This situation occurs when:
-            java/util/regex/Matcher.java
-            bool search(int from)
-              boolean result = parentPattern.root.match(this, from, text);
-
-            The acc_flags of the match method is 0, and what is found is java/util/regex/Patter$Node#match，
-            Correct should use java/util/regex/Patter$Start#match
-            */
-            self.mir.method.is_abstract()
-                || (self.mir.method.is_public() && !self.mir.method.is_final())
-                || (self.mir.method.is_protected() && !self.mir.method.is_final())
-                || (self.mir.method.acc_flags == 0)
-        };
-        trace!(
-            "resolve_virtual_method resolve_again={}, acc_flags = {}",
-            resolve_again,
-            self.mir.method.acc_flags
-        );
-        if resolve_again {
-            let this = self.args.get(0).unwrap();
-            let rf = this.extract_ref();
-            let ptr = rf.get_raw_ptr();
-            unsafe {
-                if let oop::RefKind::Inst(inst) = &(*ptr).v {
-                    let name = self.mir.method.name.clone();
-                    let desc = self.mir.method.desc.clone();
-                    let cls = inst.class.get_class();
-                    match cls.get_virtual_method(&name, &desc) {
-                        Ok(mir) => self.mir = mir,
-                        _ => {
-                            let cls = self.mir.method.class.get_class();
-                            warn!(
-                                "resolve again failed, {}:{}:{}, acc_flags = {}",
-                                String::from_utf8_lossy(cls.name.as_slice()),
-                                String::from_utf8_lossy(name.as_slice()),
-                                String::from_utf8_lossy(desc.as_slice()),
-                                self.mir.method.acc_flags
-                            );
-                        }
+    //select the method by the class of receiver, the exception is pending if failed
+    fn resolve_virtual_method(&mut self, force_no_resolve: bool) -> Result<(), ()> {
+        if force_no_resolve || self.mir.vtable_index.is_none() {
+            return Ok(());
+        }
+
+        let this = self.args.get(0).unwrap();
+        let rf = this.extract_ref();
+        let ptr = rf.get_raw_ptr();
+        unsafe {
+            if let oop::RefKind::Inst(inst) = &(*ptr).v {
+                let cls = inst.class.get_class();
+                match cls.select_method(&self.mir) {
+                    Ok(mir) => self.mir = mir,
+                    Err((name, msg)) => {
+                        exception::meet_ex(name, Some(msg));
+                        return Err(());
                     }
                 }
             }
         }
+
+        Ok(())
     }
 
     fn debug(&self) {
@@ -318,8 +312,17 @@ impl JavaCall {
     }
 }
 
-fn build_args_from_caller_stack(caller: &DataArea, sig: &MethodSignature) -> Vec<Oop> {
-    let mut caller = caller.stack.borrow_mut();
+//unlocked already by monitorexit, IllegalMonitorStateException
+pub fn fin_sync(locked: Option<Oop>) {
+    if let Some(v) = locked {
+        if v.extract_ref().monitor_exit().is_err() && !thread::is_meet_ex() {
+            exception::meet_ex(cls_const::J_ILLEGAL_MONITOR_STATE, None);
+        }
+    }
+}
+
+pub fn build_args_from_caller_stack(caller: &DataArea, sig: &MethodSignature) -> Vec<Oop> {
+    let caller = caller.stack();
     let mut args = Vec::with_capacity(sig.args.len() + 1);
 
     //build args from caller's stack, so should rev the signature args
@@ -345,7 +348,7 @@ fn build_args_from_caller_stack(caller: &DataArea, sig: &MethodSignature) -> Vec
                 let v = caller.pop_double();
                 Oop::new_double(v)
             }
-            SignatureType::Object(_, _, _) | SignatureType::Array(_) => caller.pop_ref(),
+            SignatureType::Object(_, _, _) | SignatureType::Array(_) => caller.pop_ref().clone(),
             t => unreachable!("t = {:?}", t),
         };
 
@@ -359,10 +362,8 @@ fn build_args_from_caller_stack(caller: &DataArea, sig: &MethodSignature) -> Vec
 }
 
 pub fn set_return(caller: &DataArea, return_type: &SignatureType, v: Oop) {
-    let with_nop = match return_type {
-        SignatureType::Double | SignatureType::Long => true,
-        _ => false,
-    };
-    let mut stack = caller.stack.borrow_mut();
-    stack.push_ref(v, with_nop);
+    match (return_type, v) {
+        (SignatureType::Void, _) => (),
+        (_, v) => caller.stack().push_value(v),
+    }
 }
@@ -0,0 +1,127 @@
+use crate::runtime::{self, exception, ClassLoader};
+use crate::types::ClassRef;
+use classfile::consts as cls_consts;
+use rustc_hash::FxHashMap;
+use std::sync::Mutex;
+
+/*
+Loader constraints, JVM spec 5.3.4
+
+When a class refers to a field or method of a class defined by another
+loader, the classes named in the descriptor must be the same for both
+loaders. The constraint is recorded as a set of loaders per class name,
+all the loaders of a set must load the same class, whenever one of them
+loads it.
+*/
+lazy_static! {
+    static ref CONSTRAINTS: Mutex<FxHashMap<Vec<u8>, Vec<Vec<ClassLoader>>>> =
+        Mutex::new(FxHashMap::default());
+}
+
+//the classes named in the field or method descriptor 'desc', ClassFormatError if it's malformed
+pub fn add_for_desc(
+    desc: &[u8],
+    l1: Option<ClassLoader>,
+    l2: Option<ClassLoader>,
+) -> Result<(), ()> {
+    let l1 = l1.unwrap_or(ClassLoader::Bootstrap);
+    let l2 = l2.unwrap_or(ClassLoader::Bootstrap);
+    if l1 == l2 || !(is_user(l1) || is_user(l2)) {
+        return Ok(());
+    }
+
+    let mut i = 0;
+    while i < desc.len() {
+        if desc[i] == b'L' {
+            let end = match desc[i..].iter().position(|&c| c == b';') {
+                Some(n) => i + n,
+                None => {
+                    let desc = String::from_utf8_lossy(desc);
+                    let msg = format!("Illegal descriptor {}", desc);
+                    exception::meet_ex(cls_consts::J_CLASS_FORMAT_ERROR, Some(msg));
+                    return Err(());
+                }
+            };
+            add(&desc[i + 1..end], l1, l2)?;
+            i = end;
+        }
+        i += 1;
+    }
+
+    Ok(())
+}
+
+//'name' loaded by 'l1' and 'l2' must be the same class
+pub fn add(name: &[u8], l1: ClassLoader, l2: ClassLoader) -> Result<(), ()> {
+    let mut constraints = CONSTRAINTS.lock().unwrap();
+    let sets = constraints.entry(Vec::from(name)).or_default();
+
+    //merge the sets of l1 and l2
+    let joined = |set: &Vec<ClassLoader>| set.contains(&l1) || set.contains(&l2);
+    let mut merged = vec![l1, l2];
+    for set in sets.iter().filter(|set| joined(set)) {
+        for it in set.iter() {
+            if !merged.contains(it) {
+                merged.push(*it);
+            }
+        }
+    }
+
+    let mut loaded: Option<ClassRef> = None;
+    for &loader in merged.iter() {
+        if let Some(class) = runtime::sys_dic_find(loader, name) {
+            match &loaded {
+                Some(it) if !std::ptr::eq(it.get_class(), class.get_class()) => {
+                    drop(constraints);
+                    violated(name);
+                    return Err(());
+                }
+                Some(_) => (),
+                None => loaded = Some(class),
+            }
+        }
+    }
+
+    sets.retain(|set| !joined(set));
+    sets.push(merged);
+    Ok(())
+}
+
+//called before 'loader' records 'class' as 'name', None for a class being defined
+pub fn check(loader: ClassLoader, name: &[u8], class: Option<&ClassRef>) -> Result<(), ()> {
+    let constraints = CONSTRAINTS.lock().unwrap();
+    let set = constraints
+        .get(name)
+        .and_then(|sets| sets.iter().find(|set| set.contains(&loader)));
+
+    if let Some(set) = set {
+        let conflict = set.iter().filter(|it| **it != loader).any(|&it| {
+            match (runtime::sys_dic_find(it, name), class) {
+                (Some(other), Some(class)) => !std::ptr::eq(other.get_class(), class.get_class()),
+                (Some(_), None) => true,
+                (None, _) => false,
+            }
+        });
+
+        if conflict {
+            drop(constraints);
+            violated(name);
+            return Err(());
+        }
+    }
+
+    Ok(())
+}
+
+fn is_user(loader: ClassLoader) -> bool {
+    matches!(loader, ClassLoader::User(_))
+}
+
+fn violated(name: &[u8]) {
+    let name = String::from_utf8_lossy(name).replace('/', ".");
+    let msg = format!(
+        "loader constraint violation: loaders have different Class objects for the type {}",
+        name
+    );
+    exception::meet_ex(cls_consts::J_LINKAGE_ERROR, Some(msg));
+}
@@ -1,8 +1,8 @@
 use crate::native::JNINativeMethod;
+use crate::oop::Class;
 use crate::oop::{self, ValueType};
-use crate::runtime::local::Local;
-use crate::runtime::stack::Stack;
-use crate::runtime::{self, require_class2};
+use crate::runtime::bytecode;
+use crate::runtime::{self, exception, require_class2, ClassLoader};
 use crate::types::ClassRef;
 use crate::types::*;
 use crate::util::PATH_SEP;
@@ -15,27 +15,65 @@ use classfile::{
 use std::fmt;
 use std::fmt::Formatter;
 use std::ops::Deref;
-use std::sync::Arc;
-
-pub fn get_method_ref(cp: &ConstantPool, idx: usize) -> Result<MethodIdRef, ()> {
+use std::sync::{Arc, RwLock};
+
+//'accessor' owns the pool 'cp'
+pub fn get_method_ref(
+    accessor: &ClassRef,
+    cp: &ConstantPool,
+    idx: usize,
+) -> Result<MethodIdRef, ()> {
     let (tag, class_index, name_and_type_index) = constant_pool::get_method_ref(cp, idx);
+    let class_loader = accessor.get_class().class_loader;
 
     //load Method's Class, then init it
-    let class = require_class2(class_index, cp).unwrap_or_else(|| {
-        panic!(
-            "Unknown method class {:?}",
-            cp.get(class_index as usize)
-                .expect("Missing item")
-                .as_cp_item(cp)
-        )
-    });
+    let class = require_class2(class_loader, class_index, cp).ok_or(())?;
+
+    let is_interface = class.get_class().is_interface();
+    if is_interface != (tag == consts::CONSTANT_INTERFACE_METHOD_REF_TAG) {
+        let name = String::from_utf8_lossy(class.get_class().name.as_slice()).replace('/', ".");
+        let msg = if is_interface {
+            format!("Found interface {}, but class was expected", name)
+        } else {
+            format!("Found class {}, but interface was expected", name)
+        };
+        exception::meet_ex(consts::J_INCOMPATIBLE_CLASS_CHANGE_ERROR, Some(msg));
+        return Err(());
+    }
 
-    oop::class::init_class(&class);
-    oop::class::init_class_fully(&class);
+    oop::class::init_class(&class)?;
 
     let (name, desc) = constant_pool::get_name_and_type(cp, name_and_type_index as usize);
-    let class = class.get_class();
+    let mir = match resolve_method(class.get_class(), tag, name, desc) {
+        Ok(mir) => mir,
+        Err(_) if runtime::thread::is_meet_ex() => return Err(()),
+        Err(_) => {
+            let msg = format!(
+                "{}.{}{}",
+                String::from_utf8_lossy(class.get_class().name.as_slice()).replace('/', "."),
+                String::from_utf8_lossy(name.as_slice()),
+                String::from_utf8_lossy(desc.as_slice())
+            );
+            exception::meet_ex(consts::J_NO_SUCH_METHOD_ERROR, Some(msg));
+            return Err(());
+        }
+    };
+
+    runtime::access::check_method(accessor, &class, &mir)?;
+
+    let defining = mir.method.class.get_class().class_loader;
+    let desc = mir.method.desc.as_slice();
+    runtime::loader_constraints::add_for_desc(desc, class_loader, defining)?;
 
+    Ok(mir)
+}
+
+fn resolve_method(
+    class: &Class,
+    tag: u8,
+    name: &BytesRef,
+    desc: &BytesRef,
+) -> Result<MethodIdRef, ()> {
     trace!(
         "get_method_ref cls={}, name={}, desc={}",
         unsafe { std::str::from_utf8_unchecked(class.name.as_slice()) },
@@ -43,8 +81,15 @@ pub fn get_method_ref(cp: &ConstantPool, idx: usize) -> Result<MethodIdRef, ()>
         unsafe { std::str::from_utf8_unchecked(desc.as_slice()) },
     );
     if tag == consts::CONSTANT_METHOD_REF_TAG {
+        if runtime::method_handles::is_signature_polymorphic(class.name.as_slice(), name.as_slice())
+        {
+            return runtime::method_handles::link_signature_polymorphic(class, name, desc);
+        }
+
         // invokespecial, invokestatic and invokevirtual
-        class.get_class_method(name, desc)
+        class
+            .get_class_method(name, desc)
+            .or_else(|_| class.get_super_interface_method(name, desc))
     } else {
         // invokeinterface
         class.get_interface_method(name, desc)
@@ -54,12 +99,15 @@ pub fn get_method_ref(cp: &ConstantPool, idx: usize) -> Result<MethodIdRef, ()>
 #[derive(Clone)]
 pub struct MethodId {
     pub offset: usize,
+    //the index in the vtable of its class, or in the itable entry of its interface,
+    //None if it's not selected by the receiver, such as static, private and <init>
+    pub vtable_index: Option<usize>,
     pub method: Method,
     pub native_impl: Option<JNINativeMethod>,
 }
 
 impl MethodId {
-    pub fn new(offset: usize, method: Method) -> Arc<Self> {
+    pub fn new(offset: usize, vtable_index: Option<usize>, method: Method) -> Arc<Self> {
         let native_impl = if method.is_native() {
             let package = method.class.get_class().name.as_slice();
             let desc = method.desc.as_slice();
@@ -71,6 +119,7 @@ impl MethodId {
 
         Arc::new(Self {
             offset,
+            vtable_index,
             method,
             native_impl,
         })
@@ -89,6 +138,8 @@ pub struct Method {
 
     pub code: Option<Code>,
     pub line_num_table: Vec<LineNumber>,
+    //decoded for the interpreter on the first call, see bytecode
+    decoded: Arc<RwLock<Option<Arc<bytecode::Code>>>>,
 
     method_info_index: usize,
 }
@@ -119,10 +170,33 @@ impl Method {
             signature,
             code,
             line_num_table,
+            decoded: Arc::new(RwLock::new(None)),
             method_info_index,
         }
     }
 
+    pub fn get_decoded_code(&self) -> Arc<bytecode::Code> {
+        if let Some(code) = self.decoded.read().unwrap().as_ref() {
+            if !code.is_stale() {
+                return code.clone();
+            }
+        }
+
+        let mut decoded = self.decoded.write().unwrap();
+        match decoded.as_ref() {
+            Some(code) if !code.is_stale() => code.clone(),
+            _ => {
+                let code = match &self.code {
+                    Some(code) => code.code.as_slice(),
+                    None => &[],
+                };
+                let code = Arc::new(bytecode::Code::new(code));
+                *decoded = Some(code.clone());
+                code
+            }
+        }
+    }
+
     pub fn get_max_locals(&self) -> usize {
         match &self.code {
             Some(code) => code.max_locals as usize,
@@ -137,16 +211,6 @@ impl Method {
         }
     }
 
-    pub fn build_local(&self) -> Local {
-        let max_locals = self.get_max_locals();
-        Local::new(max_locals)
-    }
-
-    pub fn build_stack(&self) -> Stack {
-        let max_stack = self.get_max_stack();
-        Stack::new(max_stack)
-    }
-
     pub fn find_exception_handler(&self, cp: &ConstantPool, pc: U2, ex: ClassRef) -> Option<U2> {
         if let Some(code) = &self.code {
             for e in code.exceptions.iter() {
@@ -155,7 +219,11 @@ impl Method {
                         return Some(e.handler_pc);
                     }
 
-                    if let Some(class) = runtime::require_class2(e.catch_type, cp) {
+                    //not thrown if the catch type is not found, the exception is pending
+                    let class_loader = self.class.get_class().class_loader;
+                    let name = constant_pool::get_class_name(cp, e.catch_type as usize);
+                    let class = runtime::require_class(class_loader, name);
+                    if let Some(class) = class {
                         if runtime::cmp::instance_of(ex.clone(), class) {
                             return Some(e.handler_pc);
                         }
@@ -0,0 +1,611 @@
+use crate::native::{self, common::reflect, JNIEnv, JNIResult, NativeMethodPtr};
+use crate::new_br;
+use crate::oop::{self, Class, Oop, OopPtr};
+use crate::runtime::{self, exception, require_class3, thread, DataArea, JavaCall};
+use crate::types::{ClassRef, FieldIdRef, MethodIdRef};
+use crate::util;
+use class_parser::{FieldSignature, MethodSignature};
+use classfile::{constant_pool, consts as cls_const, BytesRef, ConstantPool, SignatureType, U2};
+use rustc_hash::FxHashMap;
+use std::sync::{Arc, Mutex, RwLock};
+
+//java/lang/invoke/MethodHandleNatives.Constants
+pub const MN_IS_METHOD: i32 = 0x0001_0000;
+pub const MN_IS_CONSTRUCTOR: i32 = 0x0002_0000;
+pub const MN_IS_FIELD: i32 = 0x0004_0000;
+pub const MN_IS_TYPE: i32 = 0x0008_0000;
+pub const MN_CALLER_SENSITIVE: i32 = 0x0010_0000;
+pub const MN_REFERENCE_KIND_SHIFT: i32 = 24;
+pub const MN_REFERENCE_KIND_MASK: i32 = 0x0F;
+pub const MN_SEARCH_SUPERCLASSES: i32 = 0x0010_0000;
+pub const MN_SEARCH_INTERFACES: i32 = 0x0020_0000;
+
+static mut MEMBER_NAME_VMTARGET_OFFSET: usize = 0;
+
+lazy_static! {
+    static ref S_POLYMORPHIC_SIG: BytesRef = new_br("([Ljava/lang/Object;)Ljava/lang/Object;");
+    static ref VM_TARGETS: RwLock<VmTargets> = RwLock::new(VmTargets::default());
+}
+
+/*
+MemberName.vmtarget
+
+Like hotspot, the field is injected into java/lang/invoke/MemberName when it's linked,
+it's invisible to java. The value is an index of VM_TARGETS.
+*/
+#[derive(Default)]
+struct VmTargets {
+    methods: Vec<MethodIdRef>,
+    //address of MethodId -> index of methods
+    index: FxHashMap<usize, usize>,
+}
+
+pub fn set_member_name_vmtarget_offset(offset: usize) {
+    unsafe {
+        MEMBER_NAME_VMTARGET_OFFSET = offset;
+    }
+}
+
+fn get_member_name_vmtarget_offset() -> usize {
+    unsafe { MEMBER_NAME_VMTARGET_OFFSET }
+}
+
+pub fn set_vmtarget(mn: &Oop, mir: MethodIdRef) {
+    let idx = {
+        let mut targets = VM_TARGETS.write().unwrap();
+        let k = Arc::as_ptr(&mir) as usize;
+        match targets.index.get(&k) {
+            Some(idx) => *idx,
+            None => {
+                let idx = targets.methods.len();
+                targets.methods.push(mir);
+                targets.index.insert(k, idx);
+                idx
+            }
+        }
+    };
+
+    let offset = get_member_name_vmtarget_offset();
+    Class::put_field_value2(mn.extract_ref(), offset, Oop::new_long(idx as i64));
+}
+
+//the method MemberName refers to, resolved again if the vmtarget is not set
+pub fn get_vmtarget(mn: &Oop) -> Result<MethodIdRef, ()> {
+    let offset = get_member_name_vmtarget_offset();
+    match Class::get_field_value2(mn.extract_ref(), offset) {
+        Oop::Long(idx) => {
+            let targets = VM_TARGETS.read().unwrap();
+            Ok(targets.methods[idx as usize].clone())
+        }
+        _ => {
+            let mir = resolve_member_name_method(mn)?;
+            set_vmtarget(mn, mir.clone());
+            Ok(mir)
+        }
+    }
+}
+
+pub fn get_field(obj: &Oop, cls: &[u8], name: &str, desc: &str) -> Oop {
+    let cls = require_class3(None, cls).unwrap();
+    let cls = cls.get_class();
+    let fid = cls.get_field_id(&new_br(name), &new_br(desc), false);
+    Class::get_field_value(obj.extract_ref(), fid)
+}
+
+pub fn put_field(obj: &Oop, cls: &[u8], name: &str, desc: &str, v: Oop) {
+    let cls = require_class3(None, cls).unwrap();
+    let cls = cls.get_class();
+    let fid = cls.get_field_id(&new_br(name), &new_br(desc), false);
+    Class::put_field_value(obj.extract_ref(), fid, v);
+}
+
+pub fn mirror_target(mirror: &Oop) -> Option<ClassRef> {
+    match mirror {
+        Oop::Null => None,
+        mirror => {
+            let rf = mirror.extract_ref();
+            rf.extract_mirror().target.clone()
+        }
+    }
+}
+
+//Class => "I", "[I", "Ljava/lang/String;"
+pub fn mirror_desc(mirror: &Oop) -> Vec<u8> {
+    let rf = mirror.extract_ref();
+    let mirror = rf.extract_mirror();
+    match &mirror.target {
+        Some(target) => {
+            let cls = target.get_class();
+            if cls.is_array() {
+                cls.name.to_vec()
+            } else {
+                let mut desc = Vec::with_capacity(cls.name.len() + 2);
+                desc.push(b'L');
+                desc.extend_from_slice(cls.name.as_slice());
+                desc.push(b';');
+                desc
+            }
+        }
+        None => {
+            let t: &[u8] = mirror.value_type.into();
+            t.to_vec()
+        }
+    }
+}
+
+pub fn method_type_desc(mt: &Oop) -> Vec<u8> {
+    let rtype = get_field(mt, cls_const::J_METHODTYPE, "rtype", "Ljava/lang/Class;");
+    let ptypes = get_field(mt, cls_const::J_METHODTYPE, "ptypes", "[Ljava/lang/Class;");
+    build_method_desc(&rtype, &ptypes)
+}
+
+fn build_method_desc(rtype: &Oop, ptypes: &Oop) -> Vec<u8> {
+    let mut desc = vec![b'('];
+    {
+        let rf = ptypes.extract_ref();
+        let ary = rf.extract_array();
+        ary.elements
+            .iter()
+            .for_each(|it| desc.extend_from_slice(mirror_desc(it).as_slice()));
+    }
+    desc.push(b')');
+    desc.extend_from_slice(mirror_desc(rtype).as_slice());
+    desc
+}
+
+//MemberName.type, a String, a Class, a MethodType or {rtype, ptypes}
+pub fn type_desc(t: &Oop) -> Vec<u8> {
+    let rf = t.extract_ref();
+    if OopPtr::is_java_lang_string(rf.clone()) {
+        return OopPtr::java_lang_string(rf).into_bytes();
+    }
+
+    let ptr = rf.get_raw_ptr();
+    unsafe {
+        match &(*ptr).v {
+            oop::RefKind::Mirror(_) => mirror_desc(t),
+            oop::RefKind::Array(ary) => build_method_desc(&ary.elements[0], &ary.elements[1]),
+            _ => method_type_desc(t),
+        }
+    }
+}
+
+//invoke a method, and take the return value, None means an exception occurred
+pub fn call(mir: MethodIdRef, args: Vec<Oop>) -> Option<Oop> {
+    let mut jc = JavaCall::new_with_args(mir, args);
+    let area = DataArea::new();
+    jc.invoke(Some(&area), true);
+
+    if thread::is_meet_ex() {
+        return None;
+    }
+
+    //Oop::Null if void
+    let v = area.return_v.borrow_mut().take().unwrap_or(Oop::Null);
+    Some(v)
+}
+
+fn call_natives(name: &str, desc: &str, args: Vec<Oop>) -> Option<Oop> {
+    let cls = require_class3(None, cls_const::J_METHOD_HANDLE_NATIVES).unwrap();
+    let _ = oop::class::init_class(&cls);
+
+    let mir = {
+        let cls = cls.get_class();
+        cls.get_static_method(&new_br(name), &new_br(desc)).unwrap()
+    };
+    call(mir, args)
+}
+
+//int => Integer...
+pub fn box_value(v: Oop) -> Option<Oop> {
+    let (cls, desc): (&[u8], &str) = match &v {
+        Oop::Int(_) => (b"java/lang/Integer", "(I)Ljava/lang/Integer;"),
+        Oop::Long(_) => (b"java/lang/Long", "(J)Ljava/lang/Long;"),
+        Oop::Float(_) => (b"java/lang/Float", "(F)Ljava/lang/Float;"),
+        Oop::Double(_) => (b"java/lang/Double", "(D)Ljava/lang/Double;"),
+        _ => return Some(v),
+    };
+
+    let cls = require_class3(None, cls).unwrap();
+    let _ = oop::class::init_class(&cls);
+
+    let mir = {
+        let cls = cls.get_class();
+        cls.get_static_method(&new_br("valueOf"), &new_br(desc))
+            .unwrap()
+    };
+    call(mir, vec![v])
+}
+
+//ldc MethodType
+pub fn new_method_type(desc: &[u8]) -> Option<Oop> {
+    let sig = MethodSignature::new(desc);
+    let rtype = reflect::create_value_type(sig.retype);
+    let ptypes: Vec<Oop> = sig
+        .args
+        .iter()
+        .map(|t| reflect::create_value_type(t.clone()))
+        .collect();
+    let cls = require_class3(None, b"[Ljava/lang/Class;").unwrap();
+    let ptypes = Oop::new_ref_ary2(cls, ptypes);
+
+    call_natives(
+        "findMethodHandleType",
+        "(Ljava/lang/Class;[Ljava/lang/Class;)Ljava/lang/invoke/MethodType;",
+        vec![rtype, ptypes],
+    )
+}
+
+//ldc MethodHandle
+pub fn new_method_handle(
+    host: &ClassRef,
+    cp: &ConstantPool,
+    ref_kind: u8,
+    ref_index: U2,
+) -> Option<Oop> {
+    let is_field = ref_kind <= cls_const::REF_PUT_STATIC;
+    let (class_index, nt_idx) = if is_field {
+        constant_pool::get_field_ref(cp, ref_index as usize)
+    } else {
+        let (_, class_index, nt_idx) = constant_pool::get_method_ref(cp, ref_index as usize);
+        (class_index, nt_idx)
+    };
+    let (name, desc) = constant_pool::get_name_and_type(cp, nt_idx as usize);
+
+    let class_loader = host.get_class().class_loader;
+    let defc = runtime::require_class2(class_loader, class_index, cp)?;
+    let defc = { defc.get_class().get_mirror() };
+    let typ = if is_field {
+        let sig = FieldSignature::new(desc.as_slice());
+        reflect::create_value_type(sig.field_type)
+    } else {
+        new_method_type(desc.as_slice())?
+    };
+    let name = util::oop::new_java_lang_string3(name.as_slice());
+    let caller = { host.get_class().get_mirror() };
+
+    call_natives(
+        "linkMethodHandleConstant",
+        "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;)Ljava/lang/invoke/MethodHandle;",
+        vec![caller, Oop::new_int(ref_kind as i32), defc, name, typ],
+    )
+}
+
+//the invoker and appendix of a call site, which bootstrap method is run by java
+pub fn link_call_site(
+    host: &ClassRef,
+    bsm: Oop,
+    name: &BytesRef,
+    desc: &BytesRef,
+    static_args: Vec<Oop>,
+) -> Option<(MethodIdRef, Oop)> {
+    let caller = { host.get_class().get_mirror() };
+    let name = util::oop::new_java_lang_string3(name.as_slice());
+    let typ = new_method_type(desc.as_slice())?;
+    let info = if static_args.is_empty() {
+        Oop::Null
+    } else {
+        let cls = require_class3(None, b"[Ljava/lang/Object;").unwrap();
+        Oop::new_ref_ary2(cls, static_args)
+    };
+    let appendix = new_appendix_result();
+
+    let mn = call_natives(
+        "linkCallSite",
+        "(Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+        vec![caller, bsm, name, typ, info, appendix.clone()],
+    )?;
+
+    take_linked(&mn, &appendix)
+}
+
+fn new_appendix_result() -> Oop {
+    let cls = require_class3(None, b"[Ljava/lang/Object;").unwrap();
+    Oop::new_ref_ary(cls, 1)
+}
+
+fn take_linked(mn: &Oop, appendix: &Oop) -> Option<(MethodIdRef, Oop)> {
+    let target = match get_vmtarget(mn) {
+        Ok(mir) => mir,
+        Err(_) => {
+            if !thread::is_meet_ex() {
+                let msg = "unresolved invoker".to_string();
+                exception::meet_ex(cls_const::J_INTERNAL_ERROR, Some(msg));
+            }
+            return None;
+        }
+    };
+
+    let appendix = {
+        let rf = appendix.extract_ref();
+        let ary = rf.extract_array();
+        ary.elements[0].clone()
+    };
+
+    Some((target, appendix))
+}
+
+/*
+MemberName => method
+
+The method is looked up by (clazz, name, type) according to the reference kind
+in flags, JVM spec, 5.4.3.5
+*/
+pub fn resolve_member_name_method(mn: &Oop) -> Result<MethodIdRef, ()> {
+    let clazz = get_field(mn, cls_const::J_MEMBER_NAME, "clazz", "Ljava/lang/Class;");
+    let name = get_field(mn, cls_const::J_MEMBER_NAME, "name", "Ljava/lang/String;");
+    let typ = get_field(mn, cls_const::J_MEMBER_NAME, "type", "Ljava/lang/Object;");
+    let flags = get_field(mn, cls_const::J_MEMBER_NAME, "flags", "I").extract_int();
+
+    let defc = mirror_target(&clazz).ok_or(())?;
+    if name.is_null() || typ.is_null() {
+        return Err(());
+    }
+    let name = OopPtr::java_lang_string(name.extract_ref());
+    let name = new_br(name.as_str());
+    let desc = Arc::new(type_desc(&typ));
+    let ref_kind = ((flags >> MN_REFERENCE_KIND_SHIFT) & MN_REFERENCE_KIND_MASK) as u8;
+
+    find_method(&defc, &name, &desc, ref_kind)
+}
+
+pub fn find_method(
+    defc: &ClassRef,
+    name: &BytesRef,
+    desc: &BytesRef,
+    ref_kind: u8,
+) -> Result<MethodIdRef, ()> {
+    let cls = defc.get_class();
+    if is_signature_polymorphic(cls.name.as_slice(), name.as_slice()) {
+        return link_signature_polymorphic(cls, name, desc);
+    }
+
+    match ref_kind {
+        cls_const::REF_INVOKE_INTERFACE => cls.get_interface_method(name, desc),
+        cls_const::REF_NEW_INVOKE_SPECIAL => cls.get_this_class_method(name, desc),
+        _ => cls
+            .get_class_method(name, desc)
+            .or_else(|_| cls.get_interface_method(name, desc)),
+    }
+}
+
+pub fn find_field(
+    defc: &ClassRef,
+    name: &BytesRef,
+    desc: &BytesRef,
+    is_static: bool,
+) -> Option<FieldIdRef> {
+    let mut cur = Some(defc.clone());
+    while let Some(cls) = cur {
+        let cls = cls.get_class();
+        if let oop::class::ClassKind::Instance(cls_obj) = &cls.kind {
+            let k = (cls.name.clone(), name.clone(), desc.clone());
+            let fields = if is_static {
+                &cls_obj.static_fields
+            } else {
+                &cls_obj.inst_fields
+            };
+            if let Some(fid) = fields.get(&k) {
+                return Some(fid.clone());
+            }
+        }
+
+        cur = cls.super_class.clone();
+    }
+
+    None
+}
+
+/*
+Signature polymorphic methods, JVM spec 2.9
+
+The methods of MethodHandle are declared as 'native Object m(Object...)',
+but can be invoked with any descriptor. For every call site, a MethodId
+with the descriptor of the call site is built, which dispatches to the
+target according to the name.
+*/
+pub fn is_signature_polymorphic(cls: &[u8], name: &[u8]) -> bool {
+    if cls != cls_const::J_METHODHANDLE {
+        return false;
+    }
+
+    matches!(
+        name,
+        b"invokeExact"
+            | b"invoke"
+            | b"invokeBasic"
+            | b"linkToStatic"
+            | b"linkToSpecial"
+            | b"linkToVirtual"
+            | b"linkToInterface"
+    )
+}
+
+pub fn link_signature_polymorphic(
+    cls: &Class,
+    name: &BytesRef,
+    desc: &BytesRef,
+) -> Result<MethodIdRef, ()> {
+    let declared = cls.get_this_class_method(name, &S_POLYMORPHIC_SIG)?;
+
+    let mut method = declared.method.clone();
+    method.desc = desc.clone();
+    method.signature = MethodSignature::new(desc.as_slice());
+
+    let (fn_name, f): (&'static str, NativeMethodPtr) = match name.as_slice() {
+        b"invokeBasic" => ("invokeBasic", Box::new(invoke_basic)),
+        b"linkToStatic" => ("linkToStatic", Box::new(link_to_static)),
+        b"linkToSpecial" => ("linkToSpecial", Box::new(link_to_static)),
+        b"linkToVirtual" => ("linkToVirtual", Box::new(link_to_virtual)),
+        b"linkToInterface" => ("linkToInterface", Box::new(link_to_virtual)),
+        b"invokeExact" => {
+            let invoker = Invoker::new(name.clone(), desc.clone());
+            (
+                "invokeExact",
+                Box::new(move |_env, args| invoker.invoke(args)),
+            )
+        }
+        _ => {
+            let invoker = Invoker::new(name.clone(), desc.clone());
+            ("invoke", Box::new(move |_env, args| invoker.invoke(args)))
+        }
+    };
+
+    Ok(Arc::new(runtime::method::MethodId {
+        offset: declared.offset,
+        vtable_index: None,
+        method,
+        native_impl: Some(native::new_fn(
+            fn_name,
+            "([Ljava/lang/Object;)Ljava/lang/Object;",
+            f,
+        )),
+    }))
+}
+
+//MethodHandle.invokeExact & invoke, linked by MethodHandleNatives.linkMethod lazily
+struct Invoker {
+    name: BytesRef,
+    desc: BytesRef,
+    linked: Mutex<Option<(MethodIdRef, Oop)>>,
+}
+
+impl Invoker {
+    fn new(name: BytesRef, desc: BytesRef) -> Self {
+        Self {
+            name,
+            desc,
+            linked: Mutex::new(None),
+        }
+    }
+
+    fn invoke(&self, args: &[Oop]) -> JNIResult {
+        if let Some(Oop::Null) = args.first() {
+            return Err(exception::new(cls_const::J_NPE, None));
+        }
+
+        let (target, appendix) = match self.link() {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let mut args = args.to_vec();
+        args.push(appendix);
+        Ok(call(target, args))
+    }
+
+    fn link(&self) -> Option<(MethodIdRef, Oop)> {
+        {
+            let linked = self.linked.lock().unwrap();
+            if linked.is_some() {
+                return linked.clone();
+            }
+        }
+
+        //the frame of this native method is the last one
+        let caller = {
+            let jt = runtime::thread::current_java_thread();
+            let jt = jt.read().unwrap();
+            let n = jt.frames.len();
+            let frame = &jt.frames[n - 2];
+            frame.class.get_class().get_mirror()
+        };
+        let defc = {
+            let cls = require_class3(None, cls_const::J_METHODHANDLE).unwrap();
+            let cls = cls.get_class();
+            cls.get_mirror()
+        };
+        let name = util::oop::new_java_lang_string3(self.name.as_slice());
+        let typ = new_method_type(self.desc.as_slice())?;
+        let appendix = new_appendix_result();
+
+        let mn = call_natives(
+            "linkMethod",
+            "(Ljava/lang/Class;ILjava/lang/Class;Ljava/lang/String;Ljava/lang/Object;[Ljava/lang/Object;)Ljava/lang/invoke/MemberName;",
+            vec![
+                caller,
+                Oop::new_int(cls_const::REF_INVOKE_VIRTUAL as i32),
+                defc,
+                name,
+                typ,
+                appendix.clone(),
+            ],
+        )?;
+        let linked = take_linked(&mn, &appendix)?;
+
+        let mut v = self.linked.lock().unwrap();
+        *v = Some(linked.clone());
+        Some(linked)
+    }
+}
+
+//mh.form.vmentry
+fn invoke_basic(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let mh = args.first().unwrap();
+    if mh.is_null() {
+        return Err(exception::new(cls_const::J_NPE, None));
+    }
+
+    let form = get_field(
+        mh,
+        cls_const::J_METHODHANDLE,
+        "form",
+        "Ljava/lang/invoke/LambdaForm;",
+    );
+    let vmentry = get_field(
+        &form,
+        cls_const::J_LAMBDA_FORM,
+        "vmentry",
+        "Ljava/lang/invoke/MemberName;",
+    );
+    match get_vmtarget(&vmentry) {
+        Ok(mir) => Ok(call(mir, args.to_vec())),
+        Err(_) => Err(exception::new(
+            cls_const::J_INTERNAL_ERROR,
+            Some("unresolved LambdaForm.vmentry".to_string()),
+        )),
+    }
+}
+
+//the last arg is the MemberName of target
+fn link_to_static(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let (mn, args) = args.split_last().unwrap();
+    match get_vmtarget(mn) {
+        Ok(mir) => Ok(call(mir, args.to_vec())),
+        Err(_) => Ok(None),
+    }
+}
+
+//the target is selected by the class of receiver
+fn link_to_virtual(_env: JNIEnv, args: &[Oop]) -> JNIResult {
+    let (mn, args) = args.split_last().unwrap();
+    let mir = match get_vmtarget(mn) {
+        Ok(mir) => mir,
+        Err(_) => return Ok(None),
+    };
+
+    let receiver = args.first().unwrap();
+    if receiver.is_null() {
+        return Err(exception::new(cls_const::J_NPE, None));
+    }
+
+    let cls = {
+        let rf = receiver.extract_ref();
+        let ptr = rf.get_raw_ptr();
+        unsafe {
+            match &(*ptr).v {
+                oop::RefKind::Inst(inst) => Some(inst.class.clone()),
+                _ => None,
+            }
+        }
+    };
+    let mir = match cls {
+        Some(cls) => match cls.get_class().select_method(&mir) {
+            Ok(mir) => mir,
+            Err((name, msg)) => return Err(exception::new(name, Some(msg))),
+        },
+        None => mir,
+    };
+
+    Ok(call(mir, args.to_vec()))
+}
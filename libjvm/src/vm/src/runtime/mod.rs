@@ -1,20 +1,24 @@
 #![allow(unused)]
 
-pub use class_loader::{require_class, require_class2, require_class3, ClassLoader};
+pub use class_loader::{
+    define_class, no_class_def_found, require_class, require_class2, require_class3, ClassLoader,
+};
 pub use class_path_manager::{
     add_path as add_class_path, add_paths as add_class_paths,
     find_class as find_class_in_classpath, ClassPathResult,
 };
 pub use constant_pool::ConstantPoolCache;
-pub use consts::THREAD_MAX_STACK_FRAMES;
+pub use consts::{THREAD_DEFAULT_STACK_SIZE, THREAD_STACK_FRAME_SIZE, THREAD_STACK_RESERVED_ZONE};
 pub use dataarea::DataArea;
 pub use frame::Frame;
 pub use interp::Interp;
 pub use invoke::JavaCall;
-pub use slot::Slot;
-pub use sys_dic::{find as sys_dic_find, put as sys_dic_put};
+pub use sys_dic::{find as sys_dic_find, put as sys_dic_put, remove as sys_dic_remove};
 pub use thread::JavaThread;
 
+pub mod access;
+pub mod arith;
+pub mod bytecode;
 mod class_loader;
 mod class_path_manager;
 pub mod cmp;
@@ -23,15 +27,17 @@ mod consts;
 mod dataarea;
 pub mod exception;
 mod frame;
+pub mod indy;
 mod init_vm;
 pub mod interp;
 pub mod invoke;
-mod local;
+pub mod loader_constraints;
 pub mod method;
-mod slot;
-mod stack;
+pub mod method_handles;
+pub mod stack;
 mod sys_dic;
 pub mod thread;
+pub mod verifier;
 pub mod vm;
 
 pub fn init() {
@@ -1,286 +1,425 @@
-use crate::oop::{consts, Oop};
-use crate::runtime::Slot;
-
-#[derive(Debug)]
+use crate::oop::{Oop, OopPtr};
+use std::cell::Cell;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+/*
+The value stack of a thread: the locals and then the operand stack of each
+frame in one contiguous region of untagged 64 bit slots, a frame pops its
+region when it returns. long and double take two slots, the value in the
+upper one, like the locals of JVM spec, 2.6.1.
+
+A reference is the address of its handle, it's not counted as a handle. The
+collector scans the stacks of all the threads, with the world stopped, any
+slot holding the address of an object is a root. The handles never move, so
+a slot holding an int that looks like an address only keeps garbage alive.
+
+The stack is only accessed by its thread, the borrow returned by current
+must not be held across a call that may run java.
+*/
 pub struct Stack {
-    inner: Vec<Slot>,
+    slots: Vec<u64>,
+}
+
+lazy_static! {
+    //the stacks of the live threads, see scan_roots
+    static ref STACKS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+}
+
+thread_local! {
+    static CURRENT: Cell<*mut Stack> = const { Cell::new(ptr::null_mut()) };
+    static OWNER: Owner = Owner::new();
+}
+
+//registers the stack of the thread, and frees it when the thread exits
+struct Owner(*mut Stack);
+
+impl Owner {
+    fn new() -> Self {
+        let stack = Box::into_raw(Box::new(Stack::new()));
+        STACKS.lock().unwrap().push(stack as usize);
+        Owner(stack)
+    }
+}
+
+impl Drop for Owner {
+    fn drop(&mut self) {
+        STACKS.lock().unwrap().retain(|it| *it != self.0 as usize);
+        let _ = CURRENT.try_with(|v| v.set(ptr::null_mut()));
+        unsafe {
+            drop(Box::from_raw(self.0));
+        }
+    }
+}
+
+//the stack of the current thread
+#[inline]
+pub fn current() -> &'static mut Stack {
+    let mut stack = CURRENT.with(|v| v.get());
+    if stack.is_null() {
+        stack = OWNER.with(|v| v.0);
+        CURRENT.with(|v| v.set(stack));
+    }
+    unsafe { &mut *stack }
+}
+
+//the slots of all the stacks, called by the collector with the world stopped
+pub fn scan_roots<F: FnMut(usize)>(mut f: F) {
+    let stacks = STACKS.lock().unwrap();
+    for it in stacks.iter() {
+        let stack = unsafe { &*(*it as *const Stack) };
+        for v in stack.slots.iter() {
+            f(*v as usize);
+        }
+    }
+}
+
+#[inline]
+fn to_slot(v: &Oop) -> u64 {
+    match v {
+        Oop::Ref(rf) => Arc::as_ptr(rf) as u64,
+        Oop::Null => 0,
+        t => unreachable!("t = {:?}", t),
+    }
+}
+
+/*
+A reference read from a slot, it borrows the handle of the slot, the count
+of the handle is not touched.
+
+The object is not collected before the next safepoint of the thread, even
+if the slot was popped. clone() it for a handle kept across a safepoint, or
+a call that may run java.
+*/
+pub struct RefSlot(ManuallyDrop<Oop>);
+
+impl Deref for RefSlot {
+    type Target = Oop;
+
+    #[inline]
+    fn deref(&self) -> &Oop {
+        &self.0
+    }
+}
+
+#[inline]
+fn from_slot(v: u64) -> RefSlot {
+    let v = if v == 0 {
+        Oop::Null
+    } else {
+        Oop::Ref(unsafe { Arc::from_raw(v as *const OopPtr) })
+    };
+    RefSlot(ManuallyDrop::new(v))
 }
 
 impl Stack {
-    pub fn new(size: usize) -> Self {
+    fn new() -> Self {
         Self {
-            inner: Vec::with_capacity(size),
+            slots: Vec::with_capacity(1024),
         }
     }
 
     #[inline]
-    pub fn push_int(&mut self, v: i32) {
-        self.inner.push(Slot::I32(v));
+    pub fn len(&self) -> usize {
+        self.slots.len()
     }
 
     #[inline]
-    pub fn push_int2(&mut self, v: &[u8; 4]) {
-        let v = i32::from_be_bytes(*v);
-        self.inner.push(Slot::I32(v));
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
     }
 
+    //the locals of a new frame, zeroed
     #[inline]
-    pub fn push_float(&mut self, v: f32) {
-        self.inner.push(Slot::F32(v));
+    pub fn alloc(&mut self, n: usize) -> usize {
+        let base = self.slots.len();
+        self.slots.resize(base + n, 0);
+        base
     }
 
+    //pop the slots above 'len', the region of a frame or its operand stack
     #[inline]
-    pub fn push_float2(&mut self, v: &[u8; 4]) {
-        let v = u32::from_be_bytes(*v);
-        let v = f32::from_bits(v);
-        self.inner.push(Slot::F32(v));
+    pub fn truncate(&mut self, len: usize) {
+        self.slots.truncate(len);
     }
+}
 
+//the locals, 'i' is the index in the stack
+impl Stack {
     #[inline]
-    pub fn push_double(&mut self, v: f64) {
-        self.push_nop();
-        self.inner.push(Slot::F64(v));
+    pub fn get_int(&self, i: usize) -> i32 {
+        self.slots[i] as i32
     }
 
     #[inline]
-    pub fn push_double2(&mut self, v: &[u8; 8]) {
-        let v = u64::from_be_bytes(*v);
-        let v = f64::from_bits(v);
-        self.push_double(v);
+    pub fn set_int(&mut self, i: usize, v: i32) {
+        self.slots[i] = v as u32 as u64;
     }
 
     #[inline]
-    pub fn push_long(&mut self, v: i64) {
-        self.push_nop();
-        self.inner.push(Slot::I64(v));
+    pub fn get_long(&self, i: usize) -> i64 {
+        self.slots[i + 1] as i64
     }
 
     #[inline]
-    pub fn push_long2(&mut self, v: &[u8; 8]) {
-        let v = i64::from_be_bytes(*v);
-        self.push_long(v);
+    pub fn set_long(&mut self, i: usize, v: i64) {
+        self.slots[i + 1] = v as u64;
     }
 
     #[inline]
-    pub fn push_null(&mut self) {
-        self.inner.push(Slot::Ref(Oop::Null));
+    pub fn get_float(&self, i: usize) -> f32 {
+        f32::from_bits(self.slots[i] as u32)
     }
 
     #[inline]
-    pub fn push_const_m1(&mut self) {
-        self.inner.push(Slot::ConstM1);
+    pub fn set_float(&mut self, i: usize, v: f32) {
+        self.slots[i] = v.to_bits() as u64;
     }
 
-    /*
-    double & long, with_nop = true
-    */
     #[inline]
-    pub fn push_const0(&mut self, with_nop: bool) {
-        if with_nop {
-            self.push_nop();
-        }
-        self.inner.push(Slot::Const0);
+    pub fn get_double(&self, i: usize) -> f64 {
+        f64::from_bits(self.slots[i + 1])
+    }
+
+    #[inline]
+    pub fn set_double(&mut self, i: usize, v: f64) {
+        self.slots[i + 1] = v.to_bits();
     }
 
-    /*
-       double & long, with_nop = true
-    */
     #[inline]
-    pub fn push_const1(&mut self, with_nop: bool) {
-        if with_nop {
-            self.push_nop();
+    pub fn get_ref(&self, i: usize) -> RefSlot {
+        from_slot(self.slots[i])
+    }
+
+    #[inline]
+    pub fn set_ref(&mut self, i: usize, v: Oop) {
+        self.slots[i] = to_slot(&v);
+    }
+
+    //the value of type of 'v'
+    pub fn set_value(&mut self, i: usize, v: Oop) -> usize {
+        match v {
+            Oop::Int(v) => self.set_int(i, v),
+            Oop::Float(v) => self.set_float(i, v),
+            Oop::Long(v) => {
+                self.set_long(i, v);
+                return 2;
+            }
+            Oop::Double(v) => {
+                self.set_double(i, v);
+                return 2;
+            }
+            v => self.set_ref(i, v),
         }
-        self.inner.push(Slot::Const1);
+        1
+    }
+}
+
+//the operand stack
+impl Stack {
+    #[inline]
+    pub fn push_int(&mut self, v: i32) {
+        self.slots.push(v as u32 as u64);
+    }
+
+    #[inline]
+    pub fn push_int2(&mut self, v: &[u8; 4]) {
+        self.push_int(i32::from_be_bytes(*v));
+    }
+
+    #[inline]
+    pub fn push_float(&mut self, v: f32) {
+        self.slots.push(v.to_bits() as u64);
     }
 
     #[inline]
-    pub fn push_const2(&mut self) {
-        self.inner.push(Slot::Const2);
+    pub fn push_float2(&mut self, v: &[u8; 4]) {
+        self.push_float(f32::from_be_bytes(*v));
     }
 
     #[inline]
-    pub fn push_const3(&mut self) {
-        self.inner.push(Slot::Const3);
+    pub fn push_double(&mut self, v: f64) {
+        self.slots.push(0);
+        self.slots.push(v.to_bits());
     }
 
     #[inline]
-    pub fn push_const4(&mut self) {
-        self.inner.push(Slot::Const4);
+    pub fn push_double2(&mut self, v: &[u8; 8]) {
+        self.push_double(f64::from_be_bytes(*v));
     }
 
     #[inline]
-    pub fn push_const5(&mut self) {
-        self.inner.push(Slot::Const5);
+    pub fn push_long(&mut self, v: i64) {
+        self.slots.push(0);
+        self.slots.push(v as u64);
     }
 
     #[inline]
-    pub fn push_ref(&mut self, v: Oop, with_nop: bool) {
-        if with_nop {
-            self.push_nop();
+    pub fn push_long2(&mut self, v: &[u8; 8]) {
+        self.push_long(i64::from_be_bytes(*v));
+    }
+
+    #[inline]
+    pub fn push_null(&mut self) {
+        self.slots.push(0);
+    }
+
+    #[inline]
+    pub fn push_ref(&mut self, v: Oop) {
+        self.slots.push(to_slot(&v));
+    }
+
+    //the value of type of 'v', a field value or a return value
+    pub fn push_value(&mut self, v: Oop) {
+        match v {
+            Oop::Int(v) => self.push_int(v),
+            Oop::Float(v) => self.push_float(v),
+            Oop::Long(v) => self.push_long(v),
+            Oop::Double(v) => self.push_double(v),
+            v => self.push_ref(v),
         }
-        self.inner.push(Slot::Ref(v));
     }
 
     #[inline]
     pub fn pop_int(&mut self) -> i32 {
-        match self.inner.pop().unwrap() {
-            Slot::ConstM1 => -1,
-            Slot::Const0 => 0,
-            Slot::Const1 => 1,
-            Slot::Const2 => 2,
-            Slot::Const3 => 3,
-            Slot::Const4 => 4,
-            Slot::Const5 => 5,
-            Slot::I32(v) => v,
-            Slot::Ref(v) => v.extract_int(),
-            _ => panic!("Illegal type"),
-        }
+        self.slots.pop().unwrap() as i32
     }
 
     #[inline]
     pub fn pop_float(&mut self) -> f32 {
-        match self.inner.pop().unwrap() {
-            Slot::Const0 => 0.0,
-            Slot::Const1 => 1.0,
-            Slot::Const2 => 2.0,
-            Slot::F32(v) => v,
-            Slot::Ref(v) => v.extract_float(),
-            _ => panic!("Illegal type"),
-        }
+        f32::from_bits(self.slots.pop().unwrap() as u32)
     }
 
     #[inline]
     pub fn pop_double(&mut self) -> f64 {
-        match self.inner.pop() {
-            Some(v) => {
-                self.pop_nop();
-                match v {
-                    Slot::Const0 => 0.0,
-                    Slot::Const1 => 1.0,
-                    Slot::F64(v) => v,
-                    Slot::Ref(v) => v.extract_double(),
-                    _ => panic!("Illegal type"),
-                }
-            }
-            None => panic!("Empty Stack!"),
-        }
+        let v = self.slots.pop().unwrap();
+        self.slots.pop();
+        f64::from_bits(v)
     }
 
     #[inline]
     pub fn pop_long(&mut self) -> i64 {
-        match self.inner.pop() {
-            Some(v) => {
-                self.pop_nop();
-                match v {
-                    Slot::Const0 => 0,
-                    Slot::Const1 => 1,
-                    Slot::I64(v) => v,
-                    Slot::Ref(v) => v.extract_long(),
-                    _ => panic!("Illegal type"),
-                }
-            }
-            _ => panic!("Empty Stack!"),
-        }
+        let v = self.slots.pop().unwrap();
+        self.slots.pop();
+        v as i64
     }
 
     #[inline]
-    pub fn pop_ref(&mut self) -> Oop {
-        match self.inner.pop() {
-            Some(Slot::Ref(v)) => v,
-            t => panic!("Illegal type = {:?}", t),
-        }
+    pub fn pop_ref(&mut self) -> RefSlot {
+        from_slot(self.slots.pop().unwrap())
     }
 
+    //the top moved to the local 'i' as is, a reference or a returnAddress
     #[inline]
-    pub fn drop_top(&mut self) {
-        let _ = self.inner.pop();
+    pub fn pop_to(&mut self, i: usize) {
+        let v = self.slots.pop().unwrap();
+        self.slots[i] = v;
     }
 
-    pub fn clear(&mut self) {
-        self.inner.clear();
+    //the local 'i' pushed as is, aload
+    #[inline]
+    pub fn push_from(&mut self, i: usize) {
+        let v = self.slots[i];
+        self.slots.push(v);
+    }
+
+    #[inline]
+    pub fn drop_top(&mut self) {
+        let _ = self.slots.pop();
     }
 
     #[inline]
     pub fn dup(&mut self) {
-        let v = self.inner.pop().unwrap();
-        self.inner.push(v.clone());
-        self.inner.push(v);
+        let v = *self.slots.last().unwrap();
+        self.slots.push(v);
     }
 
     #[inline]
     pub fn dup_x1(&mut self) {
-        let v1 = self.inner.pop().unwrap();
-        let v2 = self.inner.pop().unwrap();
-        self.inner.push(v1.clone());
-        self.inner.push(v2);
-        self.inner.push(v1);
+        let n = self.slots.len();
+        let v1 = self.slots[n - 1];
+        self.slots.insert(n - 2, v1);
     }
 
     #[inline]
     pub fn dup_x2(&mut self) {
-        let v1 = self.inner.pop().unwrap();
-        let v2 = self.inner.pop().unwrap();
-        let v3 = self.inner.pop().unwrap();
-        self.inner.push(v1.clone());
-        self.inner.push(v3);
-        self.inner.push(v2);
-        self.inner.push(v1);
+        let n = self.slots.len();
+        let v1 = self.slots[n - 1];
+        self.slots.insert(n - 3, v1);
     }
 
     #[inline]
     pub fn dup2(&mut self) {
-        let v1 = self.inner.pop().unwrap();
-        let v2 = self.inner.pop().unwrap();
-        self.inner.push(v2.clone());
-        self.inner.push(v1.clone());
-        self.inner.push(v2);
-        self.inner.push(v1);
+        let n = self.slots.len();
+        self.slots.extend_from_within(n - 2..);
     }
 
     #[inline]
     pub fn dup2_x1(&mut self) {
-        let v1 = self.inner.pop().unwrap();
-        let v2 = self.inner.pop().unwrap();
-        let v3 = self.inner.pop().unwrap();
-        self.inner.push(v2.clone());
-        self.inner.push(v1.clone());
-        self.inner.push(v3);
-        self.inner.push(v2);
-        self.inner.push(v1);
+        let n = self.slots.len();
+        let (v2, v1) = (self.slots[n - 2], self.slots[n - 1]);
+        self.slots.splice(n - 3..n - 3, [v2, v1]);
     }
 
     #[inline]
     pub fn dup2_x2(&mut self) {
-        let v1 = self.inner.pop().unwrap();
-        let v2 = self.inner.pop().unwrap();
-        let v3 = self.inner.pop().unwrap();
-        let v4 = self.inner.pop().unwrap();
-        self.inner.push(v2.clone());
-        self.inner.push(v1.clone());
-        self.inner.push(v4);
-        self.inner.push(v3);
-        self.inner.push(v2);
-        self.inner.push(v1);
+        let n = self.slots.len();
+        let (v2, v1) = (self.slots[n - 2], self.slots[n - 1]);
+        self.slots.splice(n - 4..n - 4, [v2, v1]);
     }
 
     #[inline]
     pub fn swap(&mut self) {
-        let v1 = self.inner.pop().unwrap();
-        let v2 = self.inner.pop().unwrap();
-        self.inner.push(v1);
-        self.inner.push(v2);
+        let n = self.slots.len();
+        self.slots.swap(n - 1, n - 2);
     }
 }
 
-impl Stack {
-    fn push_nop(&mut self) {
-        self.inner.push(Slot::Nop);
-    }
-
-    fn pop_nop(&mut self) {
-        match self.inner.pop() {
-            Some(Slot::Nop) => (),
-            _ => panic!("Should be Nop!"),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn t_slots() {
+        let stack = current();
+        let base = stack.alloc(4);
+        stack.set_long(base, -2);
+        stack.set_float(base + 2, 1.5);
+        assert_eq!(stack.get_long(base), -2);
+        assert_eq!(stack.get_float(base + 2), 1.5);
+
+        stack.push_int(-1);
+        stack.push_double(0.25);
+        stack.push_int(7);
+        stack.dup_x2();
+        assert_eq!(stack.pop_int(), 7);
+        assert_eq!(stack.pop_double(), 0.25);
+        assert_eq!(stack.pop_int(), 7);
+        assert_eq!(stack.pop_int(), -1);
+
+        stack.push_int(1);
+        stack.push_int(2);
+        stack.push_int(3);
+        stack.dup2_x1();
+        let v: Vec<i32> = (0..5).map(|_| stack.pop_int()).collect();
+        assert_eq!(v, vec![3, 2, 1, 3, 2]);
+
+        (1..=4).for_each(|it| stack.push_int(it));
+        stack.dup2_x2();
+        stack.swap();
+        let v: Vec<i32> = (0..6).map(|_| stack.pop_int()).collect();
+        assert_eq!(v, vec![3, 4, 2, 1, 4, 3]);
+
+        //the returnAddress of jsr, stored by astore, read by ret
+        stack.push_int(17);
+        stack.pop_to(base + 3);
+        assert_eq!(stack.get_int(base + 3), 17);
+
+        stack.push_value(Oop::new_long(i64::MIN));
+        assert_eq!(stack.pop_long(), i64::MIN);
+        stack.truncate(base);
+        assert!(stack.is_empty());
     }
 }
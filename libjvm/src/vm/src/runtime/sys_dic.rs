@@ -1,30 +1,39 @@
+use crate::runtime::ClassLoader;
 use crate::types::ClassRef;
 use crate::util;
 
 use rustc_hash::FxHashMap;
 use std::sync::{Arc, Mutex};
 
-type SystemDictionary = Mutex<FxHashMap<String, ClassRef>>;
+//the classes are keyed by the initiating loader and the name
+type SystemDictionary = Mutex<FxHashMap<(ClassLoader, String), ClassRef>>;
 
 lazy_static! {
     static ref SYS_DIC: SystemDictionary = { Mutex::new(FxHashMap::default()) };
 }
 
-pub fn put(key: &[u8], klass: ClassRef) {
+pub fn put(loader: ClassLoader, key: &[u8], klass: ClassRef) {
     debug_assert!(!key.contains(&b'.'));
 
     let key = Vec::from(key);
     let key = unsafe { String::from_utf8_unchecked(key) };
     let mut dict = SYS_DIC.lock().unwrap();
-    dict.insert(key, klass);
+    dict.insert((loader, key), klass);
+}
+
+//the class failed to be linked is forgotten
+pub fn remove(loader: ClassLoader, key: &[u8]) {
+    let key = unsafe { std::str::from_utf8_unchecked(key) };
+    let mut dict = SYS_DIC.lock().unwrap();
+    dict.remove(&(loader, key.to_string()));
 }
 
 //key style: "sun/security/provider/Sun"
-pub fn find(key: &[u8]) -> Option<ClassRef> {
+pub fn find(loader: ClassLoader, key: &[u8]) -> Option<ClassRef> {
     debug_assert!(!key.contains(&b'.'));
     let key = unsafe { std::str::from_utf8_unchecked(key) };
     let dict = SYS_DIC.lock().unwrap();
-    dict.get(key).cloned()
+    dict.get(&(loader, key.to_string())).cloned()
 }
 
 pub fn init() {
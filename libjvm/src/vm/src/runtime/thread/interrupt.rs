@@ -0,0 +1,105 @@
+use crate::gc;
+use crate::oop::Oop;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/*
+The interrupt status of a java thread, shared with the threads calling
+Thread.interrupt. The thread blocked in sleep, park or Object.wait is woken.
+*/
+pub struct Interrupt {
+    interrupted: AtomicBool,
+    //the native thread, woken from sleep and park
+    native: Mutex<Option<Thread>>,
+    //the object of Object.wait
+    waiting_on: Mutex<Option<Oop>>,
+}
+
+impl Interrupt {
+    pub fn new() -> Self {
+        Self {
+            interrupted: AtomicBool::new(false),
+            native: Mutex::new(None),
+            waiting_on: Mutex::new(None),
+        }
+    }
+
+    pub fn is_interrupted(&self, clear: bool) -> bool {
+        if clear {
+            self.interrupted.swap(false, Ordering::SeqCst)
+        } else {
+            self.interrupted.load(Ordering::SeqCst)
+        }
+    }
+
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+
+        if let Some(native) = self.native.lock().unwrap().as_ref() {
+            native.unpark();
+        }
+
+        //the waiting thread checks the status before it waits, not lost
+        let obj = self.waiting_on.lock().unwrap().clone();
+        if let Some(obj) = obj {
+            obj.extract_ref().wake_waiters();
+        }
+    }
+
+    //the current native thread is going to park, unparked by interrupt
+    fn park_current(&self) {
+        self.native.lock().unwrap().replace(thread::current());
+    }
+
+    /*
+    Sleep for 'duration', false is returned if it's interrupted, and the
+    status is cleared.
+    */
+    pub fn sleep(&self, duration: Duration) -> bool {
+        self.park_current();
+        let deadline = Instant::now().checked_add(duration);
+
+        loop {
+            if self.is_interrupted(true) {
+                return false;
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return true;
+                    }
+                    gc::blocking(|| thread::park_timeout(deadline - now));
+                }
+                None => gc::blocking(thread::park),
+            }
+        }
+    }
+
+    /*
+    Record 'obj' waited on, called owning the monitor of 'obj'. false is
+    returned if it's interrupted already, the thread should not wait.
+    */
+    pub fn begin_wait(&self, obj: Oop) -> bool {
+        self.waiting_on.lock().unwrap().replace(obj);
+        if self.is_interrupted(false) {
+            self.end_wait();
+            false
+        } else {
+            true
+        }
+    }
+
+    pub fn end_wait(&self) {
+        self.waiting_on.lock().unwrap().take();
+    }
+}
+
+impl Default for Interrupt {
+    fn default() -> Self {
+        Self::new()
+    }
+}
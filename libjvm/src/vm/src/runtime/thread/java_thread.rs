@@ -1,5 +1,8 @@
-use crate::oop::{self, consts, Oop};
+use crate::new_br;
+use crate::oop::{self, consts, Class, Oop};
+use crate::runtime::thread::{Interrupt, Parker};
 use crate::types::{FrameRef, JavaThreadRef};
+use classfile::consts::J_THREAD;
 use std::cell::RefCell;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
@@ -9,6 +12,10 @@ thread_local! {
     pub static IS_MEET_EX: AtomicBool = AtomicBool::new(false);
 }
 
+//java.lang.Thread.threadStatus, the JVMTI thread state
+pub const THREAD_STATUS_RUNNABLE: i32 = 0x0005;
+pub const THREAD_STATUS_TERMINATED: i32 = 0x0002;
+
 pub fn current_java_thread() -> JavaThreadRef {
     THREAD.with(|t| t.borrow().clone())
 }
@@ -31,6 +38,8 @@ pub struct JavaThread {
     pub ex: Option<Oop>,
     pub is_alive: bool,
     pub eetop: i64,
+    pub interrupt: Arc<Interrupt>,
+    pub parker: Arc<Parker>,
 
     pub tag: String, //for debug
 }
@@ -46,6 +55,8 @@ impl JavaThread {
             ex: None,
             is_alive: false,
             eetop,
+            interrupt: Arc::new(Interrupt::new()),
+            parker: Arc::new(Parker::new()),
             tag,
         };
         Arc::new(RwLock::new(Box::new(t)))
@@ -58,6 +69,27 @@ impl JavaThread {
     pub fn set_java_thread_obj(&mut self, obj: Oop) {
         self.java_thread_obj = Some(obj);
     }
+
+    pub fn is_daemon(&self) -> bool {
+        match &self.java_thread_obj {
+            Some(obj) => {
+                let cls = oop::class::load_and_init(J_THREAD);
+                let cls = cls.get_class();
+                let fid = cls.get_field_id(&new_br("daemon"), &new_br("Z"), false);
+                Class::get_field_value(obj.extract_ref(), fid).extract_int() != 0
+            }
+            None => false,
+        }
+    }
+
+    pub fn set_status(&self, status: i32) {
+        if let Some(obj) = &self.java_thread_obj {
+            let cls = oop::class::load_and_init(J_THREAD);
+            let cls = cls.get_class();
+            let fid = cls.get_field_id(&new_br("threadStatus"), &new_br("I"), false);
+            Class::put_field_value(obj.extract_ref(), fid, Oop::new_int(status));
+        }
+    }
 }
 
 //exception
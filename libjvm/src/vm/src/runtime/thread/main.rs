@@ -1,6 +1,5 @@
 use crate::oop::{self, Class, Oop, OopPtr};
-use crate::runtime::thread::thread_pool;
-use crate::runtime::{self, init_vm, vm, DataArea, JavaCall, JavaThread};
+use crate::runtime::{self, vm, DataArea, JavaCall, JavaThread};
 use crate::types::{ClassRef, FrameRef, JavaThreadRef, MethodIdRef};
 use crate::{new_br, util};
 use std::borrow::Borrow;
@@ -8,76 +7,107 @@ use std::borrow::Borrow;
 pub struct MainThread {
     pub class: String,
     pub args: Vec<String>,
-    dispatch_uncaught_exception_called: bool,
 }
 
 impl MainThread {
     pub fn new(class: String, args: Vec<String>) -> Self {
-        Self {
-            class,
-            args,
-            dispatch_uncaught_exception_called: false,
-        }
+        Self { class, args }
     }
 
-    pub fn run(&mut self) {
-        let vm = vm::VM::new(3);
-
-        //attach 'main' thread
-        vm.threads.attach_current_thread();
-
+    //Err if the main class or the main method is not found, or main ended by
+    //an exception, the message or the stack trace is printed
+    pub fn run(&mut self) -> Result<(), ()> {
         info!("init vm start");
-        init_vm::initialize_jvm();
+        if vm::create().is_err() {
+            eprintln!("Error occurred during initialization of VM");
+            let ex = runtime::thread::current_java_thread()
+                .write()
+                .unwrap()
+                .take_ex();
+            if let Some(ex) = ex {
+                eprintln!("{}", describe_ex(&ex));
+            }
+            return Err(());
+        }
         info!("init vm end");
 
-        let main_class = oop::class::load_and_init(self.class.as_bytes());
+        let jt = runtime::thread::current_java_thread();
+        let r = match self.find_main() {
+            Ok(Some(mir)) => {
+                let args = self.build_main_arg();
+                let mut jc = JavaCall::new_with_args(mir, args);
+                jc.invoke(None, true);
+                Ok(())
+            }
+            Ok(None) => Ok(()),
+            Err(msg) => {
+                eprintln!("{}", msg);
+                Err(())
+            }
+        };
 
-        let mir = {
-            let cls = main_class.get_class();
+        //thrown by main, or by loading and initializing the main class
+        let ex = jt.write().unwrap().take_ex();
+        let r = match ex {
+            Some(ex) => {
+                self.uncaught_ex(ex);
+                Err(())
+            }
+            None => r,
+        };
 
-            /*
-            path info should be included in "--cp", and avoid same class load 2
-            times, otherwise, "<clinit>" invoked 2 times.
+        //wait for the threads not daemon, the daemon threads end with the process
+        let _ = vm::destroy();
 
-            For example:
-              "MyFile.java":
-                private static File gf = newFile();
+        r
+    }
 
-            if allowed, as follows:
-              "cargo run -- --cp $JDK:$MY_TEST test/with_package/my.ns.HelloWorld"
-            will cause "<clinit>" invoked 2 times, "newFile()" invoked 2 times,
-            maybe create 2 files.
+    //None if thrown by loading or "<clinit>", Err with the message of launcher
+    fn find_main(&self) -> Result<Option<MethodIdRef>, String> {
+        let name = self.class.replace(util::FILE_SEP, ".");
+        let not_found = format!("Error: Could not find or load main class {}", name);
+        let main_class = match runtime::require_class3(None, self.class.as_bytes()) {
+            Some(cls) => cls,
+            None if runtime::thread::is_meet_ex() => return Ok(None),
+            None => return Err(not_found),
+        };
 
-            should be like this:
-              "cargo run -- --cp $JDK:$MY_TEST:test/with_package my.ns.HelloWorld"
-            */
-            if self.class.as_bytes() != cls.name.as_slice() {
-                panic!("Error: Could not find or load main class {}", self.class);
-            }
+        /*
+        path info should be included in "--cp", and avoid same class load 2
+        times, otherwise, "<clinit>" invoked 2 times.
+
+        For example:
+          "MyFile.java":
+            private static File gf = newFile();
 
+        if allowed, as follows:
+          "cargo run -- --cp $JDK:$MY_TEST test/with_package/my.ns.HelloWorld"
+        will cause "<clinit>" invoked 2 times, "newFile()" invoked 2 times,
+        maybe create 2 files.
+
+        should be like this:
+          "cargo run -- --cp $JDK:$MY_TEST:test/with_package my.ns.HelloWorld"
+        */
+        if self.class.as_bytes() != main_class.get_class().name.as_slice() {
+            return Err(not_found);
+        }
+
+        let mir = {
+            let cls = main_class.get_class();
             cls.get_static_method(&new_br("main"), &new_br("([Ljava/lang/String;)V"))
         };
-
-        let jt = runtime::thread::current_java_thread();
         match mir {
-            Ok(mir) => {
-                let args = self.build_main_arg();
-                let mut jc = JavaCall::new_with_args(mir, args);
-                jt.write().unwrap().is_alive = true;
-                jc.invoke(None, true);
-                jt.write().unwrap().is_alive = false;
+            Ok(mir) if mir.method.is_static() && mir.method.is_public() => {
+                if oop::class::init_class(&main_class).is_err() {
+                    return Ok(None);
+                }
+                Ok(Some(mir))
             }
-            _ => unreachable!("NotFound \"main\""),
-        }
-
-        if jt.read().unwrap().ex.is_some() {
-            self.uncaught_ex(main_class);
+            _ => Err(format!(
+                "Error: Main method not found in class {}, please define the main method as:\n   public static void main(String[] args)",
+                name
+            )),
         }
-
-        //detach main thread
-        vm.threads.detach_current_thread();
-
-        vm.threads.join_all();
     }
 }
 
@@ -94,86 +124,55 @@ impl MainThread {
         vec![Oop::new_ref_ary2(ary_str_class, args)]
     }
 
-    fn uncaught_ex(&mut self, main_cls: ClassRef) {
-        if self.dispatch_uncaught_exception_called {
-            self.uncaught_ex_internal();
-        } else {
-            self.dispatch_uncaught_exception_called = true;
-            self.call_dispatch_uncaught_exception(main_cls);
-        }
-    }
-
-    fn call_dispatch_uncaught_exception(&mut self, main_cls: ClassRef) {
+    fn uncaught_ex(&self, ex: Oop) {
         let jt = runtime::thread::current_java_thread();
-        let v = {
-            let jt = jt.read().unwrap();
-            jt.java_thread_obj.clone()
-        };
-        match v {
-            Some(v) => {
-                let cls = {
-                    let rf = v.extract_ref();
-                    let inst = rf.extract_inst();
-                    inst.class.clone()
-                };
-
-                let mir = {
-                    let cls = cls.get_class();
-                    cls.get_this_class_method(
-                        &new_br("dispatchUncaughtException"),
-                        &new_br("(Ljava/lang/Throwable;)V"),
-                    )
-                };
-
-                match mir {
-                    Ok(mir) => {
-                        let ex = {
-                            let mut jt = jt.write().unwrap();
-                            jt.take_ex().unwrap()
-                        };
-                        let args = vec![v, ex];
-                        let mut jc = JavaCall::new_with_args(mir, args);
-                        jc.invoke(None, false);
-                    }
-                    _ => self.uncaught_ex_internal(),
-                }
-            }
-
-            None => self.uncaught_ex_internal(),
+        let thread_oop = { jt.read().unwrap().java_thread_obj.clone() };
+        match thread_oop {
+            Some(thread_oop) => vm::dispatch_uncaught_exception(thread_oop, ex),
+            None => print_stack_trace(ex),
         }
     }
+}
 
-    fn uncaught_ex_internal(&mut self) {
-        let jt = runtime::thread::current_java_thread();
-        let ex = {
-            let mut jt = jt.write().unwrap();
-            jt.take_ex().unwrap()
-        };
-
-        let cls = {
-            let rf = ex.extract_ref();
-            let inst = rf.extract_inst();
-            inst.class.clone()
-        };
-
-        let detail_message = {
-            let fid = {
-                let cls = cls.get_class();
-                cls.get_field_id(
-                    &new_br("detailMessage"),
-                    &new_br("Ljava/lang/String;"),
-                    false,
-                )
-            };
-            let v = Class::get_field_value(ex.extract_ref(), fid);
-            OopPtr::java_lang_string(v.extract_ref())
-        };
-        let name = {
-            let cls = cls.get_class();
-            cls.name.clone()
-        };
+//"java.lang.Error: message", like Throwable.toString, which may not run yet
+fn describe_ex(ex: &Oop) -> String {
+    let rf = ex.extract_ref();
+    let cls = rf.extract_inst().class.clone();
+    let cls = cls.get_class();
+    let name = String::from_utf8_lossy(cls.name.as_slice()).replace('/', ".");
+
+    let msg = cls
+        .find_field_id(
+            &new_br("detailMessage"),
+            &new_br("Ljava/lang/String;"),
+            false,
+        )
+        .map(|fid| Class::get_field_value(rf.clone(), fid));
+    match msg {
+        Some(Oop::Ref(msg)) => format!("{}: {}", name, OopPtr::java_lang_string(msg)),
+        _ => name,
+    }
+}
 
-        let cls_name = String::from_utf8_lossy(name.as_slice());
-        error!("Name={}, detailMessage={}", cls_name, detail_message);
+//the main thread without the Thread object, printed as ThreadGroup.uncaughtException
+fn print_stack_trace(ex: Oop) {
+    let cls = {
+        let rf = ex.extract_ref();
+        let inst = rf.extract_inst();
+        inst.class.clone()
+    };
+    let mir = {
+        let cls = cls.get_class();
+        cls.get_virtual_method(&new_br("printStackTrace"), &new_br("()V"))
+    };
+
+    eprint!("Exception in thread \"main\" ");
+    if let Ok(mir) = mir {
+        let mut jc = JavaCall::new_with_args(mir, vec![ex]);
+        jc.invoke(None, false);
     }
+    let _ = runtime::thread::current_java_thread()
+        .write()
+        .unwrap()
+        .take_ex();
 }
@@ -1,18 +1,20 @@
-mod condvar;
+mod interrupt;
 mod java_thread;
 mod main;
-mod mutex;
-mod thread_pool;
+mod monitor;
+mod parker;
+mod stack;
 mod threads;
 
-pub use condvar::Condvar;
+pub use interrupt::Interrupt;
 pub use java_thread::current_java_thread;
 pub use java_thread::JavaThread;
 pub use java_thread::THREAD;
+pub use java_thread::{THREAD_STATUS_RUNNABLE, THREAD_STATUS_TERMINATED};
 pub use main::MainThread;
-pub use mutex::raw as mutex_raw;
-pub use mutex::ReentrantMutex;
-pub use thread_pool::ThreadPool;
+pub use monitor::Monitor;
+pub use parker::Parker;
+pub use stack::{is_stack_overflow, new_stack_overflow_error};
 pub use threads::Threads;
 
 pub use java_thread::is_meet_ex;
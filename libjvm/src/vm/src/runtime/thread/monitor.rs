@@ -0,0 +1,239 @@
+use crate::gc;
+use crate::runtime::thread::Interrupt;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant};
+
+static NEXT_THREAD_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    //the owner id of the native thread, never reused unlike pthread_self
+    static THREAD_ID: usize = NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed);
+}
+
+fn current_id() -> usize {
+    THREAD_ID.with(|id| *id)
+}
+
+struct State {
+    //0 for not owned
+    owner: usize,
+    //the times locked by the owner
+    count: usize,
+    //the wait set, in the order of waiting, a notified waiter is removed
+    waiters: VecDeque<usize>,
+    next_ticket: usize,
+}
+
+/*
+The monitor of an object: the owner with the recursion count, the threads
+blocked in enter (the entry set), and the threads in wait (the wait set).
+Allocated when the object is first locked.
+*/
+pub struct Monitor {
+    state: Mutex<State>,
+    //the entry set, waiting for the owner to exit
+    entry: Condvar,
+    //the wait set, waiting for notify, interrupt or timeout
+    wait_set: Condvar,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                owner: 0,
+                count: 0,
+                waiters: VecDeque::new(),
+                next_ticket: 0,
+            }),
+            entry: Condvar::new(),
+            wait_set: Condvar::new(),
+        }
+    }
+
+    pub fn enter(&self) {
+        let id = current_id();
+        let mut state = self.lock_state();
+        if state.owner == id {
+            state.count += 1;
+            return;
+        }
+
+        state = self.acquire(state, id);
+        state.count = 1;
+    }
+
+    //Err if the current thread is not the owner
+    pub fn exit(&self) -> Result<(), ()> {
+        let mut state = self.lock_state();
+        if state.owner != current_id() {
+            return Err(());
+        }
+
+        state.count -= 1;
+        if state.count == 0 {
+            state.owner = 0;
+            drop(state);
+            self.entry.notify_one();
+        }
+        Ok(())
+    }
+
+    pub fn is_owned_by_current(&self) -> bool {
+        self.lock_state().owner == current_id()
+    }
+
+    /*
+    Release the monitor and wait until notified, interrupted or 'timeout'
+    elapsed, None to wait forever. The monitor is then reacquired with the
+    recursion count restored. Err if the current thread is not the owner.
+    */
+    pub fn wait(&self, timeout: Option<Duration>, interrupt: &Interrupt) -> Result<(), ()> {
+        let id = current_id();
+        let mut state = self.lock_state();
+        if state.owner != id {
+            return Err(());
+        }
+
+        let ticket = state.next_ticket;
+        state.next_ticket = state.next_ticket.wrapping_add(1);
+        state.waiters.push_back(ticket);
+        let count = state.count;
+        state.owner = 0;
+        state.count = 0;
+        self.entry.notify_one();
+
+        let deadline = timeout.and_then(|d| Instant::now().checked_add(d));
+        loop {
+            //interrupt checks the status holding the state lock, not lost
+            if !state.waiters.contains(&ticket) || interrupt.is_interrupted(false) {
+                break;
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    state = gc::blocking(|| self.wait_set.wait_timeout(state, deadline - now))
+                        .unwrap()
+                        .0;
+                }
+                None => state = gc::blocking(|| self.wait_set.wait(state)).unwrap(),
+            }
+        }
+        state.waiters.retain(|it| *it != ticket);
+
+        state = self.acquire(state, id);
+        state.count = count;
+        Ok(())
+    }
+
+    //wake the longest waiting thread
+    pub fn notify(&self) -> Result<(), ()> {
+        let mut state = self.lock_state();
+        if state.owner != current_id() {
+            return Err(());
+        }
+
+        if state.waiters.pop_front().is_some() {
+            //the other waiters find themselves still in the wait set
+            self.wait_set.notify_all();
+        }
+        Ok(())
+    }
+
+    pub fn notify_all(&self) -> Result<(), ()> {
+        let mut state = self.lock_state();
+        if state.owner != current_id() {
+            return Err(());
+        }
+
+        if !state.waiters.is_empty() {
+            state.waiters.clear();
+            self.wait_set.notify_all();
+        }
+        Ok(())
+    }
+
+    //the waiters check their interrupt status, called by Thread.interrupt
+    pub fn wake_waiters(&self) {
+        let _state = self.lock_state();
+        self.wait_set.notify_all();
+    }
+
+    //the holder may be stopped by gc reattaching, block as a safepoint
+    fn lock_state(&self) -> MutexGuard<'_, State> {
+        match self.state.try_lock() {
+            Ok(state) => state,
+            Err(TryLockError::WouldBlock) => gc::blocking(|| self.state.lock()).unwrap(),
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        }
+    }
+
+    fn acquire<'a>(&self, mut state: MutexGuard<'a, State>, id: usize) -> MutexGuard<'a, State> {
+        while state.owner != 0 {
+            state = gc::blocking(|| self.entry.wait(state)).unwrap();
+        }
+        state.owner = id;
+        state
+    }
+}
+
+impl Default for Monitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn t_wait_notify() {
+        let monitor = Arc::new(Monitor::new());
+        let interrupt = Interrupt::new();
+
+        //reentrant, only the owner exits
+        monitor.enter();
+        monitor.enter();
+        assert!(monitor.is_owned_by_current());
+        assert!(monitor
+            .wait(Some(Duration::from_millis(10)), &interrupt)
+            .is_ok());
+        assert!(monitor.exit().is_ok());
+        assert!(monitor.is_owned_by_current());
+        assert!(monitor.exit().is_ok());
+        assert!(monitor.exit().is_err());
+        assert!(monitor.notify().is_err());
+
+        let m = monitor.clone();
+        let waiter = thread::spawn(move || {
+            let interrupt = Interrupt::new();
+            m.enter();
+            m.wait(None, &interrupt).unwrap();
+            let owned = m.is_owned_by_current();
+            m.exit().unwrap();
+            owned
+        });
+
+        //notify until the waiter is in the wait set
+        loop {
+            monitor.enter();
+            let waiting = !monitor.lock_state().waiters.is_empty();
+            monitor.notify().unwrap();
+            monitor.exit().unwrap();
+            if waiting {
+                break;
+            }
+            thread::yield_now();
+        }
+        assert!(waiter.join().unwrap());
+    }
+}
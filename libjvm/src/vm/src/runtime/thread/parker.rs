@@ -0,0 +1,56 @@
+use crate::runtime::thread::Interrupt;
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+/*
+The permit of LockSupport.park/unpark, at most one. unpark makes the permit
+available, park consumes it, or blocks until it's available, the thread is
+interrupted or the deadline passed.
+*/
+pub struct Parker {
+    permit: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Parker {
+    pub fn new() -> Self {
+        Self {
+            permit: Mutex::new(false),
+            cond: Condvar::new(),
+        }
+    }
+
+    /*
+    None to park until unparked or interrupted. Should be called in
+    gc::blocking, the thread is not stopped holding the permit.
+    */
+    pub fn park(&self, deadline: Option<Instant>, interrupt: &Interrupt) {
+        let mut permit = self.permit.lock().unwrap();
+        //interrupt unparks after setting the status, not lost
+        while !*permit && !interrupt.is_interrupted(false) {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    permit = self.cond.wait_timeout(permit, deadline - now).unwrap().0;
+                }
+                None => permit = self.cond.wait(permit).unwrap(),
+            }
+        }
+        *permit = false;
+    }
+
+    pub fn unpark(&self) {
+        let mut permit = self.permit.lock().unwrap();
+        *permit = true;
+        self.cond.notify_one();
+    }
+}
+
+impl Default for Parker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
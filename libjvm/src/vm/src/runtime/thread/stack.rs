@@ -0,0 +1,153 @@
+use crate::oop::Oop;
+use crate::runtime::{
+    exception, vm, THREAD_DEFAULT_STACK_SIZE, THREAD_STACK_FRAME_SIZE, THREAD_STACK_RESERVED_ZONE,
+};
+use classfile::consts as cls_consts;
+use std::cell::Cell;
+
+/*
+The stack of the current thread, sized by -Xss or Thread.stackSize. The java
+frames run in the dispatch loop of Interp are not on the native stack, they
+are accounted THREAD_STACK_FRAME_SIZE each; the native stack is still used
+by the native methods calling java. A call is refused with StackOverflowError
+when either would leave less than the reserved zone, which is then used to
+create the error, like the yellow zone of hotspot. The zone is available
+again once the stack is unwound, the thread can go on after catching it.
+If the zone overflows too, the error preallocated at the start of the vm
+is thrown instead.
+*/
+
+#[derive(Clone, Copy)]
+struct Limit {
+    //the lowest usable address, above the guard page
+    low: usize,
+    reserved: usize,
+    max_frames: usize,
+}
+
+thread_local! {
+    static LIMIT: Cell<Option<Limit>> = const { Cell::new(None) };
+    static IN_RESERVED_ZONE: Cell<bool> = const { Cell::new(false) };
+}
+
+//a java call with 'depth' frames below would overflow the stack
+pub fn is_stack_overflow(depth: usize) -> bool {
+    let limit = LIMIT.with(|limit| match limit.get() {
+        Some(v) => v,
+        None => {
+            let (low, size) = stack_bounds();
+            let reserved = THREAD_STACK_RESERVED_ZONE.min(size / 4);
+            let v = Limit {
+                low,
+                reserved,
+                max_frames: (size - reserved) / THREAD_STACK_FRAME_SIZE,
+            };
+            limit.set(Some(v));
+            v
+        }
+    });
+
+    let sp = current_sp();
+    let reserved_frames = limit.reserved / THREAD_STACK_FRAME_SIZE;
+    if IN_RESERVED_ZONE.with(|v| v.get()) {
+        sp < limit.low + limit.reserved / 4 || depth >= limit.max_frames + reserved_frames
+    } else {
+        sp < limit.low + limit.reserved || depth >= limit.max_frames
+    }
+}
+
+//the StackOverflowError to throw, created on the reserved zone
+pub fn new_stack_overflow_error() -> Oop {
+    if IN_RESERVED_ZONE.with(|v| v.get()) {
+        return exception::preallocated_stack_overflow_error();
+    }
+
+    IN_RESERVED_ZONE.with(|v| v.set(true));
+    let ex = exception::new(cls_consts::J_SOE, None);
+    IN_RESERVED_ZONE.with(|v| v.set(false));
+    ex
+}
+
+#[inline(never)]
+fn current_sp() -> usize {
+    let v = 0u8;
+    &v as *const u8 as usize
+}
+
+//(the lowest usable address, the usable size)
+#[cfg(target_os = "linux")]
+fn stack_bounds() -> (usize, usize) {
+    use std::{mem, ptr};
+
+    unsafe {
+        let mut attr: libc::pthread_attr_t = mem::zeroed();
+        if libc::pthread_getattr_np(libc::pthread_self(), &mut attr) == 0 {
+            let mut addr = ptr::null_mut();
+            let mut size = 0;
+            let mut guard = 0;
+            let r = libc::pthread_attr_getstack(&attr, &mut addr, &mut size);
+            libc::pthread_attr_getguardsize(&attr, &mut guard);
+            libc::pthread_attr_destroy(&mut attr);
+
+            if r == 0 && size > guard {
+                return (addr as usize + guard, size - guard);
+            }
+        }
+    }
+
+    estimated_bounds()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn stack_bounds() -> (usize, usize) {
+    estimated_bounds()
+}
+
+//from the first java call, near the top of the stack
+fn estimated_bounds() -> (usize, usize) {
+    let size = vm::thread_stack_size().unwrap_or(THREAD_DEFAULT_STACK_SIZE);
+    let size = size - size / 8;
+    (current_sp().saturating_sub(size), size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn depth(n: usize) -> usize {
+        let pad = [0u8; 1024];
+        let n = if is_stack_overflow(0) {
+            n
+        } else {
+            depth(n + 1)
+        };
+        n + std::hint::black_box(&pad)[0] as usize
+    }
+
+    #[test]
+    fn t_stack_overflow() {
+        let t = thread::Builder::new().stack_size(512 * 1024).spawn(|| {
+            //stopped before the guard page, and again after unwinding
+            let n = depth(0);
+            assert!(n > 0);
+            assert!(!is_stack_overflow(0));
+            assert_eq!(depth(0), n);
+
+            //the java frames, about the stack less the reserved zone
+            let max = LIMIT.with(|limit| limit.get().unwrap().max_frames);
+            assert!(max > 256 * 1024 / THREAD_STACK_FRAME_SIZE);
+            assert!(!is_stack_overflow(max - 1));
+            assert!(is_stack_overflow(max));
+
+            //the reserved zone has room for more, and is refused past it
+            let reserved = LIMIT.with(|limit| limit.get().unwrap().reserved);
+            let reserved_frames = reserved / THREAD_STACK_FRAME_SIZE;
+            IN_RESERVED_ZONE.with(|v| v.set(true));
+            assert!(!is_stack_overflow(max));
+            assert!(is_stack_overflow(max + reserved_frames));
+            IN_RESERVED_ZONE.with(|v| v.set(false));
+        });
+        t.unwrap().join().unwrap();
+    }
+}
@@ -1,21 +1,22 @@
+use crate::gc;
 use crate::runtime;
-use crate::runtime::thread::ThreadPool;
+use crate::runtime::vm;
 use crate::types::JavaThreadRef;
 use std::borrow::Borrow;
+use std::io;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
 
 pub struct Threads {
-    pool: Mutex<ThreadPool>,
     threads: Mutex<Vec<JavaThreadRef>>,
     cond_join: Condvar,
     next_id: AtomicI64,
 }
 
 impl Threads {
-    pub fn new(thread_pool_count: usize) -> Threads {
+    pub fn new() -> Threads {
         Threads {
-            pool: Mutex::new(ThreadPool::new(thread_pool_count)),
             threads: Mutex::new(Vec::new()),
             cond_join: Condvar::new(),
             next_id: AtomicI64::new(1),
@@ -23,6 +24,12 @@ impl Threads {
     }
 }
 
+impl Default for Threads {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Threads {
     pub fn next_id(&self) -> i64 {
         self.next_id.fetch_add(1, Ordering::SeqCst)
@@ -41,11 +48,26 @@ impl Threads {
     }
 
     pub fn detach_current_thread(&self) {
+        runtime::thread::THREAD.with(|thread| self.detach_java_thread(&thread.borrow()));
+    }
+
+    pub fn detach_java_thread(&self, thread: &JavaThreadRef) {
+        let mut threads = self.threads.lock().unwrap();
+        threads.retain(|elem| !Arc::ptr_eq(elem, thread));
+        self.cond_join.notify_all();
+    }
+
+    pub fn is_current_attached(&self) -> bool {
         runtime::thread::THREAD.with(|thread| {
-            let mut threads = self.threads.lock().unwrap();
-            threads.retain(|elem| !Arc::ptr_eq(elem, &*thread.borrow()));
-            self.cond_join.notify_all();
-        });
+            let threads = self.threads.lock().unwrap();
+            threads
+                .iter()
+                .any(|elem| Arc::ptr_eq(elem, &*thread.borrow()))
+        })
+    }
+
+    pub fn java_threads(&self) -> Vec<JavaThreadRef> {
+        self.threads.lock().unwrap().clone()
     }
 
     pub fn find_java_thread(&self, eetop: i64) -> Option<JavaThreadRef> {
@@ -56,16 +78,37 @@ impl Threads {
             .cloned()
     }
 
-    pub fn join_all(&self) {
+    //wait for the threads not daemon, but the current one, to exit
+    pub fn join_non_daemon(&self) {
+        let current = runtime::thread::current_java_thread();
+        let is_waited =
+            |t: &JavaThreadRef| !Arc::ptr_eq(t, &current) && !t.read().unwrap().is_daemon();
         let mut threads = self.threads.lock().unwrap();
 
-        while threads.len() > 0 {
-            threads = self.cond_join.wait(threads).unwrap();
+        while threads.iter().any(is_waited) {
+            threads = gc::blocking(|| self.cond_join.wait(threads).unwrap());
         }
     }
 
-    pub fn spawn_java_thread<F: FnOnce() + Send + 'static>(&self, f: F) {
-        let pool = self.pool.lock().unwrap();
-        pool.execute(f);
+    /*
+    Each java thread runs on its own native thread. The stack size is
+    'stack_size' if it's not 0, like Thread.stackSize, or -Xss.
+    */
+    pub fn spawn_java_thread<F: FnOnce() + Send + 'static>(
+        &self,
+        name: String,
+        stack_size: usize,
+        f: F,
+    ) -> io::Result<()> {
+        let mut builder = thread::Builder::new().name(name);
+        let stack_size = match stack_size {
+            0 => vm::thread_stack_size(),
+            v => Some(v),
+        };
+        if let Some(size) = stack_size {
+            builder = builder.stack_size(size);
+        }
+
+        builder.spawn(f).map(|_| ())
     }
 }
@@ -0,0 +1,168 @@
+use crate::oop::class::ClassKind;
+use crate::runtime::{self, thread, ClassLoader};
+use crate::types::ClassRef;
+use class_verification::checker::{self, Checker};
+use class_verification::ClassHierarchy;
+use classfile::{constant_pool, consts as cls_consts, BytesRef, ClassFile, ConstantPoolType};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/*
+Bytecode verification of the classes loaded from class files, -Xverify
+
+The classes built by the vm, like the lambda classes, are not verified.
+*/
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Mode {
+    None,
+    //the classes not from the jdk
+    Remote,
+    All,
+}
+
+static MODE: AtomicU8 = AtomicU8::new(Mode::Remote as u8);
+
+//the jdk packages trusted in the remote mode
+const TRUSTED: [&[u8]; 5] = [b"java/", b"javax/", b"sun/", b"com/sun/", b"jdk/"];
+
+pub fn set_mode(mode: Mode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn mode() -> Mode {
+    match MODE.load(Ordering::Relaxed) {
+        0 => Mode::None,
+        1 => Mode::Remote,
+        _ => Mode::All,
+    }
+}
+
+//"none", "remote" or "all"
+pub fn parse_mode(v: &str) -> Option<Mode> {
+    match v {
+        "none" => Some(Mode::None),
+        "remote" => Some(Mode::Remote),
+        "all" => Some(Mode::All),
+        _ => None,
+    }
+}
+
+/*
+The format checking of a class file, JVMS 4.8, done before the class
+is created whatever the mode is. The error class and the message are
+returned if it fails.
+*/
+pub fn check_format(cf: &ClassFile) -> Result<(), (&'static [u8], String)> {
+    match cf.check(&cf.cp) {
+        Ok(_) => Ok(()),
+        Err(checker::Err::UnsupportedVersion) => {
+            let msg = format!(
+                "{} : Unsupported major.minor version {}.{}",
+                class_name(cf),
+                cf.version.major,
+                cf.version.minor
+            );
+            Err((cls_consts::J_UNSUPPORTED_CLASS_VERSION_ERROR, msg))
+        }
+        Err(e) => {
+            let msg = format!("{} in class file {}", e, class_name(cf));
+            Err((cls_consts::J_CLASS_FORMAT_ERROR, msg))
+        }
+    }
+}
+
+//this_class is not checked yet
+fn class_name(cf: &ClassFile) -> String {
+    let idx = cf.this_class as usize;
+    match cf.cp.get(idx) {
+        Some(ConstantPoolType::Class { name_index }) => match cf.cp.get(*name_index as usize) {
+            Some(ConstantPoolType::Utf8 { .. }) => {
+                let name = constant_pool::get_class_name(&cf.cp, idx);
+                String::from_utf8_lossy(name.as_slice()).to_string()
+            }
+            _ => "<Unknown>".to_string(),
+        },
+        _ => "<Unknown>".to_string(),
+    }
+}
+
+//verify 'class', the message of VerifyError is returned if it fails
+pub fn verify(class: &ClassRef) -> Result<(), String> {
+    let (loader, name, cf) = {
+        let cls = class.get_class();
+        match &cls.kind {
+            ClassKind::Instance(obj) => {
+                (cls.class_loader, cls.name.clone(), obj.class_file.clone())
+            }
+            _ => return Ok(()),
+        }
+    };
+
+    let enabled = match mode() {
+        Mode::None => false,
+        Mode::All => true,
+        Mode::Remote => {
+            let is_boot = matches!(loader, None | Some(ClassLoader::Bootstrap));
+            !(is_boot && TRUSTED.iter().any(|it| name.starts_with(it)))
+        }
+    };
+    if !enabled {
+        return Ok(());
+    }
+
+    class_verification::verify_class(&cf, &Hierarchy { loader }).map_err(|e| e.to_string())
+}
+
+//the classes are loaded by the loader of the class verified
+struct Hierarchy {
+    loader: Option<ClassLoader>,
+}
+
+impl Hierarchy {
+    //the exception of the loading is dropped, the types are not assignable
+    fn find(&self, name: &[u8]) -> Option<ClassRef> {
+        let class = runtime::require_class3(self.loader, name);
+        if class.is_none() && thread::is_meet_ex() {
+            let jt = thread::current_java_thread();
+            jt.write().unwrap().take_ex();
+        }
+        class
+    }
+}
+
+impl ClassHierarchy for Hierarchy {
+    fn is_interface(&self, name: &[u8]) -> bool {
+        match self.find(name) {
+            Some(class) => class.get_class().is_interface(),
+            None => false,
+        }
+    }
+
+    fn super_class(&self, name: &[u8]) -> Option<BytesRef> {
+        let class = self.find(name)?;
+        let super_class = class.get_class().get_super_class()?;
+        let name = super_class.get_class().name.clone();
+        Some(name)
+    }
+
+    fn is_subclass(&self, sub: &[u8], sup: &[u8]) -> bool {
+        let mut class = match self.find(sub) {
+            Some(class) => class,
+            None => return false,
+        };
+
+        loop {
+            let super_class = {
+                let cls = class.get_class();
+                if cls.name.as_slice() == sup {
+                    return true;
+                }
+                cls.get_super_class()
+            };
+
+            match super_class {
+                Some(super_class) => class = super_class,
+                None => return false,
+            }
+        }
+    }
+}
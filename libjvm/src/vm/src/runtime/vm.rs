@@ -1,8 +1,29 @@
-use crate::runtime::thread::Threads;
+use crate::gc;
+use crate::new_br;
+use crate::oop::{self, Class, Oop};
+use crate::runtime::thread::{
+    JavaThread, Threads, THREAD, THREAD_STATUS_RUNNABLE, THREAD_STATUS_TERMINATED,
+};
+use crate::runtime::{self, init_vm, thread, JavaCall};
+use crate::util;
+use classfile::consts::J_THREAD;
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 static mut VM_GLOBAL: *const u8 = ptr::null();
 
+//-Xss, 0 for the default of the platform
+static THREAD_STACK_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    //-D options, override the defaults of System.initProperties
+    static ref PROPERTIES: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+
+    //the vm created by the invocation api
+    static ref EMBEDDED: Mutex<Option<Box<VM>>> = Mutex::new(None);
+}
+
 pub fn get_vm() -> &'static VM {
     unsafe { &*(VM_GLOBAL as *const VM) }
 }
@@ -20,9 +41,9 @@ pub struct VM {
 }
 
 impl VM {
-    pub fn new(thread_pool_count: usize) -> Box<VM> {
+    pub fn new() -> Box<VM> {
         let vm = Box::new(VM {
-            threads: Threads::new(thread_pool_count),
+            threads: Threads::new(),
         });
 
         set_vm(&vm);
@@ -30,3 +51,263 @@ impl VM {
         vm
     }
 }
+
+pub fn set_property(k: &str, v: &str) {
+    let mut props = PROPERTIES.lock().unwrap();
+    props.retain(|(it, _)| it != k);
+    props.push((k.to_string(), v.to_string()));
+}
+
+pub fn properties() -> Vec<(String, String)> {
+    PROPERTIES.lock().unwrap().clone()
+}
+
+//should be called before the vm is created
+pub fn set_thread_stack_size(size: usize) {
+    THREAD_STACK_SIZE.store(size, Ordering::Relaxed);
+}
+
+pub fn thread_stack_size() -> Option<usize> {
+    match THREAD_STACK_SIZE.load(Ordering::Relaxed) {
+        0 => None,
+        v => Some(v),
+    }
+}
+
+/*
+Create the vm for the invocation api, the calling thread becomes the main
+thread. The natives, the class paths and the options should be set up first.
+*/
+pub fn create() -> Result<(), ()> {
+    let vm = VM::new();
+    //eetop 0 is the thread not started
+    thread::current_java_thread().write().unwrap().eetop = vm.threads.next_id();
+    vm.threads.attach_current_thread();
+    gc::attach_thread();
+    EMBEDDED.lock().unwrap().replace(vm);
+
+    init_vm::initialize_jvm();
+    if thread::is_meet_ex() {
+        return Err(());
+    }
+
+    let jt = thread::current_java_thread();
+    let mut jt = jt.write().unwrap();
+    jt.is_alive = true;
+    jt.set_status(THREAD_STATUS_RUNNABLE);
+    Ok(())
+}
+
+pub fn is_current_thread_attached() -> bool {
+    let vm = get_vm();
+    vm.threads.is_current_attached()
+}
+
+/*
+Attach the current native thread, a java.lang.Thread is created for it
+in 'group', or the main thread group if 'group' is null.
+*/
+pub fn attach_current_thread(name: Option<String>, group: Oop, daemon: bool) -> Result<(), ()> {
+    let vm = get_vm();
+    if vm.threads.is_current_attached() {
+        return Ok(());
+    }
+
+    let eetop = vm.threads.next_id();
+    let jt = JavaThread::new(name.clone(), eetop);
+    THREAD.with(|t| *t.borrow_mut() = jt.clone());
+
+    let thread_cls = oop::class::load_and_init(J_THREAD);
+    let thread_oop = Oop::new_inst(thread_cls.clone());
+    {
+        let cls = thread_cls.get_class();
+        let id = cls.get_field_id(&new_br("priority"), &new_br("I"), false);
+        Class::put_field_value(thread_oop.extract_ref(), id, Oop::new_int(5));
+        let id = cls.get_field_id(&new_br("eetop"), &new_br("J"), false);
+        Class::put_field_value(thread_oop.extract_ref(), id, Oop::new_long(eetop));
+    }
+    jt.write().unwrap().set_java_thread_obj(thread_oop.clone());
+
+    vm.threads.attach_java_thread(jt.clone());
+    gc::attach_thread();
+
+    let group = match group {
+        Oop::Null => init_vm::main_thread_group(),
+        group => group,
+    };
+    match name {
+        Some(name) => {
+            let args = vec![
+                thread_oop.clone(),
+                group.clone(),
+                util::oop::new_java_lang_string2(&name),
+            ];
+            let desc = new_br("(Ljava/lang/ThreadGroup;Ljava/lang/String;)V");
+            runtime::invoke::invoke_ctor(thread_cls.clone(), desc, args);
+        }
+        //named "Thread-<n>" by java
+        None => {
+            let args = vec![thread_oop.clone(), group.clone(), Oop::Null];
+            let desc = new_br("(Ljava/lang/ThreadGroup;Ljava/lang/Runnable;)V");
+            runtime::invoke::invoke_ctor(thread_cls.clone(), desc, args);
+        }
+    }
+
+    if daemon && !thread::is_meet_ex() {
+        let cls = thread_cls.get_class();
+        let id = cls.get_field_id(&new_br("daemon"), &new_br("Z"), false);
+        Class::put_field_value(thread_oop.extract_ref(), id, Oop::new_int(1));
+    }
+
+    //a member of the group, like a started thread
+    if !thread::is_meet_ex() {
+        let mir = {
+            let cls = group.extract_ref().extract_inst().class.clone();
+            let cls = cls.get_class();
+            cls.get_virtual_method(&new_br("add"), &new_br("(Ljava/lang/Thread;)V"))
+                .unwrap()
+        };
+        let mut jc = JavaCall::new_with_args(mir, vec![group, thread_oop]);
+        jc.invoke(None, false);
+    }
+
+    if thread::is_meet_ex() {
+        jt.write().unwrap().take_ex();
+        gc::detach_thread();
+        vm.threads.detach_current_thread();
+        THREAD.with(|t| *t.borrow_mut() = JavaThread::main());
+        return Err(());
+    }
+
+    let mut jt = jt.write().unwrap();
+    jt.is_alive = true;
+    jt.set_status(THREAD_STATUS_RUNNABLE);
+    Ok(())
+}
+
+/*
+Detach the current thread, the thread must not be running java code.
+The pending exception goes to the uncaught exception handler, Thread.exit
+removes it from its group, and the joining threads are woken.
+*/
+pub fn detach_current_thread() -> Result<(), ()> {
+    let vm = get_vm();
+    if !vm.threads.is_current_attached() {
+        return Ok(());
+    }
+
+    let jt = thread::current_java_thread();
+    if !jt.read().unwrap().frames.is_empty() {
+        return Err(());
+    }
+
+    let thread_oop = jt.read().unwrap().java_thread_obj.clone();
+    if let Some(thread_oop) = thread_oop {
+        let ex = jt.write().unwrap().take_ex();
+        if let Some(ex) = ex {
+            dispatch_uncaught_exception(thread_oop.clone(), ex);
+        }
+
+        call_thread_method("exit", "()V", vec![thread_oop.clone()]);
+        jt.write().unwrap().take_ex();
+
+        //Thread.join checks isAlive holding the lock of the thread
+        let rf = thread_oop.extract_ref();
+        rf.monitor_enter();
+        {
+            let mut jt = jt.write().unwrap();
+            jt.is_alive = false;
+            jt.set_status(THREAD_STATUS_TERMINATED);
+        }
+        let _ = rf.notify_all();
+        let _ = rf.monitor_exit();
+    }
+
+    gc::detach_thread();
+    vm.threads.detach_current_thread();
+    THREAD.with(|t| *t.borrow_mut() = JavaThread::main());
+    Ok(())
+}
+
+/*
+Thread.dispatchUncaughtException, the default handler prints
+'Exception in thread "main" ' and the stack trace, the exception thrown
+by the handler is reported and discarded, like hotspot.
+*/
+pub fn dispatch_uncaught_exception(thread_oop: Oop, ex: Oop) {
+    call_thread_method(
+        "dispatchUncaughtException",
+        "(Ljava/lang/Throwable;)V",
+        vec![thread_oop.clone(), ex],
+    );
+
+    let jt = thread::current_java_thread();
+    let ex = jt.write().unwrap().take_ex();
+    if let Some(ex) = ex {
+        let name = {
+            let rf = ex.extract_ref();
+            let cls = rf.extract_inst().class.get_class();
+            String::from_utf8_lossy(cls.name.as_slice()).replace('/', ".")
+        };
+        eprintln!(
+            "Exception: {} thrown from the UncaughtExceptionHandler in thread \"{}\"",
+            name,
+            thread_name(&thread_oop)
+        );
+    }
+}
+
+//Thread.name, a char[] of jdk 8
+fn thread_name(thread_oop: &Oop) -> String {
+    let fid = {
+        let cls = oop::class::load_and_init(J_THREAD);
+        let cls = cls.get_class();
+        cls.get_field_id(&new_br("name"), &new_br("[C"), false)
+    };
+    match Class::get_field_value(thread_oop.extract_ref(), fid) {
+        Oop::Null => String::new(),
+        v => {
+            let rf = v.extract_ref();
+            String::from_utf16_lossy(rf.extract_type_array().extract_chars())
+        }
+    }
+}
+
+//the private methods of java.lang.Thread
+fn call_thread_method(name: &str, desc: &str, args: Vec<Oop>) {
+    let mir = {
+        let cls = oop::class::load_and_init(J_THREAD);
+        let cls = cls.get_class();
+        cls.get_this_class_method(&new_br(name), &new_br(desc))
+    };
+    if let Ok(mir) = mir {
+        let mut jc = JavaCall::new_with_args(mir, args);
+        jc.invoke(None, false);
+    }
+}
+
+/*
+Wait for the other threads not daemon to exit, run the shutdown hooks,
+then detach the current thread. The vm can't be created again.
+*/
+pub fn destroy() -> Result<(), ()> {
+    attach_current_thread(None, Oop::Null, false)?;
+
+    let vm = get_vm();
+    vm.threads.join_non_daemon();
+
+    let cls = oop::class::load_and_init(b"java/lang/Shutdown");
+    let mir = {
+        let cls = cls.get_class();
+        cls.get_static_method(&new_br("shutdown"), &new_br("()V"))
+    };
+    if let Ok(mir) = mir {
+        let mut jc = JavaCall::new_with_args(mir, vec![]);
+        jc.invoke(None, false);
+    }
+
+    let jt = thread::current_java_thread();
+    jt.write().unwrap().take_ex();
+
+    detach_current_thread()
+}
@@ -1,3 +1,4 @@
+use crate::gc::Payload;
 use crate::oop::class::ClassPtr;
 use crate::oop::field::FieldId;
 use crate::runtime::method::MethodId;
@@ -11,15 +12,15 @@ pub type MethodIdRef = Arc<MethodId>;
 pub type ClassRef = Arc<ClassPtr>;
 
 def_ref!(ClassFileRef, ClassFile);
-def_sync_ref!(FrameRef, Frame);
+pub type FrameRef = Arc<Frame>;
 def_sync_ref!(JavaThreadRef, JavaThread);
 
-// Runtime string allocation
-def_ptr!(ByteAry, Vec<u8>);
-def_ptr!(BoolAry, Vec<u8>);
-def_ptr!(CharAry, Vec<u16>);
-def_ptr!(ShortAry, Vec<i16>);
-def_ptr!(IntAry, Vec<i32>);
-def_ptr!(LongAry, Vec<i64>);
-def_ptr!(FloatAry, Vec<f32>);
-def_ptr!(DoubleAry, Vec<f64>);
+//the elements of the primitive arrays, see gc::Payload
+pub type ByteAry = Payload<u8>;
+pub type BoolAry = Payload<u8>;
+pub type CharAry = Payload<u16>;
+pub type ShortAry = Payload<i16>;
+pub type IntAry = Payload<i32>;
+pub type LongAry = Payload<i64>;
+pub type FloatAry = Payload<f32>;
+pub type DoubleAry = Payload<f64>;
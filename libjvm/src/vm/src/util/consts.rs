@@ -10,7 +10,9 @@ lazy_static! {
     pub static ref S_CLAZZ: BytesRef = new_br("clazz");
     pub static ref S_FD: BytesRef = new_br("fd");
     pub static ref S_I: BytesRef = new_br("I");
+    pub static ref S_Z: BytesRef = new_br("Z");
     pub static ref S_SLOT: BytesRef = new_br("slot");
+    pub static ref S_OVERRIDE: BytesRef = new_br("override");
     pub static ref S_MODIFIERS: BytesRef = new_br("modifiers");
     pub static ref S_NAME: BytesRef = new_br("name");
     pub static ref S_SIGNATURE: BytesRef = new_br("signature");
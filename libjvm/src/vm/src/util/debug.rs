@@ -4,26 +4,34 @@ use crate::runtime::thread::JavaThread;
 use std::fmt::Write;
 use std::sync::atomic::Ordering;
 
+//the frames of the thread, innermost first, as Throwable.printStackTrace
 pub fn print_stack_trace(jt: &JavaThread) {
     let mut w = String::new();
 
     let _ = writeln!(&mut w);
-    for (count, it) in jt.frames.iter().enumerate().rev() {
-        let frame = it.read().unwrap();
-        let cls = frame.mir.method.class.get_class();
-        let method_id = frame.mir.method.name.clone();
-        let line_num = {
+    for frame in jt.frames.iter().rev() {
+        let method = &frame.mir.method;
+        let cls = method.class.get_class();
+        let location = if method.is_native() {
+            "Native Method".to_string()
+        } else {
             let pc = frame.pc.load(Ordering::Relaxed);
-            frame.mir.method.get_line_num(pc as u16)
+            let line_num = method.get_line_num((pc - 1).max(0) as u16);
+            match cls.get_source_file() {
+                Some(file) if line_num >= 0 => {
+                    format!("{}:{}", String::from_utf8_lossy(file.as_slice()), line_num)
+                }
+                Some(file) => String::from_utf8_lossy(file.as_slice()).to_string(),
+                None => "Unknown Source".to_string(),
+            }
         };
 
         let _ = writeln!(
             &mut w,
-            "{}{}:{}(:{})",
-            " ".repeat(jt.frames.len() - count),
-            String::from_utf8_lossy(cls.name.as_slice()),
-            String::from_utf8_lossy(method_id.as_slice()),
-            line_num
+            "\tat {}.{}({})",
+            String::from_utf8_lossy(cls.name.as_slice()).replace('/', "."),
+            String::from_utf8_lossy(method.name.as_slice()),
+            location
         );
     }
 
@@ -16,3 +16,15 @@ use std::sync::Arc;
 pub fn new_field_id(cls: &[u8], name: &[u8], desc: &[u8]) -> BytesRef {
     Arc::new(vec![cls, name, desc].join(PATH_SEP.as_bytes()))
 }
+
+//sizes of the options like hotspot: 1024, 64k, 512m, 1g
+pub fn parse_size(v: &str) -> Option<usize> {
+    let (n, unit) = match v.char_indices().last()? {
+        (i, 'k') | (i, 'K') => (&v[..i], 1 << 10),
+        (i, 'm') | (i, 'M') => (&v[..i], 1 << 20),
+        (i, 'g') | (i, 'G') => (&v[..i], 1 << 30),
+        _ => (v, 1),
+    };
+
+    n.parse::<usize>().ok()?.checked_mul(unit)
+}
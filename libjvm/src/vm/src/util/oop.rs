@@ -53,3 +53,16 @@ pub fn new_java_lang_string3(bs: &[u8]) -> Oop {
 
     string_oop
 }
+
+pub fn new_java_lang_string4(v: &[u16]) -> Oop {
+    //build "char value[]"
+    let ary = Oop::char_ary_from1(v);
+
+    //new String(char value[])
+    let string_cls = require_class3(None, b"java/lang/String").unwrap();
+    let string_oop = Oop::new_inst(string_cls.clone());
+    let args = vec![string_oop.clone(), ary];
+    runtime::invoke::invoke_ctor(string_cls, util::S_NEW_STRING_SIG.clone(), args);
+
+    string_oop
+}